@@ -0,0 +1,90 @@
+//! Counts of fetches and focus changes per subtree path during a session,
+//! shown as a heatmap over subtree frames to spot hot paths.
+
+use std::{cell::RefCell, collections::HashMap};
+
+use eframe::egui::Color32;
+
+use crate::protocol::FetchCommand;
+
+/// Opacity of the tint of the most accessed subtree
+const MAX_TINT_ALPHA: f32 = 96.;
+
+#[derive(Default)]
+pub(crate) struct AccessCounts {
+    counts: RefCell<HashMap<Vec<Vec<u8>>, u32>>,
+}
+
+impl AccessCounts {
+    pub(crate) fn record(&self, path: &[Vec<u8>]) {
+        *self.counts.borrow_mut().entry(path.to_vec()).or_default() += 1;
+    }
+
+    /// Records every subtree the command fetches from
+    pub(crate) fn record_command(&self, command: &FetchCommand) {
+        match command {
+            FetchCommand::FetchRoot => self.record(&[]),
+            FetchCommand::FetchNode { path, .. }
+            | FetchCommand::FetchRawNode { path, .. }
+            | FetchCommand::CountSubtree { path }
+            | FetchCommand::FetchReferrers { path, .. }
+            | FetchCommand::FetchMerkLevels { path, .. } => self.record(path),
+            FetchCommand::ProvePathQuery { path_query } | FetchCommand::FetchWithPathQuery { path_query } => {
+                self.record(&path_query.path)
+            }
+            FetchCommand::FetchWithPathQueries { path_queries } => path_queries
+                .iter()
+                .for_each(|path_query| self.record(&path_query.path)),
+            FetchCommand::FetchNodes { nodes } => nodes.iter().for_each(|(path, _)| self.record(path)),
+            // The subtree is not known until the node is found
+            FetchCommand::FindNodeByHash { .. } => {}
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        self.counts.borrow_mut().clear();
+    }
+
+    pub(crate) fn count(&self, path: &[Vec<u8>]) -> u32 {
+        self.counts.borrow().get(path).copied().unwrap_or_default()
+    }
+
+    /// Accesses of the path relative to the most accessed one, from 0 to 1
+    pub(crate) fn heat(&self, path: &[Vec<u8>]) -> f32 {
+        let max = self.counts.borrow().values().copied().max().unwrap_or_default();
+        if max == 0 {
+            0.
+        } else {
+            self.count(path) as f32 / max as f32
+        }
+    }
+}
+
+/// Fill of a subtree frame for its heat, transparent for cold subtrees
+pub(crate) fn tint(heat: f32) -> Color32 {
+    Color32::from_rgba_unmultiplied(255, 64, 0, (heat.clamp(0., 1.) * MAX_TINT_ALPHA) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heat_is_relative_to_the_hottest_path() {
+        let counts = AccessCounts::default();
+        assert_eq!(counts.heat(&[]), 0.);
+
+        counts.record_command(&FetchCommand::FetchNodes {
+            nodes: vec![
+                (vec![b"a".to_vec()], b"k1".to_vec()),
+                (vec![b"a".to_vec()], b"k2".to_vec()),
+            ],
+        });
+        counts.record(&[b"b".to_vec()]);
+
+        assert_eq!(counts.count(&[b"a".to_vec()]), 2);
+        assert_eq!(counts.heat(&[b"a".to_vec()]), 1.);
+        assert_eq!(counts.heat(&[b"b".to_vec()]), 0.5);
+        assert_eq!(counts.heat(&[]), 0.);
+    }
+}
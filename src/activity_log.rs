@@ -0,0 +1,177 @@
+//! Structured feed of processed protocol commands with their outcomes.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use eframe::egui::{self, Color32, RichText};
+use strum::IntoEnumIterator;
+
+use crate::{
+    budget::format_size,
+    format,
+    protocol::{ActivityEvent, ActivitySeverity, CommandKind},
+};
+
+const MAX_EVENTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LogTab {
+    Activity,
+    Raw,
+}
+
+pub(crate) struct ActivityLog {
+    /// Events with their sequence numbers, oldest first
+    events: VecDeque<(u64, ActivityEvent)>,
+    next_seq: u64,
+    selected: Option<u64>,
+    tab: LogTab,
+    hidden_commands: BTreeSet<CommandKind>,
+    min_severity: ActivitySeverity,
+}
+
+impl Default for ActivityLog {
+    fn default() -> Self {
+        Self {
+            events: Default::default(),
+            next_seq: 0,
+            selected: None,
+            tab: LogTab::Activity,
+            hidden_commands: Default::default(),
+            min_severity: ActivitySeverity::Info,
+        }
+    }
+}
+
+fn severity_icon(severity: ActivitySeverity) -> &'static str {
+    match severity {
+        ActivitySeverity::Info => egui_phosphor::regular::CHECK_CIRCLE,
+        ActivitySeverity::Warning => egui_phosphor::regular::WARNING,
+        ActivitySeverity::Error => egui_phosphor::regular::X_CIRCLE,
+    }
+}
+
+fn severity_color(ui: &egui::Ui, severity: ActivitySeverity) -> Color32 {
+    match severity {
+        ActivitySeverity::Info => ui.visuals().text_color(),
+        ActivitySeverity::Warning => ui.visuals().warn_fg_color,
+        ActivitySeverity::Error => ui.visuals().error_fg_color,
+    }
+}
+
+impl ActivityLog {
+    pub(crate) fn push(&mut self, event: ActivityEvent) {
+        if self.events.len() >= MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back((self.next_seq, event));
+        self.next_seq += 1;
+    }
+
+    pub(crate) fn draw(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|line| {
+            line.selectable_value(&mut self.tab, LogTab::Activity, "Activity");
+            line.selectable_value(&mut self.tab, LogTab::Raw, "Raw log");
+        });
+        ui.separator();
+
+        match self.tab {
+            LogTab::Activity => self.draw_activity(ui),
+            LogTab::Raw => egui_logger::logger_ui().show(ui),
+        }
+    }
+
+    fn draw_filters(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|line| {
+            line.menu_button(egui_phosphor::regular::FUNNEL, |menu| {
+                for command in CommandKind::iter() {
+                    let mut shown = !self.hidden_commands.contains(&command);
+                    if menu.checkbox(&mut shown, command.as_ref()).changed() {
+                        if shown {
+                            self.hidden_commands.remove(&command);
+                        } else {
+                            self.hidden_commands.insert(command);
+                        }
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Filter by command type");
+
+            egui::ComboBox::from_id_salt("activity_severity")
+                .selected_text(format!("{}+", self.min_severity.as_ref()))
+                .show_ui(line, |combo| {
+                    for severity in ActivitySeverity::iter() {
+                        combo.selectable_value(&mut self.min_severity, severity, severity.as_ref());
+                    }
+                });
+
+            if line.button("Clear").clicked() {
+                self.events.clear();
+                self.selected = None;
+            }
+        });
+    }
+
+    fn draw_activity(&mut self, ui: &mut egui::Ui) {
+        self.draw_filters(ui);
+
+        if let Some((_, event)) = self
+            .selected
+            .and_then(|selected| self.events.iter().find(|(seq, _)| *seq == selected))
+        {
+            ui.group(|group| {
+                group.label(RichText::new(event.command.as_ref()).strong());
+                group.label(format!("Started: {}", format::timestamp(&event.started)));
+                group.label(format!("Duration: {} ms", event.duration.num_milliseconds()));
+                if let Some(items) = event.items {
+                    group.label(format!("Items returned: {}", format::number(items as u64)));
+                }
+                if let Some(transfer) = event.transfer {
+                    group.label(format!(
+                        "Response: {} received in {} ms",
+                        format_size(transfer.payload_bytes),
+                        transfer.round_trip.num_milliseconds()
+                    ));
+                }
+                if let Some(message) = &event.message {
+                    group.label(
+                        RichText::new(format!("{}: {message}", event.severity.as_ref()))
+                            .color(severity_color(group, event.severity)),
+                    );
+                }
+            });
+        }
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, true])
+            .stick_to_bottom(true)
+            .show(ui, |scroll| {
+                for (seq, event) in self.events.iter().filter(|(_, event)| {
+                    event.severity >= self.min_severity && !self.hidden_commands.contains(&event.command)
+                }) {
+                    let mut text = format!(
+                        "{} {} {} · {} ms",
+                        severity_icon(event.severity),
+                        format::time_of_day(&event.started),
+                        event.command.as_ref(),
+                        event.duration.num_milliseconds(),
+                    );
+                    if let Some(items) = event.items {
+                        text.push_str(&format!(" · {} items", format::number(items as u64)));
+                    }
+                    if let Some(transfer) = event.transfer {
+                        text.push_str(&format!(" · {}", format_size(transfer.payload_bytes)));
+                    }
+
+                    let selected = self.selected == Some(*seq);
+                    let response = scroll.selectable_label(
+                        selected,
+                        RichText::new(text).color(severity_color(scroll, event.severity)),
+                    );
+                    if response.clicked() {
+                        self.selected = if selected { None } else { Some(*seq) };
+                    }
+                }
+            });
+    }
+}
@@ -0,0 +1,234 @@
+//! Limits on the amount of loaded data. Browser tabs have much less memory to
+//! spare, so it's enabled by default for the web version only.
+
+use std::collections::BTreeSet;
+
+use eframe::{
+    egui::{self, DragValue, RichText},
+    Storage,
+};
+use grovedbg_types::{Element, Key, PathQuery};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    theme::input_error_color,
+    tree_data::{SubtreeData, TreeData},
+    tree_view::ElementOrPlaceholder,
+};
+
+const DATA_BUDGET_KEY: &'static str = "data_budget";
+const KB: usize = 1024;
+const MB: usize = 1024 * 1024;
+/// Rough size of an element view apart from its key and value
+const ELEMENT_OVERHEAD: usize = 256;
+/// Rough size of a key stored in a set apart from its bytes
+const KEY_OVERHEAD: usize = 32;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct DataBudget {
+    pub(crate) enabled: bool,
+    /// Estimated usage in megabytes after which a warning is shown
+    pub(crate) warning_mb: usize,
+    /// Estimated usage in megabytes after which new fetches are refused
+    pub(crate) cap_mb: usize,
+    /// Fetches of more nodes at once are cut down to this number
+    pub(crate) max_nodes_per_fetch: u16,
+    /// Unload subtrees that are neither shown nor pinned once usage is over
+    /// the warning level
+    #[serde(default)]
+    pub(crate) evict_hidden: bool,
+}
+
+impl Default for DataBudget {
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(target_arch = "wasm32"),
+            warning_mb: 256,
+            cap_mb: 512,
+            max_nodes_per_fetch: 1000,
+            evict_hidden: false,
+        }
+    }
+}
+
+/// What to do with a fetch request according to the budget
+pub(crate) enum BudgetCheck {
+    Allow,
+    /// Path query limit was cut down to fit the budget
+    Chunked,
+    Refuse,
+}
+
+impl DataBudget {
+    pub(crate) fn persist(&self, storage: &mut dyn Storage) {
+        if let Ok(s) = serde_json::to_string(self) {
+            storage.set_string(DATA_BUDGET_KEY, s);
+        }
+    }
+
+    pub(crate) fn restore(storage: Option<&dyn Storage>) -> Self {
+        storage
+            .and_then(|s| s.get_string(DATA_BUDGET_KEY))
+            .and_then(|param| serde_json::from_str(&param).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn is_over_warning(&self, usage: usize) -> bool {
+        self.enabled && usage > self.warning_mb * MB
+    }
+
+    pub(crate) fn is_over_cap(&self, usage: usize) -> bool {
+        self.enabled && usage > self.cap_mb * MB
+    }
+
+    /// Number of bytes to free by unloading hidden subtrees, if eviction is
+    /// enabled and usage is over the warning level
+    pub(crate) fn eviction_target(&self, usage: usize) -> Option<usize> {
+        (self.evict_hidden && self.is_over_warning(usage)).then(|| usage - self.warning_mb * MB)
+    }
+
+    /// Checks a path query against the budget, limiting it in place if needed
+    pub(crate) fn check_path_query(&self, usage: usize, path_query: &mut PathQuery) -> BudgetCheck {
+        if self.is_over_cap(usage) {
+            return BudgetCheck::Refuse;
+        }
+        if !self.enabled {
+            return BudgetCheck::Allow;
+        }
+
+        let limit = self.effective_limit(path_query.query.limit);
+        if limit != path_query.query.limit {
+            path_query.query.limit = limit;
+            BudgetCheck::Chunked
+        } else {
+            BudgetCheck::Allow
+        }
+    }
+
+    /// Limit a fetch ends up with after the budget check
+    pub(crate) fn effective_limit(&self, limit: Option<u16>) -> Option<u16> {
+        if !self.enabled {
+            return limit;
+        }
+        Some(
+            limit
+                .map(|l| l.min(self.max_nodes_per_fetch))
+                .unwrap_or(self.max_nodes_per_fetch),
+        )
+    }
+
+    /// Draw budget settings
+    pub(crate) fn draw(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Limit loaded data");
+        ui.add_enabled_ui(self.enabled, |ui| {
+            egui::Grid::new("data_budget").num_columns(2).show(ui, |grid| {
+                grid.label("Warning at (MB):");
+                grid.add(DragValue::new(&mut self.warning_mb).range(1..=self.cap_mb));
+                grid.end_row();
+
+                grid.label("Refuse fetches at (MB):");
+                grid.add(DragValue::new(&mut self.cap_mb).range(self.warning_mb..=16 * 1024));
+                grid.end_row();
+
+                grid.label("Max nodes per fetch:");
+                grid.add(DragValue::new(&mut self.max_nodes_per_fetch).range(1..=u16::MAX));
+                grid.end_row();
+            });
+            ui.checkbox(
+                &mut self.evict_hidden,
+                "Unload hidden subtrees over the warning level",
+            )
+            .on_hover_text(
+                "Subtrees that are neither shown nor pinned are unloaded, least recently fetched first",
+            );
+        });
+    }
+
+    /// Draw estimated usage, highlighted once over the warning level
+    pub(crate) fn draw_usage(&self, ui: &mut egui::Ui, usage: usize) {
+        if !self.enabled {
+            return;
+        }
+
+        let text = format!(
+            "{} {:.1} / {} MB",
+            egui_phosphor::regular::MEMORY,
+            usage as f64 / MB as f64,
+            self.cap_mb
+        );
+        if self.is_over_warning(usage) {
+            ui.label(RichText::new(text).color(input_error_color(ui.ctx())))
+                .on_hover_text(if self.is_over_cap(usage) {
+                    "Loaded data is over the budget, new fetches are refused. Clear some subtrees to continue"
+                } else {
+                    "Loaded data is close to the budget"
+                });
+        } else {
+            ui.label(text).on_hover_text("Estimated size of loaded data");
+        }
+    }
+}
+
+/// Estimated memory used by loaded elements in bytes
+pub(crate) fn estimate_usage(tree_data: &TreeData) -> usize {
+    tree_data.usage()
+}
+
+/// Estimated memory used by a subtree's elements and view state in bytes
+pub(crate) fn subtree_usage(subtree: &SubtreeData) -> usize {
+    let elements: usize = subtree
+        .elements
+        .iter()
+        .map(|(key, element)| element_usage(key, &element.value))
+        .sum();
+    let keys = |keys: &BTreeSet<Key>| keys.iter().map(|k| KEY_OVERHEAD + k.len()).sum::<usize>();
+    let referrers: usize = subtree
+        .back_references
+        .values()
+        .flatten()
+        .map(|(path, key)| KEY_OVERHEAD + key.len() + path.iter().map(Vec::len).sum::<usize>())
+        .sum();
+
+    elements
+        + keys(&subtree.subtree_keys)
+        + keys(&subtree.visible_keys)
+        + keys(&subtree.selected_keys)
+        + referrers
+}
+
+/// Estimated memory used by an element view in bytes
+pub(crate) fn element_usage(key: &[u8], value: &ElementOrPlaceholder) -> usize {
+    ELEMENT_OVERHEAD + key.len() + value_size(value)
+}
+
+fn value_size(value: &ElementOrPlaceholder) -> usize {
+    match value {
+        ElementOrPlaceholder::Element(Element::Item { value, element_flags }) => {
+            value.len() + element_flags.as_ref().map(Vec::len).unwrap_or_default()
+        }
+        _ => 0,
+    }
+}
+
+/// Human readable size in bytes
+pub(crate) fn format_size(bytes: usize) -> String {
+    if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.1} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{bytes} B")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_formatted_by_magnitude() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * MB), "3.0 MB");
+    }
+}
@@ -1,20 +1,95 @@
 //! Visualizer subsystem dedicated to simplify interactions between loosely
 //! coupled components.
 
-use std::{cell::RefCell, collections::VecDeque};
+use std::{
+    cell::{Cell, RefCell, RefMut},
+    collections::VecDeque,
+};
 
-use grovedbg_types::{Key, SessionId};
+use eframe::egui::{self, DragValue};
+use grovedbg_types::{CryptoHash, Key, PathQuery, Query, QueryItem, SessionId, SizedQuery, SubqueryBranch};
+use tokio::sync::mpsc::error::TrySendError;
 
 use crate::{
+    access_heatmap::AccessCounts,
+    budget::{BudgetCheck, DataBudget},
+    command_palette::PaletteCommand,
+    notes::NoteTarget,
     path_ctx::Path,
-    protocol::{FetchCommand, ProtocolCommand},
+    protocol::{Capability, EndpointInfo, FetchCommand, HashLookupKind, ProtocolCommand},
+    script::Recorder,
     ProtocolSender,
 };
 
+/// Minimal interval in seconds between two throttled fetch commands
+const THROTTLE_INTERVAL: f64 = 0.05;
+pub(crate) const DEFAULT_MAX_IN_FLIGHT: usize = 4;
+/// Interval in seconds between root hash checks in follow mode
+const FOLLOW_INTERVAL: f64 = 2.;
+/// Default interval in seconds between keep-alive requests of an idle session
+pub(crate) const DEFAULT_KEEP_ALIVE_INTERVAL: u32 = 60;
+/// Limit forced on unbounded queries while expensive operations are guarded
+pub(crate) const GUARDED_LIMIT: u16 = 100;
+
+/// Path and key of a single node fetch
+type NodeId = (Vec<Vec<u8>>, Key);
+
+/// What an in-flight command is kept track of for
+enum Tracked {
+    /// Fetch of a single node, to not fetch it twice
+    Node(NodeId),
+    /// Fetch of several nodes at once, for the same reason
+    Nodes(Vec<NodeId>),
+    /// Refetch of a subtree, its changes are computed once it's processed
+    Refetch(Vec<Vec<u8>>),
+}
+
 pub(crate) struct CommandBus<'pa> {
     session: RefCell<Option<SessionId>>,
+    comparison_session: RefCell<Option<SessionId>>,
     protocol_sender: ProtocolSender,
     actions_queue: RefCell<VecDeque<UserAction<'pa>>>,
+    /// Fetch commands with whether they refetch a subtree
+    throttled_queue: RefCell<VecDeque<(FetchCommand, bool)>>,
+    last_throttled_time: Cell<f64>,
+    budget: RefCell<DataBudget>,
+    /// Estimated size of loaded data in bytes
+    data_usage: Cell<usize>,
+    /// Commands sent to the protocol thread and not processed yet, oldest
+    /// first
+    in_flight: RefCell<VecDeque<Option<Tracked>>>,
+    /// Fetch commands waiting for a free in-flight slot, with whether they
+    /// refetch a subtree
+    waiting: RefCell<VecDeque<(FetchCommand, bool)>>,
+    /// Session and other non-fetch commands waiting for a free in-flight
+    /// slot, sent ahead of waiting fetches
+    outgoing: RefCell<VecDeque<(ProtocolCommand, Option<Tracked>)>>,
+    /// Commands passed to the protocol task at once, never more than its
+    /// commands channel holds so sending doesn't block the UI
+    max_in_flight: Cell<usize>,
+    /// Fetch root nodes of child subtrees of shown subtrees in advance
+    prefetch_child_roots: Cell<bool>,
+    /// Shown subtrees or their nodes changed since the last prefetch
+    prefetch_due: Cell<bool>,
+    endpoint_info: RefCell<Option<EndpointInfo>>,
+    /// Switch to a new session once GroveDB root hash changes
+    follow: Cell<bool>,
+    last_follow_time: Cell<f64>,
+    /// Seconds between keep-alive requests of an idle session, zero disables
+    /// them
+    keep_alive_interval: Cell<u32>,
+    last_keep_alive_time: Cell<f64>,
+    /// Force a limit on queries that may scan whole subtrees, to not put load
+    /// on shared endpoints by accident
+    guard_expensive: Cell<bool>,
+    /// Expensive operations were explicitly allowed for the current session
+    unlocked: Cell<bool>,
+    /// The next received proof is verified against the session root hash
+    verify_next_proof: Cell<bool>,
+    /// Fetches and focus changes per path since the session was started
+    access: AccessCounts,
+    /// Navigation script being recorded
+    recorder: Recorder,
 }
 
 #[derive(Clone)]
@@ -22,46 +97,611 @@ pub(crate) enum UserAction<'pa> {
     FocusSubtree(Path<'pa>),
     FocusSubtreeKey(Path<'pa>, Key),
     DropFocus,
+    /// Open the split pane focused on the subtree
+    FocusSplitSubtree(Path<'pa>, Option<Key>),
+    DropSplitFocus,
     SelectMerkView(Path<'pa>),
+    NavigateBack(usize),
+    NavigateForward(usize),
+    ExpandSubtrees(Path<'pa>, usize),
+    CollapseSubtrees(Path<'pa>),
+    QuerySelectedKeys(Path<'pa>, Vec<QueryItem>),
+    /// Prefill the query builder with a single key of the subtree and limit 1
+    QueryKey(Path<'pa>, Key),
+    /// Attach a query template to the active profile's entry of the path
+    SetDefaultQuery(Path<'pa>, Option<SizedQuery>),
+    /// Add a top panel shortcut for the path to the active profile
+    AddShortcut(Path<'pa>, Option<SizedQuery>),
+    /// Recompute hashes of a fetched node locally
+    VerifyNodeHash(Path<'pa>, Key),
+    /// Open the what-if sandbox for an item
+    OpenWhatIf(Path<'pa>, Key),
+    /// Show the notes panel editing the note of the target
+    OpenNote(NoteTarget),
+    /// Command chosen in the command palette
+    RunCommand(PaletteCommand),
 }
 
 impl<'pa> CommandBus<'pa> {
-    pub(crate) fn new(protocol_sender: ProtocolSender) -> Self {
+    pub(crate) fn new(
+        protocol_sender: ProtocolSender,
+        budget: DataBudget,
+        max_in_flight: usize,
+        prefetch_child_roots: bool,
+        guard_expensive: bool,
+        keep_alive_interval: u32,
+    ) -> Self {
         Self {
             session: Default::default(),
+            comparison_session: Default::default(),
             protocol_sender,
             actions_queue: Default::default(),
+            throttled_queue: Default::default(),
+            last_throttled_time: Default::default(),
+            budget: RefCell::new(budget),
+            data_usage: Default::default(),
+            in_flight: Default::default(),
+            waiting: Default::default(),
+            outgoing: Default::default(),
+            max_in_flight: Cell::new(max_in_flight.clamp(1, protocol_sender.max_capacity())),
+            prefetch_child_roots: Cell::new(prefetch_child_roots),
+            prefetch_due: Cell::new(true),
+            endpoint_info: Default::default(),
+            follow: Default::default(),
+            last_follow_time: Default::default(),
+            keep_alive_interval: Cell::new(keep_alive_interval),
+            last_keep_alive_time: Default::default(),
+            guard_expensive: Cell::new(guard_expensive),
+            unlocked: Default::default(),
+            verify_next_proof: Default::default(),
+            access: Default::default(),
+            recorder: Default::default(),
+        }
+    }
+
+    pub(crate) fn budget(&self) -> RefMut<DataBudget> {
+        self.budget.borrow_mut()
+    }
+
+    pub(crate) fn data_usage(&self) -> usize {
+        self.data_usage.get()
+    }
+
+    pub(crate) fn set_data_usage(&self, usage: usize) {
+        self.data_usage.set(usage);
+    }
+
+    /// Checks a fetch command against the data budget, oversized path queries
+    /// are cut down in place. Returns `false` if the command shall not be sent.
+    fn check_budget(&self, command: &mut FetchCommand) -> bool {
+        let budget = self.budget.borrow();
+        let usage = self.data_usage.get();
+
+        let check = match command {
+            FetchCommand::FetchRoot => BudgetCheck::Allow,
+            FetchCommand::FetchWithPathQuery { path_query } => budget.check_path_query(usage, path_query),
+            FetchCommand::FetchWithPathQueries { path_queries } => {
+                let mut check = BudgetCheck::Allow;
+                for path_query in path_queries.iter_mut() {
+                    match budget.check_path_query(usage, path_query) {
+                        BudgetCheck::Allow => {}
+                        BudgetCheck::Chunked => check = BudgetCheck::Chunked,
+                        BudgetCheck::Refuse => {
+                            check = BudgetCheck::Refuse;
+                            break;
+                        }
+                    }
+                }
+                check
+            }
+            FetchCommand::FetchNode { .. }
+            | FetchCommand::FetchNodes { .. }
+            | FetchCommand::FetchMerkLevels { .. }
+            | FetchCommand::FetchRawNode { .. }
+            | FetchCommand::CountSubtree { .. }
+            | FetchCommand::FindNodeByHash { .. }
+            | FetchCommand::FetchReferrers { .. }
+            | FetchCommand::ProvePathQuery { .. } => {
+                if budget.is_over_cap(usage) {
+                    BudgetCheck::Refuse
+                } else {
+                    BudgetCheck::Allow
+                }
+            }
+        };
+
+        match check {
+            BudgetCheck::Allow => true,
+            BudgetCheck::Chunked => {
+                log::warn!(
+                    "Fetch was limited to {} nodes to fit the data budget",
+                    budget.max_nodes_per_fetch
+                );
+                true
+            }
+            BudgetCheck::Refuse => {
+                log::warn!("Loaded data is over the budget, clear some subtrees to fetch more");
+                false
+            }
+        }
+    }
+
+    /// Forces a limit on path queries that could return a whole subtree while
+    /// expensive operations are guarded
+    fn apply_guard(&self, command: &mut FetchCommand) {
+        if !self.is_guarded() {
+            return;
+        }
+        let path_queries = match command {
+            FetchCommand::FetchWithPathQuery { path_query } | FetchCommand::ProvePathQuery { path_query } => {
+                std::slice::from_mut(path_query)
+            }
+            FetchCommand::FetchWithPathQueries { path_queries } => path_queries.as_mut_slice(),
+            _ => return,
+        };
+        for path_query in path_queries
+            .iter_mut()
+            .filter(|path_query| is_unbounded(path_query))
+        {
+            path_query.query.limit = Some(GUARDED_LIMIT);
+            log::warn!(
+                "Unbounded query was limited to {GUARDED_LIMIT} elements, unlock expensive operations for \
+                 the session to lift it"
+            );
         }
     }
 
+    pub(crate) fn guard_expensive(&self) -> bool {
+        self.guard_expensive.get()
+    }
+
+    /// Unbounded queries are limited right now
+    pub(crate) fn is_guarded(&self) -> bool {
+        self.guard_expensive.get() && !self.unlocked.get()
+    }
+
+    /// Limit a subtree fetch ends up with after the guard and budget checks
+    pub(crate) fn effective_limit(&self, limit: Option<u16>) -> Option<u16> {
+        let limit = if self.is_guarded() {
+            limit.or(Some(GUARDED_LIMIT))
+        } else {
+            limit
+        };
+        self.budget.borrow().effective_limit(limit)
+    }
+
+    /// Draws the lock allowing expensive operations for the current session,
+    /// nothing is shown if the guard is disabled
+    pub(crate) fn draw_guard_lock(&self, ui: &mut egui::Ui) {
+        if !self.guard_expensive.get() {
+            return;
+        }
+        if self.unlocked.get() {
+            if ui
+                .button(egui_phosphor::regular::LOCK_OPEN)
+                .on_hover_text("Expensive operations are allowed for this session, click to guard them again")
+                .clicked()
+            {
+                self.unlocked.set(false);
+            }
+        } else if ui
+            .button(egui_phosphor::regular::LOCK)
+            .on_hover_text(format!(
+                "Unbounded queries are limited to {GUARDED_LIMIT} elements, click to allow them for this \
+                 session"
+            ))
+            .clicked()
+        {
+            self.unlocked.set(true);
+        }
+    }
+
+    pub(crate) fn max_in_flight(&self) -> usize {
+        self.max_in_flight.get()
+    }
+
+    pub(crate) fn prefetch_child_roots(&self) -> bool {
+        self.prefetch_child_roots.get()
+    }
+
+    /// Whether child subtree roots should be prefetched now, once per change
+    /// of shown subtrees
+    pub(crate) fn take_prefetch_due(&self) -> bool {
+        self.prefetch_child_roots.get() && self.prefetch_due.replace(false)
+    }
+
+    /// Schedules a prefetch of child subtree roots, used when nodes arrive or
+    /// a subtree is shown
+    pub(crate) fn request_prefetch(&self) {
+        self.prefetch_due.set(true);
+    }
+
+    /// Draw fetch queue settings
+    pub(crate) fn draw_fetch_settings(&self, ui: &mut egui::Ui) {
+        let mut max_in_flight = self.max_in_flight.get();
+        ui.horizontal(|line| {
+            line.label("Max requests in flight:");
+            line.add(DragValue::new(&mut max_in_flight).range(1..=self.protocol_sender.max_capacity()))
+                .on_hover_text("Limited by the capacity of the protocol task's commands channel");
+        });
+        if max_in_flight != self.max_in_flight.get() {
+            self.max_in_flight.set(max_in_flight);
+            self.dispatch();
+        }
+
+        let mut prefetch_child_roots = self.prefetch_child_roots.get();
+        ui.checkbox(&mut prefetch_child_roots, "Prefetch child subtree roots")
+            .on_hover_text("Fetch root nodes of child subtrees of shown subtrees to see one level ahead");
+        if self.prefetch_child_roots.replace(prefetch_child_roots) != prefetch_child_roots {
+            self.request_prefetch();
+        }
+
+        let mut guard_expensive = self.guard_expensive.get();
+        ui.checkbox(&mut guard_expensive, "Guard expensive operations")
+            .on_hover_text(format!(
+                "Limit whole subtree fetches and queries without a limit to {GUARDED_LIMIT} elements unless \
+                 unlocked for the session"
+            ));
+        self.guard_expensive.set(guard_expensive);
+
+        let mut keep_alive_interval = self.keep_alive_interval.get();
+        ui.horizontal(|line| {
+            line.label("Keep session alive every, s:");
+            line.add(DragValue::new(&mut keep_alive_interval).range(0..=3600))
+                .on_hover_text(
+                    "Ping GroveDB while idle so it doesn't expire the session, zero disables pings",
+                );
+        });
+        self.keep_alive_interval.set(keep_alive_interval);
+    }
+
+    pub(crate) fn keep_alive_interval(&self) -> u32 {
+        self.keep_alive_interval.get()
+    }
+
+    /// Number of fetch commands waiting to be sent
+    pub(crate) fn waiting_fetches(&self) -> usize {
+        self.waiting.borrow().len()
+    }
+
+    /// No commands are waiting or being processed
+    pub(crate) fn is_idle(&self) -> bool {
+        self.in_flight.borrow().is_empty()
+            && self.waiting.borrow().is_empty()
+            && self.outgoing.borrow().is_empty()
+    }
+
+    /// Queues a command to the protocol thread, it is sent once there is a free
+    /// in-flight slot and kept track of until [Self::command_processed] is
+    /// called
+    fn send_protocol_command(&self, command: ProtocolCommand, tracked: Option<Tracked>) {
+        self.outgoing.borrow_mut().push_back((command, tracked));
+        self.dispatch();
+    }
+
+    /// Passes a command to the protocol thread without waiting, returns it
+    /// back if the commands channel is full
+    fn try_send(
+        &self,
+        command: ProtocolCommand,
+        tracked: Option<Tracked>,
+    ) -> Result<(), (ProtocolCommand, Option<Tracked>)> {
+        match self.protocol_sender.try_send(command) {
+            Ok(_) => {
+                self.in_flight.borrow_mut().push_back(tracked);
+                Ok(())
+            }
+            Err(TrySendError::Full(command)) => Err((command, tracked)),
+            Err(TrySendError::Closed(_)) => {
+                log::error!("Unable to reach GroveDBG protocol thread");
+                Ok(())
+            }
+        }
+    }
+
+    /// Marks the oldest in-flight command as processed, sending waiting fetches
+    /// if there is a free slot now. Returns the subtree path if the command
+    /// was a refetch.
+    pub(crate) fn command_processed(&self) -> Option<Vec<Vec<u8>>> {
+        let processed = self.in_flight.borrow_mut().pop_front().flatten();
+        self.dispatch();
+        match processed {
+            Some(Tracked::Refetch(path)) => Some(path),
+            _ => None,
+        }
+    }
+
+    fn dispatch(&self) {
+        while self.in_flight.borrow().len() < self.max_in_flight.get() {
+            let outgoing = self.outgoing.borrow_mut().pop_front();
+            if let Some((command, tracked)) = outgoing {
+                if let Err(returned) = self.try_send(command, tracked) {
+                    self.outgoing.borrow_mut().push_front(returned);
+                    break;
+                }
+                continue;
+            }
+
+            let Some((command, refetch)) = self.waiting.borrow_mut().pop_front() else {
+                break;
+            };
+            let Some(session_id) = *self.session.borrow() else {
+                log::warn!("Need to start a session first");
+                continue;
+            };
+            let tracked = match &command {
+                FetchCommand::FetchNode { path, key } => Some(Tracked::Node((path.clone(), key.clone()))),
+                FetchCommand::FetchNodes { nodes } => Some(Tracked::Nodes(nodes.clone())),
+                FetchCommand::FetchWithPathQuery { path_query } if refetch => {
+                    Some(Tracked::Refetch(path_query.path.clone()))
+                }
+                _ => None,
+            };
+            if let Err((ProtocolCommand::Fetch { command, .. }, _)) =
+                self.try_send(ProtocolCommand::Fetch { session_id, command }, tracked)
+            {
+                self.waiting.borrow_mut().push_front((command, refetch));
+                break;
+            }
+        }
+    }
+
+    /// Checks if the same node is already going to be fetched
+    pub(crate) fn is_node_pending(&self, path: &[Vec<u8>], key: &[u8]) -> bool {
+        let is_node = |(p, k): &NodeId| p == path && k == key;
+        self.in_flight
+            .borrow()
+            .iter()
+            .flatten()
+            .any(|tracked| match tracked {
+                Tracked::Node(node) => is_node(node),
+                Tracked::Nodes(nodes) => nodes.iter().any(is_node),
+                Tracked::Refetch(_) => false,
+            })
+            || self.waiting.borrow().iter().any(|(c, _)| match c {
+                FetchCommand::FetchNode { path: p, key: k } => p == path && k == key,
+                FetchCommand::FetchNodes { nodes } => nodes.iter().any(is_node),
+                _ => false,
+            })
+    }
+
     pub(crate) fn new_session(&self) {
-        let _ = self
-            .protocol_sender
-            .blocking_send(ProtocolCommand::NewSession {
+        // Waiting commands belong to the old session
+        self.waiting.borrow_mut().clear();
+        self.send_protocol_command(
+            ProtocolCommand::NewSession {
                 old_session: self.session.take(),
-            })
-            .inspect_err(|_| log::error!("Unable to reach GroveDBG protocol thread"));
+            },
+            None,
+        );
+    }
+
+    /// Replaces a session GroveDB doesn't know anymore, there is nothing to
+    /// drop. Further failures of the same session are ignored until the new
+    /// one arrives.
+    pub(crate) fn renew_expired_session(&self) {
+        if self.session.take().is_none() {
+            return;
+        }
+        self.waiting.borrow_mut().clear();
+        self.send_protocol_command(ProtocolCommand::NewSession { old_session: None }, None);
+        // The kept session expires along with the current one, so comparing
+        // goes on against a new one
+        if self.comparison_session.take().is_some() {
+            self.send_protocol_command(ProtocolCommand::NewComparisonSession, None);
+        }
+    }
+
+    /// Starts a new session, but keeps the current one alive to compare nodes
+    /// against it. A session kept this way before is dropped.
+    pub(crate) fn new_session_keep_current(&self) {
+        let current = self.session.take();
+        let old_session = if current.is_some() {
+            self.comparison_session.replace(current)
+        } else {
+            None
+        };
+        self.waiting.borrow_mut().clear();
+        self.send_protocol_command(ProtocolCommand::NewSession { old_session }, None);
     }
 
-    pub(crate) fn set_session(&self, session_id: SessionId) {
+    /// Drops the session kept to compare nodes against
+    pub(crate) fn stop_comparison(&self) {
+        if let Some(session_id) = self.comparison_session.take() {
+            self.send_protocol_command(ProtocolCommand::DropSession { session_id }, None);
+        }
+    }
+
+    pub(crate) fn set_comparison_session(&self, session_id: SessionId) {
+        *self.comparison_session.borrow_mut() = Some(session_id);
+    }
+
+    /// Draws the follow mode toggle
+    pub(crate) fn draw_follow_toggle(&self, ui: &mut egui::Ui) {
+        let mut follow = self.follow.get();
+        ui.toggle_value(
+            &mut follow,
+            format!("{} Follow chain", egui_phosphor::regular::BROADCAST),
+        )
+        .on_hover_text(
+            "Poll GroveDB root hash and switch to a new session refetching shown subtrees once it changes",
+        );
+        self.follow.set(follow);
+    }
+
+    /// Checks GroveDB root hash in follow mode once the interval has passed
+    /// and nothing else is being fetched
+    pub(crate) fn process_follow(&self, time: f64) {
+        if !self.follow.get() || !self.is_idle() || time - self.last_follow_time.get() < FOLLOW_INTERVAL {
+            return;
+        }
+        let Some(session_id) = *self.session.borrow() else {
+            return;
+        };
+        self.last_follow_time.set(time);
+        self.send_protocol_command(ProtocolCommand::Follow { session_id }, None);
+    }
+
+    /// Pings GroveDB within the session once the keep-alive interval has
+    /// passed with nothing being fetched, follow mode keeps the session busy
+    /// on its own
+    pub(crate) fn process_keep_alive(&self, time: f64) {
+        let interval = self.keep_alive_interval.get();
+        if interval == 0 || self.follow.get() || !self.is_idle() {
+            self.last_keep_alive_time.set(time);
+            return;
+        }
+        if time - self.last_keep_alive_time.get() < interval as f64 {
+            return;
+        }
+        let Some(session_id) = *self.session.borrow() else {
+            return;
+        };
+        self.last_keep_alive_time.set(time);
+        self.send_protocol_command(ProtocolCommand::KeepAlive { session_id }, None);
+    }
+
+    /// Whether the received proof was requested to be verified, resets the
+    /// request
+    pub(crate) fn take_proof_verification(&self) -> bool {
+        self.verify_next_proof.replace(false)
+    }
+
+    /// Accesses of subtrees since the session was started, follow mode keeps
+    /// counting over new sessions
+    pub(crate) fn access(&self) -> &AccessCounts {
+        &self.access
+    }
+
+    pub(crate) fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+
+    pub(crate) fn session(&self) -> Option<SessionId> {
+        *self.session.borrow()
+    }
+
+    pub(crate) fn comparison_session(&self) -> Option<SessionId> {
+        *self.comparison_session.borrow()
+    }
+
+    pub(crate) fn set_session(&self, session_id: SessionId, endpoint_info: Option<EndpointInfo>) {
+        *self.session.borrow_mut() = Some(session_id);
+        self.unlocked.set(false);
+        self.access.clear();
+        *self.endpoint_info.borrow_mut() = endpoint_info;
+    }
+
+    /// Replaces the session with one of the same endpoint, an unlock of
+    /// expensive operations carries over
+    pub(crate) fn follow_session(&self, session_id: SessionId) {
         *self.session.borrow_mut() = Some(session_id);
     }
 
+    pub(crate) fn endpoint_info(&self) -> Option<EndpointInfo> {
+        self.endpoint_info.borrow().clone()
+    }
+
+    /// Endpoints that don't advertise capabilities are assumed to support
+    /// only what GroveDBG used before the capabilities were introduced
+    pub(crate) fn supports(&self, capability: Capability) -> bool {
+        self.endpoint_info
+            .borrow()
+            .as_ref()
+            .map(|info| info.capabilities.contains(capability.as_ref()))
+            .unwrap_or(capability.is_legacy())
+    }
+
+    /// Starts building a fetch request, see [FetchBuilder].
+    pub(crate) fn fetch(&self) -> FetchBuilder<'_, 'pa> {
+        FetchBuilder {
+            bus: self,
+            throttled: false,
+            refetch: false,
+        }
+    }
+
+    /// Queues a fetch command to be sent once there is a free in-flight slot,
+    /// fetches of a node that is already going to be fetched are dropped
     pub(crate) fn fetch_command(&self, command: FetchCommand) {
-        if let Some(session_id) = self.session.borrow().as_ref() {
-            let _ = self
-                .protocol_sender
-                .blocking_send(ProtocolCommand::Fetch {
-                    session_id: *session_id,
-                    command,
-                })
-                .inspect_err(|_| log::error!("Unable to reach GroveDBG protocol thread"));
-        } else {
+        self.queue_fetch(command, false);
+    }
+
+    /// Returns `false` if the command was refused or the same node is already
+    /// going to be fetched
+    fn queue_fetch(&self, mut command: FetchCommand, refetch: bool) -> bool {
+        self.apply_guard(&mut command);
+        if !self.check_budget(&mut command) {
+            return false;
+        }
+        if self.session.borrow().is_none() {
             log::warn!("Need to start a session first");
+            return false;
+        }
+        if let FetchCommand::FetchNode { path, key } = &command {
+            if self.is_node_pending(path, key) {
+                return false;
+            }
+        }
+        self.access.record_command(&command);
+        self.recorder.record_command(&command);
+        self.waiting.borrow_mut().push_back((command, refetch));
+        self.dispatch();
+        true
+    }
+
+    pub(crate) fn fetch_comparison(&self, path: Vec<Vec<u8>>, key: Key) {
+        match (*self.session.borrow(), self.comparison_session()) {
+            (Some(session_id), Some(other_session_id)) => {
+                self.send_protocol_command(
+                    ProtocolCommand::FetchComparison {
+                        session_id,
+                        other_session_id,
+                        path,
+                        key,
+                    },
+                    None,
+                );
+            }
+            (None, _) => log::warn!("Need to start a session first"),
+            (_, None) => log::warn!("No session was kept for comparison"),
+        }
+    }
+
+    /// Queues a fetch command to be sent later by [Self::process_throttled],
+    /// used for bulk operations to not flood GroveDB endpoint.
+    fn fetch_command_throttled(&self, command: FetchCommand, refetch: bool) {
+        self.throttled_queue.borrow_mut().push_back((command, refetch));
+    }
+
+    /// Sends the next throttled fetch command if enough time has passed since
+    /// the previous one, `time` is in seconds. Returns `true` if there are
+    /// commands left in the queue.
+    pub(crate) fn process_throttled(&self, time: f64) -> bool {
+        if time - self.last_throttled_time.get() >= THROTTLE_INTERVAL {
+            let command = self.throttled_queue.borrow_mut().pop_front();
+            if let Some((command, refetch)) = command {
+                self.queue_fetch(command, refetch);
+                self.last_throttled_time.set(time);
+            }
         }
+        !self.throttled_queue.borrow().is_empty()
     }
 
     pub(crate) fn user_action(&self, action: UserAction<'pa>) {
+        match &action {
+            UserAction::FocusSubtree(path)
+            | UserAction::FocusSubtreeKey(path, _)
+            | UserAction::FocusSplitSubtree(path, _) => self.access.record(&path.to_vec()),
+            _ => {}
+        }
+        match &action {
+            UserAction::FocusSubtree(path) => self.recorder.record_focus(path.to_vec(), None),
+            UserAction::FocusSubtreeKey(path, key) => {
+                self.recorder.record_focus(path.to_vec(), Some(key.clone()))
+            }
+            _ => {}
+        }
         self.actions_queue.borrow_mut().push_back(action);
     }
 
@@ -73,3 +713,226 @@ impl<'pa> CommandBus<'pa> {
         }
     }
 }
+
+/// A path query without a limit that may return more than its listed keys
+fn is_unbounded(path_query: &PathQuery) -> bool {
+    let query = &path_query.query.query;
+    path_query.query.limit.is_none()
+        && (query.items.iter().any(|item| !matches!(item, QueryItem::Key(_)))
+            || query.default_subquery_branch.subquery.is_some()
+            || !query.conditional_subquery_branches.is_empty())
+}
+
+/// Typed layer on top of [FetchCommand] construction, the request is sent once
+/// finished, like `bus.fetch().subtree(path).limit(100).send()`.
+pub(crate) struct FetchBuilder<'b, 'pa> {
+    bus: &'b CommandBus<'pa>,
+    throttled: bool,
+    refetch: bool,
+}
+
+impl<'b, 'pa> FetchBuilder<'b, 'pa> {
+    /// Send the request through the throttled queue, see
+    /// [CommandBus::fetch_command_throttled].
+    pub(crate) fn throttled(mut self) -> Self {
+        self.throttled = true;
+        self
+    }
+
+    /// Returns `true` if the command was queued
+    fn send(self, command: FetchCommand) -> bool {
+        if self.throttled {
+            self.bus.fetch_command_throttled(command, self.refetch);
+            true
+        } else {
+            self.bus.queue_fetch(command, self.refetch)
+        }
+    }
+
+    /// Fetch GroveDB root node
+    pub(crate) fn root(self) {
+        self.send(FetchCommand::FetchRoot);
+    }
+
+    /// Fetch a single node by its path and key
+    pub(crate) fn node(self, path: Path<'_>, key: Key) {
+        let path = path.to_vec();
+        self.send(FetchCommand::FetchNode { path, key });
+    }
+
+    /// Fetch stored bytes of a node's element for inspection
+    pub(crate) fn raw_node(self, path: Path<'_>, key: Key) {
+        let path = path.to_vec();
+        self.send(FetchCommand::FetchRawNode { path, key });
+    }
+
+    /// Request the number of elements in the subtree from the endpoint, only
+    /// if it advertises counting
+    pub(crate) fn subtree_count(self, path: Path<'_>) {
+        if !self.bus.supports(Capability::CountSubtree) {
+            log::warn!("GroveDB endpoint doesn't count subtree elements");
+            return;
+        }
+        let path = path.to_vec();
+        self.send(FetchCommand::CountSubtree { path });
+    }
+
+    /// Ask the endpoint for a node by its hash, wherever it is in the session,
+    /// only if it advertises a hash index
+    pub(crate) fn node_by_hash(self, hash: CryptoHash, kind: HashLookupKind) {
+        if !self.bus.supports(Capability::FindNodeByHash) {
+            log::warn!("GroveDB endpoint doesn't look nodes up by hash");
+            return;
+        }
+        self.send(FetchCommand::FindNodeByHash { hash, kind });
+    }
+
+    /// Fetch elements with references pointing at the node, their subtrees
+    /// are loaded along
+    pub(crate) fn referrers(self, path: Path<'_>, key: Key) {
+        let path = path.to_vec();
+        self.send(FetchCommand::FetchReferrers { path, key });
+    }
+
+    /// Fetch several nodes at once, the protocol task fetches them
+    /// concurrently
+    pub(crate) fn nodes(self, nodes: impl IntoIterator<Item = (Path<'pa>, Key)>) {
+        let nodes: Vec<_> = nodes
+            .into_iter()
+            .map(|(path, key)| (path.to_vec(), key))
+            .collect();
+        if !nodes.is_empty() {
+            self.send(FetchCommand::FetchNodes { nodes });
+        }
+    }
+
+    /// Fetch the upper `levels` levels of a subtree's Merk tree starting from
+    /// its root node
+    pub(crate) fn merk_levels(self, path: Path<'_>, root_key: Key, levels: u8) {
+        let path = path.to_vec();
+        self.send(FetchCommand::FetchMerkLevels {
+            path,
+            root_key,
+            levels,
+        });
+    }
+
+    /// Fetch elements of a subtree in key order, all of them unless limited
+    pub(crate) fn subtree(self, path: Path<'_>) -> SubtreeFetchBuilder<'b, 'pa> {
+        SubtreeFetchBuilder {
+            fetch: self,
+            path: path.to_vec(),
+            limit: None,
+            left_to_right: true,
+        }
+    }
+
+    /// Fetch nodes with an arbitrary path query, returns `true` if the fetch
+    /// was queued
+    pub(crate) fn path_query(self, path_query: PathQuery) -> bool {
+        self.send(FetchCommand::FetchWithPathQuery { path_query })
+    }
+
+    /// Fetch nodes of several path queries at once, the protocol task runs
+    /// them concurrently
+    pub(crate) fn path_queries(self, path_queries: Vec<PathQuery>) {
+        if !path_queries.is_empty() {
+            self.send(FetchCommand::FetchWithPathQueries { path_queries });
+        }
+    }
+
+    /// Request a proof for a path query
+    pub(crate) fn prove(self, path_query: PathQuery) {
+        self.send(FetchCommand::ProvePathQuery { path_query });
+    }
+
+    /// Request a proof for a path query and verify it against the session
+    /// root hash once received
+    pub(crate) fn prove_and_verify(self, path_query: PathQuery) {
+        self.bus.verify_next_proof.set(true);
+        self.prove(path_query);
+    }
+}
+
+pub(crate) struct SubtreeFetchBuilder<'b, 'pa> {
+    fetch: FetchBuilder<'b, 'pa>,
+    path: Vec<Vec<u8>>,
+    limit: Option<u16>,
+    left_to_right: bool,
+}
+
+impl<'b, 'pa> SubtreeFetchBuilder<'b, 'pa> {
+    pub(crate) fn limit(mut self, limit: u16) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Fetch elements starting from the last key
+    pub(crate) fn reversed(mut self) -> Self {
+        self.left_to_right = false;
+        self
+    }
+
+    /// Report the subtree path with [CommandBus::command_processed] once the
+    /// fetch is processed, to compare the elements with a refetch snapshot
+    pub(crate) fn refetch(mut self) -> Self {
+        self.fetch.refetch = true;
+        self
+    }
+
+    /// Returns `true` if the fetch was queued, the budget or a missing session
+    /// may refuse it
+    pub(crate) fn send(self) -> bool {
+        let path_query = PathQuery {
+            path: self.path,
+            query: SizedQuery {
+                query: Query {
+                    items: vec![QueryItem::RangeFull],
+                    default_subquery_branch: SubqueryBranch {
+                        subquery_path: None,
+                        subquery: None,
+                    },
+                    conditional_subquery_branches: Vec::new(),
+                    left_to_right: self.left_to_right,
+                },
+                limit: self.limit,
+                offset: None,
+            },
+        };
+        self.fetch.path_query(path_query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_query(items: Vec<QueryItem>, limit: Option<u16>) -> PathQuery {
+        PathQuery {
+            path: Vec::new(),
+            query: SizedQuery {
+                query: Query {
+                    items,
+                    default_subquery_branch: SubqueryBranch {
+                        subquery_path: None,
+                        subquery: None,
+                    },
+                    conditional_subquery_branches: Vec::new(),
+                    left_to_right: true,
+                },
+                limit,
+                offset: None,
+            },
+        }
+    }
+
+    #[test]
+    fn only_limitless_ranges_are_unbounded() {
+        assert!(is_unbounded(&path_query(vec![QueryItem::RangeFull], None)));
+        assert!(!is_unbounded(&path_query(vec![QueryItem::RangeFull], Some(10))));
+        assert!(!is_unbounded(&path_query(
+            vec![QueryItem::Key(b"a".to_vec()), QueryItem::Key(b"b".to_vec())],
+            None
+        )));
+    }
+}
@@ -1,16 +1,26 @@
+mod preview;
+mod structured;
+mod version_prefix;
+
 use std::{cell::Cell, fmt::Write, hash::Hash};
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use dpp::serialization::PlatformDeserializable;
-use eframe::egui::{self, Color32, Label, RichText, Sense, TextEdit};
+use eframe::egui::{self, text::LayoutJob, Color32, FontId, Label, RichText, Sense, TextEdit, TextFormat};
 use integer_encoding::VarInt;
+pub(crate) use preview::bytes_preview;
 use serde::{Deserialize, Serialize};
+pub(crate) use structured::bytes_as_structure;
 use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+pub(crate) use version_prefix::VersionPrefix;
 
-use crate::theme::input_error_color;
+use crate::{format, theme::input_error_color};
 
 const MAX_BYTES: usize = 10;
 const MAX_HEX_LENGTH: usize = 32;
 const HEX_PARTS_LENGTH: usize = 12;
+/// Alternative readings of bytes listed in their context menu
+const MAX_ALTERNATIVES: usize = 5;
 
 #[derive(Debug, AsRefStr, EnumIter, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
 pub(crate) enum BytesDisplayVariant {
@@ -21,6 +31,10 @@ pub(crate) enum BytesDisplayVariant {
     String,
     #[strum(serialize = "Hex")]
     Hex,
+    #[strum(serialize = "Base58")]
+    Base58,
+    #[strum(serialize = "Base64")]
+    Base64,
     #[strum(serialize = "Signed integer")]
     SignedInt,
     #[strum(serialize = "Unigned integer")]
@@ -31,16 +45,103 @@ pub(crate) enum BytesDisplayVariant {
     DriveTimestamp,
     #[strum(serialize = "DPP Vote Poll")]
     DppVotePoll,
+    #[strum(serialize = "Image")]
+    Image,
+    #[strum(serialize = "QR code")]
+    QrCode,
+    #[strum(serialize = "Structured guess")]
+    StructuredGuess,
+}
+
+/// Display variant the bytes are plausible as, with a confidence from 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Guess {
+    pub(crate) variant: BytesDisplayVariant,
+    pub(crate) confidence: f32,
 }
 
 impl BytesDisplayVariant {
+    /// The most confident of the cheap guesses, see [Self::guesses]
     pub(crate) fn guess(bytes: &[u8]) -> Self {
-        match bytes.len() {
-            1 => Self::U8,
-            2 | 4 | 8 => Self::SignedInt,
-            32 => Self::Hex,
-            _ => Self::String,
-        }
+        Self::length_guesses(bytes)
+            .first()
+            .map(|guess| guess.variant)
+            .unwrap_or_default()
+    }
+
+    /// Display variants the bytes are plausible as, most confident first.
+    /// Guesses that need to decode the bytes are never more confident than
+    /// the ones by length and content, so the first guess is the one
+    /// [Self::guess] picks.
+    pub(crate) fn guesses(bytes: &[u8]) -> Vec<Guess> {
+        let mut guesses = Self::length_guesses(bytes);
+        let decoded = [
+            (Self::Image, preview::image_format(bytes).is_some(), 0.5),
+            (
+                Self::StructuredGuess,
+                !bytes.is_empty() && bytes_as_structure(bytes).is_some(),
+                0.4,
+            ),
+            (Self::DppVotePoll, bytes_as_dpp_vote_poll(bytes).is_some(), 0.4),
+        ];
+        guesses.extend(
+            decoded
+                .into_iter()
+                .filter(|(_, plausible, _)| *plausible)
+                .map(|(variant, _, confidence)| Guess { variant, confidence }),
+        );
+        guesses.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        guesses
+    }
+
+    /// 32 bytes and longer binary data stay hex by default, base58 for Drive
+    /// identifiers and base64 are offered as alternatives. 8 bytes falling
+    /// into recent years as Drive timestamps are shown as such.
+    fn length_guesses(bytes: &[u8]) -> Vec<Guess> {
+        let len = bytes.len();
+        let utf8 = std::str::from_utf8(bytes).ok();
+        let printable = utf8.is_some_and(|s| !s.is_empty() && !s.chars().any(char::is_control));
+        let plausible_timestamp =
+            drive_timestamp_millis(bytes).is_some_and(format::is_plausible_timestamp_millis);
+        let whole_varint = len > 1 && i64::decode_var(bytes).is_some_and(|(_, read)| read == len);
+        // Strings of lengths typical for integers and identifiers are rather
+        // a coincidence
+        let typical_len = matches!(len, 1 | 2 | 4 | 8 | 32);
+
+        let guesses = [
+            (Self::U8, len == 1, 0.9),
+            (Self::DriveTimestamp, plausible_timestamp, 0.9),
+            (Self::Hex, len == 32, 0.8),
+            (Self::SignedInt, matches!(len, 2 | 4 | 8), 0.7),
+            (Self::String, printable && !typical_len, 0.8),
+            (Self::String, printable && typical_len, 0.6),
+            (Self::Base58, len == 32, 0.55),
+            (
+                Self::UnsignedInt,
+                matches!(len, 2 | 4 | 8) && bytes[0] & 0x80 != 0,
+                0.6,
+            ),
+            (
+                Self::String,
+                !printable && !typical_len && (len <= 32 || utf8.is_some()),
+                0.5,
+            ),
+            (Self::Hex, len != 32 && utf8.is_none(), 0.45),
+            (Self::Base64, len > 32 && utf8.is_none(), 0.4),
+            (
+                Self::UnsignedInt,
+                matches!(len, 2 | 4 | 8) && bytes[0] & 0x80 == 0,
+                0.4,
+            ),
+            (Self::VarInt, whole_varint, 0.3),
+        ];
+        let mut guesses: Vec<Guess> = guesses
+            .into_iter()
+            .filter(|(_, plausible, _)| *plausible)
+            .map(|(variant, _, confidence)| Guess { variant, confidence })
+            .collect();
+        guesses.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        guesses
     }
 
     pub(crate) fn draw(&mut self, ui: &mut egui::Ui) {
@@ -58,6 +159,10 @@ pub(crate) enum BytesInputVariant {
     String,
     #[strum(serialize = "Hex")]
     Hex,
+    #[strum(serialize = "Base58")]
+    Base58,
+    #[strum(serialize = "Base64")]
+    Base64,
     #[strum(serialize = "Variable length integer")]
     VarInt,
     #[strum(serialize = "I16")]
@@ -80,6 +185,28 @@ impl BytesInputVariant {
             ui.radio_value(self, variant, variant.as_ref());
         }
     }
+
+    /// Parses the input as bytes of this variant, integers are big endian
+    pub(crate) fn parse(&self, input: &str) -> Option<Vec<u8>> {
+        match self {
+            BytesInputVariant::U8 => input
+                .split_whitespace()
+                .map(|int| int.parse::<u8>())
+                .collect::<Result<Vec<u8>, _>>()
+                .ok(),
+            BytesInputVariant::String => Some(input.as_bytes().to_vec()),
+            BytesInputVariant::Hex => hex::decode(input).ok(),
+            BytesInputVariant::Base58 => bs58::decode(input).into_vec().ok(),
+            BytesInputVariant::Base64 => BASE64.decode(input).ok(),
+            BytesInputVariant::VarInt => input.parse::<i64>().map(|int| int.encode_var_vec()).ok(),
+            BytesInputVariant::I16 => input.parse::<i16>().map(|int| int.to_be_bytes().to_vec()).ok(),
+            BytesInputVariant::I32 => input.parse::<i32>().map(|int| int.to_be_bytes().to_vec()).ok(),
+            BytesInputVariant::I64 => input.parse::<i64>().map(|int| int.to_be_bytes().to_vec()).ok(),
+            BytesInputVariant::U16 => input.parse::<u16>().map(|int| int.to_be_bytes().to_vec()).ok(),
+            BytesInputVariant::U32 => input.parse::<u32>().map(|int| int.to_be_bytes().to_vec()).ok(),
+            BytesInputVariant::U64 => input.parse::<u64>().map(|int| int.to_be_bytes().to_vec()).ok(),
+        }
+    }
 }
 
 pub(crate) struct BytesView {
@@ -95,6 +222,14 @@ impl BytesView {
         }
     }
 
+    /// Hashes are shown as hex regardless of their length
+    pub(crate) fn new_hash(bytes: Vec<u8>) -> Self {
+        Self {
+            display_variant: BytesDisplayVariant::Hex,
+            bytes,
+        }
+    }
+
     pub(crate) fn draw(&mut self, ui: &mut egui::Ui) {
         binary_label(ui, &self.bytes, &mut self.display_variant);
     }
@@ -166,47 +301,7 @@ impl BytesInput {
             return Vec::new();
         }
 
-        let bytes_opt = match self.input_variant {
-            BytesInputVariant::U8 => self
-                .input
-                .split_whitespace()
-                .map(|int| int.parse::<u8>())
-                .collect::<Result<Vec<u8>, _>>()
-                .ok(),
-            BytesInputVariant::String => Some(self.input.as_bytes().to_vec()),
-            BytesInputVariant::Hex => hex::decode(&self.input).ok(),
-            BytesInputVariant::VarInt => self.input.parse::<i64>().map(|int| int.encode_var_vec()).ok(),
-            BytesInputVariant::I16 => self
-                .input
-                .parse::<i16>()
-                .map(|int| int.to_be_bytes().to_vec())
-                .ok(),
-            BytesInputVariant::I32 => self
-                .input
-                .parse::<i32>()
-                .map(|int| int.to_be_bytes().to_vec())
-                .ok(),
-            BytesInputVariant::I64 => self
-                .input
-                .parse::<i64>()
-                .map(|int| int.to_be_bytes().to_vec())
-                .ok(),
-            BytesInputVariant::U16 => self
-                .input
-                .parse::<u16>()
-                .map(|int| int.to_be_bytes().to_vec())
-                .ok(),
-            BytesInputVariant::U32 => self
-                .input
-                .parse::<u32>()
-                .map(|int| int.to_be_bytes().to_vec())
-                .ok(),
-            BytesInputVariant::U64 => self
-                .input
-                .parse::<u64>()
-                .map(|int| int.to_be_bytes().to_vec())
-                .ok(),
-        };
+        let bytes_opt = self.input_variant.parse(&self.input);
 
         if bytes_opt.is_none() {
             self.err.set(true);
@@ -246,6 +341,7 @@ fn display_variant_dropdown<'a>(
         });
 
     response.context_menu(|menu| {
+        draw_alternatives(menu, bytes, display_variant);
         for variant in BytesDisplayVariant::iter() {
             menu.radio_value(display_variant, variant, variant.as_ref());
         }
@@ -253,6 +349,38 @@ fn display_variant_dropdown<'a>(
     response
 }
 
+/// Other plausible ways to read the bytes, a click switches to one
+fn draw_alternatives(menu: &mut egui::Ui, bytes: &[u8], display_variant: &mut BytesDisplayVariant) {
+    if bytes.is_empty() {
+        return;
+    }
+    let alternatives: Vec<Guess> = BytesDisplayVariant::guesses(bytes)
+        .into_iter()
+        .filter(|guess| guess.variant != *display_variant)
+        .take(MAX_ALTERNATIVES)
+        .collect();
+    if alternatives.is_empty() {
+        return;
+    }
+
+    menu.label(RichText::new("Also valid as:").weak());
+    for guess in alternatives {
+        if menu
+            .button(bytes_by_display_variant(bytes, &guess.variant))
+            .on_hover_text(format!(
+                "{}, {:.0}% confidence",
+                guess.variant.as_ref(),
+                guess.confidence * 100.
+            ))
+            .clicked()
+        {
+            *display_variant = guess.variant;
+            menu.close_menu();
+        }
+    }
+    menu.separator();
+}
+
 pub(crate) fn binary_label<'a>(
     ui: &mut egui::Ui,
     bytes: &[u8],
@@ -261,6 +389,38 @@ pub(crate) fn binary_label<'a>(
     binary_label_colored(ui, bytes, display_variant, Color32::GRAY)
 }
 
+/// Draw two byte strings as hex one under another, highlighting bytes that
+/// differ
+pub(crate) fn bytes_diff(ui: &mut egui::Ui, left: &[u8], right: &[u8]) {
+    let diff_color = input_error_color(ui.ctx());
+
+    for (bytes, other) in [(left, right), (right, left)] {
+        if bytes.is_empty() {
+            ui.label("empty");
+            continue;
+        }
+
+        let mut job = LayoutJob::default();
+        for (i, byte) in bytes.iter().enumerate() {
+            let color = if other.get(i) == Some(byte) {
+                Color32::GRAY
+            } else {
+                diff_color
+            };
+            job.append(
+                &format!("{byte:02x}"),
+                if i > 0 { 2. } else { 0. },
+                TextFormat {
+                    font_id: FontId::monospace(12.),
+                    color,
+                    ..Default::default()
+                },
+            );
+        }
+        ui.add(Label::new(job).wrap());
+    }
+}
+
 fn bytes_as_slice(bytes: &[u8]) -> String {
     if bytes.len() <= MAX_BYTES {
         format!("{:?}", bytes)
@@ -275,13 +435,17 @@ fn bytes_as_slice(bytes: &[u8]) -> String {
 }
 
 pub(crate) fn bytes_as_hex(bytes: &[u8]) -> String {
-    let hex_str = hex::encode(bytes);
-    if hex_str.len() <= MAX_HEX_LENGTH {
-        hex_str
+    shorten_encoded(hex::encode(bytes))
+}
+
+/// Keeps the beginning and the end of a long ASCII encoded string
+fn shorten_encoded(encoded: String) -> String {
+    if encoded.len() <= MAX_HEX_LENGTH {
+        encoded
     } else {
-        let mut buf = String::from(&hex_str[0..HEX_PARTS_LENGTH]);
+        let mut buf = String::from(&encoded[0..HEX_PARTS_LENGTH]);
         buf.push_str("..");
-        buf.push_str(&hex_str[(hex_str.len() - HEX_PARTS_LENGTH)..]);
+        buf.push_str(&encoded[(encoded.len() - HEX_PARTS_LENGTH)..]);
         buf
     }
 }
@@ -322,14 +486,47 @@ fn bytes_as_varint(bytes: &[u8]) -> String {
         .unwrap_or_else(|| "varint: MSB".to_owned())
 }
 
+/// Numeric value of bytes shown as a number, used to order keys by it
+pub(crate) fn bytes_as_number(bytes: &[u8], display_variant: &BytesDisplayVariant) -> Option<i128> {
+    match (display_variant, bytes.len()) {
+        (BytesDisplayVariant::U8, 1) => Some(bytes[0].into()),
+        (BytesDisplayVariant::SignedInt, 2) => {
+            bytes.try_into().ok().map(|arr| i16::from_be_bytes(arr).into())
+        }
+        (BytesDisplayVariant::SignedInt, 4) => {
+            bytes.try_into().ok().map(|arr| i32::from_be_bytes(arr).into())
+        }
+        (BytesDisplayVariant::SignedInt, 8) => {
+            bytes.try_into().ok().map(|arr| i64::from_be_bytes(arr).into())
+        }
+        (BytesDisplayVariant::UnsignedInt, 2) => {
+            bytes.try_into().ok().map(|arr| u16::from_be_bytes(arr).into())
+        }
+        (BytesDisplayVariant::UnsignedInt, 4) => {
+            bytes.try_into().ok().map(|arr| u32::from_be_bytes(arr).into())
+        }
+        (BytesDisplayVariant::UnsignedInt, 8) => {
+            bytes.try_into().ok().map(|arr| u64::from_be_bytes(arr).into())
+        }
+        (BytesDisplayVariant::VarInt, _) => i64::decode_var(bytes)
+            .filter(|(_, len)| *len == bytes.len())
+            .map(|(x, _)| x.into()),
+        (BytesDisplayVariant::DriveTimestamp, _) => drive_timestamp_millis(bytes).map(Into::into),
+        _ => None,
+    }
+}
+
+/// Drive stores timestamps as big endian integers with the sign bit flipped
+fn drive_timestamp_millis(bytes: &[u8]) -> Option<i64> {
+    TryInto::<[u8; 8]>::try_into(bytes).ok().map(|mut arr| {
+        arr[0] ^= 0b1000_0000;
+        i64::from_be_bytes(arr)
+    })
+}
+
 fn bytes_as_drive_timestamp(bytes: &[u8]) -> String {
-    TryInto::<[u8; 8]>::try_into(bytes)
-        .ok()
-        .and_then(|mut arr| {
-            arr[0] ^= 0b1000_0000;
-            chrono::DateTime::from_timestamp_millis(i64::from_be_bytes(arr))
-                .map(|dt| format!("{}", dt.naive_utc()))
-        })
+    drive_timestamp_millis(bytes)
+        .and_then(format::timestamp_millis)
         .unwrap_or_else(|| "[E]: must be 8 bytes".into())
 }
 
@@ -345,6 +542,26 @@ fn bytes_as_dpp_vote_poll_pretty(bytes: &[u8]) -> String {
         .unwrap_or_else(|| "[E] unable to deserialize as VotePoll".to_owned())
 }
 
+fn bytes_as_image_line(bytes: &[u8]) -> String {
+    preview::image_format(bytes)
+        .map(|format| format!("{format} image, {} bytes", bytes.len()))
+        .unwrap_or_else(|| "[E]: not a PNG or JPEG image".to_owned())
+}
+
+fn bytes_as_qr_code_line(bytes: &[u8]) -> String {
+    if preview::fits_qr_code(bytes) {
+        format!("QR code, {} bytes", bytes.len())
+    } else {
+        "[E]: too long for a QR code".to_owned()
+    }
+}
+
+fn bytes_as_structure_line(bytes: &[u8]) -> String {
+    bytes_as_structure(bytes)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "[E]: unknown structure".to_owned())
+}
+
 pub(crate) fn bytes_as_dpp_vote_poll(bytes: &[u8]) -> Option<dpp::voting::vote_polls::VotePoll> {
     dpp::voting::vote_polls::VotePoll::deserialize_from_bytes(bytes).ok()
 }
@@ -357,11 +574,18 @@ pub(crate) fn bytes_by_display_variant(bytes: &[u8], display_variant: &BytesDisp
             BytesDisplayVariant::U8 => bytes_as_slice(bytes),
             BytesDisplayVariant::String => format!("str: {}", String::from_utf8_lossy(bytes).to_string()),
             BytesDisplayVariant::Hex => format!("hex: {}", bytes_as_hex(bytes)),
+            BytesDisplayVariant::Base58 => {
+                format!("b58: {}", shorten_encoded(bs58::encode(bytes).into_string()))
+            }
+            BytesDisplayVariant::Base64 => format!("b64: {}", shorten_encoded(BASE64.encode(bytes))),
             BytesDisplayVariant::SignedInt => bytes_as_signed_int(bytes),
             BytesDisplayVariant::UnsignedInt => bytes_as_unsigned_int(bytes),
             BytesDisplayVariant::VarInt => format!("varint: {}", bytes_as_varint(bytes)),
             BytesDisplayVariant::DriveTimestamp => bytes_as_drive_timestamp(bytes),
             BytesDisplayVariant::DppVotePoll => bytes_as_dpp_vote_poll_line(bytes),
+            BytesDisplayVariant::Image => bytes_as_image_line(bytes),
+            BytesDisplayVariant::QrCode => bytes_as_qr_code_line(bytes),
+            BytesDisplayVariant::StructuredGuess => bytes_as_structure_line(bytes),
         }
     }
 }
@@ -374,10 +598,48 @@ pub(crate) fn bytes_by_display_variant_explicit(
         BytesDisplayVariant::U8 => format!("{bytes:?}"),
         BytesDisplayVariant::String => String::from_utf8_lossy(bytes).to_string(),
         BytesDisplayVariant::Hex => hex::encode(bytes),
+        BytesDisplayVariant::Base58 => bs58::encode(bytes).into_string(),
+        BytesDisplayVariant::Base64 => BASE64.encode(bytes),
         BytesDisplayVariant::SignedInt => bytes_as_signed_int(bytes),
         BytesDisplayVariant::UnsignedInt => bytes_as_unsigned_int(bytes),
         BytesDisplayVariant::VarInt => bytes_as_varint(bytes),
         BytesDisplayVariant::DriveTimestamp => bytes_as_drive_timestamp(bytes),
         BytesDisplayVariant::DppVotePoll => bytes_as_dpp_vote_poll_pretty(bytes),
+        BytesDisplayVariant::Image => bytes_as_image_line(bytes),
+        BytesDisplayVariant::QrCode => bytes_as_qr_code_line(bytes),
+        BytesDisplayVariant::StructuredGuess => bytes_as_structure_line(bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_are_ranked_by_confidence() {
+        let id = [7u8; 32];
+        assert_eq!(BytesDisplayVariant::guess(&id), BytesDisplayVariant::Hex);
+        let guesses = BytesDisplayVariant::guesses(&id);
+        assert_eq!(guesses[0].variant, BytesDisplayVariant::Hex);
+        assert!(guesses.iter().any(|g| g.variant == BytesDisplayVariant::Base58));
+
+        let int = (-5i32).to_be_bytes();
+        let variants: Vec<_> = BytesDisplayVariant::guesses(&int)
+            .iter()
+            .map(|g| g.variant)
+            .collect();
+        assert_eq!(variants[0], BytesDisplayVariant::SignedInt);
+        assert!(variants.contains(&BytesDisplayVariant::UnsignedInt));
+
+        assert_eq!(BytesDisplayVariant::guess(b"a"), BytesDisplayVariant::U8);
+        assert_eq!(
+            BytesDisplayVariant::guess(b"balance"),
+            BytesDisplayVariant::String
+        );
+        assert_eq!(BytesDisplayVariant::guess(&[0xff; 40]), BytesDisplayVariant::Hex);
+        assert!(BytesDisplayVariant::guesses(&[0xff; 40])
+            .iter()
+            .any(|g| g.variant == BytesDisplayVariant::Base64));
+        assert_eq!(BytesDisplayVariant::guess(&[]), BytesDisplayVariant::String);
     }
 }
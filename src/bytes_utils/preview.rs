@@ -0,0 +1,105 @@
+//! Rendering of bytes as images: decoded PNG/JPEG thumbnails and QR codes.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use eframe::egui::{self, Color32, ColorImage, TextureHandle, TextureOptions, Vec2};
+use qrcode::QrCode;
+
+use super::BytesDisplayVariant;
+
+const THUMBNAIL_SIZE: f32 = 64.;
+const PREVIEW_SIZE: f32 = 400.;
+const QR_QUIET_ZONE: usize = 2;
+/// Bytes fitting into the largest QR code version at the medium error
+/// correction level `QrCode::new` uses
+const QR_BYTE_CAPACITY: usize = 2331;
+
+/// Image formats that can be decoded, detected by magic bytes
+pub(super) fn image_format(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("jpeg")
+    } else {
+        None
+    }
+}
+
+fn bytes_id(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Draws a thumbnail for display variants that render bytes as an image, a
+/// bigger version is shown on hover
+pub(crate) fn bytes_preview(ui: &mut egui::Ui, bytes: &[u8], display_variant: &BytesDisplayVariant) {
+    match display_variant {
+        BytesDisplayVariant::Image => {
+            let Some(format) = image_format(bytes) else {
+                return;
+            };
+            let uri = format!("bytes://{:x}.{format}", bytes_id(bytes));
+            let image = egui::Image::from_bytes(uri, bytes.to_vec());
+            ui.add(image.clone().max_size(Vec2::splat(THUMBNAIL_SIZE)))
+                .on_hover_ui(|hover| {
+                    hover.add(image.max_size(Vec2::splat(PREVIEW_SIZE)));
+                });
+        }
+        BytesDisplayVariant::QrCode => {
+            let Some(texture) = qr_texture(ui.ctx(), bytes) else {
+                return;
+            };
+            ui.add(egui::Image::new(&texture).fit_to_exact_size(Vec2::splat(THUMBNAIL_SIZE)))
+                .on_hover_ui(|hover| {
+                    hover.add(egui::Image::new(&texture).fit_to_exact_size(Vec2::splat(PREVIEW_SIZE)));
+                });
+        }
+        _ => {}
+    }
+}
+
+/// Renders bytes as a QR code texture, cached by bytes
+fn qr_texture(ctx: &egui::Context, bytes: &[u8]) -> Option<TextureHandle> {
+    let id = egui::Id::new("qr_code").with(bytes_id(bytes));
+    if let Some(texture) = ctx.data(|data| data.get_temp::<TextureHandle>(id)) {
+        return Some(texture);
+    }
+    if !fits_qr_code(bytes) {
+        return None;
+    }
+
+    let code = QrCode::new(bytes).ok()?;
+    let width = code.width();
+    let size = width + QR_QUIET_ZONE * 2;
+    let mut image = ColorImage::new([size, size], Color32::WHITE);
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == qrcode::Color::Dark {
+            let (x, y) = (i % width + QR_QUIET_ZONE, i / width + QR_QUIET_ZONE);
+            image.pixels[y * size + x] = Color32::BLACK;
+        }
+    }
+
+    let texture = ctx.load_texture("qr_code", image, TextureOptions::NEAREST);
+    ctx.data_mut(|data| data.insert_temp(id, texture.clone()));
+    Some(texture)
+}
+
+/// Fits into the QR code capacity, checked by length as the display line
+/// is built every frame
+pub(super) fn fits_qr_code(bytes: &[u8]) -> bool {
+    bytes.len() <= QR_BYTE_CAPACITY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qr_code_capacity_matches_the_encoder() {
+        let bytes = vec![0xff; QR_BYTE_CAPACITY + 1];
+        assert!(QrCode::new(&bytes[..QR_BYTE_CAPACITY]).is_ok());
+        assert!(QrCode::new(&bytes).is_err());
+        assert!(!fits_qr_code(&bytes));
+    }
+}
@@ -0,0 +1,127 @@
+//! Schemaless decoding of binary payloads to guess their structure.
+
+use integer_encoding::VarInt;
+use serde_json::{json, Value};
+
+/// Tries to decode bytes as a protobuf message, then as a sequence of bincode
+/// varints, both shall consume all bytes
+pub(crate) fn bytes_as_structure(bytes: &[u8]) -> Option<Value> {
+    if let Some(fields) = protobuf_message(bytes) {
+        Some(json!({ "protobuf": fields }))
+    } else {
+        bincode_varints(bytes).map(|ints| json!({ "bincode varints": ints }))
+    }
+}
+
+fn protobuf_message(mut bytes: &[u8]) -> Option<Vec<Value>> {
+    let mut fields = Vec::new();
+
+    while !bytes.is_empty() {
+        let (tag, read) = u64::decode_var(bytes)?;
+        bytes = &bytes[read..];
+        let field = tag >> 3;
+        if field == 0 {
+            return None;
+        }
+
+        let (wire_type, value) = match tag & 7 {
+            0 => {
+                let (int, read) = u64::decode_var(bytes)?;
+                bytes = &bytes[read..];
+                ("varint", json!(int))
+            }
+            1 => {
+                let (fixed, rest) = bytes.split_first_chunk::<8>()?;
+                bytes = rest;
+                ("i64", json!(u64::from_le_bytes(*fixed)))
+            }
+            2 => {
+                let (len, read) = u64::decode_var(bytes)?;
+                bytes = &bytes[read..];
+                let len = usize::try_from(len).ok().filter(|len| *len <= bytes.len())?;
+                let (payload, rest) = bytes.split_at(len);
+                bytes = rest;
+                ("len", length_delimited(payload))
+            }
+            5 => {
+                let (fixed, rest) = bytes.split_first_chunk::<4>()?;
+                bytes = rest;
+                ("i32", json!(u32::from_le_bytes(*fixed)))
+            }
+            _ => return None,
+        };
+
+        fields.push(json!({ "field": field, "type": wire_type, "value": value }));
+    }
+
+    (!fields.is_empty()).then_some(fields)
+}
+
+/// Length delimited payload is shown as text if printable, as a nested message
+/// if it parses as one and as hex otherwise
+fn length_delimited(payload: &[u8]) -> Value {
+    match std::str::from_utf8(payload) {
+        Ok(s) if !s.chars().any(char::is_control) => json!(s),
+        _ => protobuf_message(payload)
+            .map(Value::Array)
+            .unwrap_or_else(|| json!(hex::encode(payload))),
+    }
+}
+
+/// Bincode varint encoding stores values under 251 in one byte, otherwise a
+/// marker byte is followed by a little endian integer
+fn bincode_varints(mut bytes: &[u8]) -> Option<Vec<u64>> {
+    let mut ints = Vec::new();
+
+    while let Some((marker, rest)) = bytes.split_first() {
+        bytes = rest;
+        let int = match marker {
+            0..=250 => *marker as u64,
+            251 => {
+                let (int, rest) = bytes.split_first_chunk::<2>()?;
+                bytes = rest;
+                u16::from_le_bytes(*int) as u64
+            }
+            252 => {
+                let (int, rest) = bytes.split_first_chunk::<4>()?;
+                bytes = rest;
+                u32::from_le_bytes(*int) as u64
+            }
+            253 => {
+                let (int, rest) = bytes.split_first_chunk::<8>()?;
+                bytes = rest;
+                u64::from_le_bytes(*int)
+            }
+            _ => return None,
+        };
+        ints.push(int);
+    }
+
+    (!ints.is_empty()).then_some(ints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_protobuf_nested_message() {
+        // field 1: varint 150, field 2: message with field 1: "ab"
+        let bytes = [0x08, 0x96, 0x01, 0x12, 0x04, 0x0a, 0x02, b'a', b'b'];
+        assert_eq!(
+            bytes_as_structure(&bytes).unwrap(),
+            json!({ "protobuf": [
+                { "field": 1, "type": "varint", "value": 150 },
+                { "field": 2, "type": "len", "value": [
+                    { "field": 1, "type": "len", "value": "ab" },
+                ]},
+            ]})
+        );
+    }
+
+    #[test]
+    fn decode_bincode_varints() {
+        assert_eq!(bincode_varints(&[7, 251, 0x00, 0x01]).unwrap(), vec![7, 256]);
+        assert!(bincode_varints(&[252, 0x00]).is_none());
+    }
+}
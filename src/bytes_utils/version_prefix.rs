@@ -0,0 +1,47 @@
+//! Values stored in some Drive trees start with a serialization version, it is
+//! split off so the payload goes through the usual decoders.
+
+use integer_encoding::VarInt;
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, EnumIter};
+
+#[derive(Debug, AsRefStr, EnumIter, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub(crate) enum VersionPrefix {
+    #[default]
+    #[strum(serialize = "Single byte")]
+    Byte,
+    #[strum(serialize = "Variable length integer")]
+    VarInt,
+}
+
+impl VersionPrefix {
+    /// Splits value bytes into the version and the payload, `None` if there
+    /// is no valid prefix
+    pub(crate) fn split<'a>(&self, bytes: &'a [u8]) -> Option<(u64, &'a [u8])> {
+        match self {
+            VersionPrefix::Byte => bytes
+                .split_first()
+                .map(|(version, payload)| (*version as u64, payload)),
+            VersionPrefix::VarInt => u64::decode_var(bytes).map(|(version, len)| (version, &bytes[len..])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_version_prefix() {
+        assert_eq!(
+            VersionPrefix::Byte.split(&[1, 2, 3]),
+            Some((1, [2, 3].as_slice()))
+        );
+        assert_eq!(VersionPrefix::Byte.split(&[]), None);
+        assert_eq!(
+            VersionPrefix::VarInt.split(&[0xac, 0x02, 7]),
+            Some((300, [7].as_slice()))
+        );
+        assert_eq!(VersionPrefix::VarInt.split(&[0x80]), None);
+    }
+}
@@ -0,0 +1,129 @@
+//! Pan and zoom handling shared by the tree and merk canvases, covering mouse,
+//! trackpad and touch screen input.
+
+use eframe::{
+    egui::{self, Pos2, Rect},
+    emath::TSTransform,
+};
+
+/// Space left around the content when fitting it into the view
+const FIT_MARGIN: f32 = 20.;
+/// Fitting a huge tree doesn't zoom out beyond this
+const MIN_FIT_SCALING: f32 = 0.1;
+
+/// Updates canvas `transform` according to user input for this frame.
+///
+/// Mouse drag and one finger drag pan the canvas, scroll pans as well, while
+/// ctrl+scroll and pinch zoom around the pointer (or fingers' center).
+/// Returns `true` if the transform was changed.
+pub(crate) fn handle_pan_zoom(ui: &egui::Ui, response: &egui::Response, transform: &mut TSTransform) -> bool {
+    let transform_before = *transform;
+    let (multi_touch, hover_pos, zoom_delta, scroll_delta) = ui.ctx().input(|i| {
+        (
+            i.multi_touch(),
+            i.pointer.hover_pos(),
+            i.zoom_delta(),
+            i.smooth_scroll_delta,
+        )
+    });
+
+    let zoom_center = match multi_touch {
+        // Two and more fingers pan together, while one finger drag is emulated as a
+        // pointer drag
+        Some(touch) if response.contains_pointer() => {
+            *transform = TSTransform::from_translation(touch.translation_delta) * *transform;
+            Some(touch.center_pos)
+        }
+        Some(_) => None,
+        None => {
+            if response.dragged() {
+                transform.translation += response.drag_delta();
+            }
+            hover_pos.filter(|_| response.hovered())
+        }
+    };
+
+    if let Some(center) = zoom_center {
+        // egui's zoom delta covers both ctrl+scroll and pinch
+        let center_in_layer = transform.inverse() * center;
+        *transform = *transform
+            * TSTransform::from_translation(center_in_layer.to_vec2())
+            * TSTransform::from_scaling(zoom_delta)
+            * TSTransform::from_translation(-center_in_layer.to_vec2());
+        *transform = TSTransform::from_translation(scroll_delta) * *transform;
+    }
+
+    transform_before != *transform
+}
+
+/// Keeps the content under the center of the canvas in place when the canvas
+/// is resized, be it by the window, a side panel or a changed pixel density
+/// shrinking the canvas in points.
+pub(crate) fn follow_resize(transform: &mut TSTransform, previous: &mut Option<Rect>, viewport: Rect) {
+    if let Some(previous) = previous.replace(viewport) {
+        transform.translation += viewport.center() - previous.center();
+    }
+}
+
+/// Transform that shows `bounds`, given in canvas coordinates, within the
+/// `viewport`; zooms out to make it fit, but never zooms in beyond 100%.
+pub(crate) fn fit_transform(bounds: Rect, viewport: Rect) -> TSTransform {
+    let inner = viewport.shrink(FIT_MARGIN);
+    let scaling = (inner.width() / bounds.width())
+        .min(inner.height() / bounds.height())
+        .clamp(MIN_FIT_SCALING, 1.);
+    TSTransform::new(
+        viewport.center().to_vec2() - bounds.center().to_vec2() * scaling,
+        scaling,
+    )
+}
+
+/// Moves `target`, given in canvas coordinates, to the center of the
+/// `viewport` keeping the zoom level.
+pub(crate) fn center_transform(transform: TSTransform, target: Pos2, viewport: Rect) -> TSTransform {
+    TSTransform::new(
+        viewport.center().to_vec2() - target.to_vec2() * transform.scaling,
+        transform.scaling,
+    )
+}
+
+/// Fit and center shortcuts apply only to the hovered canvas and not while
+/// typing
+pub(crate) fn shortcut_pressed(ui: &egui::Ui, response: &egui::Response, key: egui::Key) -> bool {
+    response.hovered()
+        && !ui.ctx().wants_keyboard_input()
+        && ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::vec2;
+
+    use super::*;
+
+    #[test]
+    fn fit_zooms_out_and_centers() {
+        let viewport = Rect::from_min_size(Pos2::ZERO, vec2(440., 240.));
+        let bounds = Rect::from_min_size(Pos2::new(1000., 0.), vec2(800., 400.));
+        let transform = fit_transform(bounds, viewport);
+        assert_eq!(transform.scaling, 0.5);
+        assert_eq!(transform * bounds.center(), viewport.center());
+
+        let small = Rect::from_min_size(Pos2::ZERO, vec2(10., 10.));
+        assert_eq!(fit_transform(small, viewport).scaling, 1.);
+    }
+
+    #[test]
+    fn resize_keeps_the_centered_content() {
+        let mut transform = TSTransform::new(vec2(30., 10.), 2.);
+        let mut viewport = None;
+        let before = Rect::from_min_size(Pos2::new(0., 40.), vec2(400., 200.));
+        follow_resize(&mut transform, &mut viewport, before);
+        let centered = transform.inverse() * before.center();
+
+        let after = Rect::from_min_size(Pos2::new(0., 40.), vec2(250., 500.));
+        follow_resize(&mut transform, &mut viewport, after);
+        assert_eq!(transform * centered, after.center());
+        assert_eq!(viewport, Some(after));
+    }
+}
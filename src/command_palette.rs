@@ -0,0 +1,259 @@
+//! Command palette opened with Ctrl+P: actions of the top panel and profile
+//! shortcuts found by fuzzy search and run with the keyboard only. Chosen
+//! commands are dispatched as user actions.
+
+use eframe::egui::{self, Align2, Key, Modifiers, RichText};
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+
+use crate::{
+    bus::{CommandBus, UserAction},
+    go_to_path::parse_path,
+    profiles::ProfilesView,
+};
+
+const PALETTE_WIDTH: f32 = 480.;
+const MAX_SHOWN: usize = 12;
+
+/// Panels and windows the palette can toggle
+#[derive(Clone, Copy, PartialEq, AsRefStr, EnumIter)]
+pub(crate) enum Panel {
+    #[strum(serialize = "query builder")]
+    QueryBuilder,
+    #[strum(serialize = "proof viewer")]
+    ProofViewer,
+    #[strum(serialize = "profiles")]
+    Profiles,
+    #[strum(serialize = "log")]
+    Log,
+    #[strum(serialize = "merk view")]
+    MerkView,
+    #[strum(serialize = "notes")]
+    Notes,
+    #[strum(serialize = "memory")]
+    Memory,
+    #[strum(serialize = "replay")]
+    Replay,
+    #[strum(serialize = "navigation script")]
+    Script,
+    #[strum(serialize = "root history")]
+    RootHistory,
+    #[strum(serialize = "JSON schemas")]
+    Schemas,
+    #[strum(serialize = "update conflicts")]
+    Conflicts,
+    #[strum(serialize = "settings")]
+    Settings,
+    #[strum(serialize = "help")]
+    Help,
+}
+
+#[derive(Clone, PartialEq)]
+pub(crate) enum PaletteCommand {
+    NewSession,
+    KeepAndNewSession,
+    /// Open the dialog to paste a path into
+    GoToPastedPath,
+    /// Open the dialog to find a node by its hash
+    FindByHash,
+    /// Focus a path typed into the palette
+    GoToPath(Vec<Vec<u8>>),
+    /// Profile shortcut by its index
+    RunShortcut(usize),
+    TogglePanel(Panel),
+    ToggleRedaction,
+    CopySnapshot,
+    NavigateBack,
+    NavigateForward,
+}
+
+struct Entry {
+    label: String,
+    command: PaletteCommand,
+}
+
+#[derive(Default)]
+pub(crate) struct CommandPalette {
+    open: bool,
+    input: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub(crate) fn draw<'pa>(
+        &mut self,
+        ctx: &egui::Context,
+        bus: &CommandBus<'pa>,
+        profiles_view: &ProfilesView,
+    ) {
+        if ctx.input_mut(|input| input.consume_key(Modifiers::COMMAND, Key::P)) {
+            self.open = !self.open;
+            self.input.clear();
+            self.selected = 0;
+        }
+        if !self.open {
+            return;
+        }
+
+        let entries = entries(&self.input, profiles_view);
+        let (up, down, enter, escape) = ctx.input_mut(|input| {
+            (
+                input.consume_key(Modifiers::NONE, Key::ArrowUp),
+                input.consume_key(Modifiers::NONE, Key::ArrowDown),
+                input.consume_key(Modifiers::NONE, Key::Enter),
+                input.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down {
+            self.selected += 1;
+        }
+        self.selected = self.selected.min(entries.len().saturating_sub(1));
+
+        let mut chosen = enter
+            .then(|| entries.get(self.selected))
+            .flatten()
+            .map(|e| e.command.clone());
+
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .anchor(Align2::CENTER_TOP, (0., 40.))
+            .default_width(PALETTE_WIDTH)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let input = ui.add(
+                    egui::TextEdit::singleline(&mut self.input)
+                        .hint_text("Type a command or paste a path")
+                        .desired_width(f32::INFINITY),
+                );
+                input.request_focus();
+                if input.changed() {
+                    self.selected = 0;
+                }
+
+                if entries.is_empty() {
+                    ui.label(RichText::new("No matching commands").weak());
+                }
+                for (i, entry) in entries.iter().enumerate() {
+                    if ui.selectable_label(i == self.selected, &entry.label).clicked() {
+                        chosen = Some(entry.command.clone());
+                    }
+                }
+            });
+
+        if let Some(command) = chosen {
+            bus.user_action(UserAction::RunCommand(command));
+            self.open = false;
+        } else if escape {
+            self.open = false;
+        }
+    }
+}
+
+/// Entries matching the input, best matches first
+fn entries(input: &str, profiles_view: &ProfilesView) -> Vec<Entry> {
+    let mut all = vec![
+        Entry {
+            label: "New session".to_owned(),
+            command: PaletteCommand::NewSession,
+        },
+        Entry {
+            label: "Keep and new session".to_owned(),
+            command: PaletteCommand::KeepAndNewSession,
+        },
+        Entry {
+            label: "Go to a pasted path".to_owned(),
+            command: PaletteCommand::GoToPastedPath,
+        },
+        Entry {
+            label: "Find a node by hash".to_owned(),
+            command: PaletteCommand::FindByHash,
+        },
+        Entry {
+            label: "Toggle redaction".to_owned(),
+            command: PaletteCommand::ToggleRedaction,
+        },
+        Entry {
+            label: "Copy session snapshot".to_owned(),
+            command: PaletteCommand::CopySnapshot,
+        },
+        Entry {
+            label: "Go back".to_owned(),
+            command: PaletteCommand::NavigateBack,
+        },
+        Entry {
+            label: "Go forward".to_owned(),
+            command: PaletteCommand::NavigateForward,
+        },
+    ];
+    all.extend(Panel::iter().map(|panel| Entry {
+        label: format!("Toggle {}", panel.as_ref()),
+        command: PaletteCommand::TogglePanel(panel),
+    }));
+    all.extend(
+        profiles_view
+            .shortcut_labels()
+            .into_iter()
+            .enumerate()
+            .map(|(i, label)| Entry {
+                label: format!("Shortcut: {label}"),
+                command: PaletteCommand::RunShortcut(i),
+            }),
+    );
+
+    let mut scored: Vec<(u32, Entry)> = all
+        .into_iter()
+        .filter_map(|entry| fuzzy_score(input, &entry.label).map(|score| (score, entry)))
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    let mut matched: Vec<Entry> = Vec::new();
+    if input.trim_start().starts_with('[') {
+        if let Ok(path) = parse_path(input) {
+            matched.push(Entry {
+                label: format!("Go to {}", input.trim()),
+                command: PaletteCommand::GoToPath(path),
+            });
+        }
+    }
+    matched.extend(scored.into_iter().map(|(_, entry)| entry));
+    matched.truncate(MAX_SHOWN);
+    matched
+}
+
+/// Score of the label if all characters of the pattern appear in it in order,
+/// ignoring case. Consecutive characters and word starts score higher.
+fn fuzzy_score(pattern: &str, label: &str) -> Option<u32> {
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for c in pattern.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + label[position..].iter().position(|l| *l == c)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 2;
+        }
+        if found == 0 || label[found - 1] == ' ' {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches_are_ranked() {
+        assert_eq!(fuzzy_score("", "New session"), Some(0));
+        assert_eq!(fuzzy_score("xyz", "New session"), None);
+        assert_eq!(fuzzy_score("ssn", "New session"), Some(6));
+        assert!(fuzzy_score("ns", "New session") > fuzzy_score("ns", "Toggle notes"));
+        assert!(fuzzy_score("log", "Toggle log") > fuzzy_score("log", "Toggle root history"));
+    }
+}
@@ -0,0 +1,91 @@
+//! Diagnostics panel listing proved nodes fetched with a different value in
+//! the session of the proof.
+
+use eframe::egui::{self, Grid, RichText};
+
+use crate::{
+    bus::{CommandBus, UserAction},
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    path_ctx::path_label,
+    profiles::ProfilesView,
+    theme::input_error_color,
+    tree_data::TreeData,
+};
+
+/// Hex digits of a value hash shown in the list
+const SHORT_HASH_LEN: usize = 16;
+
+pub(crate) fn draw<'pa>(
+    ui: &mut egui::Ui,
+    tree_data: &mut TreeData<'pa>,
+    bus: &CommandBus<'pa>,
+    profiles_view: &ProfilesView,
+) {
+    ui.label(
+        "Nodes fetched in the session of a proof with a value hash other than the proved one. Sessions work \
+         on snapshots, so these point at a server-side session bug.",
+    );
+
+    if tree_data.update_conflicts.is_empty() {
+        ui.label("No conflicts found");
+        return;
+    }
+
+    if ui.button("Clear").clicked() {
+        tree_data.update_conflicts.clear();
+        return;
+    }
+
+    egui::ScrollArea::vertical().show(ui, |scroll| {
+        Grid::new("update_conflicts").striped(true).show(scroll, |grid| {
+            grid.label(RichText::new("Session").strong());
+            grid.label(RichText::new("Node").strong());
+            grid.label(RichText::new("Proved value hash").strong());
+            grid.label(RichText::new("Fetched value hash").strong());
+            grid.label(RichText::new("Fetched by").strong());
+            grid.end_row();
+
+            for conflict in tree_data.update_conflicts.iter() {
+                grid.label(format!(
+                    "{} at {}",
+                    conflict.session_id,
+                    conflict.at.format("%H:%M:%S")
+                ));
+                grid.horizontal(|line| {
+                    if line
+                        .button(egui_phosphor::regular::MAGNIFYING_GLASS)
+                        .on_hover_text("Reveal in tree")
+                        .clicked()
+                    {
+                        bus.user_action(UserAction::FocusSubtreeKey(conflict.path, conflict.key.clone()));
+                    }
+                    path_label(
+                        line,
+                        conflict.path,
+                        &profiles_view
+                            .active_profile_root_ctx()
+                            .fast_forward(conflict.path),
+                    );
+                    line.label(bytes_by_display_variant(
+                        &conflict.key,
+                        &BytesDisplayVariant::guess(&conflict.key),
+                    ));
+                });
+                grid.label(RichText::new(short_hash(&conflict.proved_value_hash)).monospace());
+                grid.label(
+                    RichText::new(short_hash(&conflict.fetched_value_hash))
+                        .monospace()
+                        .color(input_error_color(grid.ctx())),
+                );
+                grid.label(conflict.source.as_ref());
+                grid.end_row();
+            }
+        });
+    });
+}
+
+fn short_hash(hash: &[u8]) -> String {
+    let mut hex = hex::encode(hash);
+    hex.truncate(SHORT_HASH_LEN);
+    format!("{hex}…")
+}
@@ -0,0 +1,123 @@
+//! Page size of subtrees and spacing of nodes on the canvas. Values are global
+//! since they are read by layout and fetch code with no access to settings,
+//! and the layout is recomputed each frame so changes apply live.
+
+use std::sync::RwLock;
+
+use eframe::{
+    egui::{self, DragValue},
+    Storage,
+};
+use serde::{Deserialize, Serialize};
+
+const DENSITY_KEY: &'static str = "canvas_density";
+
+static DENSITY: RwLock<Density> = RwLock::new(Density::DEFAULT);
+
+fn current() -> Density {
+    DENSITY.read().map(|d| *d).unwrap_or_default()
+}
+
+/// Elements shown on a subtree page and fetched at once for it
+pub(crate) fn kv_per_page() -> usize {
+    current().kv_per_page
+}
+
+pub(crate) fn node_width() -> f32 {
+    current().node_width
+}
+
+/// Distance from a subtree to the level of its child subtrees
+pub(crate) fn margin_vertical() -> f32 {
+    current().margin_vertical
+}
+
+/// Gap between neighbouring subtrees of one level
+pub(crate) fn margin_horizontal() -> f32 {
+    current().margin_horizontal
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Density {
+    kv_per_page: usize,
+    node_width: f32,
+    margin_vertical: f32,
+    margin_horizontal: f32,
+}
+
+impl Default for Density {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Density {
+    const DEFAULT: Density = Density {
+        kv_per_page: 10,
+        node_width: 300.,
+        margin_vertical: 400.,
+        margin_horizontal: 50.,
+    };
+
+    pub(crate) fn current() -> Self {
+        current()
+    }
+
+    pub(crate) fn apply(&self) {
+        if let Ok(mut density) = DENSITY.write() {
+            *density = *self;
+        }
+    }
+
+    pub(crate) fn persist(&self, storage: &mut dyn Storage) {
+        if let Ok(s) = serde_json::to_string(self) {
+            storage.set_string(DENSITY_KEY, s);
+        }
+    }
+
+    pub(crate) fn restore(storage: Option<&dyn Storage>) -> Self {
+        storage
+            .and_then(|s| s.get_string(DENSITY_KEY))
+            .and_then(|param| serde_json::from_str(&param).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn draw(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("canvas_density").num_columns(2).show(ui, |grid| {
+            grid.label("Elements per page:");
+            grid.add(DragValue::new(&mut self.kv_per_page).range(1..=1000))
+                .on_hover_text("Also the number of elements fetched for a subtree at once");
+            grid.end_row();
+
+            grid.label("Node width:");
+            grid.add(
+                DragValue::new(&mut self.node_width)
+                    .range(150. ..=1000.)
+                    .suffix(" px"),
+            );
+            grid.end_row();
+
+            grid.label("Vertical margin:");
+            grid.add(
+                DragValue::new(&mut self.margin_vertical)
+                    .range(50. ..=2000.)
+                    .suffix(" px"),
+            )
+            .on_hover_text("Distance between levels of subtrees");
+            grid.end_row();
+
+            grid.label("Horizontal margin:");
+            grid.add(
+                DragValue::new(&mut self.margin_horizontal)
+                    .range(0. ..=500.)
+                    .suffix(" px"),
+            )
+            .on_hover_text("Gap between subtrees of one level");
+            grid.end_row();
+        });
+
+        if ui.button("Reset").clicked() {
+            *self = Default::default();
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Panels detached into separate native windows on desktop, so on several
+//! monitors the tree and a panel can both be shown at full size. Detached
+//! panels are immediate viewports to keep direct access to the app state.
+
+use std::collections::BTreeSet;
+
+use eframe::egui::{self, Context, ViewportBuilder, ViewportId};
+use strum::AsRefStr;
+
+const DETACHED_WINDOW_SIZE: [f32; 2] = [640., 800.];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, AsRefStr)]
+pub(crate) enum DetachablePanel {
+    #[strum(serialize = "Proof viewer")]
+    ProofViewer,
+    #[strum(serialize = "Merk view")]
+    MerkView,
+    #[strum(serialize = "Log")]
+    Log,
+}
+
+#[derive(Default)]
+pub(crate) struct DetachedPanels {
+    panels: BTreeSet<DetachablePanel>,
+}
+
+impl DetachedPanels {
+    pub(crate) fn is_detached(&self, panel: DetachablePanel) -> bool {
+        self.panels.contains(&panel)
+    }
+
+    /// Button detaching the panel, native windows are not available on the
+    /// web
+    pub(crate) fn draw_detach_button(&mut self, ui: &mut egui::Ui, panel: DetachablePanel) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui
+            .button(egui_phosphor::regular::ARROW_SQUARE_OUT)
+            .on_hover_text("Detach into a separate window")
+            .clicked()
+        {
+            self.panels.insert(panel);
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = (ui, panel);
+    }
+
+    /// Draws the panel in its own window if it's detached, returns `false` if
+    /// the panel is to be drawn in place
+    pub(crate) fn show(
+        &mut self,
+        ctx: &Context,
+        panel: DetachablePanel,
+        add_contents: impl FnOnce(&mut egui::Ui),
+    ) -> bool {
+        if !self.is_detached(panel) {
+            return false;
+        }
+
+        let attach = ctx.show_viewport_immediate(
+            ViewportId::from_hash_of(panel.as_ref()),
+            ViewportBuilder::default()
+                .with_title(format!("GroveDBG: {}", panel.as_ref()))
+                .with_inner_size(DETACHED_WINDOW_SIZE),
+            |viewport_ctx, _| {
+                let mut attach = false;
+                egui::CentralPanel::default().show(viewport_ctx, |ui| {
+                    attach = ui
+                        .button(format!("{} Attach back", egui_phosphor::regular::ARROW_SQUARE_IN))
+                        .clicked();
+                    ui.separator();
+                    add_contents(ui);
+                });
+                attach || viewport_ctx.input(|i| i.viewport().close_requested())
+            },
+        );
+
+        if attach {
+            self.panels.remove(&panel);
+        }
+        true
+    }
+}
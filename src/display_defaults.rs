@@ -0,0 +1,138 @@
+//! Default display variants of bytes used for newly fetched data instead of
+//! guessing it for each node.
+
+use eframe::{
+    egui::{self, ComboBox, DragValue},
+    Storage,
+};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::bytes_utils::BytesDisplayVariant;
+
+const DISPLAY_DEFAULTS_KEY: &'static str = "display_defaults";
+const DEFAULT_LARGE_VALUE_BYTES: usize = 4096;
+
+fn default_large_value_bytes() -> usize {
+    DEFAULT_LARGE_VALUE_BYTES
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DisplayDefaults {
+    /// `None` stands for guessing by bytes
+    pub(crate) keys: Option<BytesDisplayVariant>,
+    /// `None` stands for guessing by bytes
+    pub(crate) item_values: Option<BytesDisplayVariant>,
+    pub(crate) hashes: BytesDisplayVariant,
+    pub(crate) flags: BytesDisplayVariant,
+    /// Item values longer than this are shown truncated until requested
+    #[serde(default = "default_large_value_bytes")]
+    pub(crate) large_value_bytes: usize,
+    /// Show timestamps in UTC instead of local time
+    #[serde(default)]
+    pub(crate) utc_timestamps: bool,
+}
+
+impl Default for DisplayDefaults {
+    fn default() -> Self {
+        Self {
+            keys: None,
+            item_values: None,
+            hashes: BytesDisplayVariant::Hex,
+            flags: BytesDisplayVariant::U8,
+            large_value_bytes: DEFAULT_LARGE_VALUE_BYTES,
+            utc_timestamps: false,
+        }
+    }
+}
+
+type Reapply = bool;
+
+impl DisplayDefaults {
+    pub(crate) fn persist(&self, storage: &mut dyn Storage) {
+        if let Ok(s) = serde_json::to_string(self) {
+            storage.set_string(DISPLAY_DEFAULTS_KEY, s);
+        }
+    }
+
+    pub(crate) fn restore(storage: Option<&dyn Storage>) -> Self {
+        storage
+            .and_then(|s| s.get_string(DISPLAY_DEFAULTS_KEY))
+            .and_then(|param| serde_json::from_str(&param).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn key(&self, key: &[u8]) -> BytesDisplayVariant {
+        self.keys.unwrap_or_else(|| BytesDisplayVariant::guess(key))
+    }
+
+    pub(crate) fn item_value(&self, value: &[u8]) -> BytesDisplayVariant {
+        self.item_values
+            .unwrap_or_else(|| BytesDisplayVariant::guess(&value[..value.len().min(self.large_value_bytes)]))
+    }
+
+    /// Draw display defaults settings, returns `true` if the defaults shall be
+    /// applied to already loaded data
+    pub(crate) fn draw(&mut self, ui: &mut egui::Ui) -> Reapply {
+        egui::Grid::new("display_defaults")
+            .num_columns(2)
+            .show(ui, |grid| {
+                grid.label("Keys:");
+                guessed_variant_combo(grid, "keys", &mut self.keys);
+                grid.end_row();
+
+                grid.label("Item values:");
+                guessed_variant_combo(grid, "item_values", &mut self.item_values);
+                grid.end_row();
+
+                grid.label("Hashes:");
+                variant_combo(grid, "hashes", &mut self.hashes);
+                grid.end_row();
+
+                grid.label("Flags:");
+                variant_combo(grid, "flags", &mut self.flags);
+                grid.end_row();
+
+                grid.label("Large values:");
+                grid.add(
+                    DragValue::new(&mut self.large_value_bytes)
+                        .range(64..=usize::MAX)
+                        .suffix(" bytes"),
+                )
+                .on_hover_text("Item values above the size are shown truncated until loaded fully");
+                grid.end_row();
+
+                grid.label("Timestamps:");
+                grid.horizontal(|line| {
+                    line.radio_value(&mut self.utc_timestamps, false, "Local time");
+                    line.radio_value(&mut self.utc_timestamps, true, "UTC");
+                });
+                grid.end_row();
+            });
+
+        ui.button("Re-apply defaults")
+            .on_hover_text("Reset display variants of all loaded data to the defaults")
+            .clicked()
+    }
+}
+
+fn guessed_variant_combo(ui: &mut egui::Ui, id: &str, variant: &mut Option<BytesDisplayVariant>) {
+    ComboBox::from_id_salt(id)
+        .selected_text(variant.as_ref().map(|v| v.as_ref()).unwrap_or("Guess"))
+        .show_ui(ui, |combo| {
+            combo.selectable_value(variant, None, "Guess");
+            for v in BytesDisplayVariant::iter() {
+                combo.selectable_value(variant, Some(v), v.as_ref());
+            }
+        });
+}
+
+fn variant_combo(ui: &mut egui::Ui, id: &str, variant: &mut BytesDisplayVariant) {
+    ComboBox::from_id_salt(id)
+        .selected_text(variant.as_ref())
+        .show_ui(ui, |combo| {
+            for v in BytesDisplayVariant::iter() {
+                combo.selectable_value(variant, v, v.as_ref());
+            }
+        });
+}
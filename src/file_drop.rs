@@ -0,0 +1,149 @@
+//! Files dropped onto the window: proofs open in the proof viewer,
+//! snapshots are loaded into the current state and operation logs are
+//! loaded for replay. Proofs come either bare, as returned by GroveDB, or
+//! within a reproduction bundle that has the path query too.
+
+use eframe::egui::{self, Align2, Color32, FontId, Id, LayerId, Order};
+use grovedbg_types::{PathQuery, Proof, Query, SizedQuery, SubqueryBranch};
+use serde::Deserialize;
+
+use crate::{
+    replay::{self, Operation},
+    snapshot::SessionSnapshot,
+};
+
+pub(crate) enum DroppedPayload {
+    Proof(Proof, PathQuery),
+    Snapshot(SessionSnapshot),
+    OperationLog(Vec<Operation>),
+}
+
+/// Part of a proof reproduction bundle needed to view the proof
+#[derive(Deserialize)]
+struct ProofBundle {
+    path_query: PathQuery,
+    proof: Proof,
+}
+
+/// Bare proofs come without a query, so no absences can be derived
+fn empty_path_query() -> PathQuery {
+    PathQuery {
+        path: Vec::new(),
+        query: SizedQuery {
+            query: Query {
+                items: Vec::new(),
+                default_subquery_branch: SubqueryBranch {
+                    subquery_path: None,
+                    subquery: None,
+                },
+                conditional_subquery_branches: Vec::new(),
+                left_to_right: true,
+            },
+            limit: None,
+            offset: None,
+        },
+    }
+}
+
+/// Detects the payload type by the fields of the JSON object, operation logs
+/// are JSON lines so only their first line is checked
+fn parse(bytes: &[u8]) -> Result<DroppedPayload, String> {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        let first_line = text.lines().find(|line| !line.trim().is_empty());
+        if first_line.is_some_and(|line| {
+            serde_json::from_str::<serde_json::Value>(line).is_ok_and(|value| value.get("op").is_some())
+        }) {
+            return replay::parse_log(text)
+                .map(DroppedPayload::OperationLog)
+                .map_err(|e| format!("invalid operation log: {e}"));
+        }
+    }
+
+    let value: serde_json::Value =
+        serde_json::from_slice(bytes).map_err(|e| format!("not a JSON file: {e}"))?;
+    let Some(object) = value.as_object() else {
+        return Err("expected a JSON object".to_owned());
+    };
+
+    if object.contains_key("proof") {
+        serde_json::from_value::<ProofBundle>(value)
+            .map(|bundle| DroppedPayload::Proof(bundle.proof, bundle.path_query))
+            .map_err(|e| format!("invalid proof bundle: {e}"))
+    } else if object.contains_key("root_layer") {
+        serde_json::from_value(value)
+            .map(|proof| DroppedPayload::Proof(proof, empty_path_query()))
+            .map_err(|e| format!("invalid proof: {e}"))
+    } else if object.contains_key("nodes") {
+        serde_json::from_value(value)
+            .map(DroppedPayload::Snapshot)
+            .map_err(|e| format!("invalid snapshot: {e}"))
+    } else {
+        Err("neither a proof nor a snapshot".to_owned())
+    }
+}
+
+/// Contents of a dropped file, the web version gets bytes while native gets a
+/// path to read
+fn file_bytes(file: &egui::DroppedFile) -> Result<Vec<u8>, String> {
+    if let Some(bytes) = &file.bytes {
+        return Ok(bytes.to_vec());
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &file.path {
+        return std::fs::read(path).map_err(|e| e.to_string());
+    }
+    Err("file contents are not available".to_owned())
+}
+
+fn file_name(file: &egui::DroppedFile) -> String {
+    file.path
+        .as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| file.name.clone())
+}
+
+/// Files dropped this frame with their parsed payloads
+pub(crate) fn take_dropped(ctx: &egui::Context) -> Vec<(String, Result<DroppedPayload, String>)> {
+    ctx.input(|i| i.raw.dropped_files.clone())
+        .iter()
+        .map(|file| (file_name(file), file_bytes(file).and_then(|bytes| parse(&bytes))))
+        .collect()
+}
+
+/// Dims the window while files are dragged over it
+pub(crate) fn draw_hover_overlay(ctx: &egui::Context) {
+    if ctx.input(|i| i.raw.hovered_files.is_empty()) {
+        return;
+    }
+    let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("file_drop")));
+    let screen_rect = ctx.screen_rect();
+    painter.rect_filled(screen_rect, 0., Color32::from_black_alpha(192));
+    painter.text(
+        screen_rect.center(),
+        Align2::CENTER_CENTER,
+        "Drop a proof, a snapshot or an operation log to open it",
+        FontId::proportional(24.),
+        Color32::WHITE,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_type_is_detected() {
+        let snapshot = r#"{"version": 1, "grovedbg_version": "1.0.0", "grovedb_version": null,
+            "exported_at": "2024-01-01T00:00:00Z", "nodes": []}"#;
+        assert!(matches!(
+            parse(snapshot.as_bytes()),
+            Ok(DroppedPayload::Snapshot(s)) if s.nodes.is_empty()
+        ));
+        assert!(matches!(
+            parse(b"{\"op\": \"delete\", \"path\": [], \"key\": [1]}\n"),
+            Ok(DroppedPayload::OperationLog(operations)) if operations.len() == 1
+        ));
+        assert!(parse(br#"{"something": "else"}"#).is_err());
+        assert!(parse(b"not json").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Element flags decoders, a profile picks the ones that match its GroveDB
+//! usage.
+
+use eframe::egui;
+use grovedb_epoch_based_storage_flags::StorageFlags;
+use integer_encoding::VarInt;
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+
+#[derive(Debug, AsRefStr, EnumIter, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum FlagsDecoder {
+    #[strum(serialize = "Drive storage flags")]
+    StorageFlags,
+    #[strum(serialize = "u32 BE version")]
+    U32BeVersion,
+    #[strum(serialize = "u64 BE")]
+    U64Be,
+    #[strum(serialize = "Variable length integer")]
+    VarInt,
+    #[strum(serialize = "String")]
+    String,
+    #[strum(serialize = "JSON")]
+    Json,
+}
+
+impl FlagsDecoder {
+    /// Returns `None` if flags are not in the decoder's format
+    pub(crate) fn decode(&self, flags: &[u8]) -> Option<String> {
+        self.try_decode(flags).ok()
+    }
+
+    /// Same as `decode`, but tells why flags are not in the decoder's format
+    pub(crate) fn try_decode(&self, flags: &[u8]) -> Result<String, String> {
+        match self {
+            FlagsDecoder::StorageFlags => match StorageFlags::deserialize(flags) {
+                Ok(Some(f)) => Ok(f.to_string()),
+                Ok(None) => Err("Flags are empty".to_owned()),
+                Err(e) => Err(storage_flags_error(flags, e)),
+            },
+            FlagsDecoder::U32BeVersion => <[u8; 4]>::try_from(flags)
+                .map(|b| format!("v{}", u32::from_be_bytes(b)))
+                .map_err(|_| format!("Expected 4 bytes, got {}", flags.len())),
+            FlagsDecoder::U64Be => <[u8; 8]>::try_from(flags)
+                .map(|b| u64::from_be_bytes(b).to_string())
+                .map_err(|_| format!("Expected 8 bytes, got {}", flags.len())),
+            FlagsDecoder::VarInt => match i64::decode_var(flags) {
+                Some((int, len)) if len == flags.len() => Ok(int.to_string()),
+                Some((_, len)) => Err(format!(
+                    "The integer ends at byte {len}, {} bytes are left",
+                    flags.len() - len
+                )),
+                None => Err("No byte ends the integer".to_owned()),
+            },
+            FlagsDecoder::String => std::str::from_utf8(flags)
+                .map(ToOwned::to_owned)
+                .map_err(|e| format!("Invalid UTF-8 at byte {}", e.valid_up_to())),
+            FlagsDecoder::Json => serde_json::from_slice::<serde_json::Value>(flags)
+                .map(|v| v.to_string())
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// The first of decoders that succeeds, or errors of all of them
+    pub(crate) fn decode_first(
+        decoders: &[FlagsDecoder],
+        flags: &[u8],
+    ) -> Result<String, Vec<(Self, String)>> {
+        let mut errors = Vec::new();
+        for decoder in decoders {
+            match decoder.try_decode(flags) {
+                Ok(decoded) => return Ok(decoded),
+                Err(e) => errors.push((*decoder, e)),
+            }
+        }
+        Err(errors)
+    }
+
+    /// Draw a profile's decoders list, with add and remove controls unless
+    /// `read_only`
+    pub(crate) fn draw_list(ui: &mut egui::Ui, decoders: &mut Vec<FlagsDecoder>, read_only: bool) {
+        let mut to_delete = None;
+
+        for (idx, decoder) in decoders.iter().enumerate() {
+            ui.horizontal(|line| {
+                if !read_only
+                    && line
+                        .button(egui_phosphor::regular::TRASH_SIMPLE)
+                        .on_hover_text("Remove flags decoder")
+                        .clicked()
+                {
+                    to_delete = Some(idx);
+                }
+                line.label(decoder.as_ref());
+            });
+        }
+
+        if let Some(idx) = to_delete {
+            decoders.remove(idx);
+        }
+
+        if !read_only {
+            ui.menu_button(egui_phosphor::regular::PLUS_SQUARE, |menu| {
+                for decoder in FlagsDecoder::iter().filter(|d| !decoders.contains(d)) {
+                    if menu.button(decoder.as_ref()).clicked() {
+                        decoders.push(decoder);
+                        menu.close_menu();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Add flags decoder");
+        }
+    }
+}
+
+/// Storage flags error with the layout the type byte promises, since the
+/// error alone doesn't tell where the bytes went wrong
+fn storage_flags_error(flags: &[u8], error: impl std::fmt::Display) -> String {
+    let layout = match flags.first() {
+        Some(0) => "single epoch flags are 3 bytes: type and base epoch",
+        Some(1) => "multi epoch flags are the type, base epoch and pairs of epoch and varint bytes",
+        Some(2) => "owned single epoch flags are 35 bytes: type, owner id and base epoch",
+        Some(3) => {
+            "owned multi epoch flags are the type, owner id, base epoch and pairs of epoch and varint bytes"
+        }
+        Some(t) => return format!("{error}: unknown flags type {t} at byte 0"),
+        None => return error.to_string(),
+    };
+    format!("{error}: {} bytes given, {layout}", flags.len())
+}
+
+/// Decoders used without a profile and by profiles that don't specify any,
+/// same as it was before decoders were configurable
+pub(crate) const DEFAULT_FLAGS_DECODERS: &[FlagsDecoder] = &[FlagsDecoder::StorageFlags];
+
+pub(crate) fn default_flags_decoders() -> Vec<FlagsDecoder> {
+    DEFAULT_FLAGS_DECODERS.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoders_tell_where_flags_break() {
+        assert_eq!(
+            FlagsDecoder::String.try_decode(b"ab\xff"),
+            Err("Invalid UTF-8 at byte 2".to_owned())
+        );
+        assert_eq!(
+            FlagsDecoder::U32BeVersion.try_decode(&[0, 1]),
+            Err("Expected 4 bytes, got 2".to_owned())
+        );
+
+        let errors = FlagsDecoder::decode_first(&[FlagsDecoder::U64Be, FlagsDecoder::VarInt], &[1, 2])
+            .expect_err("two bytes aren't a single varint");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            FlagsDecoder::decode_first(&[FlagsDecoder::VarInt], &[2]),
+            Ok("1".to_owned())
+        );
+    }
+}
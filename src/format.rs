@@ -0,0 +1,99 @@
+//! Formatting of numbers and timestamps shared by views, so sums, counts and
+//! times look the same everywhere.
+
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+
+/// Show timestamps in UTC instead of local time. It's global since bytes are
+/// formatted deep inside of views with no access to settings.
+static UTC_TIMESTAMPS: AtomicBool = AtomicBool::new(false);
+
+const THOUSANDS_SEPARATOR: char = ',';
+/// Milliseconds values outside of 2015-01-01..2100-01-01 are unlikely to be
+/// timestamps
+const PLAUSIBLE_TIMESTAMP_MS: Range<i64> = 1_420_070_400_000..4_102_444_800_000;
+
+pub(crate) fn set_utc_timestamps(utc: bool) {
+    UTC_TIMESTAMPS.store(utc, Ordering::Relaxed);
+}
+
+fn utc_timestamps() -> bool {
+    UTC_TIMESTAMPS.load(Ordering::Relaxed)
+}
+
+/// Integer with thousands separators
+pub(crate) fn number(n: impl Into<i128>) -> String {
+    let n = n.into();
+    let digits = n.unsigned_abs().to_string();
+    let mut grouped = String::new();
+    if n < 0 {
+        grouped.push('-');
+    }
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(THOUSANDS_SEPARATOR);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Date and time with milliseconds in the preferred time zone
+pub(crate) fn timestamp<Tz: TimeZone>(at: &DateTime<Tz>) -> String {
+    if utc_timestamps() {
+        at.with_timezone(&Utc)
+            .format("%Y-%m-%d %H:%M:%S%.3f UTC")
+            .to_string()
+    } else {
+        at.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S%.3f %:z")
+            .to_string()
+    }
+}
+
+/// Time of day in the preferred time zone
+pub(crate) fn time_of_day<Tz: TimeZone>(at: &DateTime<Tz>) -> String {
+    if utc_timestamps() {
+        at.with_timezone(&Utc).format("%H:%M:%S UTC").to_string()
+    } else {
+        at.with_timezone(&Local).format("%H:%M:%S").to_string()
+    }
+}
+
+/// Timestamp given in milliseconds since the Unix epoch
+pub(crate) fn timestamp_millis(ms: i64) -> Option<String> {
+    DateTime::from_timestamp_millis(ms).map(|at| timestamp(&at))
+}
+
+pub(crate) fn is_plausible_timestamp_millis(ms: i64) -> bool {
+    PLAUSIBLE_TIMESTAMP_MS.contains(&ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_grouped_by_thousands() {
+        assert_eq!(number(0), "0");
+        assert_eq!(number(999u64), "999");
+        assert_eq!(number(1000u64), "1,000");
+        assert_eq!(number(-1234567i64), "-1,234,567");
+        assert_eq!(number(i64::MIN), "-9,223,372,036,854,775,808");
+    }
+
+    #[test]
+    fn utc_timestamps_from_millis() {
+        set_utc_timestamps(true);
+        assert_eq!(
+            timestamp_millis(1_700_000_000_123).as_deref(),
+            Some("2023-11-14 22:13:20.123 UTC")
+        );
+        assert!(is_plausible_timestamp_millis(1_700_000_000_123));
+        assert!(!is_plausible_timestamp_millis(42));
+    }
+}
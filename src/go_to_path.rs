@@ -0,0 +1,176 @@
+//! Dialog to jump to a path pasted from elsewhere, like server logs.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use eframe::egui::{self, RichText};
+use grovedbg_types::Key;
+use integer_encoding::VarInt;
+
+use crate::{
+    bus::{CommandBus, UserAction},
+    path_ctx::PathCtx,
+    theme::input_error_color,
+};
+
+#[derive(Default)]
+pub(crate) struct GoToPathDialog {
+    path_input: String,
+    key_input: String,
+    error: Option<String>,
+}
+
+impl GoToPathDialog {
+    pub(crate) fn draw<'pa>(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+        ui.label("Path:");
+        ui.add(
+            egui::TextEdit::multiline(&mut self.path_input)
+                .hint_text(r#"["0a1b", "ff"] or [hex: 0a1b, [255]]"#)
+                .desired_rows(2),
+        );
+        ui.label("Key (optional):");
+        ui.add(egui::TextEdit::singleline(&mut self.key_input).hint_text("hex: 0a1b"));
+
+        if ui.button("Go").clicked() {
+            self.error = None;
+            match self.parse() {
+                Ok((path, key)) => {
+                    let path = path_ctx.add_path(path);
+                    if let Some(key) = key {
+                        bus.fetch().node(path, key.clone());
+                        bus.user_action(UserAction::FocusSubtreeKey(path, key));
+                    } else {
+                        bus.user_action(UserAction::FocusSubtree(path));
+                    }
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(input_error_color(ui.ctx())));
+        }
+    }
+
+    fn parse(&self) -> Result<(Vec<Vec<u8>>, Option<Key>), String> {
+        let path = parse_path(&self.path_input)?;
+        let key = if self.key_input.trim().is_empty() {
+            None
+        } else {
+            Some(parse_segment(self.key_input.trim()).map_err(|e| format!("Key: {e}"))?)
+        };
+        Ok((path, key))
+    }
+}
+
+/// Parses a path either as a JSON array of hex strings or byte arrays, or as
+/// the bracketed format GroveDBG displays paths with.
+pub(crate) fn parse_path(input: &str) -> Result<Vec<Vec<u8>>, String> {
+    let input = input.trim();
+    if input.is_empty() || input == "Root tree" {
+        return Ok(Vec::new());
+    }
+
+    if let Ok(segments) = serde_json::from_str::<Vec<String>>(input) {
+        return segments
+            .iter()
+            .map(|s| hex::decode(s).map_err(|e| format!("Segment {s}: {e}")))
+            .collect();
+    }
+    if let Ok(segments) = serde_json::from_str::<Vec<Vec<u8>>>(input) {
+        return Ok(segments);
+    }
+
+    let inner = input
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or_else(|| "Path shall be enclosed in square brackets".to_owned())?;
+
+    split_top_level(inner)
+        .into_iter()
+        .map(|s| parse_segment(s).map_err(|e| format!("Segment {s}: {e}")))
+        .collect()
+}
+
+/// Splits by commas not enclosed in nested brackets
+fn split_top_level(input: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                segments.push(input[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    segments.push(input[start..].trim());
+    segments.retain(|s| !s.is_empty());
+    segments
+}
+
+/// Parses a single segment in one of the lossless display formats, plain hex
+/// otherwise
+fn parse_segment(segment: &str) -> Result<Vec<u8>, String> {
+    if segment == "empty" {
+        Ok(Vec::new())
+    } else if segment.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(segment).map_err(|e| e.to_string())
+    } else if let Some(hex) = segment.strip_prefix("hex:") {
+        hex::decode(hex.trim()).map_err(|e| e.to_string())
+    } else if let Some(b58) = segment.strip_prefix("b58:") {
+        bs58::decode(b58.trim()).into_vec().map_err(|e| e.to_string())
+    } else if let Some(b64) = segment.strip_prefix("b64:") {
+        BASE64.decode(b64.trim()).map_err(|e| e.to_string())
+    } else if let Some(s) = segment.strip_prefix("str: ") {
+        Ok(s.as_bytes().to_vec())
+    } else if let Some(int) = segment.strip_prefix("varint:") {
+        int.trim()
+            .parse::<i64>()
+            .map(|int| int.encode_var_vec())
+            .map_err(|e| e.to_string())
+    } else {
+        hex::decode(segment).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytes_utils::{bytes_by_display_variant, BytesDisplayVariant};
+
+    #[test]
+    fn parse_json_hex_path() {
+        assert_eq!(
+            parse_path(r#"["0a1b", "ff"]"#).unwrap(),
+            vec![vec![0x0a, 0x1b], vec![0xff]]
+        );
+    }
+
+    #[test]
+    fn parse_displayed_path() {
+        assert_eq!(
+            parse_path("[[64], hex: 0a1b, str: abc, [1, 2]]").unwrap(),
+            vec![vec![64], vec![0x0a, 0x1b], b"abc".to_vec(), vec![1, 2]]
+        );
+    }
+
+    #[test]
+    fn parse_base58_and_base64_round_trip() {
+        let id = vec![7u8; 32];
+        let data = vec![0xffu8; 20];
+        let displayed = format!(
+            "[{}, {}]",
+            bytes_by_display_variant(&id, &BytesDisplayVariant::Base58),
+            bytes_by_display_variant(&data, &BytesDisplayVariant::Base64)
+        );
+        assert_eq!(parse_path(&displayed).unwrap(), vec![id, data]);
+    }
+
+    #[test]
+    fn parse_truncated_hex_fails() {
+        assert!(parse_path("[hex: 0a1b..ffff]").is_err());
+    }
+}
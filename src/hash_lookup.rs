@@ -0,0 +1,194 @@
+//! Dialog to find a node by its node hash or value hash, for when all there is
+//! to go on is a hash from a log line. Loaded data is searched first, GroveDB
+//! is asked only if the endpoint exposes a hash index.
+
+use eframe::egui::{self, RichText};
+use grovedbg_types::{CryptoHash, Key};
+use strum::IntoEnumIterator;
+
+use crate::{
+    bus::{CommandBus, UserAction},
+    path_ctx::{path_as_json_hex, Path},
+    protocol::{Capability, HashLookup, HashLookupKind},
+    theme::input_error_color,
+    tree_data::TreeData,
+};
+
+/// Loaded nodes with the hash of the kind
+pub(crate) fn find_loaded<'pa>(
+    tree_data: &TreeData<'pa>,
+    hash: &CryptoHash,
+    kind: HashLookupKind,
+) -> Vec<(Path<'pa>, Key)> {
+    tree_data
+        .data
+        .iter()
+        .flat_map(|(path, subtree_data)| {
+            subtree_data
+                .borrow()
+                .elements
+                .iter()
+                .filter(|(_, element)| {
+                    let element_hash = match kind {
+                        HashLookupKind::Node => element.node_hash,
+                        HashLookupKind::Value => element.value_hash,
+                    };
+                    element_hash.as_ref() == Some(hash)
+                })
+                .map(|(key, _)| (*path, key.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn parse_hash(input: &str) -> Result<CryptoHash, String> {
+    let bytes = hex::decode(input.trim().trim_start_matches("0x")).map_err(|e| e.to_string())?;
+    let len = bytes.len();
+    bytes
+        .try_into()
+        .map_err(|_| format!("A hash is 32 bytes long, got {len}"))
+}
+
+enum RemoteLookup {
+    Pending,
+    Found(Vec<Vec<u8>>, Key),
+    NotFound,
+}
+
+#[derive(Default)]
+pub(crate) struct HashLookupDialog {
+    hash_input: String,
+    kind: HashLookupKind,
+    remote: Option<(CryptoHash, HashLookupKind, RemoteLookup)>,
+}
+
+impl HashLookupDialog {
+    /// Takes the endpoint's answer if it's for the lookup the dialog waits for
+    pub(crate) fn set_result(&mut self, lookup: &HashLookup) {
+        let Some((hash, kind, remote)) = &mut self.remote else {
+            return;
+        };
+        if *hash == lookup.hash && *kind == lookup.kind {
+            *remote = match &lookup.node {
+                Some(node) => RemoteLookup::Found(node.path.clone(), node.key.clone()),
+                None => RemoteLookup::NotFound,
+            };
+        }
+    }
+
+    pub(crate) fn draw<'pa>(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, tree_data: &TreeData<'pa>) {
+        ui.horizontal(|line| {
+            for kind in HashLookupKind::iter() {
+                line.selectable_value(&mut self.kind, kind, kind.as_ref());
+            }
+        });
+        ui.add(
+            egui::TextEdit::singleline(&mut self.hash_input)
+                .hint_text("Hash in hex")
+                .desired_width(f32::INFINITY),
+        );
+        if self.hash_input.trim().is_empty() {
+            return;
+        }
+        let hash = match parse_hash(&self.hash_input) {
+            Ok(hash) => hash,
+            Err(e) => {
+                ui.label(RichText::new(e).color(input_error_color(ui.ctx())));
+                return;
+            }
+        };
+        ui.separator();
+
+        let loaded = find_loaded(tree_data, &hash, self.kind);
+        if loaded.is_empty() {
+            ui.label("No loaded node has the hash");
+        }
+        for (path, key) in loaded {
+            focus_line(ui, bus, path, key);
+        }
+        ui.separator();
+
+        // Without a hash index on the endpoint the search is limited to
+        // loaded nodes
+        if !bus.supports(Capability::FindNodeByHash) {
+            ui.weak("The endpoint doesn't expose a hash index, only loaded nodes are searched");
+            return;
+        }
+        if ui
+            .button("Ask GroveDB")
+            .on_hover_text("Look the hash up in the whole session")
+            .clicked()
+        {
+            bus.fetch().node_by_hash(hash, self.kind);
+            self.remote = Some((hash, self.kind, RemoteLookup::Pending));
+        }
+
+        let remote = self
+            .remote
+            .as_ref()
+            .filter(|(remote_hash, kind, _)| *remote_hash == hash && *kind == self.kind);
+        match remote.map(|(_, _, remote)| remote) {
+            Some(RemoteLookup::Pending) => {
+                ui.spinner();
+            }
+            Some(RemoteLookup::Found(path, key)) => {
+                ui.label(format!(
+                    "Found under the key {} at {}",
+                    hex::encode(key),
+                    path_as_json_hex(path)
+                ));
+            }
+            Some(RemoteLookup::NotFound) => {
+                ui.label("GroveDB has no node with the hash");
+            }
+            None => {}
+        }
+    }
+}
+
+fn focus_line<'pa>(ui: &mut egui::Ui, bus: &CommandBus<'pa>, path: Path<'pa>, key: Key) {
+    ui.horizontal(|line| {
+        if line
+            .button(egui_phosphor::regular::CROSSHAIR)
+            .on_hover_text("Focus the node")
+            .clicked()
+        {
+            bus.user_action(UserAction::FocusSubtreeKey(path, key.clone()));
+        }
+        line.label(
+            RichText::new(format!(
+                "{} / {}",
+                path_as_json_hex(&path.to_vec()),
+                hex::encode(&key)
+            ))
+            .monospace(),
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{display_defaults::DisplayDefaults, path_ctx::PathCtx, tree_view::ElementView};
+
+    #[test]
+    fn loaded_nodes_are_found_by_hash_kind() {
+        let path_ctx = PathCtx::new();
+        let mut tree_data = TreeData::new(&path_ctx, DisplayDefaults::default());
+        let path = path_ctx.get_root().child(b"a".to_vec());
+        {
+            let mut subtree = tree_data.get_or_create_mut(path);
+            let mut element = ElementView::new_placeholder(b"k".to_vec());
+            (element.value_hash, element.node_hash) = (Some([1; 32]), Some([2; 32]));
+            subtree.elements.insert(b"k".to_vec(), element);
+        }
+
+        let found = find_loaded(&tree_data, &[2; 32], HashLookupKind::Node);
+        assert!(found == vec![(path, b"k".to_vec())]);
+        assert!(find_loaded(&tree_data, &[2; 32], HashLookupKind::Value).is_empty());
+        assert_eq!(find_loaded(&tree_data, &[1; 32], HashLookupKind::Value).len(), 1);
+
+        assert!(parse_hash(&"ab".repeat(32)).is_ok());
+        assert!(parse_hash("abcd").is_err());
+    }
+}
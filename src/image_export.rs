@@ -0,0 +1,299 @@
+//! Export of the tree and merk canvases as images for issue reports and
+//! documentation. A PNG captures the canvas as it's currently shown, while an
+//! SVG diagram covers the loaded structure up to a depth regardless of pan and
+//! zoom.
+
+use std::fmt::Write as _;
+
+use eframe::egui::{self, DragValue};
+
+use crate::{
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    path_ctx::Path,
+};
+
+const SVG_COLUMN_WIDTH: f32 = 170.;
+const SVG_ROW_HEIGHT: f32 = 90.;
+const SVG_BOX_WIDTH: f32 = 150.;
+const SVG_BOX_HEIGHT: f32 = 44.;
+const SVG_MARGIN: f32 = 20.;
+/// Longer labels are cut to fit into a box
+const MAX_LABEL_CHARS: usize = 20;
+const DEFAULT_DEPTH: usize = 4;
+const MAX_DEPTH: usize = 32;
+
+struct DiagramNode {
+    label: String,
+    detail: String,
+    children: Vec<usize>,
+}
+
+/// Tree of labeled boxes, the first node added is the root
+#[derive(Default)]
+pub(crate) struct Diagram {
+    nodes: Vec<DiagramNode>,
+}
+
+impl Diagram {
+    /// Adds a node under `parent` returning its index
+    pub(crate) fn add(&mut self, parent: Option<usize>, label: String, detail: String) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(DiagramNode {
+            label,
+            detail,
+            children: Vec::new(),
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(idx);
+        }
+        idx
+    }
+
+    /// Column and level of each node: leaves take consecutive columns and
+    /// parents are centered above their children
+    fn positions(&self) -> Vec<(f32, usize)> {
+        let mut positions = vec![(0., 0); self.nodes.len()];
+        if !self.nodes.is_empty() {
+            self.place(0, 0, &mut 0., &mut positions);
+        }
+        positions
+    }
+
+    fn place(&self, idx: usize, level: usize, next_column: &mut f32, positions: &mut [(f32, usize)]) -> f32 {
+        let children = &self.nodes[idx].children;
+        let column = if children.is_empty() {
+            let column = *next_column;
+            *next_column += 1.;
+            column
+        } else {
+            let columns: Vec<f32> = children
+                .iter()
+                .map(|child| self.place(*child, level + 1, next_column, positions))
+                .collect();
+            (columns[0] + columns[columns.len() - 1]) / 2.
+        };
+        positions[idx] = (column, level);
+        column
+    }
+
+    pub(crate) fn to_svg(&self) -> String {
+        let positions = self.positions();
+        let columns = positions.iter().map(|(c, _)| *c).fold(0., f32::max) + 1.;
+        let levels = positions.iter().map(|(_, l)| *l).max().unwrap_or_default() + 1;
+        let width = columns * SVG_COLUMN_WIDTH + 2. * SVG_MARGIN;
+        let height = levels as f32 * SVG_ROW_HEIGHT + 2. * SVG_MARGIN;
+        // Top center of a node's box
+        let anchor = |idx: usize| {
+            let (column, level) = positions[idx];
+            (
+                SVG_MARGIN + (column + 0.5) * SVG_COLUMN_WIDTH,
+                SVG_MARGIN + level as f32 * SVG_ROW_HEIGHT,
+            )
+        };
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             font-family=\"monospace\" font-size=\"12\">\n"
+        );
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let (x, y) = anchor(idx);
+            for child in &node.children {
+                let (child_x, child_y) = anchor(*child);
+                let _ = writeln!(
+                    svg,
+                    "<line x1=\"{x}\" y1=\"{}\" x2=\"{child_x}\" y2=\"{child_y}\" stroke=\"gray\"/>",
+                    y + SVG_BOX_HEIGHT
+                );
+            }
+        }
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let (x, y) = anchor(idx);
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{}\" y=\"{y}\" width=\"{SVG_BOX_WIDTH}\" height=\"{SVG_BOX_HEIGHT}\" rx=\"4\" \
+                 fill=\"white\" stroke=\"dimgray\"/>",
+                x - SVG_BOX_WIDTH / 2.
+            );
+            let _ = writeln!(
+                svg,
+                "<text x=\"{x}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                y + 18.,
+                svg_text(&node.label)
+            );
+            let _ = writeln!(
+                svg,
+                "<text x=\"{x}\" y=\"{}\" text-anchor=\"middle\" fill=\"gray\">{}</text>",
+                y + 34.,
+                svg_text(&node.detail)
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Label cut to the box width and escaped
+fn svg_text(text: &str) -> String {
+    let text: String = if text.chars().count() > MAX_LABEL_CHARS {
+        text.chars().take(MAX_LABEL_CHARS - 1).chain(['…']).collect()
+    } else {
+        text.to_owned()
+    };
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Key shown the same way as in the subtree view
+pub(crate) fn key_label(path: Path, key: &[u8]) -> String {
+    let display = path
+        .child(key.to_vec())
+        .get_display_variant()
+        .unwrap_or_else(|| BytesDisplayVariant::guess(key));
+    bytes_by_display_variant(key, &display)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn file_name(name: &str, extension: &str) -> String {
+    format!(
+        "grovedbg-{}-{}.{extension}",
+        name.chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect::<String>(),
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_png(image: &egui::ColorImage, file_name: &str) -> Result<(), image::ImageError> {
+    let [width, height] = image.size;
+    let pixels = image.pixels.iter().flat_map(|p| p.to_array()).collect();
+    image::RgbaImage::from_raw(width as u32, height as u32, pixels)
+        .expect("pixels match the image size")
+        .save(file_name)
+}
+
+/// Image export menu and state of a canvas
+pub(crate) struct ImageExport {
+    depth: usize,
+    /// A screenshot was requested and will be cropped to the canvas once it
+    /// arrives
+    #[cfg(not(target_arch = "wasm32"))]
+    awaiting_screenshot: bool,
+}
+
+impl Default for ImageExport {
+    fn default() -> Self {
+        Self {
+            depth: DEFAULT_DEPTH,
+            #[cfg(not(target_arch = "wasm32"))]
+            awaiting_screenshot: false,
+        }
+    }
+}
+
+impl ImageExport {
+    /// Draws the export menu, `diagram` builds a diagram of the given depth
+    pub(crate) fn draw_menu(&mut self, ui: &mut egui::Ui, name: &str, diagram: impl Fn(usize) -> Diagram) {
+        ui.menu_button(egui_phosphor::regular::IMAGE, |menu| {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                if menu
+                    .button("Save view as PNG")
+                    .on_hover_text("Save the canvas as currently shown to the working directory")
+                    .clicked()
+                {
+                    self.awaiting_screenshot = true;
+                    menu.ctx().send_viewport_cmd(egui::ViewportCommand::Screenshot);
+                    menu.close_menu();
+                }
+                menu.separator();
+            }
+
+            menu.horizontal(|line| {
+                line.label("Diagram depth:");
+                line.add(DragValue::new(&mut self.depth).range(1..=MAX_DEPTH));
+            });
+
+            if menu
+                .button("Copy as SVG")
+                .on_hover_text("Copy a diagram of loaded nodes up to the depth")
+                .clicked()
+            {
+                menu.ctx().copy_text(diagram(self.depth).to_svg());
+                log::info!("Diagram of {name} copied to the clipboard as SVG");
+                menu.close_menu();
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if menu
+                .button("Save as SVG")
+                .on_hover_text("Save a diagram of loaded nodes up to the depth to the working directory")
+                .clicked()
+            {
+                let file_name = file_name(name, "svg");
+                match std::fs::write(&file_name, diagram(self.depth).to_svg()) {
+                    Ok(_) => log::info!("Diagram of {name} saved to {file_name}"),
+                    Err(e) => log::error!("Unable to save diagram of {name}: {e}"),
+                }
+                menu.close_menu();
+            }
+        })
+        .response
+        .on_hover_text("Export as image");
+    }
+
+    /// Saves the requested screenshot cropped to the canvas `rect` once it
+    /// arrives, shall be called every frame the canvas is drawn
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn process_screenshot(&mut self, ctx: &egui::Context, rect: egui::Rect, name: &str) {
+        if !self.awaiting_screenshot {
+            return;
+        }
+        let screenshot = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        let Some(screenshot) = screenshot else {
+            return;
+        };
+        self.awaiting_screenshot = false;
+
+        let file_name = file_name(name, "png");
+        match save_png(
+            &screenshot.region(&rect, Some(ctx.pixels_per_point())),
+            &file_name,
+        ) {
+            Ok(_) => log::info!("View of {name} saved to {file_name}"),
+            Err(e) => log::error!("Unable to save view of {name}: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parents_are_centered_above_children() {
+        let mut diagram = Diagram::default();
+        let root = diagram.add(None, "root".to_owned(), String::new());
+        let left = diagram.add(Some(root), "a".to_owned(), String::new());
+        diagram.add(Some(left), "a1".to_owned(), String::new());
+        diagram.add(Some(left), "a2".to_owned(), String::new());
+        diagram.add(Some(root), "b".to_owned(), String::new());
+
+        assert_eq!(
+            diagram.positions(),
+            vec![(1.25, 0), (0.5, 1), (0., 2), (1., 2), (2., 1)]
+        );
+    }
+
+    #[test]
+    fn svg_labels_are_escaped_and_cut() {
+        assert_eq!(svg_text("a<b>&c"), "a&lt;b&gt;&amp;c");
+        assert_eq!(svg_text(&"x".repeat(30)).chars().count(), MAX_LABEL_CHARS);
+    }
+}
@@ -2,40 +2,107 @@
 
 #![deny(missing_docs)]
 
+mod access_heatmap;
+mod activity_log;
+mod budget;
 mod bus;
 mod bytes_utils;
+mod canvas;
+mod command_palette;
+mod conflicts_view;
+mod density;
+mod detached;
+mod display_defaults;
+mod file_drop;
+mod flags_decoder;
+mod format;
+mod go_to_path;
+mod hash_lookup;
 mod help;
+mod image_export;
+mod memory_view;
+mod merk_hash;
 mod merk_view;
+mod navigation;
+mod node_comparison;
+mod notes;
 mod path_ctx;
+mod permalink;
 mod profiles;
 mod proof_viewer;
 mod protocol;
 mod query_builder;
+mod raw_node_view;
+mod redaction;
+mod replay;
+mod root_history;
+mod schema_registry;
+mod script;
+mod snapshot;
 mod theme;
+mod toasts;
 mod tree_data;
 mod tree_view;
+mod what_if;
 
-use std::time::Duration;
+use std::{collections::VecDeque, time::Duration};
 
+use activity_log::ActivityLog;
+use budget::DataBudget;
 use bus::CommandBus;
+use command_palette::{CommandPalette, PaletteCommand, Panel};
+use density::Density;
+use detached::{DetachablePanel, DetachedPanels};
+use display_defaults::DisplayDefaults;
 use eframe::{
     egui::{self, Context, Theme},
     App, CreationContext, Storage,
 };
-use grovedbg_types::Key;
+use file_drop::DroppedPayload;
+use go_to_path::GoToPathDialog;
+use grovedbg_types::{Key, NodeUpdate};
+use hash_lookup::HashLookupDialog;
 use merk_view::MerkView;
+use navigation::NavigationHistory;
+use node_comparison::NodeComparisonView;
+use notes::Notes;
 use path_ctx::{Path, PathCtx};
 use profiles::ProfilesView;
 use proof_viewer::ProofViewer;
 pub use protocol::start_grovedbg_protocol;
-use protocol::{FetchCommand, GroveGdbUpdate, ProtocolCommand};
+use protocol::{
+    Capability, CommandKind, EndpointInfo, GroveGdbUpdate, NodeSource, ProtocolCommand, ProtocolError,
+    SubtreeCount, UnparsedNode,
+};
 use query_builder::QueryBuilder;
+use raw_node_view::RawNodeView;
+use replay::Replay;
+use root_history::RootHistory;
+use schema_registry::SchemaRegistryView;
+use script::ScriptPlayer;
+use toasts::Toasts;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tree_data::TreeData;
+use tree_data::{ElementCount, SavedLayout, TreeData};
 use tree_view::TreeView;
+use what_if::WhatIf;
 
 const PANEL_MARGIN: f32 = 5.;
 const DARK_THEME_KEY: &'static str = "dark_theme";
+const MAX_IN_FLIGHT_KEY: &'static str = "max_in_flight";
+const PREFETCH_CHILD_ROOTS_KEY: &'static str = "prefetch_child_roots";
+const GUARD_EXPENSIVE_KEY: &'static str = "guard_expensive";
+const KEEP_ALIVE_INTERVAL_KEY: &'static str = "keep_alive_interval";
+
+/// Node updates merged into the tree data per frame, the rest waits for the
+/// next frames so an update storm doesn't freeze the UI (or a browser tab on
+/// wasm, where the protocol shares the thread with rendering). A larger batch
+/// is split across frames.
+///
+/// Responses are already decoded into [NodeUpdate]s by the protocol task, but
+/// merging stays on the UI thread: [TreeData] borrows the path context and is
+/// read on every frame, so it can't be handed to a web worker or another
+/// thread without being rebuilt around shared ownership.
+const NODE_UPDATES_PER_FRAME: usize = 2000;
 
 type ProtocolSender = Sender<ProtocolCommand>;
 type UpdatesReceiver = Receiver<GroveGdbUpdate>;
@@ -49,6 +116,7 @@ pub fn start_grovedbg_app(
     let mut fonts = egui::FontDefinitions::default();
     egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
     cc.egui_ctx.set_fonts(fonts);
+    egui_extras::install_image_loaders(&cc.egui_ctx);
 
     let dark_theme = cc
         .storage
@@ -61,16 +129,53 @@ pub fn start_grovedbg_app(
 
     let path_ctx = Box::leak(Box::new(PathCtx::new()));
 
-    let bus = CommandBus::new(protocol_sender);
+    let max_in_flight = cc
+        .storage
+        .and_then(|s| s.get_string(MAX_IN_FLIGHT_KEY))
+        .and_then(|param| param.parse::<usize>().ok())
+        .unwrap_or(bus::DEFAULT_MAX_IN_FLIGHT);
+
+    let prefetch_child_roots = cc
+        .storage
+        .and_then(|s| s.get_string(PREFETCH_CHILD_ROOTS_KEY))
+        .and_then(|param| param.parse::<bool>().ok())
+        .unwrap_or_default();
+
+    let guard_expensive = cc
+        .storage
+        .and_then(|s| s.get_string(GUARD_EXPENSIVE_KEY))
+        .and_then(|param| param.parse::<bool>().ok())
+        .unwrap_or_default();
+
+    let keep_alive_interval = cc
+        .storage
+        .and_then(|s| s.get_string(KEEP_ALIVE_INTERVAL_KEY))
+        .and_then(|param| param.parse::<u32>().ok())
+        .unwrap_or(bus::DEFAULT_KEEP_ALIVE_INTERVAL);
+
+    let bus = CommandBus::new(
+        protocol_sender,
+        DataBudget::restore(cc.storage),
+        max_in_flight,
+        prefetch_child_roots,
+        guard_expensive,
+        keep_alive_interval,
+    );
 
     bus.new_session();
 
+    #[cfg(target_arch = "wasm32")]
+    let permalink = permalink::init(&cc.integration_info.web_info.location);
+    #[cfg(not(target_arch = "wasm32"))]
+    let permalink = None;
+
     Box::new(GroveDbgApp::new(
         cc.storage,
         bus,
         updates_receiver,
         path_ctx,
         dark_theme,
+        permalink,
     ))
 }
 
@@ -90,10 +195,44 @@ struct GroveDbgApp {
     profiles_view: ProfilesView,
     show_help: bool,
     show_log: bool,
+    activity_log: ActivityLog,
+    toasts: Toasts,
     show_merk_view: bool,
     merk_panel_width: f32,
     focused_subtree: Option<FocusedSubree<'static>>,
+    navigation: NavigationHistory<'static>,
+    /// Layout of the previous run, restored once the first session is started
+    saved_layout: Option<SavedLayout>,
+    /// Element of the link the app was opened with, focused once the first
+    /// session is started
+    permalink: Option<permalink::Target>,
     blocked: bool,
+    node_comparison: Option<NodeComparisonView>,
+    raw_node: Option<RawNodeView>,
+    what_if: Option<WhatIf<'static>>,
+    unparsed_nodes: Vec<UnparsedNode>,
+    /// Rest of a batch of node updates too large to merge in one frame, it is
+    /// merged before any later update
+    deferred_node_updates: VecDeque<(NodeSource, NodeUpdate)>,
+    show_unparsed_nodes: bool,
+    show_settings: bool,
+    go_to_path: Option<GoToPathDialog>,
+    hash_lookup: Option<HashLookupDialog>,
+    notes: Notes,
+    show_notes: bool,
+    show_memory: bool,
+    show_conflicts: bool,
+    replay: Replay,
+    show_replay: bool,
+    script_player: ScriptPlayer,
+    show_script: bool,
+    root_history: RootHistory<'static>,
+    show_root_history: bool,
+    schema_registry: SchemaRegistryView,
+    show_schemas: bool,
+    split_pane: Option<SplitPane>,
+    detached: DetachedPanels,
+    command_palette: CommandPalette,
 }
 
 const SHOW_QUERY_BUILDER_KEY: &'static str = "show_query_builder";
@@ -110,7 +249,10 @@ impl GroveDbgApp {
         updates_receiver: UpdatesReceiver,
         path_ctx: &'static PathCtx,
         dark_theme: bool,
+        permalink: Option<permalink::Target>,
     ) -> Self {
+        Density::restore(storage).apply();
+        schema_registry::restore(storage);
         GroveDbgApp {
             tree_view: TreeView::new(path_ctx),
             merk_view: MerkView::new(),
@@ -119,7 +261,7 @@ impl GroveDbgApp {
             path_ctx,
             query_builder: QueryBuilder::new(),
             proof_viewer: None,
-            tree_data: TreeData::new(path_ctx),
+            tree_data: TreeData::new(path_ctx, DisplayDefaults::restore(storage)),
             show_query_builder: storage
                 .and_then(|s| s.get_string(SHOW_QUERY_BUILDER_KEY))
                 .and_then(|param| param.parse::<bool>().ok())
@@ -139,16 +281,53 @@ impl GroveDbgApp {
                 .and_then(|s| s.get_string(SHOW_LOG_KEY))
                 .and_then(|param| param.parse::<bool>().ok())
                 .unwrap_or(true),
+            activity_log: Default::default(),
+            toasts: Default::default(),
             show_merk_view: storage
                 .and_then(|s| s.get_string(SHOW_MERK_VIEW_KEY))
                 .and_then(|param| param.parse::<bool>().ok())
                 .unwrap_or(true),
             merk_panel_width: 0.,
             focused_subtree: None,
+            navigation: Default::default(),
+            saved_layout: SavedLayout::restore(storage),
+            permalink,
             blocked: false,
+            node_comparison: None,
+            raw_node: None,
+            what_if: None,
+            unparsed_nodes: Vec::new(),
+            deferred_node_updates: VecDeque::new(),
+            show_unparsed_nodes: false,
+            show_settings: false,
+            go_to_path: None,
+            hash_lookup: None,
+            notes: Notes::restore(storage),
+            show_notes: false,
+            show_memory: false,
+            show_conflicts: false,
+            replay: Default::default(),
+            show_replay: false,
+            script_player: Default::default(),
+            show_script: false,
+            root_history: Default::default(),
+            show_root_history: false,
+            schema_registry: Default::default(),
+            show_schemas: false,
+            split_pane: None,
+            detached: Default::default(),
+            command_palette: Default::default(),
         }
     }
 
+    /// Shows the proof in place of the previous one, which passes on the
+    /// proof pinned for comparison
+    fn open_proof(&mut self, mut proof_viewer: ProofViewer) {
+        proof_viewer.keep_pinned(self.proof_viewer.take());
+        self.proof_viewer = Some(proof_viewer);
+        self.show_proof_viewer = true;
+    }
+
     fn draw_profiles_panel(&mut self, ctx: &Context) {
         egui::SidePanel::left("profiles")
             .default_width(10.)
@@ -168,7 +347,8 @@ impl GroveDbgApp {
                     egui::Frame::default()
                         .outer_margin(PANEL_MARGIN)
                         .show(ui, |frame| {
-                            self.profiles_view.draw(frame, &self.bus, self.path_ctx);
+                            self.profiles_view
+                                .draw(frame, &self.bus, self.path_ctx, &self.tree_data);
                         });
                 } else {
                     if ui
@@ -206,6 +386,7 @@ impl GroveDbgApp {
                                 &self.path_ctx,
                                 self.profiles_view.active_profile_root_ctx(),
                                 &self.bus,
+                                &self.tree_data,
                             );
                         });
                 } else {
@@ -220,7 +401,22 @@ impl GroveDbgApp {
             });
     }
 
+    fn draw_proof_viewer(&mut self, ui: &mut egui::Ui) {
+        if let Some(proof_viewer) = &mut self.proof_viewer {
+            proof_viewer.draw(ui, &self.bus, &self.path_ctx, &self.tree_data.proof_mismatches);
+        } else {
+            ui.label("No proof to show yet");
+        }
+    }
+
     fn draw_proof_viewer_panel(&mut self, ctx: &Context) {
+        let mut detached = std::mem::take(&mut self.detached);
+        let shown = detached.show(ctx, DetachablePanel::ProofViewer, |ui| self.draw_proof_viewer(ui));
+        self.detached = detached;
+        if shown {
+            return;
+        }
+
         egui::SidePanel::left("proof_viewer")
             .default_width(10.)
             .show(ctx, |ui| {
@@ -234,17 +430,13 @@ impl GroveDbgApp {
                             self.show_proof_viewer = false;
                         }
                         line.label("Proof viewer");
+                        self.detached
+                            .draw_detach_button(line, DetachablePanel::ProofViewer);
                     });
                     ui.separator();
                     egui::Frame::default()
                         .outer_margin(PANEL_MARGIN)
-                        .show(ui, |frame| {
-                            if let Some(proof_viewer) = &mut self.proof_viewer {
-                                proof_viewer.draw(frame, &self.bus, &self.path_ctx);
-                            } else {
-                                frame.label("No proof to show yet");
-                            }
-                        });
+                        .show(ui, |frame| self.draw_proof_viewer(frame));
                 } else {
                     if ui
                         .button(egui_phosphor::variants::regular::LOCK_KEY)
@@ -258,6 +450,13 @@ impl GroveDbgApp {
     }
 
     fn draw_log_panel(&mut self, ctx: &Context) {
+        if self
+            .detached
+            .show(ctx, DetachablePanel::Log, |ui| self.activity_log.draw(ui))
+        {
+            return;
+        }
+
         egui::SidePanel::right("log").default_width(10.).show(ctx, |ui| {
             if self.show_log {
                 ui.horizontal(|line| {
@@ -269,13 +468,14 @@ impl GroveDbgApp {
                     {
                         self.show_log = false;
                     }
+                    self.detached.draw_detach_button(line, DetachablePanel::Log);
                 });
                 ui.separator();
 
                 egui::Frame::default()
                     .outer_margin(PANEL_MARGIN)
                     .show(ui, |frame| {
-                        egui_logger::logger_ui().show(frame);
+                        self.activity_log.draw(frame);
                     });
             } else {
                 if ui
@@ -289,7 +489,62 @@ impl GroveDbgApp {
         });
     }
 
+    fn draw_unparsed_nodes_window(&mut self, ctx: &Context) {
+        let mut clear = false;
+        egui::Window::new("Unparsed nodes")
+            .open(&mut self.show_unparsed_nodes)
+            .show(ctx, |ui| {
+                if ui.button("Clear").clicked() {
+                    clear = true;
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |scroll| {
+                    for (idx, UnparsedNode { raw, error }) in self.unparsed_nodes.iter().enumerate() {
+                        scroll.label(error);
+                        egui::CollapsingHeader::new("Raw data")
+                            .id_salt(idx)
+                            .show(scroll, |collapsing| {
+                                collapsing.add(egui::Label::new(raw).wrap());
+                            });
+                        scroll.separator();
+                    }
+                });
+            });
+
+        if clear {
+            self.unparsed_nodes.clear();
+            self.show_unparsed_nodes = false;
+        }
+    }
+
+    fn draw_merk_view(&mut self, ui: &mut egui::Ui) {
+        self.merk_view.draw(
+            ui,
+            &self.bus,
+            self.tree_data.merk_selected,
+            &mut self.tree_data.data,
+            self.tree_data.proof_data.get_mut(&self.tree_data.merk_selected),
+            &self.tree_data.proof_mismatches,
+            self.tree_data
+                .proof_absences
+                .get(&self.tree_data.merk_selected)
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+            self.profiles_view
+                .active_profile_root_ctx()
+                .fast_forward(self.tree_data.merk_selected),
+        );
+    }
+
     fn draw_merk_view_panel(&mut self, ctx: &Context) {
+        let mut detached = std::mem::take(&mut self.detached);
+        let shown = detached.show(ctx, DetachablePanel::MerkView, |ui| self.draw_merk_view(ui));
+        self.detached = detached;
+        if shown {
+            self.merk_panel_width = 0.;
+            return;
+        }
+
         let width = egui::SidePanel::left("merk_view")
             .default_width(10.)
             .show(ctx, |ui| {
@@ -303,22 +558,12 @@ impl GroveDbgApp {
                             self.show_merk_view = false;
                         }
                         line.label("Merk view");
+                        self.detached.draw_detach_button(line, DetachablePanel::MerkView);
                     });
                     ui.separator();
                     egui::Frame::default()
                         .outer_margin(PANEL_MARGIN)
-                        .show(ui, |frame| {
-                            self.merk_view.draw(
-                                frame,
-                                &self.bus,
-                                self.tree_data.merk_selected,
-                                &mut self.tree_data.data,
-                                self.tree_data.proof_data.get_mut(&self.tree_data.merk_selected),
-                                self.profiles_view
-                                    .active_profile_root_ctx()
-                                    .fast_forward(self.tree_data.merk_selected),
-                            );
-                        });
+                        .show(ui, |frame| self.draw_merk_view(frame));
                 } else {
                     if ui
                         .button(egui_phosphor::variants::regular::TREE_STRUCTURE)
@@ -335,6 +580,52 @@ impl GroveDbgApp {
 
         self.merk_panel_width = width;
     }
+
+    /// Runs a command chosen in the command palette
+    fn run_command(&mut self, ctx: &egui::Context, command: PaletteCommand) {
+        match command {
+            PaletteCommand::NewSession => self.bus.new_session(),
+            PaletteCommand::KeepAndNewSession => {
+                if self.bus.supports(Capability::ConcurrentSessions) {
+                    self.bus.new_session_keep_current();
+                }
+            }
+            PaletteCommand::GoToPastedPath => self.go_to_path = Some(Default::default()),
+            PaletteCommand::FindByHash => self.hash_lookup = Some(Default::default()),
+            PaletteCommand::GoToPath(path) => {
+                let path = self.path_ctx.add_path(path);
+                self.bus.user_action(bus::UserAction::FocusSubtree(path));
+            }
+            PaletteCommand::RunShortcut(idx) => {
+                self.profiles_view.open_shortcut(idx, &self.bus, self.path_ctx)
+            }
+            PaletteCommand::TogglePanel(panel) => {
+                let show = match panel {
+                    Panel::QueryBuilder => &mut self.show_query_builder,
+                    Panel::ProofViewer => &mut self.show_proof_viewer,
+                    Panel::Profiles => &mut self.show_profiles,
+                    Panel::Log => &mut self.show_log,
+                    Panel::MerkView => &mut self.show_merk_view,
+                    Panel::Notes => &mut self.show_notes,
+                    Panel::Memory => &mut self.show_memory,
+                    Panel::Replay => &mut self.show_replay,
+                    Panel::Script => &mut self.show_script,
+                    Panel::RootHistory => &mut self.show_root_history,
+                    Panel::Schemas => &mut self.show_schemas,
+                    Panel::Conflicts => &mut self.show_conflicts,
+                    Panel::Settings => &mut self.show_settings,
+                    Panel::Help => &mut self.show_help,
+                };
+                *show = !*show;
+            }
+            PaletteCommand::ToggleRedaction => redaction::set_enabled(ctx, !redaction::enabled(ctx)),
+            PaletteCommand::CopySnapshot => {
+                snapshot::copy_snapshot(ctx, &self.tree_data, &self.notes, &self.bus)
+            }
+            PaletteCommand::NavigateBack => self.bus.user_action(bus::UserAction::NavigateBack(1)),
+            PaletteCommand::NavigateForward => self.bus.user_action(bus::UserAction::NavigateForward(1)),
+        }
+    }
 }
 
 impl App for GroveDbgApp {
@@ -345,8 +636,24 @@ impl App for GroveDbgApp {
         storage.set_string(SHOW_LOG_KEY, self.show_log.to_string());
         storage.set_string(SHOW_MERK_VIEW_KEY, self.show_merk_view.to_string());
         storage.set_string(DARK_THEME_KEY, self.dark_theme.to_string());
+        storage.set_string(MAX_IN_FLIGHT_KEY, self.bus.max_in_flight().to_string());
+        storage.set_string(
+            PREFETCH_CHILD_ROOTS_KEY,
+            self.bus.prefetch_child_roots().to_string(),
+        );
+        storage.set_string(GUARD_EXPENSIVE_KEY, self.bus.guard_expensive().to_string());
+        storage.set_string(
+            KEEP_ALIVE_INTERVAL_KEY,
+            self.bus.keep_alive_interval().to_string(),
+        );
 
         self.profiles_view.persist(storage);
+        self.notes.persist(storage);
+        self.bus.budget().persist(storage);
+        self.tree_data.display_defaults().persist(storage);
+        Density::current().persist(storage);
+        schema_registry::persist(storage);
+        self.tree_data.saved_layout().persist(storage);
     }
 
     fn auto_save_interval(&self) -> Duration {
@@ -358,6 +665,11 @@ impl App for GroveDbgApp {
             ui.horizontal(|line| {
                 egui::widgets::global_theme_preference_buttons(line);
 
+                line.separator();
+                self.navigation.draw(line, &self.bus, &self.profiles_view);
+                line.separator();
+                self.profiles_view.draw_shortcuts(line, &self.bus, self.path_ctx);
+
                 if line
                     .button("New session")
                     .on_hover_text(
@@ -368,48 +680,341 @@ impl App for GroveDbgApp {
                     self.bus.new_session();
                 }
 
+                if line
+                    .add_enabled(
+                        self.bus.supports(Capability::ConcurrentSessions),
+                        egui::Button::new("Keep and new session"),
+                    )
+                    .on_disabled_hover_text("GroveDB endpoint doesn't support concurrent sessions")
+                    .on_hover_text(
+                        "Request a new session, but keep the current one to compare nodes against it",
+                    )
+                    .clicked()
+                {
+                    self.bus.new_session_keep_current();
+                }
+
+                self.bus.draw_follow_toggle(line);
+
+                self.bus.draw_guard_lock(line);
+
+                draw_endpoint_info(line, self.bus.endpoint_info());
+
+                draw_traffic(line, protocol::traffic::current());
+
+                if let Some(session_id) = self.bus.comparison_session() {
+                    line.label(format!("Comparing with session {session_id}"));
+                    if line
+                        .button(egui_phosphor::regular::X)
+                        .on_hover_text("Stop comparing and drop the kept session")
+                        .clicked()
+                    {
+                        self.bus.stop_comparison();
+                        self.node_comparison = None;
+                    }
+                }
+
+                if !self.unparsed_nodes.is_empty()
+                    && line
+                        .button(format!(
+                            "{} {} unparsed nodes",
+                            egui_phosphor::regular::WARNING,
+                            self.unparsed_nodes.len()
+                        ))
+                        .on_hover_text("Show nodes received from GroveDB that couldn't be parsed")
+                        .clicked()
+                {
+                    self.show_unparsed_nodes = true;
+                }
+
+                if line
+                    .button(egui_phosphor::regular::CLIPBOARD_TEXT)
+                    .on_hover_text("Go to a pasted path")
+                    .clicked()
+                {
+                    self.go_to_path = Some(Default::default());
+                }
+
+                if line
+                    .button(egui_phosphor::regular::HASH)
+                    .on_hover_text("Find a node by its hash")
+                    .clicked()
+                {
+                    self.hash_lookup = Some(Default::default());
+                }
+
+                line.toggle_value(&mut self.show_notes, egui_phosphor::regular::NOTE)
+                    .on_hover_text("Notes");
+
+                line.toggle_value(&mut self.show_memory, egui_phosphor::regular::CHART_BAR)
+                    .on_hover_text("Memory");
+
+                line.toggle_value(&mut self.show_replay, egui_phosphor::regular::FILM_STRIP)
+                    .on_hover_text("Operation log replay");
+
+                line.toggle_value(&mut self.show_script, egui_phosphor::regular::SCROLL)
+                    .on_hover_text("Navigation script recording and replay");
+
+                line.toggle_value(
+                    &mut self.show_root_history,
+                    egui_phosphor::regular::CLOCK_COUNTER_CLOCKWISE,
+                )
+                .on_hover_text("Root history of pinned subtrees");
+
+                line.toggle_value(&mut self.show_schemas, egui_phosphor::regular::BRACKETS_CURLY)
+                    .on_hover_text("JSON schemas to validate item values with");
+
+                let conflicts = self.tree_data.update_conflicts.len();
+                let conflicts_text = if conflicts > 0 {
+                    egui::RichText::new(format!("{} {conflicts}", egui_phosphor::regular::WARNING_OCTAGON))
+                        .color(theme::input_error_color(ctx))
+                } else {
+                    egui::RichText::new(egui_phosphor::regular::WARNING_OCTAGON)
+                };
+                line.toggle_value(&mut self.show_conflicts, conflicts_text)
+                    .on_hover_text("Conflicts between proofs and fetches of the same session");
+
+                let mut redacted = redaction::enabled(ctx);
+                if line
+                    .toggle_value(&mut redacted, egui_phosphor::regular::EYE_SLASH)
+                    .on_hover_text("Redact item values, subtrees can be exempted in the profile")
+                    .changed()
+                {
+                    redaction::set_enabled(ctx, redacted);
+                }
+
+                if line
+                    .button(egui_phosphor::regular::GEAR)
+                    .on_hover_text("Settings")
+                    .clicked()
+                {
+                    self.show_settings = !self.show_settings;
+                }
+
+                self.bus.budget().draw_usage(line, self.bus.data_usage());
+
                 if self.blocked {
                     line.label("Processing updates...");
                     line.spinner();
                 }
+
+                let waiting = self.bus.waiting_fetches();
+                if waiting > 0 {
+                    line.label(format!("{waiting} fetches queued"));
+                }
             });
             ui.add_space(PANEL_MARGIN);
         });
 
-        while !self.updates_receiver.is_empty() {
+        let mut nodes_updated = false;
+        let received_updates = !self.updates_receiver.is_empty() || !self.deferred_node_updates.is_empty();
+        let mut merged_node_updates = 0;
+
+        while merged_node_updates < NODE_UPDATES_PER_FRAME {
+            if let Some((source, update)) = self.deferred_node_updates.pop_front() {
+                self.tree_data.apply_node_update(update, source);
+                merged_node_updates += 1;
+                nodes_updated = true;
+                continue;
+            }
+            if self.updates_receiver.is_empty() {
+                break;
+            }
             if let Some(update) = self.updates_receiver.blocking_recv() {
                 match update {
-                    GroveGdbUpdate::Node(node_updates) => {
-                        for update in node_updates.into_iter() {
-                            self.tree_data.apply_node_update(update);
+                    GroveGdbUpdate::Node(source, node_updates) => {
+                        // Prefetched roots don't show new subtrees, so they don't trigger another
+                        // prefetch, which would also retry failed nodes endlessly
+                        if source != NodeSource::Prefetch {
+                            self.bus.request_prefetch();
                         }
+                        self.deferred_node_updates
+                            .extend(node_updates.into_iter().map(|update| (source, update)));
+                    }
+                    GroveGdbUpdate::EmptySubtree(path) => {
+                        self.tree_data.confirm_empty(self.path_ctx.add_path(path));
+                        nodes_updated = true;
                     }
-                    GroveGdbUpdate::Proof(proof, node_updates, proof_tree) => {
+                    GroveGdbUpdate::PartialNodes(node_updates, mut unparsed) => {
+                        self.bus.request_prefetch();
+                        self.deferred_node_updates
+                            .extend(node_updates.into_iter().map(|update| (NodeSource::Query, update)));
+                        self.unparsed_nodes.append(&mut unparsed);
+                    }
+                    GroveGdbUpdate::Proof(proof, path_query, node_updates, proof_tree, payload) => {
+                        merged_node_updates += node_updates.len();
+                        self.bus.request_prefetch();
                         for update in node_updates.into_iter() {
-                            self.tree_data.apply_node_update(update);
+                            self.tree_data.apply_node_update(update, NodeSource::Proof);
                         }
-                        self.proof_viewer = Some(ProofViewer::new(proof));
-                        self.tree_data.set_proof_tree(proof_tree);
-                        self.show_proof_viewer = true;
+                        let mut proof_viewer = ProofViewer::new(proof, &path_query);
+                        proof_viewer.set_payload(payload);
+                        self.tree_data.set_proof_tree(proof_tree, proof_viewer.absences());
+                        if self.bus.take_proof_verification() {
+                            proof_viewer.verify(self.tree_data.root_hash());
+                        }
+                        self.open_proof(proof_viewer);
+                    }
+                    GroveGdbUpdate::ProofFailure(failure) => {
+                        let mut proof_viewer = ProofViewer::new_failed(*failure);
+                        if self.bus.take_proof_verification() {
+                            proof_viewer.verify(self.tree_data.root_hash());
+                        }
+                        self.open_proof(proof_viewer);
                     }
                     GroveGdbUpdate::RootUpdate(Some(root_update)) => {
+                        self.bus.request_prefetch();
                         self.tree_data.apply_root_node_update(root_update);
                     }
                     GroveGdbUpdate::RootUpdate(None) => {
                         log::warn!("Received no root node: GroveDB is empty");
+                        self.tree_data.confirm_empty(self.path_ctx.get_root());
+                    }
+                    GroveGdbUpdate::Session(session_id, endpoint_info) => {
+                        self.bus.set_session(session_id, endpoint_info);
+                        self.tree_data.set_session(session_id);
+                        self.bus.fetch().root();
+                        self.tree_data.refetch_pinned(&self.bus);
+                        if let Some(layout) = self.saved_layout.take() {
+                            self.tree_data.restore_layout(layout, &self.bus);
+                        }
+                        if let Some((path, key)) = self.permalink.take() {
+                            let path = self.path_ctx.add_path(path);
+                            if let Some(key) = key {
+                                self.bus.fetch().node(path, key.clone());
+                                self.bus.user_action(bus::UserAction::FocusSubtreeKey(path, key));
+                            } else {
+                                self.bus.user_action(bus::UserAction::FocusSubtree(path));
+                            }
+                        }
+                    }
+                    GroveGdbUpdate::Follow(Some(session_id)) => {
+                        self.bus.follow_session(session_id);
+                        self.tree_data.set_session(session_id);
+                        self.bus.fetch().root();
+                        self.tree_data.refetch_followed(&self.bus);
                     }
-                    GroveGdbUpdate::Session(session_id) => {
-                        self.bus.set_session(session_id);
-                        self.bus.fetch_command(FetchCommand::FetchRoot);
+                    GroveGdbUpdate::Follow(None)
+                    | GroveGdbUpdate::KeepAlive
+                    | GroveGdbUpdate::SessionDropped => {}
+                    GroveGdbUpdate::ComparisonSession(session_id) => {
+                        self.bus.set_comparison_session(session_id)
+                    }
+                    GroveGdbUpdate::Comparison(comparison) => {
+                        self.node_comparison = Some(NodeComparisonView::new(comparison));
+                    }
+                    GroveGdbUpdate::RawNode(raw_node) => {
+                        self.raw_node = Some(RawNodeView::new(raw_node));
+                    }
+                    GroveGdbUpdate::BatchProgress(progress) => self.query_builder.batch_progress(progress),
+                    GroveGdbUpdate::QueryCost(cost) => {
+                        if cost.command == CommandKind::ProvePathQuery {
+                            if let Some(proof_viewer) = &mut self.proof_viewer {
+                                proof_viewer.set_transfer(cost.transfer);
+                            }
+                        }
+                        self.query_builder.push_cost(cost);
+                    }
+                    GroveGdbUpdate::HashLookup(lookup) => {
+                        if let Some(hash_lookup) = &mut self.hash_lookup {
+                            hash_lookup.set_result(&lookup);
+                        }
+                        if let Some(node) = lookup.node {
+                            let path = self.path_ctx.add_path(node.path.clone());
+                            let key = node.key.clone();
+                            self.tree_data.apply_node_update(node, NodeSource::Node);
+                            nodes_updated = true;
+                            self.bus.user_action(bus::UserAction::FocusSubtreeKey(path, key));
+                        }
+                    }
+                    GroveGdbUpdate::SubtreeCount(SubtreeCount { path, count, exact }) => {
+                        self.tree_data
+                            .get_or_create_mut(self.path_ctx.add_path(path))
+                            .element_count = Some(ElementCount { count, exact });
+                    }
+                    GroveGdbUpdate::Error(ProtocolError::SessionExpired) => {
+                        log::warn!("Session expired, starting a new one");
+                        self.bus.renew_expired_session();
+                    }
+                    GroveGdbUpdate::Error(e) => log::error!("Error processing command: {e}"),
+                    GroveGdbUpdate::Activity(event) => {
+                        self.toasts.notify(&event);
+                        self.activity_log.push(event);
                     }
                     GroveGdbUpdate::Block => self.blocked = true,
-                    GroveGdbUpdate::Unblock => self.blocked = false,
+                    GroveGdbUpdate::Unblock => {
+                        self.blocked = false;
+                        if let Some(path) = self.bus.command_processed() {
+                            self.tree_data.finish_refetch(self.path_ctx.add_path(path));
+                            nodes_updated = true;
+                        }
+                    }
                 }
             } else {
                 log::error!("Protocol thread was terminated, can't receive updates anymore");
             }
         }
 
+        if nodes_updated {
+            self.tree_data.continue_expansions(&self.bus);
+        }
+
+        if !self.updates_receiver.is_empty() || !self.deferred_node_updates.is_empty() {
+            ctx.request_repaint();
+        }
+
+        if received_updates {
+            self.root_history.record(self.bus.session(), &self.tree_data);
+        }
+
+        for (name, payload) in file_drop::take_dropped(ctx) {
+            match payload {
+                Ok(DroppedPayload::Proof(proof, path_query)) => {
+                    self.open_proof(ProofViewer::new(proof, &path_query));
+                    log::info!("Opened proof from {name}");
+                }
+                Ok(DroppedPayload::Snapshot(snapshot)) => {
+                    snapshot.import(&mut self.tree_data, &mut self.notes);
+                }
+                Ok(DroppedPayload::OperationLog(operations)) => {
+                    self.replay.load(operations);
+                    self.show_replay = true;
+                }
+                Err(e) => log::error!("Unable to open {name}: {e}"),
+            }
+        }
+
+        if self.bus.take_prefetch_due() {
+            self.tree_data.prefetch_child_roots(&self.bus);
+        }
+
+        if self.bus.budget().enabled {
+            let usage = budget::estimate_usage(&self.tree_data);
+            self.bus.set_data_usage(usage);
+            let to_free = self.bus.budget().eviction_target(usage);
+            if let Some(to_free) = to_free {
+                self.tree_data.evict_hidden(to_free);
+            }
+        }
+
+        self.bus.process_follow(ctx.input(|i| i.time));
+
+        self.bus.process_keep_alive(ctx.input(|i| i.time));
+
+        self.replay.process(
+            ctx.input(|i| i.time),
+            &mut self.tree_data,
+            &self.bus,
+            self.path_ctx,
+        );
+        if self.replay.is_playing() {
+            ctx.request_repaint_after(Duration::from_secs_f64(self.replay.step_interval()));
+        }
+
+        if self.bus.process_throttled(ctx.input(|i| i.time)) {
+            ctx.request_repaint();
+        }
+
         self.draw_log_panel(ctx);
 
         self.draw_profiles_panel(ctx);
@@ -420,61 +1025,367 @@ impl App for GroveDbgApp {
 
         self.draw_merk_view_panel(ctx);
 
+        self.toasts.draw(ctx, &self.bus);
+
+        file_drop::draw_hover_overlay(ctx);
+
         if self.show_help {
             egui::Window::new("Help")
                 .open(&mut self.show_help)
                 .show(ctx, help::show_help);
         }
 
+        if self.show_unparsed_nodes {
+            self.draw_unparsed_nodes_window(ctx);
+        }
+
+        if self.show_settings {
+            egui::Window::new("Settings")
+                .open(&mut self.show_settings)
+                .show(ctx, |ui| {
+                    self.bus.budget().draw(ui);
+                    self.bus.draw_fetch_settings(ui);
+                    ui.separator();
+
+                    ui.label("Default display of bytes");
+                    let mut display_defaults = self.tree_data.display_defaults();
+                    let reapply = display_defaults.draw(ui);
+                    if display_defaults != self.tree_data.display_defaults() {
+                        self.tree_data.set_display_defaults(display_defaults);
+                    }
+                    if reapply {
+                        self.tree_data.reapply_display_defaults();
+                    }
+                    ui.separator();
+
+                    ui.label("Canvas density");
+                    let mut density = Density::current();
+                    density.draw(ui);
+                    if density != Density::current() {
+                        density.apply();
+                    }
+                });
+        }
+
+        if self.show_notes {
+            egui::Window::new("Notes")
+                .open(&mut self.show_notes)
+                .show(ctx, |ui| {
+                    snapshot::draw_export(ui, &self.tree_data, &self.notes, &self.bus);
+                    ui.separator();
+                    self.notes.draw(ui, &self.bus, self.path_ctx);
+                });
+        }
+
+        if self.show_memory {
+            egui::Window::new("Memory")
+                .open(&mut self.show_memory)
+                .show(ctx, |ui| {
+                    memory_view::draw(ui, &mut self.tree_data, &self.profiles_view);
+                });
+        }
+
+        if self.show_conflicts {
+            egui::Window::new("Update conflicts")
+                .open(&mut self.show_conflicts)
+                .show(ctx, |ui| {
+                    conflicts_view::draw(ui, &mut self.tree_data, &self.bus, &self.profiles_view);
+                });
+        }
+
+        if self.show_replay {
+            egui::Window::new("Replay")
+                .open(&mut self.show_replay)
+                .show(ctx, |ui| {
+                    self.replay
+                        .draw(ui, &mut self.tree_data, &self.bus, self.path_ctx);
+                });
+        }
+
+        if self.show_script {
+            egui::Window::new("Navigation script")
+                .open(&mut self.show_script)
+                .show(ctx, |ui| self.script_player.draw(ui, &self.bus, self.path_ctx));
+        }
+
+        if self.show_schemas {
+            egui::Window::new("JSON schemas")
+                .open(&mut self.show_schemas)
+                .show(ctx, |ui| self.schema_registry.draw(ui));
+        }
+
+        if self.show_root_history {
+            egui::Window::new("Root history")
+                .open(&mut self.show_root_history)
+                .show(ctx, |ui| {
+                    self.root_history.draw(ui, &self.profiles_view);
+                });
+        }
+
+        if let Some(go_to_path) = &mut self.go_to_path {
+            let mut open = true;
+            egui::Window::new("Go to path")
+                .open(&mut open)
+                .show(ctx, |ui| go_to_path.draw(ui, &self.bus, self.path_ctx));
+            if !open {
+                self.go_to_path = None;
+            }
+        }
+
+        if let Some(hash_lookup) = &mut self.hash_lookup {
+            let mut open = true;
+            egui::Window::new("Find by hash")
+                .open(&mut open)
+                .show(ctx, |ui| hash_lookup.draw(ui, &self.bus, &self.tree_data));
+            if !open {
+                self.hash_lookup = None;
+            }
+        }
+
+        if let Some(node_comparison) = &self.node_comparison {
+            let mut open = true;
+            egui::Window::new("Node comparison")
+                .open(&mut open)
+                .show(ctx, |ui| node_comparison.draw(ui));
+            if !open {
+                self.node_comparison = None;
+            }
+        }
+
+        if let Some(raw_node) = &self.raw_node {
+            let mut open = true;
+            egui::Window::new("Stored bytes")
+                .open(&mut open)
+                .show(ctx, |ui| raw_node.draw(ui));
+            if !open {
+                self.raw_node = None;
+            }
+        }
+
+        if let Some(what_if) = &mut self.what_if {
+            let mut open = true;
+            egui::Window::new("What-if hashes")
+                .open(&mut open)
+                .show(ctx, |ui| what_if.draw(ui, &self.tree_data));
+            if !open {
+                self.what_if = None;
+            }
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            self.tree_view.draw(
-                ui,
-                &self.bus,
-                self.merk_panel_width / 2.,
-                self.profiles_view.active_profile_root_ctx(),
-                &mut self.tree_data,
-                &self.focused_subtree,
-            );
+            let Some(split_pane) = &mut self.split_pane else {
+                self.tree_view.draw(
+                    ui,
+                    &self.bus,
+                    self.merk_panel_width / 2.,
+                    self.profiles_view.active_profile_root_ctx(),
+                    &mut self.tree_data,
+                    &self.focused_subtree,
+                );
+                return;
+            };
+
+            // Root subtrees are anchored relative to the screen, so each pane
+            // shifts them to its own center
+            let screen_center = ctx.screen_rect().center().x;
+            let mut close_split = false;
+            ui.columns(2, |columns| {
+                let offset = columns[0].max_rect().center().x - screen_center;
+                self.tree_view.draw(
+                    &mut columns[0],
+                    &self.bus,
+                    offset,
+                    self.profiles_view.active_profile_root_ctx(),
+                    &mut self.tree_data,
+                    &self.focused_subtree,
+                );
+
+                let pane = &mut columns[1];
+                close_split = pane
+                    .button(format!("{} Close split", egui_phosphor::regular::X))
+                    .clicked();
+                let offset = pane.max_rect().center().x - screen_center;
+                split_pane.tree_view.draw(
+                    pane,
+                    &self.bus,
+                    offset,
+                    self.profiles_view.active_profile_root_ctx(),
+                    &mut self.tree_data,
+                    &split_pane.focused_subtree,
+                );
+            });
+            if close_split {
+                self.split_pane = None;
+            }
+        });
+
+        ctx.input_mut(|input| {
+            if input.consume_key(egui::Modifiers::ALT, egui::Key::ArrowLeft)
+                || input.pointer.button_pressed(egui::PointerButton::Extra1)
+            {
+                self.bus.user_action(bus::UserAction::NavigateBack(1));
+            }
+            if input.consume_key(egui::Modifiers::ALT, egui::Key::ArrowRight)
+                || input.pointer.button_pressed(egui::PointerButton::Extra2)
+            {
+                self.bus.user_action(bus::UserAction::NavigateForward(1));
+            }
         });
 
+        self.command_palette.draw(ctx, &self.bus, &self.profiles_view);
+
+        let mut palette_commands = Vec::new();
         self.bus.process_actions(|action| match action {
             bus::UserAction::FocusSubtree(path) => {
-                if let Some((parent_path, parent_key)) = path.parent_with_key() {
-                    self.bus.fetch_command(FetchCommand::FetchNode {
-                        path: parent_path.to_vec(),
-                        key: parent_key,
-                    })
-                }
-                self.focused_subtree = Some(FocusedSubree { path, key: None })
+                let focus = FocusedSubree { path, key: None };
+                self.navigation.visit(focus.clone());
+                focus_subtree(&self.bus, &mut self.focused_subtree, focus);
             }
             bus::UserAction::FocusSubtreeKey(path, key) => {
-                if let Some((parent_path, parent_key)) = path.parent_with_key() {
-                    self.bus.fetch_command(FetchCommand::FetchNode {
-                        path: parent_path.to_vec(),
-                        key: parent_key,
-                    })
+                let focus = FocusedSubree { path, key: Some(key) };
+                self.navigation.visit(focus.clone());
+                focus_subtree(&self.bus, &mut self.focused_subtree, focus);
+            }
+            bus::UserAction::NavigateBack(n) => {
+                if let Some(focus) = self.navigation.back(n) {
+                    focus_subtree(&self.bus, &mut self.focused_subtree, focus);
+                }
+            }
+            bus::UserAction::NavigateForward(n) => {
+                if let Some(focus) = self.navigation.forward(n) {
+                    focus_subtree(&self.bus, &mut self.focused_subtree, focus);
                 }
-                self.focused_subtree = Some(FocusedSubree { path, key: Some(key) })
+            }
+            bus::UserAction::ExpandSubtrees(path, depth) => {
+                self.tree_data.expand_subtrees(path, depth, &self.bus);
+            }
+            bus::UserAction::CollapseSubtrees(path) => self.tree_data.collapse_subtrees(path),
+            bus::UserAction::QuerySelectedKeys(path, items) => {
+                path.select_for_query();
+                self.query_builder.prefill_items(path, items);
+                self.show_query_builder = true;
+            }
+            bus::UserAction::QueryKey(path, key) => {
+                path.select_for_query();
+                self.query_builder.prefill_key(path, key);
+                self.show_query_builder = true;
+            }
+            bus::UserAction::SetDefaultQuery(path, query) => {
+                self.profiles_view.set_default_query(path, query);
+            }
+            bus::UserAction::AddShortcut(path, query) => {
+                self.profiles_view.add_shortcut(path, query);
+            }
+            bus::UserAction::VerifyNodeHash(path, key) => self.tree_data.verify_node_hash(path, key),
+            bus::UserAction::OpenWhatIf(path, key) => {
+                self.what_if = WhatIf::new(path, key, &self.tree_data);
+            }
+            bus::UserAction::OpenNote(target) => {
+                self.notes.open(target);
+                self.show_notes = true;
             }
             bus::UserAction::DropFocus => self.focused_subtree = None,
+            bus::UserAction::FocusSplitSubtree(path, key) => {
+                let split_pane = self.split_pane.get_or_insert_with(|| SplitPane {
+                    tree_view: TreeView::new_split(self.path_ctx),
+                    focused_subtree: None,
+                });
+                focus_subtree(
+                    &self.bus,
+                    &mut split_pane.focused_subtree,
+                    FocusedSubree { path, key },
+                );
+            }
+            bus::UserAction::DropSplitFocus => {
+                if let Some(split_pane) = &mut self.split_pane {
+                    split_pane.focused_subtree = None;
+                }
+            }
             bus::UserAction::SelectMerkView(path) => {
                 let key = self.tree_data.get_or_create(path).root_key.as_ref().cloned();
                 if let Some(key) = key {
                     self.tree_data.select_for_merk(path);
-                    self.bus.fetch_command(FetchCommand::FetchNode {
-                        path: path.to_vec(),
-                        key,
-                    });
+                    self.bus.fetch().node(path, key);
                 }
             }
+            bus::UserAction::RunCommand(command) => palette_commands.push(command),
         });
+        palette_commands
+            .into_iter()
+            .for_each(|command| self.run_command(ctx, command));
 
         self.dark_theme = matches!(ctx.theme(), Theme::Dark);
         ctx.request_repaint_after(Duration::from_secs(1));
     }
 }
 
+/// Focuses on a subtree fetching its parent node to make sure the subtree is
+/// known
+fn focus_subtree<'pa>(
+    bus: &CommandBus<'pa>,
+    focused_subtree: &mut Option<FocusedSubree<'pa>>,
+    focus: FocusedSubree<'pa>,
+) {
+    if let Some((parent_path, parent_key)) = focus.path.parent_with_key() {
+        bus.fetch().node(parent_path, parent_key);
+    }
+    *focused_subtree = Some(focus);
+}
+
+#[derive(Clone, PartialEq)]
 pub(crate) struct FocusedSubree<'pa> {
     pub path: Path<'pa>,
     pub key: Option<Key>,
 }
+
+/// Second tree canvas to look at a subtree next to the main one, with its own
+/// pan, zoom and focus
+struct SplitPane {
+    tree_view: TreeView<'static>,
+    focused_subtree: Option<FocusedSubree<'static>>,
+}
+
+/// Readout of the session's requests to spot the debugger putting load on the
+/// endpoint
+fn draw_traffic(ui: &mut egui::Ui, traffic: protocol::traffic::TrafficStats) {
+    let latency = traffic
+        .average_latency()
+        .map(|latency| format!("{} ms", latency.num_milliseconds()))
+        .unwrap_or_else(|| "-".to_owned());
+    ui.label(format!(
+        "{} {} req, {}, {latency}",
+        egui_phosphor::regular::ARROWS_DOWN_UP,
+        format::number(traffic.requests),
+        budget::format_size(traffic.bytes as usize),
+    ))
+    .on_hover_text(
+        "Requests to the endpoint, downloaded bytes and average latency since the session was started",
+    );
+}
+
+fn draw_endpoint_info(ui: &mut egui::Ui, info: Option<EndpointInfo>) {
+    if let Some(info) = info {
+        ui.label(format!("GroveDB {}", info.grovedb_version))
+            .on_hover_text(format!(
+                "grovedbg-types {}\nCapabilities: {}",
+                info.grovedbg_types_version,
+                info.capabilities.iter().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        let unknown: Vec<_> = info
+            .capabilities
+            .iter()
+            .filter(|name| !Capability::is_known(name))
+            .cloned()
+            .collect();
+        if !unknown.is_empty() {
+            ui.label(egui_phosphor::regular::WARNING).on_hover_text(format!(
+                "The endpoint uses features this GroveDBG doesn't support, elements relying on them will \
+                 show up as unparsed nodes: {}",
+                unknown.join(", ")
+            ));
+        }
+    } else {
+        ui.label("GroveDB version unknown")
+            .on_hover_text("The endpoint doesn't advertise its version and capabilities");
+    }
+}
@@ -0,0 +1,93 @@
+//! Memory panel listing the biggest loaded subtrees, so they can be unloaded
+//! when a long session grows too large.
+
+use eframe::egui::{self, Grid, RichText};
+
+use crate::{
+    budget::{self, format_size},
+    format,
+    path_ctx::{path_label, Path},
+    profiles::ProfilesView,
+    tree_data::TreeData,
+    tree_view::ElementOrPlaceholder,
+};
+
+/// Number of subtrees listed
+const TOP_SUBTREES: usize = 20;
+
+pub(crate) fn draw<'pa>(ui: &mut egui::Ui, tree_data: &mut TreeData<'pa>, profiles_view: &ProfilesView) {
+    ui.label(format!(
+        "Estimated usage: {}",
+        format_size(budget::estimate_usage(tree_data))
+    ));
+
+    let shown = tree_data.shown_subtrees();
+    let mut subtrees: Vec<(Path<'pa>, usize, usize, bool)> = tree_data
+        .data
+        .iter()
+        .map(|(path, subtree_data)| {
+            let subtree_data = subtree_data.borrow();
+            let loaded = subtree_data
+                .elements
+                .values()
+                .filter(|e| matches!(e.value, ElementOrPlaceholder::Element(_)))
+                .count();
+            (
+                *path,
+                budget::subtree_usage(&subtree_data),
+                loaded,
+                subtree_data.pinned,
+            )
+        })
+        .filter(|(_, _, loaded, _)| *loaded > 0)
+        .collect();
+    subtrees.sort_by(|a, b| b.1.cmp(&a.1));
+    subtrees.truncate(TOP_SUBTREES);
+
+    if subtrees.is_empty() {
+        ui.label("No subtrees loaded");
+        return;
+    }
+
+    let mut to_unload = None;
+    egui::ScrollArea::vertical().show(ui, |scroll| {
+        Grid::new("memory_subtrees").striped(true).show(scroll, |grid| {
+            grid.label(RichText::new("Subtree").strong());
+            grid.label(RichText::new("Elements").strong());
+            grid.label(RichText::new("Size").strong());
+            grid.label("");
+            grid.end_row();
+
+            for (path, size, loaded, pinned) in subtrees {
+                grid.horizontal(|line| {
+                    path_label(
+                        line,
+                        path,
+                        &profiles_view.active_profile_root_ctx().fast_forward(path),
+                    );
+                    if pinned {
+                        line.label(egui_phosphor::regular::PUSH_PIN)
+                            .on_hover_text("Pinned");
+                    }
+                    if shown.contains(&path) {
+                        line.label(egui_phosphor::regular::EYE).on_hover_text("Shown");
+                    }
+                });
+                grid.label(format::number(loaded as u64));
+                grid.label(format_size(size));
+                if grid
+                    .button("Unload")
+                    .on_hover_text("Drop loaded elements, they can be fetched again")
+                    .clicked()
+                {
+                    to_unload = Some(path);
+                }
+                grid.end_row();
+            }
+        });
+    });
+
+    if let Some(path) = to_unload {
+        tree_data.unload_subtree(path);
+    }
+}
@@ -0,0 +1,163 @@
+//! Merk hash scheme to verify hashes reported by GroveDB against the data
+//! they were computed from.
+
+use grovedbg_types::CryptoHash;
+use integer_encoding::VarInt;
+
+/// Hash used in place of a missing child
+pub(crate) const NULL_HASH: CryptoHash = [0; 32];
+
+pub(crate) fn kv_digest_hash(key: &[u8], value_hash: &CryptoHash) -> CryptoHash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&key.len().encode_var_vec());
+    hasher.update(key);
+    hasher.update(value_hash);
+    *hasher.finalize().as_bytes()
+}
+
+pub(crate) fn node_hash(
+    kv_digest_hash: &CryptoHash,
+    left: Option<&CryptoHash>,
+    right: Option<&CryptoHash>,
+) -> CryptoHash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(kv_digest_hash);
+    hasher.update(left.unwrap_or(&NULL_HASH));
+    hasher.update(right.unwrap_or(&NULL_HASH));
+    *hasher.finalize().as_bytes()
+}
+
+/// Outcome of recomputing a single hash
+#[derive(Clone, Copy)]
+pub(crate) enum Verification {
+    Match,
+    Mismatch {
+        computed: CryptoHash,
+    },
+    /// Not enough data is loaded to recompute the hash
+    Unknown(&'static str),
+}
+
+impl Verification {
+    pub(crate) fn compare(computed: CryptoHash, reported: &CryptoHash) -> Self {
+        if &computed == reported {
+            Verification::Match
+        } else {
+            Verification::Mismatch { computed }
+        }
+    }
+
+    pub(crate) fn is_mismatch(&self) -> bool {
+        matches!(self, Verification::Mismatch { .. })
+    }
+}
+
+/// Locally recomputed hashes of a node compared to the reported ones
+#[derive(Clone, Copy)]
+pub(crate) struct HashCheck {
+    /// Recomputed for items only
+    pub(crate) value_hash: Verification,
+    pub(crate) kv_digest_hash: Verification,
+    pub(crate) node_hash: Verification,
+}
+
+impl HashCheck {
+    pub(crate) fn has_mismatch(&self) -> bool {
+        self.value_hash.is_mismatch() || self.kv_digest_hash.is_mismatch() || self.node_hash.is_mismatch()
+    }
+}
+
+/// Hash of a stored value, for subtree elements it's combined with the root
+/// hash of the child subtree
+pub(crate) fn value_hash(value: &[u8]) -> CryptoHash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&value.len().encode_var_vec());
+    hasher.update(value);
+    *hasher.finalize().as_bytes()
+}
+
+pub(crate) fn combine_hash(first: &CryptoHash, second: &CryptoHash) -> CryptoHash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(first);
+    hasher.update(second);
+    *hasher.finalize().as_bytes()
+}
+
+/// Stored bytes of a subtree element as GroveDB serializes them (bincode with
+/// big endian variable length integers), `sum` is set for sum trees
+pub(crate) fn subtree_element_bytes(
+    root_key: Option<&[u8]>,
+    sum: Option<i64>,
+    flags: Option<&[u8]>,
+) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    // Variant indices of `Element::Tree` and `Element::SumTree`
+    bytes.push(if sum.is_some() { 4 } else { 2 });
+    push_optional_bytes(&mut bytes, root_key);
+    if let Some(sum) = sum {
+        push_varint(&mut bytes, ((sum << 1) ^ (sum >> 63)) as u64);
+    }
+    push_optional_bytes(&mut bytes, flags);
+    bytes
+}
+
+/// Stored bytes of an item element as GroveDB serializes `Element::Item`
+pub(crate) fn item_element_bytes(value: &[u8], flags: Option<&[u8]>) -> Vec<u8> {
+    let mut bytes = vec![0];
+    push_varint(&mut bytes, value.len() as u64);
+    bytes.extend_from_slice(value);
+    push_optional_bytes(&mut bytes, flags);
+    bytes
+}
+
+fn push_optional_bytes(buffer: &mut Vec<u8>, bytes: Option<&[u8]>) {
+    match bytes {
+        Some(bytes) => {
+            buffer.push(1);
+            push_varint(buffer, bytes.len() as u64);
+            buffer.extend_from_slice(bytes);
+        }
+        None => buffer.push(0),
+    }
+}
+
+fn push_varint(buffer: &mut Vec<u8>, n: u64) {
+    if n < 251 {
+        buffer.push(n as u8);
+    } else if n <= u16::MAX as u64 {
+        buffer.push(251);
+        buffer.extend_from_slice(&(n as u16).to_be_bytes());
+    } else if n <= u32::MAX as u64 {
+        buffer.push(252);
+        buffer.extend_from_slice(&(n as u32).to_be_bytes());
+    } else {
+        buffer.push(253);
+        buffer.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_are_serialized_as_grovedb_does() {
+        assert_eq!(item_element_bytes(b"v", Some(&[7])), vec![0, 1, b'v', 1, 1, 7]);
+    }
+
+    #[test]
+    fn subtree_elements_are_serialized_as_grovedb_does() {
+        assert_eq!(
+            subtree_element_bytes(Some(b"k"), None, None),
+            vec![2, 1, 1, b'k', 0]
+        );
+        assert_eq!(
+            subtree_element_bytes(None, Some(-2), Some(&[7])),
+            vec![4, 0, 3, 1, 1, 7]
+        );
+        assert_eq!(
+            subtree_element_bytes(None, Some(300), None),
+            vec![4, 0, 251, 2, 88, 0]
+        );
+    }
+}
@@ -1,7 +1,7 @@
-use std::cell::RefCell;
+use std::{cell::RefCell, collections::BTreeMap};
 
 use eframe::{
-    egui::{self, Button, Color32, Context, FontId, Id, Pos2, Rect, Stroke, Vec2},
+    egui::{self, Button, Color32, Context, FontId, Id, Pos2, Rect, RichText, Stroke, Vec2},
     emath::TSTransform,
 };
 use grovedbg_types::Key;
@@ -9,12 +9,15 @@ use reingold_tilford::{Coordinate, NodeInfo};
 
 use crate::{
     bus::CommandBus,
-    path_ctx::Path,
+    canvas,
+    density::node_width,
+    image_export::{self, Diagram, ImageExport},
+    path_ctx::{draw_copy_path_menu, path_label, Path},
     profiles::ActiveProfileSubtreeContext,
-    protocol::FetchCommand,
-    theme::proof_node_color,
-    tree_data::{SubtreeData, SubtreeDataMap, SubtreeProofData},
-    tree_view::{ElementView, ElementViewContext, SubtreeElements, NODE_WIDTH},
+    proof_viewer::Absence,
+    theme::{input_error_color, proof_node_color},
+    tree_data::{ProofMismatches, SubtreeData, SubtreeDataMap, SubtreeProofData},
+    tree_view::{ElementOrPlaceholder, ElementView, ElementViewContext, SubtreeElements},
 };
 
 const INNER_MARGIN: f32 = 8.;
@@ -47,18 +50,128 @@ impl<'a> NodeInfo<&'a Key> for MerkTree<&'a SubtreeElements> {
     }
 }
 
+/// Structural checks of a Merk node against its loaded children
+#[derive(Default, Clone, Copy)]
+struct NodeCheck {
+    /// Known only when the whole subtree of the node is loaded
+    height: Option<u32>,
+    /// Right subtree height minus left subtree height
+    balance: Option<i64>,
+    /// Left child key is not less or right child key is not greater than the
+    /// node's key
+    misordered: bool,
+    /// Value hash differs from the one of the last proof
+    proof_mismatch: bool,
+}
+
+impl NodeCheck {
+    fn is_suspicious(&self) -> bool {
+        self.misordered || self.proof_mismatch || self.balance.map(|b| b.abs() > 1).unwrap_or_default()
+    }
+}
+
+fn node_checks<'pa>(
+    path: Path<'pa>,
+    elements: &SubtreeElements,
+    proof_mismatches: &ProofMismatches<'pa>,
+) -> BTreeMap<Key, NodeCheck> {
+    let mut heights = BTreeMap::new();
+    elements
+        .iter()
+        .filter(|(_, e)| matches!(e.value, ElementOrPlaceholder::Element(_)))
+        .map(|(key, element)| {
+            let left = child_height(elements, element.left_child.as_ref(), &mut heights);
+            let right = child_height(elements, element.right_child.as_ref(), &mut heights);
+            let check = NodeCheck {
+                height: left.zip(right).map(|(l, r)| l.max(r) + 1),
+                balance: left.zip(right).map(|(l, r)| r as i64 - l as i64),
+                misordered: element.left_child.as_ref().map(|l| l >= key).unwrap_or_default()
+                    || element.right_child.as_ref().map(|r| r <= key).unwrap_or_default(),
+                proof_mismatch: proof_mismatches.contains(&(path, key.clone())),
+            };
+            (key.clone(), check)
+        })
+        .collect()
+}
+
+/// Height of a child subtree, zero for no child
+fn child_height(
+    elements: &SubtreeElements,
+    child: Option<&Key>,
+    heights: &mut BTreeMap<Key, Option<u32>>,
+) -> Option<u32> {
+    let Some(key) = child else {
+        return Some(0);
+    };
+    if let Some(height) = heights.get(key) {
+        return *height;
+    }
+    // Marked as unknown first, so a corrupted tree with cycles won't recurse
+    // forever
+    heights.insert(key.clone(), None);
+
+    let height = elements
+        .get(key)
+        .filter(|e| matches!(e.value, ElementOrPlaceholder::Element(_)))
+        .and_then(|element| {
+            let left = child_height(elements, element.left_child.as_ref(), heights)?;
+            let right = child_height(elements, element.right_child.as_ref(), heights)?;
+            Some(left.max(right) + 1)
+        });
+    heights.insert(key.clone(), height);
+    height
+}
+
+/// Merk tree diagram from the root node down to `depth` levels, loaded nodes
+/// only
+fn merk_diagram(path: Path, subtree_data: &SubtreeData, depth: usize) -> Diagram {
+    let mut diagram = Diagram::default();
+    if let Some(root_key) = &subtree_data.root_key {
+        add_merk_node(&mut diagram, None, path, &subtree_data.elements, root_key, depth);
+    }
+    diagram
+}
+
+fn add_merk_node(
+    diagram: &mut Diagram,
+    parent: Option<usize>,
+    path: Path,
+    elements: &SubtreeElements,
+    key: &Key,
+    depth: usize,
+) {
+    let Some(element) = elements.get(key) else {
+        return;
+    };
+    let idx = diagram.add(
+        parent,
+        image_export::key_label(path, key),
+        element.value.type_name().to_owned(),
+    );
+    if depth > 1 {
+        for child in [&element.left_child, &element.right_child].into_iter().flatten() {
+            add_merk_node(diagram, Some(idx), path, elements, child, depth - 1);
+        }
+    }
+}
+
 pub(crate) struct MerkView {
     initial_focus: bool,
     transform: TSTransform,
+    /// Canvas area of the previous frame
+    viewport: Option<Rect>,
     node_focus: Option<Key>,
+    image_export: ImageExport,
 }
 
 impl MerkView {
     pub(crate) fn new() -> Self {
         MerkView {
             transform: TSTransform::default(),
+            viewport: None,
             initial_focus: false,
             node_focus: None,
+            image_export: Default::default(),
         }
     }
 
@@ -72,11 +185,14 @@ impl MerkView {
         subtree_proof_data: &mut Option<&mut SubtreeProofData>,
         path: Path,
         element_view_context: &mut ElementViewContext<'af, 'pa, 'pf, 'b>,
+        check: NodeCheck,
         key: Key,
         coords: Pos2,
     ) {
         let elements = &mut subtree_data.elements;
         let visiblity = &mut subtree_data.visible_keys;
+        let selection = &mut subtree_data.selected_keys;
+        let referrers = subtree_data.back_references.get(&key);
 
         let Some(mut element_view) = elements.remove(&key) else {
             return;
@@ -87,19 +203,33 @@ impl MerkView {
             .fixed_pos(coords)
             .show(ctx, |area| {
                 area.set_clip_rect(self.transform.inverse() * rect);
-                let color = subtree_proof_data
-                    .as_ref()
-                    .and_then(|pd| pd.contains_key(&key).then(|| proof_node_color(ctx)))
-                    .unwrap_or(Color32::DARK_GRAY);
+                let color = if check.is_suspicious() {
+                    input_error_color(ctx)
+                } else {
+                    subtree_proof_data
+                        .as_ref()
+                        .and_then(|pd| pd.contains_key(&key).then(|| proof_node_color(ctx)))
+                        .unwrap_or(Color32::DARK_GRAY)
+                };
 
                 let mut center_bottom = egui::Frame::default()
                     .rounding(egui::Rounding::same(4.0))
                     .inner_margin(egui::Margin::same(INNER_MARGIN))
                     .stroke(Stroke { width: 1., color })
                     .show(area, |node_ui| {
-                        node_ui.set_max_width(NODE_WIDTH);
+                        node_ui.set_max_width(node_width());
+
+                        element_view.draw(
+                            node_ui,
+                            element_view_context,
+                            visiblity,
+                            selection,
+                            referrers,
+                            None,
+                            &subtrees_map,
+                        );
 
-                        element_view.draw(node_ui, element_view_context, visiblity, &subtrees_map);
+                        draw_node_check(node_ui, &check);
 
                         if let Some(proof_node) = subtree_proof_data.as_mut().and_then(|s| s.get_mut(&key)) {
                             node_ui.separator();
@@ -132,10 +262,7 @@ impl MerkView {
                                         .or_insert_with(|| ElementView::new_placeholder(left.clone()))
                                         .merk_visible = true;
 
-                                    bus.fetch_command(FetchCommand::FetchNode {
-                                        path: path.to_vec(),
-                                        key: left.clone(),
-                                    });
+                                    bus.fetch().node(path, left.clone());
                                 }
                             } else {
                                 line.add_enabled(false, left_button);
@@ -164,10 +291,7 @@ impl MerkView {
                                         .or_insert_with(|| ElementView::new_placeholder(right.clone()))
                                         .merk_visible = true;
 
-                                    bus.fetch_command(FetchCommand::FetchNode {
-                                        path: path.to_vec(),
-                                        key: right.clone(),
-                                    });
+                                    bus.fetch().node(path, right.clone());
                                 }
                             } else {
                                 line.add_enabled(false, right_button);
@@ -257,6 +381,8 @@ impl MerkView {
         path: Path<'pa>,
         subtrees_map: &SubtreeDataMap<'pa>,
         mut subtree_proof_data: Option<&mut SubtreeProofData>,
+        proof_mismatches: &ProofMismatches<'pa>,
+        proof_absences: &[Absence],
         mut profile_ctx: ActiveProfileSubtreeContext,
     ) {
         let Some(mut subtree_data) = subtrees_map.get(&path).map(RefCell::borrow_mut) else {
@@ -277,39 +403,54 @@ impl MerkView {
             .into_iter()
             .for_each(|r| r.merk_visible = true);
 
+        for absence in proof_absences {
+            absence.draw(ui);
+        }
+
+        let export_name = path
+            .for_last_segment(|segment| format!("merk-{}", segment.view_by_display()))
+            .unwrap_or_else(|| "merk-root".to_owned());
+
+        let mut fit = false;
+        ui.horizontal(|line| {
+            fit = line
+                .button(egui_phosphor::regular::ARROWS_OUT)
+                .on_hover_text("Fit all shown nodes into the view (F)")
+                .clicked();
+            self.image_export.draw_menu(line, &export_name, |depth| {
+                merk_diagram(path, &subtree_data, depth)
+            });
+            path_label(line, path, &profile_ctx)
+                .interact(egui::Sense::click())
+                .context_menu(|menu| draw_copy_path_menu(menu, path, &profile_ctx));
+        });
+
         let (id, rect) = ui.allocate_space(ui.available_size());
+        canvas::follow_resize(&mut self.transform, &mut self.viewport, rect);
 
-        let pointer_response = ui.interact(rect, id, egui::Sense::click_and_drag());
+        #[cfg(not(target_arch = "wasm32"))]
+        self.image_export.process_screenshot(ui.ctx(), rect, &export_name);
 
-        let transform_before = self.transform;
+        let pointer_response = ui.interact(rect, id, egui::Sense::click_and_drag());
 
-        if pointer_response.dragged() {
-            self.transform.translation += pointer_response.drag_delta();
-        }
-        if pointer_response.double_clicked() {
-            self.node_focus = Some(root_key.clone());
+        if canvas::handle_pan_zoom(ui, &pointer_response, &mut self.transform) {
+            self.node_focus = None;
         }
 
-        if let Some(pointer) = ui.ctx().input(|i| i.pointer.hover_pos()) {
-            if pointer_response.hovered() {
-                let pointer_in_layer = self.transform.inverse() * pointer;
-                let zoom_delta = ui.ctx().input(|i| i.zoom_delta());
-                let pan_delta = ui.ctx().input(|i| i.smooth_scroll_delta);
+        fit |= pointer_response.double_clicked()
+            || canvas::shortcut_pressed(ui, &pointer_response, egui::Key::F);
 
-                // // Zoom in on pointer:
-                self.transform = self.transform
-                    * TSTransform::from_translation(pointer_in_layer.to_vec2())
-                    * TSTransform::from_scaling(zoom_delta)
-                    * TSTransform::from_translation(-pointer_in_layer.to_vec2());
-
-                // Pan:
-                self.transform = TSTransform::from_translation(pan_delta) * self.transform;
+        // Opened with a right click or a long press on touch screens
+        pointer_response.context_menu(|menu| {
+            if menu.button("Fit view").clicked() {
+                fit = true;
+                menu.close_menu();
             }
-        }
-
-        if transform_before != self.transform {
-            self.node_focus = None;
-        }
+            if menu.button("Focus root node").clicked() {
+                self.node_focus = Some(root_key.clone());
+                menu.close_menu();
+            }
+        });
 
         if let Some(focused_node) = &self.node_focus {
             let node_pos = ui
@@ -322,6 +463,7 @@ impl MerkView {
             }
         }
 
+        let checks = node_checks(path, &subtree_data.elements, proof_mismatches);
         let tree = MerkTree(&subtree_data.elements);
 
         let layout: Vec<(Key, Coordinate)> = reingold_tilford::layout(&tree, &root_key)
@@ -331,12 +473,28 @@ impl MerkView {
 
         let mut element_view_context = ElementViewContext {
             path,
+            pane: None,
             profile_ctx: &mut profile_ctx,
             bus,
+            keys_only: false,
         };
 
+        // Uses sizes of the nodes drawn on the previous frame
+        if fit {
+            let bounds = ui.ctx().memory(|mem| {
+                layout
+                    .iter()
+                    .filter_map(|(key, _)| mem.area_rect(Id::new(key)))
+                    .reduce(|a, b| a.union(b))
+            });
+            if let Some(bounds) = bounds {
+                self.transform = canvas::fit_transform(bounds, rect);
+                self.node_focus = None;
+            }
+        }
+
         for (key, Coordinate { x, y }) in layout {
-            let coords = Pos2::new(x as f32, y as f32) * NODE_WIDTH * 1.2;
+            let coords = Pos2::new(x as f32, y as f32) * node_width() * 1.2;
 
             self.draw_node(
                 ui.ctx(),
@@ -347,9 +505,40 @@ impl MerkView {
                 &mut subtree_proof_data,
                 path,
                 &mut element_view_context,
+                checks.get(&key).copied().unwrap_or_default(),
                 key,
                 coords,
             );
         }
     }
 }
+
+fn draw_node_check(ui: &mut egui::Ui, check: &NodeCheck) {
+    ui.horizontal(|line| {
+        if let Some(height) = check.height {
+            line.label(format!("Height: {height}"));
+        } else {
+            line.weak("Height: ?")
+                .on_hover_text("Known once the whole subtree of the node is loaded");
+        }
+
+        match check.balance {
+            Some(balance) if balance.abs() > 1 => {
+                line.label(RichText::new(format!("Balance: {balance}")).color(input_error_color(line.ctx())))
+                    .on_hover_text("AVL balance is violated, the node is suspicious");
+            }
+            Some(balance) => {
+                line.label(format!("Balance: {balance}"));
+            }
+            None => {}
+        }
+    });
+
+    if check.proof_mismatch {
+        ui.label(RichText::new("Value hash differs from the proved one").color(input_error_color(ui.ctx())))
+            .on_hover_text("The data has likely changed since the proof was generated");
+    }
+    if check.misordered {
+        ui.label(RichText::new("Children keys are out of order").color(input_error_color(ui.ctx())));
+    }
+}
@@ -0,0 +1,167 @@
+//! Browser-like back and forward navigation over focused subtrees.
+
+use eframe::egui;
+
+use crate::{
+    bus::{CommandBus, UserAction},
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    path_ctx::{full_path_display, full_path_display_iter},
+    profiles::ProfilesView,
+    FocusedSubree,
+};
+
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Default)]
+pub(crate) struct NavigationHistory<'pa> {
+    back: Vec<FocusedSubree<'pa>>,
+    current: Option<FocusedSubree<'pa>>,
+    forward: Vec<FocusedSubree<'pa>>,
+}
+
+impl<'pa> NavigationHistory<'pa> {
+    /// Records a new focus target, dropping forward history
+    pub(crate) fn visit(&mut self, focus: FocusedSubree<'pa>) {
+        if let Some(current) = self.current.take() {
+            if current == focus {
+                self.current = Some(current);
+                return;
+            }
+            self.back.push(current);
+            if self.back.len() > HISTORY_LIMIT {
+                self.back.remove(0);
+            }
+        }
+        self.current = Some(focus);
+        self.forward.clear();
+    }
+
+    /// Steps `n` entries back returning the new focus target, stops at the
+    /// oldest entry if there are fewer than `n`
+    pub(crate) fn back(&mut self, n: usize) -> Option<FocusedSubree<'pa>> {
+        let n = n.min(self.back.len());
+        if n == 0 {
+            return None;
+        }
+        let mut passed = self.back.split_off(self.back.len() - n);
+        let target = passed.remove(0);
+        self.forward.extend(self.current.replace(target));
+        self.forward.extend(passed.into_iter().rev());
+        self.current.clone()
+    }
+
+    /// Steps `n` entries forward returning the new focus target, stops at the
+    /// latest entry if there are fewer than `n`
+    pub(crate) fn forward(&mut self, n: usize) -> Option<FocusedSubree<'pa>> {
+        let n = n.min(self.forward.len());
+        if n == 0 {
+            return None;
+        }
+        let mut passed = self.forward.split_off(self.forward.len() - n);
+        let target = passed.remove(0);
+        self.back.extend(self.current.replace(target));
+        self.back.extend(passed.into_iter().rev());
+        self.current.clone()
+    }
+
+    /// Draw back/forward buttons and history dropdown
+    pub(crate) fn draw(&self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, profiles_view: &ProfilesView) {
+        if ui
+            .add_enabled(
+                !self.back.is_empty(),
+                egui::Button::new(egui_phosphor::regular::ARROW_LEFT),
+            )
+            .on_hover_text("Go back (Alt + Left or the mouse back button)")
+            .clicked()
+        {
+            bus.user_action(UserAction::NavigateBack(1));
+        }
+
+        if ui
+            .add_enabled(
+                !self.forward.is_empty(),
+                egui::Button::new(egui_phosphor::regular::ARROW_RIGHT),
+            )
+            .on_hover_text("Go forward (Alt + Right or the mouse forward button)")
+            .clicked()
+        {
+            bus.user_action(UserAction::NavigateForward(1));
+        }
+
+        ui.add_enabled_ui(!self.back.is_empty() || !self.forward.is_empty(), |ui| {
+            ui.menu_button(egui_phosphor::regular::CLOCK_COUNTER_CLOCKWISE, |menu| {
+                for (i, focus) in self.forward.iter().enumerate() {
+                    if menu.button(focus_label(focus, profiles_view)).clicked() {
+                        bus.user_action(UserAction::NavigateForward(self.forward.len() - i));
+                        menu.close_menu();
+                    }
+                }
+                if let Some(current) = &self.current {
+                    menu.add_enabled(false, egui::Button::new(focus_label(current, profiles_view)));
+                }
+                for (i, focus) in self.back.iter().rev().enumerate() {
+                    if menu.button(focus_label(focus, profiles_view)).clicked() {
+                        bus.user_action(UserAction::NavigateBack(i + 1));
+                        menu.close_menu();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Navigation history");
+        });
+    }
+}
+
+fn focus_label(focus: &FocusedSubree, profiles_view: &ProfilesView) -> String {
+    let profile_ctx = profiles_view.active_profile_root_ctx().fast_forward(focus.path);
+    let path_display = focus
+        .path
+        .for_segments(|segments_iter| full_path_display(full_path_display_iter(segments_iter, &profile_ctx)));
+
+    if let Some(key) = &focus.key {
+        let key_display = profile_ctx.key_view(key).unwrap_or_else(|| {
+            bytes_by_display_variant(
+                key,
+                &focus
+                    .path
+                    .child(key.clone())
+                    .get_display_variant()
+                    .unwrap_or_else(|| BytesDisplayVariant::guess(key)),
+            )
+        });
+        format!("{path_display} / {key_display}")
+    } else {
+        path_display
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path_ctx::PathCtx;
+
+    #[test]
+    fn steps_past_the_end_stop_at_the_last_entry() {
+        let path_ctx = PathCtx::new();
+        let focus = |key: &[u8]| FocusedSubree {
+            path: path_ctx.get_root().child(key.to_vec()),
+            key: None,
+        };
+        let mut history = NavigationHistory::default();
+        history.visit(focus(b"a"));
+        history.visit(focus(b"b"));
+        history.visit(focus(b"c"));
+
+        assert!(history.back(5) == Some(focus(b"a")));
+        assert!(history.back(1).is_none());
+        assert!(history.forward(1) == Some(focus(b"b")));
+
+        assert!(history.forward(5) == Some(focus(b"c")));
+        assert!(history.back(2) == Some(focus(b"a")));
+
+        history.visit(focus(b"d"));
+        assert!(history.forward(1).is_none());
+        assert!(history.back(1) == Some(focus(b"a")));
+        assert!(history.forward(1) == Some(focus(b"d")));
+    }
+}
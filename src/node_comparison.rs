@@ -0,0 +1,167 @@
+//! Side by side view of the same node taken from two sessions.
+
+use eframe::egui::{self, Grid, RichText};
+use grovedbg_types::{Element, Reference};
+
+use crate::{
+    bytes_utils::{bytes_by_display_variant, bytes_diff, BytesDisplayVariant},
+    protocol::NodeComparison,
+    redaction,
+    theme::input_error_color,
+};
+
+pub(crate) struct NodeComparisonView {
+    comparison: NodeComparison,
+}
+
+impl NodeComparisonView {
+    pub(crate) fn new(comparison: NodeComparison) -> Self {
+        Self { comparison }
+    }
+
+    pub(crate) fn draw(&self, ui: &mut egui::Ui) {
+        let NodeComparison {
+            path,
+            key,
+            current,
+            other,
+        } = &self.comparison;
+
+        ui.label(format!(
+            "Path: [{}]",
+            path.iter().map(hex::encode).collect::<Vec<_>>().join(", ")
+        ));
+        ui.label(format!(
+            "Key: {}",
+            bytes_by_display_variant(key, &BytesDisplayVariant::guess(key))
+        ));
+        ui.label("Upper line is for the current session, lower one is for the kept session");
+        ui.separator();
+
+        let (current, other) = match (current, other) {
+            (Some(current), Some(other)) => (current, other),
+            (current, other) => {
+                ui.label(format!(
+                    "Current session: {}",
+                    if current.is_some() { "present" } else { "missing" }
+                ));
+                ui.label(format!(
+                    "Kept session: {}",
+                    if other.is_some() { "present" } else { "missing" }
+                ));
+                return;
+            }
+        };
+
+        Grid::new("node_comparison")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |grid| {
+                let current_type = element_type(&current.element);
+                let other_type = element_type(&other.element);
+                grid.label("Type:");
+                if current_type == other_type {
+                    grid.label(current_type);
+                } else {
+                    grid.label(
+                        RichText::new(format!("{current_type} / {other_type}"))
+                            .color(input_error_color(grid.ctx())),
+                    );
+                }
+                grid.end_row();
+
+                grid.label("Value:");
+                let current_value = element_value_bytes(&current.element);
+                let other_value = element_value_bytes(&other.element);
+                grid.vertical(|cell| {
+                    if redaction::enabled(cell.ctx()) {
+                        redaction::draw_redacted(cell, current_value.len());
+                        if current_value != other_value {
+                            cell.label(RichText::new("Values differ").color(input_error_color(cell.ctx())));
+                        }
+                    } else {
+                        bytes_diff(cell, &current_value, &other_value)
+                    }
+                });
+                grid.end_row();
+
+                grid.label("Flags:");
+                grid.vertical(|cell| {
+                    bytes_diff(
+                        cell,
+                        element_flags(&current.element).unwrap_or_default(),
+                        element_flags(&other.element).unwrap_or_default(),
+                    )
+                });
+                grid.end_row();
+
+                grid.label("Value hash:");
+                grid.vertical(|cell| bytes_diff(cell, &current.value_hash, &other.value_hash));
+                grid.end_row();
+
+                grid.label("KV digest hash:");
+                grid.vertical(|cell| bytes_diff(cell, &current.kv_digest_hash, &other.kv_digest_hash));
+                grid.end_row();
+
+                grid.label("Left child:");
+                grid.vertical(|cell| {
+                    bytes_diff(
+                        cell,
+                        current.left_child.as_deref().unwrap_or_default(),
+                        other.left_child.as_deref().unwrap_or_default(),
+                    )
+                });
+                grid.end_row();
+
+                grid.label("Right child:");
+                grid.vertical(|cell| {
+                    bytes_diff(
+                        cell,
+                        current.right_child.as_deref().unwrap_or_default(),
+                        other.right_child.as_deref().unwrap_or_default(),
+                    )
+                });
+                grid.end_row();
+            });
+    }
+}
+
+fn element_type(element: &Element) -> &'static str {
+    match element {
+        Element::Subtree { .. } => "Subtree",
+        Element::Sumtree { .. } => "Sum tree",
+        Element::Item { .. } => "Item",
+        Element::SumItem { .. } => "Sum item",
+        Element::Reference(..) => "Reference",
+    }
+}
+
+/// Bytes that represent element's value: item's value, sum as big endian
+/// bytes, subtree's root key or serialized reference
+fn element_value_bytes(element: &Element) -> Vec<u8> {
+    match element {
+        Element::Subtree { root_key, .. } => root_key.clone().unwrap_or_default(),
+        Element::Sumtree { sum, .. } => sum.to_be_bytes().to_vec(),
+        Element::Item { value, .. } => value.clone(),
+        Element::SumItem { value, .. } => value.to_be_bytes().to_vec(),
+        Element::Reference(reference) => serde_json::to_vec(reference).unwrap_or_default(),
+    }
+}
+
+fn element_flags(element: &Element) -> Option<&[u8]> {
+    match element {
+        Element::Subtree { element_flags, .. }
+        | Element::Sumtree { element_flags, .. }
+        | Element::Item { element_flags, .. }
+        | Element::SumItem { element_flags, .. } => element_flags.as_deref(),
+        Element::Reference(
+            Reference::AbsolutePathReference { element_flags, .. }
+            | Reference::UpstreamRootHeightReference { element_flags, .. }
+            | Reference::UpstreamRootHeightWithParentPathAdditionReference { element_flags, .. }
+            | Reference::UpstreamFromElementHeightReference { element_flags, .. }
+            | Reference::CousinReference { element_flags, .. }
+            | Reference::RemovedCousinReference { element_flags, .. }
+            | Reference::SiblingReference { element_flags, .. },
+        ) => element_flags.as_deref(),
+    }
+}
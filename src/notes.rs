@@ -0,0 +1,188 @@
+//! Free-form notes attached to paths, keys or the session itself to record
+//! findings while digging through GroveDB state.
+
+use eframe::{
+    egui::{self, RichText, TextEdit},
+    Storage,
+};
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bus::{CommandBus, UserAction},
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    path_ctx::PathCtx,
+};
+
+const NOTES_KEY: &'static str = "notes";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum NoteTarget {
+    Session,
+    Path(Vec<Vec<u8>>),
+    Key(Vec<Vec<u8>>, Vec<u8>),
+}
+
+impl NoteTarget {
+    fn label(&self) -> String {
+        match self {
+            NoteTarget::Session => "Session".to_owned(),
+            NoteTarget::Path(path) => path_display(path),
+            NoteTarget::Key(path, key) => format!("{} {}", path_display(path), key_display(key)),
+        }
+    }
+}
+
+fn key_display(key: &[u8]) -> String {
+    bytes_by_display_variant(key, &BytesDisplayVariant::guess(key))
+}
+
+fn path_display(path: &[Vec<u8>]) -> String {
+    if path.is_empty() {
+        "Root subtree".to_owned()
+    } else {
+        format!(
+            "[{}]",
+            path.iter()
+                .map(|segment| key_display(segment))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Note {
+    pub(crate) target: NoteTarget,
+    pub(crate) text: String,
+    #[serde(skip)]
+    editing: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Notes {
+    notes: Vec<Note>,
+    #[serde(skip)]
+    markdown_cache: CommonMarkCache,
+}
+
+impl Notes {
+    pub(crate) fn persist(&self, storage: &mut dyn Storage) {
+        if let Ok(s) = serde_json::to_string(self) {
+            storage.set_string(NOTES_KEY, s);
+        }
+    }
+
+    pub(crate) fn restore(storage: Option<&dyn Storage>) -> Self {
+        storage
+            .and_then(|s| s.get_string(NOTES_KEY))
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    /// Adds imported notes, skipping targets that already have one
+    pub(crate) fn import(&mut self, notes: Vec<Note>) {
+        for note in notes {
+            if !self.notes.iter().any(|n| n.target == note.target) {
+                self.notes.push(note);
+            }
+        }
+    }
+
+    /// Starts editing the note of the target, a new one is added if there is
+    /// none yet
+    pub(crate) fn open(&mut self, target: NoteTarget) {
+        match self.notes.iter_mut().find(|note| note.target == target) {
+            Some(note) => note.editing = true,
+            None => self.notes.push(Note {
+                target,
+                text: String::new(),
+                editing: true,
+            }),
+        }
+    }
+
+    pub(crate) fn draw<'pa>(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+        ui.horizontal(|line| {
+            if line
+                .button(format!("{} Session note", egui_phosphor::regular::PLUS))
+                .on_hover_text("Add a note about the session itself")
+                .clicked()
+            {
+                self.open(NoteTarget::Session);
+            }
+            line.label(format!("{} notes", self.notes.len()));
+        });
+        ui.separator();
+
+        if self.notes.is_empty() {
+            ui.label("Add notes to subtrees and elements with the note buttons");
+            return;
+        }
+
+        let mut delete_idx = None;
+        egui::ScrollArea::vertical().show(ui, |scroll| {
+            for (idx, note) in self.notes.iter_mut().enumerate() {
+                scroll.group(|group| {
+                    group.horizontal(|line| {
+                        line.label(RichText::new(note.target.label()).strong());
+                        match &note.target {
+                            NoteTarget::Session => {}
+                            NoteTarget::Path(path) => {
+                                if line
+                                    .button(egui_phosphor::regular::CROSSHAIR)
+                                    .on_hover_text("Focus on the subtree")
+                                    .clicked()
+                                {
+                                    bus.user_action(UserAction::FocusSubtree(
+                                        path_ctx.add_path(path.clone()),
+                                    ));
+                                }
+                            }
+                            NoteTarget::Key(path, key) => {
+                                if line
+                                    .button(egui_phosphor::regular::CROSSHAIR)
+                                    .on_hover_text("Focus on the element")
+                                    .clicked()
+                                {
+                                    bus.user_action(UserAction::FocusSubtreeKey(
+                                        path_ctx.add_path(path.clone()),
+                                        key.clone(),
+                                    ));
+                                }
+                            }
+                        }
+                        line.toggle_value(&mut note.editing, egui_phosphor::regular::PENCIL_SIMPLE)
+                            .on_hover_text("Edit the note, markdown is supported");
+                        if line
+                            .button(egui_phosphor::regular::TRASH)
+                            .on_hover_text("Delete the note")
+                            .clicked()
+                        {
+                            delete_idx = Some(idx);
+                        }
+                    });
+
+                    if note.editing {
+                        group.add(
+                            TextEdit::multiline(&mut note.text)
+                                .hint_text("Markdown")
+                                .desired_width(f32::INFINITY)
+                                .desired_rows(4),
+                        );
+                    } else {
+                        CommonMarkViewer::new().show(group, &mut self.markdown_cache, &note.text);
+                    }
+                });
+            }
+        });
+
+        if let Some(idx) = delete_idx {
+            self.notes.remove(idx);
+        }
+    }
+}
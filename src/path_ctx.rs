@@ -1,7 +1,7 @@
 //! Subtrees paths manipulation and storage module.
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fmt::{self, Write},
     hash::{Hash, Hasher},
     iter,
@@ -22,6 +22,8 @@ pub(crate) struct PathCtx {
     slab: RefCell<Slab<PathSegment>>,
     root_children_slab_ids: RefCell<Vec<SegmentId>>,
     selected_for_query: RefCell<Option<SelectedForQuery>>,
+    /// Display variant for new segments, guessed by bytes if `None`
+    key_display: Cell<Option<BytesDisplayVariant>>,
 }
 
 #[derive(Clone, Copy)]
@@ -68,6 +70,18 @@ impl PathCtx {
         current_path
     }
 
+    pub fn set_key_display(&self, display: Option<BytesDisplayVariant>) {
+        self.key_display.set(display);
+    }
+
+    /// Resets display variants of all known segments to the default one
+    pub fn reset_key_displays(&self) {
+        let display = self.key_display.get();
+        self.slab.borrow_mut().iter_mut().for_each(|(_, segment)| {
+            segment.display = display.unwrap_or_else(|| BytesDisplayVariant::guess(&segment.bytes));
+        });
+    }
+
     pub fn get_selected_for_query(&self) -> Option<Path> {
         self.selected_for_query.borrow().map(|id| Path {
             head_slab_id: match id {
@@ -197,7 +211,11 @@ impl<'c> Path<'c> {
             let child_segment_id = slab.insert(PathSegment {
                 parent_slab_id: self.head_slab_id,
                 children_slab_ids: Vec::new(),
-                display: BytesDisplayVariant::guess(&key),
+                display: self
+                    .ctx
+                    .key_display
+                    .get()
+                    .unwrap_or_else(|| BytesDisplayVariant::guess(&key)),
                 bytes: key,
                 level: level + 1,
             });
@@ -315,7 +333,11 @@ where
     }
 }
 
-pub(crate) fn path_label(ui: &mut egui::Ui, path: Path, profile_ctx: &ActiveProfileSubtreeContext) {
+pub(crate) fn path_label(
+    ui: &mut egui::Ui,
+    path: Path,
+    profile_ctx: &ActiveProfileSubtreeContext,
+) -> egui::Response {
     path.for_segments(|segments_iter| {
         let mut path_segments_iter = full_path_display_iter(segments_iter, profile_ctx);
         let full_path_iter = path_segments_iter.clone();
@@ -339,10 +361,61 @@ pub(crate) fn path_label(ui: &mut egui::Ui, path: Path, profile_ctx: &ActiveProf
         };
 
         ui.add(Label::new(text).truncate())
-            .on_hover_text(full_path_display(full_path_iter));
+            .on_hover_text(full_path_display(full_path_iter))
     })
 }
 
+/// Path as a JSON array of hex encoded segments
+pub(crate) fn path_as_json_hex(path: &[Vec<u8>]) -> String {
+    serde_json::to_string(&path.iter().map(hex::encode).collect::<Vec<_>>()).unwrap_or_default()
+}
+
+/// Path as a Rust array of byte string literals
+pub(crate) fn path_as_rust_literal(path: &[Vec<u8>]) -> String {
+    let segments: Vec<String> = path
+        .iter()
+        .map(|segment| {
+            let escaped: String = segment
+                .iter()
+                .flat_map(|b| std::ascii::escape_default(*b))
+                .map(char::from)
+                .collect();
+            format!("b\"{escaped}\"")
+        })
+        .collect();
+    format!("[{}]", segments.join(", "))
+}
+
+/// Menu items copying the full path to the clipboard in different formats
+pub(crate) fn draw_copy_path_menu(
+    menu: &mut egui::Ui,
+    path: Path,
+    profile_ctx: &ActiveProfileSubtreeContext,
+) {
+    menu.menu_button(format!("{} Copy path", egui_phosphor::regular::COPY), |submenu| {
+        let mut text = None;
+        if submenu.button("As JSON array of hex").clicked() {
+            text = Some(path_as_json_hex(&path.to_vec()));
+        }
+        if submenu.button("As Rust byte strings").clicked() {
+            text = Some(path_as_rust_literal(&path.to_vec()));
+        }
+        if submenu
+            .button("As displayed")
+            .on_hover_text("With profile aliases of the segments")
+            .clicked()
+        {
+            text = Some(path.for_segments(|segments_iter| {
+                full_path_display(full_path_display_iter(segments_iter, profile_ctx))
+            }));
+        }
+        if let Some(text) = text {
+            submenu.ctx().copy_text(text);
+            submenu.close_menu();
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -384,4 +457,12 @@ mod tests {
         assert_eq!(path_vec, Vec::<Vec<u8>>::new());
         assert_eq!(path.level(), 0);
     }
+
+    #[test]
+    fn path_copy_formats() {
+        let path = vec![vec![0x20], b"key\"1".to_vec(), vec![0xff, 0x0a]];
+        assert_eq!(path_as_json_hex(&path), r#"["20","6b65792231","ff0a"]"#);
+        assert_eq!(path_as_rust_literal(&path), r#"[b" ", b"key\"1", b"\xff\n"]"#);
+        assert_eq!(path_as_rust_literal(&[]), "[]");
+    }
 }
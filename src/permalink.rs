@@ -0,0 +1,99 @@
+//! Links to elements for the web build. The path and key are put in the URL
+//! fragment as hex, so opening the link loads the same page, fetches the
+//! element once a session is started and focuses it.
+
+use std::sync::RwLock;
+
+use eframe::egui;
+use grovedbg_types::Key;
+
+/// Page URL without the fragment, known only on the web build
+static BASE_URL: RwLock<Option<String>> = RwLock::new(None);
+
+/// Element a link points to, no key for a link to a subtree
+pub(crate) type Target = (Vec<Vec<u8>>, Option<Key>);
+
+/// Remembers the page URL for new links and returns the element of the link
+/// the page was opened with
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn init(location: &eframe::Location) -> Option<Target> {
+    let base = location.url.split('#').next().unwrap_or_default();
+    if let Ok(mut base_url) = BASE_URL.write() {
+        *base_url = Some(base.to_owned());
+    }
+    parse_fragment(location.hash.trim_start_matches('#'))
+}
+
+fn base_url() -> Option<String> {
+    BASE_URL.read().ok().and_then(|base| base.clone())
+}
+
+/// The root path is encoded by omitting `path`, so a path of a single empty
+/// segment stays distinct from it
+fn element_link(base: &str, path: &[Vec<u8>], key: &[u8]) -> String {
+    let mut params = Vec::new();
+    if !path.is_empty() {
+        let segments: Vec<String> = path.iter().map(hex::encode).collect();
+        params.push(format!("path={}", segments.join(",")));
+    }
+    params.push(format!("key={}", hex::encode(key)));
+    format!("{base}#{}", params.join("&"))
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn parse_fragment(fragment: &str) -> Option<Target> {
+    let mut path = Vec::new();
+    let mut key = None;
+    for param in fragment.split('&').filter(|param| !param.is_empty()) {
+        match param.split_once('=')? {
+            ("path", segments) => {
+                path = segments
+                    .split(',')
+                    .map(hex::decode)
+                    .collect::<Result<_, _>>()
+                    .ok()?;
+            }
+            ("key", k) => key = Some(hex::decode(k).ok()?),
+            _ => {}
+        }
+    }
+    (!path.is_empty() || key.is_some()).then_some((path, key))
+}
+
+/// Button to copy a link to the element, drawn only where links can be opened
+pub(crate) fn draw_copy_link(ui: &mut egui::Ui, path: &[Vec<u8>], key: &[u8]) {
+    let Some(base) = base_url() else {
+        return;
+    };
+    if ui
+        .button(egui_phosphor::regular::LINK)
+        .on_hover_text("Copy link to this element")
+        .clicked()
+    {
+        ui.ctx().copy_text(element_link(&base, path, key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_are_parsed_back_to_the_element() {
+        let path = vec![b"a".to_vec(), Vec::new(), vec![0xff, 0]];
+        let link = element_link("http://localhost/", &path, b"k");
+        assert_eq!(link, "http://localhost/#path=61,,ff00&key=6b");
+        let fragment = link.split_once('#').unwrap().1;
+        assert_eq!(parse_fragment(fragment), Some((path, Some(b"k".to_vec()))));
+
+        let root_link = element_link("http://localhost/", &[], b"k");
+        assert_eq!(
+            parse_fragment(root_link.split_once('#').unwrap().1),
+            Some((Vec::new(), Some(b"k".to_vec())))
+        );
+        assert_eq!(parse_fragment("path="), Some((vec![Vec::new()], None)));
+
+        assert_eq!(parse_fragment(""), None);
+        assert_eq!(parse_fragment("key=zz"), None);
+    }
+}
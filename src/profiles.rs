@@ -1,15 +1,26 @@
+mod sharing;
+mod suggestion;
+
 use std::borrow::Borrow;
 
 use eframe::{
-    egui::{self, CollapsingHeader, Frame, Label, Margin, TextEdit},
+    egui::{self, Checkbox, CollapsingHeader, Color32, ComboBox, Frame, Label, Margin, TextEdit},
     Storage,
 };
+use grovedbg_types::{PathQuery, SizedQuery};
 use serde::{Deserialize, Serialize};
+use sharing::ProfilesSharing;
+use strum::IntoEnumIterator;
 
 use crate::{
     bus::{CommandBus, UserAction},
-    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant, BytesInput},
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant, BytesInput, VersionPrefix},
+    density::kv_per_page,
+    flags_decoder::{default_flags_decoders, FlagsDecoder, DEFAULT_FLAGS_DECODERS},
     path_ctx::{Path, PathCtx},
+    redaction,
+    theme::category_color,
+    tree_data::TreeData,
     PROFILES_KEY,
 };
 
@@ -59,6 +70,7 @@ impl ProfileEntryKey {
 }
 
 #[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 struct ProfileEntry {
     key: ProfileEntryKey,
     alias: String,
@@ -66,10 +78,92 @@ struct ProfileEntry {
     display: BytesDisplayVariant,
     collapsed: bool,
     value_display: Option<BytesDisplayVariant>,
+    /// Sibling subtrees of the same category are grouped on the canvas
+    category: Option<String>,
+    /// Color of the category's group frame, derived from its name if unset
+    category_color: Option<[u8; 3]>,
+    /// Query used for the subtree under the entry instead of fetching all
+    default_query: Option<SizedQuery>,
+    /// Item values of the subtree under the entry start with a version
+    version_prefix: Option<VersionPrefix>,
+    /// Item values of the subtree under the entry and its descendants are
+    /// shown in redaction mode
+    show_values: bool,
 }
 
 type ToDelete = bool;
 
+/// Top panel button that focuses a path and fetches it, with the query if
+/// one is set or the first page of the subtree otherwise
+#[derive(Clone, Serialize, Deserialize)]
+struct Shortcut {
+    label: String,
+    path: Vec<Vec<u8>>,
+    #[serde(default)]
+    query: Option<SizedQuery>,
+}
+
+impl Shortcut {
+    fn new(label: &str, path: Vec<Vec<u8>>) -> Self {
+        Shortcut {
+            label: label.to_owned(),
+            path,
+            query: None,
+        }
+    }
+
+    fn open<'pa>(&self, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+        let path = path_ctx.add_path(self.path.clone());
+        bus.user_action(UserAction::FocusSubtree(path));
+        if let Some(query) = &self.query {
+            bus.fetch().path_query(PathQuery {
+                path: self.path.clone(),
+                query: query.clone(),
+            });
+        } else {
+            bus.fetch().subtree(path).limit(kv_per_page() as u16).send();
+        }
+    }
+
+    fn draw_editor(&mut self, ui: &mut egui::Ui, read_only: bool) -> ToDelete {
+        let mut to_delete = false;
+        ui.horizontal(|line| {
+            if !read_only
+                && line
+                    .button(egui_phosphor::regular::TRASH_SIMPLE)
+                    .on_hover_text("Delete the shortcut")
+                    .clicked()
+            {
+                to_delete = true;
+            }
+            line.add_enabled(
+                !read_only,
+                TextEdit::singleline(&mut self.label).desired_width(120.),
+            );
+            line.label(if self.path.is_empty() {
+                "root".to_owned()
+            } else {
+                self.path
+                    .iter()
+                    .map(|segment| bytes_by_display_variant(segment, &BytesDisplayVariant::guess(segment)))
+                    .collect::<Vec<_>>()
+                    .join(" / ")
+            });
+            if let Some(query) = &self.query {
+                line.label(egui_phosphor::regular::FUNNEL).on_hover_text(format!(
+                    "Runs a query with {} items, limit {}",
+                    query.query.items.len(),
+                    query
+                        .limit
+                        .map(|l| l.to_string())
+                        .unwrap_or_else(|| "none".to_owned())
+                ));
+            }
+        });
+        to_delete
+    }
+}
+
 impl ProfileEntry {
     fn draw<'pa>(
         &mut self,
@@ -204,6 +298,98 @@ impl ProfileEntry {
                             }
                         });
                     }
+
+                    if read_only {
+                        frame.label(format!(
+                            "Category: {}",
+                            self.category.as_deref().unwrap_or("unset")
+                        ));
+                    } else {
+                        frame.horizontal(|line| {
+                            let checkbox_before = self.category.is_some();
+                            let mut checkbox = checkbox_before;
+                            line.checkbox(&mut checkbox, "Category");
+                            if checkbox != checkbox_before {
+                                self.category = checkbox.then(String::new);
+                            }
+                            if let Some(category) = self.category.as_mut() {
+                                line.text_edit_singleline(category);
+                                let mut color = self.category_color.unwrap_or_else(|| {
+                                    let derived = category_color(line.ctx(), category);
+                                    [derived.r(), derived.g(), derived.b()]
+                                });
+                                if line
+                                    .color_edit_button_srgb(&mut color)
+                                    .on_hover_text("Color of the category's group on the canvas")
+                                    .changed()
+                                {
+                                    self.category_color = Some(color);
+                                }
+                                if self.category_color.is_some()
+                                    && line
+                                        .small_button(egui_phosphor::regular::ARROW_COUNTER_CLOCKWISE)
+                                        .on_hover_text("Derive the color from the category name")
+                                        .clicked()
+                                {
+                                    self.category_color = None;
+                                }
+                            }
+                        });
+                    }
+                    if read_only {
+                        frame.label(format!(
+                            "Versioned values: {}",
+                            self.version_prefix.as_ref().map(AsRef::as_ref).unwrap_or("no")
+                        ));
+                    } else {
+                        frame.horizontal(|line| {
+                            let checkbox_before = self.version_prefix.is_some();
+                            let mut checkbox = checkbox_before;
+                            line.checkbox(&mut checkbox, "Versioned values")
+                                .on_hover_text("Show the version prefix of item values separately");
+                            if checkbox != checkbox_before {
+                                self.version_prefix = checkbox.then(VersionPrefix::default);
+                            }
+                            if let Some(prefix) = self.version_prefix.as_mut() {
+                                ComboBox::from_id_salt(("version_prefix", self_path))
+                                    .selected_text(prefix.as_ref())
+                                    .show_ui(line, |combo| {
+                                        for variant in VersionPrefix::iter() {
+                                            combo.selectable_value(prefix, variant, variant.as_ref());
+                                        }
+                                    });
+                            }
+                        });
+                    }
+
+                    frame
+                        .add_enabled(
+                            !read_only,
+                            Checkbox::new(&mut self.show_values, "Show values when redacted"),
+                        )
+                        .on_hover_text("Exempt the subtree and its descendants from the redaction mode");
+
+                    if let Some(query) = &self.default_query {
+                        frame.horizontal(|line| {
+                            line.label(format!(
+                                "Default query: {} items, limit {}",
+                                query.query.items.len(),
+                                query
+                                    .limit
+                                    .map(|l| l.to_string())
+                                    .unwrap_or_else(|| "none".to_owned())
+                            ))
+                            .on_hover_text("Set from the query builder");
+                            if !read_only
+                                && line
+                                    .button(egui_phosphor::regular::X)
+                                    .on_hover_text("Remove the default query")
+                                    .clicked()
+                            {
+                                self.default_query = None;
+                            }
+                        });
+                    }
                     draw_entries(frame, bus, &mut self.sub_items, read_only, self_path);
                 });
         }
@@ -227,6 +413,11 @@ fn drive_profile() -> Profile {
                 key: vec![64].into(),
                 collapsed: true,
                 alias: "Data contract documents".to_string(),
+                category: None,
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -235,11 +426,21 @@ fn drive_profile() -> Profile {
                 key: vec![32].into(),
                 collapsed: true,
                 alias: "Identities".to_string(),
+                category: Some("Identity data".to_owned()),
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: vec![ProfileEntry {
                     key: ProfileEntryKey::Capture,
                     collapsed: true,
                     alias: "ID {}".to_owned(),
+                    category: None,
+                    category_color: None,
+                    default_query: None,
+                    version_prefix: None,
+                    show_values: false,
                     value_display: None,
                     sub_items: Vec::default(),
                     display: BytesDisplayVariant::Hex,
@@ -250,6 +451,11 @@ fn drive_profile() -> Profile {
                 key: vec![24].into(),
                 collapsed: true,
                 alias: "Unique public key hashes to identities".to_string(),
+                category: Some("Identity data".to_owned()),
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -258,6 +464,11 @@ fn drive_profile() -> Profile {
                 key: vec![8].into(),
                 collapsed: true,
                 alias: "Non-unique public key Key hashes to identities".to_string(),
+                category: Some("Identity data".to_owned()),
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -266,6 +477,11 @@ fn drive_profile() -> Profile {
                 key: vec![48].into(),
                 collapsed: true,
                 alias: "Pools".to_string(),
+                category: Some("Balances".to_owned()),
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -274,6 +490,11 @@ fn drive_profile() -> Profile {
                 key: vec![40].into(),
                 collapsed: true,
                 alias: "Pre funded specialized balances".to_string(),
+                category: Some("Balances".to_owned()),
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -282,6 +503,11 @@ fn drive_profile() -> Profile {
                 key: vec![72].into(),
                 collapsed: true,
                 alias: "Spent asset lock transactions".to_string(),
+                category: Some("Transactions".to_owned()),
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -290,6 +516,11 @@ fn drive_profile() -> Profile {
                 key: vec![104].into(),
                 collapsed: true,
                 alias: "Misc".to_string(),
+                category: None,
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -298,6 +529,11 @@ fn drive_profile() -> Profile {
                 key: vec![80].into(),
                 collapsed: true,
                 alias: "Withdrawal transactions".to_string(),
+                category: Some("Transactions".to_owned()),
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -306,6 +542,11 @@ fn drive_profile() -> Profile {
                 key: vec![96].into(),
                 collapsed: true,
                 alias: "Balances".to_string(),
+                category: Some("Balances".to_owned()),
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -314,6 +555,11 @@ fn drive_profile() -> Profile {
                 key: vec![16].into(),
                 collapsed: true,
                 alias: "Token balances".to_string(),
+                category: Some("Token data".to_owned()),
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -322,6 +568,11 @@ fn drive_profile() -> Profile {
                 key: vec![120].into(),
                 collapsed: true,
                 alias: "Versions".to_string(),
+                category: None,
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: Vec::default(),
                 display: BytesDisplayVariant::U8,
@@ -330,18 +581,38 @@ fn drive_profile() -> Profile {
                 key: vec![112].into(),
                 collapsed: true,
                 alias: "Votes".to_string(),
+                category: None,
+                category_color: None,
+                default_query: None,
+                version_prefix: None,
+                show_values: false,
                 value_display: None,
                 sub_items: vec![ProfileEntry {
                     key: vec![101].into(),
                     collapsed: true,
                     alias: "Voting end dates".to_owned(),
+                    category: None,
+                    category_color: None,
+                    default_query: None,
+                    version_prefix: None,
+                    show_values: false,
                     value_display: None,
                     sub_items: vec![ProfileEntry {
                         key: ProfileEntryKey::Capture,
                         alias: "{}".to_owned(),
+                        category: None,
+                        category_color: None,
+                        default_query: None,
+                        version_prefix: None,
+                        show_values: false,
                         sub_items: vec![ProfileEntry {
                             key: ProfileEntryKey::Capture,
                             alias: "{}".to_owned(),
+                            category: None,
+                            category_color: None,
+                            default_query: None,
+                            version_prefix: None,
+                            show_values: false,
                             sub_items: Vec::default(),
                             display: BytesDisplayVariant::U8,
                             collapsed: true,
@@ -356,6 +627,12 @@ fn drive_profile() -> Profile {
                 display: BytesDisplayVariant::U8,
             },
         ],
+        flags_decoders: vec![FlagsDecoder::StorageFlags],
+        shortcuts: vec![
+            Shortcut::new("Identities", vec![vec![32]]),
+            Shortcut::new("Balances", vec![vec![96]]),
+            Shortcut::new("Votes", vec![vec![112]]),
+        ],
         read_only: true,
     }
 }
@@ -385,7 +662,15 @@ fn draw_entries<'pa>(
 #[derive(Serialize, Deserialize)]
 struct Profile {
     name: String,
+    #[serde(default)]
     entries: Vec<ProfileEntry>,
+    /// Tried in order to show element flags, the first one that succeeds wins
+    #[serde(default = "default_flags_decoders")]
+    flags_decoders: Vec<FlagsDecoder>,
+    /// Buttons of the top panel
+    #[serde(default)]
+    shortcuts: Vec<Shortcut>,
+    #[serde(default)]
     read_only: bool,
 }
 
@@ -393,6 +678,8 @@ struct Profile {
 pub(crate) struct ProfilesView {
     profiles: Vec<Profile>,
     selected: usize,
+    #[serde(skip)]
+    sharing: ProfilesSharing,
 }
 
 impl ProfilesView {
@@ -423,7 +710,43 @@ impl ProfilesView {
         profiles_view
     }
 
-    pub(crate) fn draw<'pa>(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+    pub(crate) fn draw<'pa>(
+        &mut self,
+        ui: &mut egui::Ui,
+        bus: &CommandBus<'pa>,
+        path_ctx: &'pa PathCtx,
+        tree_data: &TreeData<'pa>,
+    ) {
+        if ui
+            .button(format!(
+                "{} Suggest a profile",
+                egui_phosphor::regular::MAGIC_WAND
+            ))
+            .on_hover_text(
+                "Create an editable profile with entries for loaded root subtree keys and keys of its child \
+                 subtrees",
+            )
+            .clicked()
+        {
+            if let Some(profile) = suggestion::suggest_profile(tree_data, path_ctx.get_root()) {
+                self.profiles.push(profile);
+                self.selected = self.profiles.len() - 1;
+            } else {
+                log::warn!("Fetch the root subtree first to suggest a profile");
+            }
+        }
+
+        CollapsingHeader::new("Import / export").show(ui, |collapsing| {
+            let mut imported =
+                self.sharing
+                    .draw(collapsing, &self.profiles, self.profiles.get(self.selected));
+            if !imported.is_empty() {
+                self.profiles.append(&mut imported);
+                self.selected = self.profiles.len() - 1;
+            }
+        });
+        ui.separator();
+
         let mut selected_profile = None;
         let mut copied_profiles = Vec::new();
         let mut deleted_profiles = Vec::new();
@@ -447,6 +770,8 @@ impl ProfilesView {
                         read_only: false,
                         name: format!("{} copy", profile.name),
                         entries: profile.entries.clone(),
+                        flags_decoders: profile.flags_decoders.clone(),
+                        shortcuts: profile.shortcuts.clone(),
                     });
                 }
 
@@ -478,6 +803,21 @@ impl ProfilesView {
             if !profile.read_only && ui.button(egui_phosphor::regular::PLUS_SQUARE).clicked() {
                 profile.entries.push(Default::default());
             }
+
+            ui.separator();
+            CollapsingHeader::new("Element flags decoders").show(ui, |collapsing| {
+                FlagsDecoder::draw_list(collapsing, &mut profile.flags_decoders, profile.read_only);
+            });
+
+            CollapsingHeader::new("Shortcuts").show(ui, |collapsing| {
+                if profile.shortcuts.is_empty() {
+                    collapsing.label("Add shortcuts from the query builder");
+                }
+                let read_only = profile.read_only;
+                profile
+                    .shortcuts
+                    .retain_mut(|shortcut| !shortcut.draw_editor(collapsing, read_only));
+            });
         }
 
         self.profiles.append(&mut copied_profiles);
@@ -488,6 +828,111 @@ impl ProfilesView {
         }
     }
 
+    /// Attaches a default query to the selected profile's entry of the path,
+    /// or removes it if `None`
+    pub(crate) fn set_default_query(&mut self, path: Path, query: Option<SizedQuery>) {
+        let Some(profile) = self.profiles.get_mut(self.selected) else {
+            log::warn!("No profile is selected");
+            return;
+        };
+        if profile.read_only {
+            log::warn!("Profile {} is read only, make a copy to edit it", profile.name);
+            return;
+        }
+
+        let mut entry: Option<&mut ProfileEntry> = None;
+        for segment in path.to_vec() {
+            let entries = match entry {
+                Some(e) => &mut e.sub_items,
+                None => &mut profile.entries,
+            };
+            entry = entries.iter_mut().find(|e| match &e.key {
+                ProfileEntryKey::Key(bytes) => bytes.get_bytes() == segment,
+                ProfileEntryKey::Capture => true,
+            });
+            if entry.is_none() {
+                break;
+            }
+        }
+
+        if let Some(entry) = entry {
+            entry.default_query = query;
+        } else {
+            log::warn!("The path has no entry in the selected profile, add one first");
+        }
+    }
+
+    /// Adds a top panel button for the path to the selected profile
+    pub(crate) fn add_shortcut(&mut self, path: Path, query: Option<SizedQuery>) {
+        let Some(profile) = self.profiles.get_mut(self.selected) else {
+            log::warn!("No profile is selected");
+            return;
+        };
+        if profile.read_only {
+            log::warn!("Profile {} is read only, make a copy to edit it", profile.name);
+            return;
+        }
+
+        let label = RootActiveProfileContext::new(Some(&*profile))
+            .fast_forward(path)
+            .path_segments_aliases()
+            .last()
+            .cloned()
+            .flatten()
+            .or_else(|| path.for_last_segment(|segment| segment.view_by_display()))
+            .unwrap_or_else(|| "root".to_owned());
+        profile.shortcuts.push(Shortcut {
+            label,
+            path: path.to_vec(),
+            query,
+        });
+    }
+
+    /// Shortcut buttons of the selected profile for the top panel
+    pub(crate) fn draw_shortcuts<'pa>(
+        &self,
+        ui: &mut egui::Ui,
+        bus: &CommandBus<'pa>,
+        path_ctx: &'pa PathCtx,
+    ) {
+        let Some(profile) = self
+            .profiles
+            .get(self.selected)
+            .filter(|p| !p.shortcuts.is_empty())
+        else {
+            return;
+        };
+        for shortcut in profile.shortcuts.iter() {
+            let hover = if shortcut.query.is_some() {
+                "Focus the subtree and run the saved query"
+            } else {
+                "Focus the subtree and fetch its first page"
+            };
+            if ui.button(&shortcut.label).on_hover_text(hover).clicked() {
+                shortcut.open(bus, path_ctx);
+            }
+        }
+        ui.separator();
+    }
+
+    pub(crate) fn shortcut_labels(&self) -> Vec<String> {
+        self.profiles
+            .get(self.selected)
+            .map(|p| p.shortcuts.iter().map(|s| s.label.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Opens a shortcut of the active profile by its index
+    pub(crate) fn open_shortcut<'pa>(&self, idx: usize, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+        if let Some(shortcut) = self
+            .profiles
+            .get(self.selected)
+            .and_then(|p| p.shortcuts.get(idx))
+        {
+            shortcut.open(bus, path_ctx);
+        }
+    }
+
     pub(crate) fn active_profile_root_ctx(&self) -> RootActiveProfileContext {
         let profile = self.profiles.get(self.selected);
         RootActiveProfileContext::new(profile)
@@ -520,16 +965,22 @@ impl<'pf> RootActiveProfileContext<'pf> {
     fn new(profile: Option<&'pf Profile>) -> Self {
         RootActiveProfileContext(ActiveProfileSubtreeContext {
             profile,
+            entry: None,
             entries: profile.map(|p| &p.entries),
             path_segments: Vec::new(),
+            show_values: false,
         })
     }
 }
 
 pub(crate) struct ActiveProfileSubtreeContext<'pf> {
     profile: Option<&'pf Profile>,
+    /// Entry matching the subtree itself
+    entry: Option<&'pf ProfileEntry>,
     entries: Option<&'pf Vec<ProfileEntry>>,
     path_segments: Vec<Option<String>>,
+    /// The subtree or one of its ancestors is exempt from redaction
+    show_values: bool,
 }
 
 impl<'pf> ActiveProfileSubtreeContext<'pf> {
@@ -561,16 +1012,33 @@ impl<'pf> ActiveProfileSubtreeContext<'pf> {
             path_segments.push(None);
         }
 
+        let entry = self.entries.and_then(|e| idx.and_then(|i| e.get(i)));
+
         ActiveProfileSubtreeContext {
             profile: self.profile,
-            entries: self
-                .entries
-                .and_then(|e| idx.and_then(|i| e.get(i)))
-                .map(|e| &e.sub_items),
+            entry,
+            entries: entry.map(|e| &e.sub_items),
             path_segments,
+            show_values: self.show_values || entry.is_some_and(|e| e.show_values),
         }
     }
 
+    /// Query template of the subtree's profile entry
+    pub(crate) fn default_query(&self) -> Option<&'pf SizedQuery> {
+        self.entry.and_then(|e| e.default_query.as_ref())
+    }
+
+    /// Version prefix of item values in the subtree, if configured
+    pub(crate) fn version_prefix(&self) -> Option<VersionPrefix> {
+        self.entry.and_then(|e| e.version_prefix)
+    }
+
+    /// Item values of the subtree are masked, as the redaction mode is on
+    /// and the profile doesn't exempt the subtree
+    pub(crate) fn values_redacted(&self, ctx: &egui::Context) -> bool {
+        redaction::enabled(ctx) && !self.show_values
+    }
+
     pub(crate) fn key_view(&self, key: &[u8]) -> Option<String> {
         self.entries
             .into_iter()
@@ -596,6 +1064,36 @@ impl<'pf> ActiveProfileSubtreeContext<'pf> {
             .and_then(|e| e.value_display)
     }
 
+    pub(crate) fn flags_decoders(&self) -> &'pf [FlagsDecoder] {
+        self.profile
+            .map_or(DEFAULT_FLAGS_DECODERS, |p| p.flags_decoders.as_slice())
+    }
+
+    pub(crate) fn key_category(&self, key: &[u8]) -> Option<&'pf str> {
+        self.entries
+            .into_iter()
+            .flatten()
+            .find(|x| match &x.key {
+                ProfileEntryKey::Key(bytes) => bytes.get_bytes() == key,
+                ProfileEntryKey::Capture => true,
+            })
+            .and_then(|e| e.category.as_deref())
+            .filter(|c| !c.is_empty())
+    }
+
+    /// Color set for the key's category, if any
+    pub(crate) fn key_category_color(&self, key: &[u8]) -> Option<Color32> {
+        self.entries
+            .into_iter()
+            .flatten()
+            .find(|x| match &x.key {
+                ProfileEntryKey::Key(bytes) => bytes.get_bytes() == key,
+                ProfileEntryKey::Capture => true,
+            })
+            .and_then(|e| e.category_color)
+            .map(|[r, g, b]| Color32::from_rgb(r, g, b))
+    }
+
     pub(crate) fn path_segments_aliases(&self) -> &[Option<String>] {
         &self.path_segments
     }
@@ -0,0 +1,186 @@
+//! Import and export of profiles as JSON to share path aliases within a team.
+
+use eframe::egui::{self, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use super::Profile;
+
+/// Version of the exported JSON layout, bumped on incompatible changes
+const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct ExportBundle<'a> {
+    version: u32,
+    profiles: Vec<&'a Profile>,
+}
+
+/// Unknown fields are ignored and missing ones get defaults, so bundles of
+/// other GroveDBG versions can still be imported
+#[derive(Deserialize)]
+struct ImportBundle {
+    #[serde(default)]
+    version: u32,
+    profiles: Vec<Profile>,
+}
+
+fn export_json<'a>(profiles: impl IntoIterator<Item = &'a Profile>) -> String {
+    serde_json::to_string_pretty(&ExportBundle {
+        version: SCHEMA_VERSION,
+        profiles: profiles.into_iter().collect(),
+    })
+    .unwrap_or_else(|e| format!("Unable to serialize profiles: {e}"))
+}
+
+fn import_json(json: &str) -> Result<Vec<Profile>, serde_json::Error> {
+    let bundle: ImportBundle = serde_json::from_str(json)?;
+    if bundle.version > SCHEMA_VERSION {
+        log::warn!(
+            "Profiles were exported with a newer schema version {}, some settings may be lost",
+            bundle.version
+        );
+    }
+    Ok(bundle
+        .profiles
+        .into_iter()
+        .map(|profile| Profile {
+            read_only: false,
+            ..profile
+        })
+        .collect())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_json(name: &str, json: String) {
+    let file_name = format!(
+        "grovedbg-profiles-{}-{}.json",
+        name.chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect::<String>(),
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    );
+    match std::fs::write(&file_name, json) {
+        Ok(_) => log::info!("Profiles exported to {file_name}"),
+        Err(e) => log::error!("Unable to export profiles: {e}"),
+    }
+}
+
+/// Pasted JSON or a path to a file with it
+#[derive(Default)]
+pub(super) struct ProfilesSharing {
+    input: String,
+}
+
+impl ProfilesSharing {
+    /// Draws export buttons for the selected and all profiles and the import
+    /// input, returns imported profiles
+    pub(super) fn draw(
+        &mut self,
+        ui: &mut egui::Ui,
+        profiles: &[Profile],
+        selected: Option<&Profile>,
+    ) -> Vec<Profile> {
+        ui.horizontal(|line| {
+            line.label("Export selected:");
+            if let Some(profile) = selected {
+                if line
+                    .button(egui_phosphor::regular::COPY)
+                    .on_hover_text("Copy the selected profile as JSON")
+                    .clicked()
+                {
+                    line.ctx().copy_text(export_json([profile]));
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                if line
+                    .button(egui_phosphor::regular::FLOPPY_DISK)
+                    .on_hover_text("Save the selected profile to the working directory")
+                    .clicked()
+                {
+                    save_json(&profile.name, export_json([profile]));
+                }
+            }
+        });
+
+        ui.horizontal(|line| {
+            line.label("Export all:");
+            if line
+                .button(egui_phosphor::regular::COPY)
+                .on_hover_text("Copy all profiles as JSON")
+                .clicked()
+            {
+                line.ctx().copy_text(export_json(profiles));
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if line
+                .button(egui_phosphor::regular::FLOPPY_DISK)
+                .on_hover_text("Save all profiles to the working directory")
+                .clicked()
+            {
+                save_json("all", export_json(profiles));
+            }
+        });
+
+        let hint = if cfg!(target_arch = "wasm32") {
+            "Paste exported JSON"
+        } else {
+            "Paste exported JSON or a file path"
+        };
+        ui.add(
+            TextEdit::multiline(&mut self.input)
+                .hint_text(hint)
+                .desired_rows(2),
+        );
+
+        if !ui
+            .add_enabled(!self.input.trim().is_empty(), egui::Button::new("Import"))
+            .clicked()
+        {
+            return Vec::new();
+        }
+
+        match self
+            .read_input()
+            .and_then(|json| import_json(&json).map_err(|e| e.to_string()))
+        {
+            Ok(imported) => {
+                log::info!("Imported {} profiles", imported.len());
+                self.input.clear();
+                imported
+            }
+            Err(e) => {
+                log::error!("Unable to import profiles: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    fn read_input(&self) -> Result<String, String> {
+        let input = self.input.trim();
+        if input.starts_with('{') || cfg!(target_arch = "wasm32") {
+            Ok(input.to_owned())
+        } else {
+            std::fs::read_to_string(input).map_err(|e| format!("{input}: {e}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profiles::drive_profile;
+
+    #[test]
+    fn roundtrip_ignores_unknown_fields() {
+        let json = export_json([&drive_profile()]);
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["profiles"][0]["future_field"] = serde_json::Value::Bool(true);
+        value["profiles"][0]["entries"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("collapsed");
+
+        let imported = import_json(&value.to_string()).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].name, drive_profile().name);
+        assert!(!imported[0].read_only);
+    }
+}
@@ -0,0 +1,108 @@
+//! Profile generated from loaded data to get readable path labels for any
+//! database quickly.
+
+use grovedbg_types::Key;
+
+use super::{drive_profile, key_as_alias, Profile, ProfileEntry, ProfileEntryKey};
+use crate::{
+    bytes_utils::BytesDisplayVariant, flags_decoder::default_flags_decoders, path_ctx::Path,
+    tree_data::TreeData,
+};
+
+/// Child subtrees with more keys are likely collections, so a single capture
+/// entry is suggested instead of an entry per key
+const MAX_CHILD_ENTRIES: usize = 16;
+
+/// Builds an editable profile with entries for all loaded root subtree keys
+/// and keys of its child subtrees. Returns `None` if nothing was loaded yet.
+pub(super) fn suggest_profile<'pa>(tree_data: &TreeData<'pa>, root: Path<'pa>) -> Option<Profile> {
+    let root_data = tree_data.get(&root)?;
+    if root_data.elements.is_empty() {
+        return None;
+    }
+
+    let drive_entries = drive_profile().entries;
+    let known_entry = |key: &[u8]| {
+        drive_entries
+            .iter()
+            .find(|e| key_as_alias(&e.key).as_deref() == Some(key))
+    };
+
+    // Single byte keys are common, so Drive is assumed only when most of the
+    // root keys match
+    let drive_matches = root_data
+        .elements
+        .keys()
+        .filter(|k| known_entry(k).is_some())
+        .count();
+    let is_drive = drive_matches * 2 >= root_data.elements.len();
+
+    let entries = root_data
+        .elements
+        .keys()
+        .map(|key| {
+            let known = is_drive.then(|| known_entry(key)).flatten();
+            let child_keys: Vec<Key> = if root_data.subtree_keys.contains(key) {
+                tree_data
+                    .get(&root.child(key.clone()))
+                    .map(|child| child.elements.keys().cloned().collect())
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+
+            match known {
+                Some(entry) if !entry.sub_items.is_empty() || child_keys.is_empty() => ProfileEntry {
+                    collapsed: true,
+                    ..entry.clone()
+                },
+                Some(entry) => ProfileEntry {
+                    collapsed: true,
+                    sub_items: child_entries(child_keys),
+                    ..entry.clone()
+                },
+                None => ProfileEntry {
+                    sub_items: child_entries(child_keys),
+                    ..unknown_entry(key.clone())
+                },
+            }
+        })
+        .collect();
+
+    Some(Profile {
+        name: if is_drive {
+            "drive suggested".to_owned()
+        } else {
+            "suggested".to_owned()
+        },
+        entries,
+        flags_decoders: default_flags_decoders(),
+        shortcuts: Vec::new(),
+        read_only: false,
+    })
+}
+
+fn child_entries(keys: Vec<Key>) -> Vec<ProfileEntry> {
+    if keys.len() > MAX_CHILD_ENTRIES {
+        let display = BytesDisplayVariant::guess(&keys[0]);
+        vec![ProfileEntry {
+            key: ProfileEntryKey::Capture,
+            alias: "{}".to_owned(),
+            display,
+            collapsed: true,
+            ..Default::default()
+        }]
+    } else {
+        keys.into_iter().map(unknown_entry).collect()
+    }
+}
+
+fn unknown_entry(key: Key) -> ProfileEntry {
+    ProfileEntry {
+        alias: hex::encode(&key),
+        display: BytesDisplayVariant::guess(&key),
+        key: key.into(),
+        collapsed: true,
+        ..Default::default()
+    }
+}
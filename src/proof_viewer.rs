@@ -1,57 +1,304 @@
-use eframe::egui::{self, CollapsingHeader, ScrollArea};
+mod absence;
+mod filter;
+mod hash_chain;
+mod minimality;
+mod proof_diff;
+mod raw_bytes;
+mod repro_bundle;
+mod result_set;
+mod root_check;
+mod stack_debugger;
+
+use absence::collect_absences;
+pub(crate) use absence::Absence;
+use eframe::egui::{self, CollapsingHeader, RichText, ScrollArea};
+use filter::{FilterCriteria, ProofFilter};
+use grovedbg_types::{CryptoHash, PathQuery};
+use hash_chain::HashChain;
+use minimality::Minimality;
+use proof_diff::ProofDiff;
+use raw_bytes::RawProof;
+use repro_bundle::ReproBundle;
+use result_set::ResultSet;
+use root_check::RootCheck;
+use stack_debugger::StackDebugger;
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
 
 use crate::{
     bus::{CommandBus, UserAction},
     bytes_utils::BytesView,
+    format,
     path_ctx::{Path, PathCtx},
+    protocol::{ProofFailure, Transfer},
+    query_builder::transfer_summary,
+    redaction,
+    theme::input_error_color,
+    tree_data::ProofMismatches,
 };
 
 pub(crate) struct ProofViewer {
     prove_options: ProveOptionsView,
     root_layer: ProofLayerView,
+    result_set: ResultSet,
+    hash_chain: HashChain,
+    minimality: Minimality,
+    absences: Vec<Absence>,
+    repro_bundle: Option<ReproBundle>,
+    /// Measured proof request, known once the proof is received
+    transfer: Option<Transfer>,
+    /// Kept to pin the proof for comparison
+    proof_layer: grovedbg_types::ProofLayer,
+    /// Proof the shown one is compared against, stays pinned as other proofs
+    /// are loaded
+    pinned: Option<grovedbg_types::ProofLayer>,
+    diff: Option<ProofDiff>,
+    /// Set for proofs requested to be verified
+    root_check: Option<RootCheck>,
+    filter: ProofFilter,
+    /// Response the proof was parsed from, only kept for received proofs
+    raw: Option<RawProof>,
+    tab: ProofTab,
+}
+
+#[derive(Clone, Copy, PartialEq, Default, AsRefStr, EnumIter)]
+enum ProofTab {
+    #[default]
+    #[strum(serialize = "Proof")]
+    Proof,
+    #[strum(serialize = "Raw bytes")]
+    RawBytes,
 }
 
 impl ProofViewer {
-    pub(crate) fn new(proof: grovedbg_types::Proof) -> Self {
+    pub(crate) fn new(proof: grovedbg_types::Proof, path_query: &PathQuery) -> Self {
+        let absences = collect_absences(&proof.root_layer, path_query);
         ProofViewer {
             prove_options: ProveOptionsView::new(proof.prove_options),
-            root_layer: ProofLayerView::new(proof.root_layer),
+            result_set: ResultSet::new(&proof.root_layer),
+            hash_chain: HashChain::new(&proof.root_layer),
+            minimality: Minimality::new(&proof.root_layer, path_query),
+            root_layer: ProofLayerView::new(proof.root_layer.clone(), Vec::new(), &absences),
+            absences,
+            repro_bundle: None,
+            transfer: None,
+            proof_layer: proof.root_layer,
+            pinned: None,
+            diff: None,
+            root_check: None,
+            filter: Default::default(),
+            raw: None,
+            tab: Default::default(),
+        }
+    }
+
+    /// Shows a proof that couldn't be processed along with a way to report it
+    pub(crate) fn new_failed(failure: ProofFailure) -> Self {
+        let mut viewer = Self::new(failure.proof.clone(), &failure.path_query);
+        viewer.repro_bundle = Some(ReproBundle::new(failure));
+        viewer
+    }
+
+    /// Keys the query asked for that the proof shows to be missing
+    pub(crate) fn absences(&self) -> &[Absence] {
+        &self.absences
+    }
+
+    /// Keeps the proof pinned in the viewer being replaced and compares
+    /// against it
+    pub(crate) fn keep_pinned(&mut self, previous: Option<ProofViewer>) {
+        self.set_pinned(previous.and_then(|viewer| viewer.pinned));
+    }
+
+    fn set_pinned(&mut self, pinned: Option<grovedbg_types::ProofLayer>) {
+        self.diff = pinned
+            .as_ref()
+            .map(|pinned| ProofDiff::new(pinned, &self.proof_layer));
+        self.pinned = pinned;
+    }
+
+    /// Checks the proof against the GroveDB root hash of the session
+    pub(crate) fn verify(&mut self, session_root: Option<CryptoHash>) {
+        let root_check = RootCheck::new(&self.proof_layer, session_root);
+        let summary = root_check.summary(self.result_set.len());
+        if matches!(root_check, RootCheck::Verified(_)) {
+            log::info!("{summary}");
+        } else {
+            log::error!("{summary}");
         }
+        self.root_check = Some(root_check);
     }
 
-    pub(crate) fn draw<'pa>(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+    pub(crate) fn set_payload(&mut self, payload: Vec<u8>) {
+        self.raw = Some(RawProof::new(payload));
+    }
+
+    pub(crate) fn set_transfer(&mut self, transfer: Transfer) {
+        self.transfer = Some(transfer);
+    }
+
+    pub(crate) fn draw<'pa>(
+        &mut self,
+        ui: &mut egui::Ui,
+        bus: &CommandBus<'pa>,
+        path_ctx: &'pa PathCtx,
+        mismatches: &ProofMismatches<'pa>,
+    ) {
+        ui.horizontal(|line| {
+            for tab in ProofTab::iter() {
+                line.selectable_value(&mut self.tab, tab, tab.as_ref());
+            }
+        });
+        ui.separator();
+
+        if self.tab == ProofTab::RawBytes {
+            ScrollArea::vertical().show(ui, |scroll| match &mut self.raw {
+                Some(raw) => raw.draw(scroll),
+                None => {
+                    scroll.label("Raw bytes are only kept for proofs received from the endpoint");
+                }
+            });
+            return;
+        }
+
         ScrollArea::vertical().show(ui, |scroll| {
+            if let Some(repro_bundle) = &self.repro_bundle {
+                repro_bundle.draw(scroll);
+                scroll.separator();
+            }
+            if let Some(transfer) = &self.transfer {
+                scroll
+                    .label(format!("Proof received: {}", transfer_summary(transfer, None)))
+                    .on_hover_text("Round trip and size of the proof response");
+            }
+            if let Some(root_check) = &self.root_check {
+                root_check.draw(scroll, self.result_set.len());
+                scroll.separator();
+            }
+            if self.pinned.is_some() {
+                if scroll
+                    .button(format!("{} Unpin", egui_phosphor::regular::PUSH_PIN_SLASH))
+                    .on_hover_text("Stop comparing proofs")
+                    .clicked()
+                {
+                    self.set_pinned(None);
+                }
+            } else if scroll
+                .button(format!("{} Pin for comparison", egui_phosphor::regular::PUSH_PIN))
+                .on_hover_text("Compare the next loaded proof against this one")
+                .clicked()
+            {
+                self.set_pinned(Some(self.proof_layer.clone()));
+            }
+            if let Some(diff) = &self.diff {
+                diff.draw(scroll);
+            }
+            self.result_set.draw(scroll, bus, path_ctx);
+            if !self.absences.is_empty() {
+                CollapsingHeader::new(format!("Absence proofs ({})", self.absences.len()))
+                    .default_open(true)
+                    .show(scroll, |collapsing| {
+                        for absence in self.absences.iter() {
+                            collapsing.horizontal(|line| {
+                                line.label(result_set::path_display(&absence.path));
+                                absence.draw(line);
+                            });
+                        }
+                    });
+            }
+            self.hash_chain.draw(scroll);
+            self.minimality.draw(scroll);
+            scroll.separator();
             self.prove_options.draw(scroll);
             scroll.separator();
-            self.root_layer.draw(scroll, bus, path_ctx.get_root());
+            CollapsingHeader::new(format!("{} Filter", egui_phosphor::regular::FUNNEL))
+                .show(scroll, |collapsing| self.filter.draw(collapsing));
+            let criteria = self.filter.criteria();
+            if criteria.is_active() {
+                scroll.label(format!(
+                    "{} matching operations",
+                    self.root_layer.hits(&[], &criteria)
+                ));
+            }
+            self.root_layer
+                .draw(scroll, bus, path_ctx.get_root(), mismatches, &criteria);
         });
     }
 }
 
 struct ProofLayerView {
     merk_proof: MerkProofViewer,
+    /// Keys this layer proves to be absent
+    absences: Vec<Absence>,
     lower_layers: Vec<(BytesView, ProofLayerView)>,
 }
 
 impl ProofLayerView {
-    fn new(layer: grovedbg_types::ProofLayer) -> Self {
+    fn new(layer: grovedbg_types::ProofLayer, path: Vec<Vec<u8>>, absences: &[Absence]) -> Self {
         Self {
             merk_proof: MerkProofViewer::new(layer.merk_proof),
+            absences: absences.iter().filter(|a| a.path == path).cloned().collect(),
             lower_layers: layer
                 .lower_layers
                 .into_iter()
-                .map(|(k, v)| (BytesView::new(k), ProofLayerView::new(v)))
+                .map(|(k, v)| {
+                    let mut lower_path = path.clone();
+                    lower_path.push(k.clone());
+                    (BytesView::new(k), ProofLayerView::new(v, lower_path, absences))
+                })
                 .collect(),
         }
     }
 
-    fn draw<'pa>(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, path: Path<'pa>) {
+    /// Operations matching the filter in the layer and its lower layers
+    fn hits(&self, path: &[Vec<u8>], criteria: &FilterCriteria) -> usize {
+        let own = if criteria.layer_matches(path) {
+            self.merk_proof.hits(criteria)
+        } else {
+            0
+        };
+        let lower: usize = self
+            .lower_layers
+            .iter()
+            .map(|(key, layer)| {
+                let mut lower_path = path.to_vec();
+                lower_path.push(key.bytes.clone());
+                layer.hits(&lower_path, criteria)
+            })
+            .sum();
+        own + lower
+    }
+
+    fn draw<'pa>(
+        &mut self,
+        ui: &mut egui::Ui,
+        bus: &CommandBus<'pa>,
+        path: Path<'pa>,
+        mismatches: &ProofMismatches<'pa>,
+        criteria: &FilterCriteria,
+    ) {
         ui.label("Merk proof:");
-        self.merk_proof.draw(ui);
+        for absence in self.absences.iter() {
+            absence.draw(ui);
+        }
+        if !criteria.is_active() {
+            self.merk_proof.draw(ui, bus, path, mismatches, None);
+        } else if criteria.layer_matches(&path.to_vec()) {
+            ui.label(format!("{} matching operations", self.merk_proof.hits(criteria)));
+            self.merk_proof.draw(ui, bus, path, mismatches, Some(criteria));
+        } else {
+            ui.label("The layer path doesn't match the filter");
+        }
 
         ui.separator();
 
         for (key, layer) in self.lower_layers.iter_mut() {
+            let lower_path = path.child(key.bytes.clone());
+            let hits = criteria
+                .is_active()
+                .then(|| layer.hits(&lower_path.to_vec(), criteria));
+            if hits == Some(0) {
+                continue;
+            }
             ui.horizontal(|line| {
                 key.draw(line);
                 if line
@@ -62,10 +309,14 @@ impl ProofLayerView {
                     bus.user_action(UserAction::SelectMerkView(path.child(key.bytes.to_vec())));
                 }
             });
-            CollapsingHeader::new("Layer proof")
+            let header = match hits {
+                Some(hits) => format!("Layer proof ({hits} hits)"),
+                None => "Layer proof".to_owned(),
+            };
+            CollapsingHeader::new(header)
                 .id_salt(&key.bytes)
                 .show(ui, |collapsing| {
-                    layer.draw(collapsing, bus, path.child(key.bytes.clone()));
+                    layer.draw(collapsing, bus, lower_path, mismatches, criteria);
                 });
         }
     }
@@ -73,6 +324,7 @@ impl ProofLayerView {
 
 struct MerkProofViewer {
     merk_proof: Vec<MerkProofOpViewer>,
+    debugger: Option<StackDebugger>,
 }
 
 impl MerkProofViewer {
@@ -82,12 +334,43 @@ impl MerkProofViewer {
                 .into_iter()
                 .map(|op| MerkProofOpViewer::new(op))
                 .collect(),
+            debugger: None,
         }
     }
 
-    fn draw(&mut self, ui: &mut egui::Ui) {
-        for op in self.merk_proof.iter_mut() {
-            op.draw(ui);
+    fn hits(&self, criteria: &FilterCriteria) -> usize {
+        self.merk_proof
+            .iter()
+            .filter(|op| criteria.op_matches(op))
+            .count()
+    }
+
+    /// Operations are limited to the ones matching the filter, if any
+    fn draw<'pa>(
+        &mut self,
+        ui: &mut egui::Ui,
+        bus: &CommandBus<'pa>,
+        path: Path<'pa>,
+        mismatches: &ProofMismatches<'pa>,
+        criteria: Option<&FilterCriteria>,
+    ) {
+        let mut step_through = self.debugger.is_some();
+        if ui
+            .toggle_value(&mut step_through, egui_phosphor::regular::BUG)
+            .on_hover_text("Execute proof operations step by step")
+            .changed()
+        {
+            self.debugger = step_through.then(|| StackDebugger::new(&self.merk_proof));
+        }
+
+        if let Some(debugger) = &mut self.debugger {
+            debugger.draw(ui);
+        } else {
+            for op in self.merk_proof.iter_mut() {
+                if criteria.map(|c| c.op_matches(op)).unwrap_or(true) {
+                    op.draw(ui, bus, path, mismatches);
+                }
+            }
         }
     }
 }
@@ -117,17 +400,44 @@ impl MerkProofOpViewer {
         }
     }
 
-    fn draw(&mut self, ui: &mut egui::Ui) {
+    fn kind_name(&self) -> &'static str {
+        match self {
+            MerkProofOpViewer::Push(node) | MerkProofOpViewer::PushInverted(node) => node.kind_name(),
+            MerkProofOpViewer::Parent => "Parent",
+            MerkProofOpViewer::Child => "Child",
+            MerkProofOpViewer::ParentInverted => "ParentInverted",
+            MerkProofOpViewer::ChildInverted => "ChildInverted",
+        }
+    }
+
+    fn node(&self) -> Option<&MerkProofNodeViewer> {
+        match self {
+            MerkProofOpViewer::Push(node) | MerkProofOpViewer::PushInverted(node) => Some(node),
+            _ => None,
+        }
+    }
+
+    fn draw<'pa>(
+        &mut self,
+        ui: &mut egui::Ui,
+        bus: &CommandBus<'pa>,
+        path: Path<'pa>,
+        mismatches: &ProofMismatches<'pa>,
+    ) {
         match self {
             MerkProofOpViewer::Push(node) => {
                 ui.horizontal(|line| {
                     line.label("Push:");
+                    reveal_button(line, bus, path, node);
+                    mismatch_label(line, mismatches, path, node);
                     node.draw(line);
                 });
             }
             MerkProofOpViewer::PushInverted(node) => {
                 ui.horizontal(|line| {
                     line.label("Push inverted:");
+                    reveal_button(line, bus, path, node);
+                    mismatch_label(line, mismatches, path, node);
                     node.draw(line);
                 });
             }
@@ -147,6 +457,22 @@ impl MerkProofOpViewer {
     }
 }
 
+/// Fetches the proof node's element and focuses on it in the tree view, if
+/// the node has a key
+fn reveal_button<'pa>(ui: &mut egui::Ui, bus: &CommandBus<'pa>, path: Path<'pa>, node: &MerkProofNodeViewer) {
+    let Some(key) = node.key() else {
+        return;
+    };
+    if ui
+        .button(egui_phosphor::regular::MAGNIFYING_GLASS)
+        .on_hover_text("Reveal in tree")
+        .clicked()
+    {
+        bus.fetch().node(path, key.to_vec());
+        bus.user_action(UserAction::FocusSubtreeKey(path, key.to_vec()));
+    }
+}
+
 pub(crate) enum MerkProofNodeViewer {
     Hash(BytesView),
     KVHash(BytesView),
@@ -162,17 +488,37 @@ pub(crate) enum MerkProofNodeViewer {
     KVRefValueHash(BytesView, ElementViewer, BytesView),
 }
 
+/// Warns if the fetched node's value hash differs from the proved one
+fn mismatch_label<'pa>(
+    ui: &mut egui::Ui,
+    mismatches: &ProofMismatches<'pa>,
+    path: Path<'pa>,
+    node: &MerkProofNodeViewer,
+) {
+    if node
+        .key()
+        .map(|key| mismatches.contains(&(path, key.to_vec())))
+        .unwrap_or_default()
+    {
+        ui.label(RichText::new(egui_phosphor::regular::WARNING).color(input_error_color(ui.ctx())))
+            .on_hover_text(
+                "Fetched value hash differs from the proved one, the data has likely changed since the \
+                 proof was generated",
+            );
+    }
+}
+
 impl From<grovedbg_types::MerkProofNode> for MerkProofNodeViewer {
     fn from(value: grovedbg_types::MerkProofNode) -> Self {
         match value {
             grovedbg_types::MerkProofNode::Hash(hash) => {
-                MerkProofNodeViewer::Hash(BytesView::new(hash.to_vec()))
+                MerkProofNodeViewer::Hash(BytesView::new_hash(hash.to_vec()))
             }
             grovedbg_types::MerkProofNode::KVHash(hash) => {
-                MerkProofNodeViewer::KVHash(BytesView::new(hash.to_vec()))
+                MerkProofNodeViewer::KVHash(BytesView::new_hash(hash.to_vec()))
             }
             grovedbg_types::MerkProofNode::KVDigest(key, hash) => {
-                MerkProofNodeViewer::KVDigest(BytesView::new(key), BytesView::new(hash.to_vec()))
+                MerkProofNodeViewer::KVDigest(BytesView::new(key), BytesView::new_hash(hash.to_vec()))
             }
             grovedbg_types::MerkProofNode::KV(key, element) => {
                 MerkProofNodeViewer::KV(BytesView::new(key), ElementViewer::new(element))
@@ -181,14 +527,14 @@ impl From<grovedbg_types::MerkProofNode> for MerkProofNodeViewer {
                 MerkProofNodeViewer::KVValueHash(
                     BytesView::new(key),
                     ElementViewer::new(element),
-                    BytesView::new(hash.to_vec()),
+                    BytesView::new_hash(hash.to_vec()),
                 )
             }
             grovedbg_types::MerkProofNode::KVValueHashFeatureType(key, element, hash, ft) => {
                 MerkProofNodeViewer::KVValueHashFeatureType(
                     BytesView::new(key),
                     ElementViewer::new(element),
-                    BytesView::new(hash.to_vec()),
+                    BytesView::new_hash(hash.to_vec()),
                     ft,
                 )
             }
@@ -196,7 +542,7 @@ impl From<grovedbg_types::MerkProofNode> for MerkProofNodeViewer {
                 MerkProofNodeViewer::KVRefValueHash(
                     BytesView::new(key),
                     ElementViewer::new(element),
-                    BytesView::new(hash.to_vec()),
+                    BytesView::new_hash(hash.to_vec()),
                 )
             }
         }
@@ -208,6 +554,42 @@ impl MerkProofNodeViewer {
         node.into()
     }
 
+    pub(crate) fn kind_name(&self) -> &'static str {
+        match self {
+            MerkProofNodeViewer::Hash(_) => "Hash",
+            MerkProofNodeViewer::KVHash(_) => "KVHash",
+            MerkProofNodeViewer::KVDigest(..) => "KVDigest",
+            MerkProofNodeViewer::KV(..) => "KV",
+            MerkProofNodeViewer::KVValueHash(..) => "KVValueHash",
+            MerkProofNodeViewer::KVValueHashFeatureType(..) => "KVValueHashFeatureType",
+            MerkProofNodeViewer::KVRefValueHash(..) => "KVRefValueHash",
+        }
+    }
+
+    fn key(&self) -> Option<&[u8]> {
+        match self {
+            MerkProofNodeViewer::Hash(_) | MerkProofNodeViewer::KVHash(_) => None,
+            MerkProofNodeViewer::KVDigest(key, _)
+            | MerkProofNodeViewer::KV(key, _)
+            | MerkProofNodeViewer::KVValueHash(key, ..)
+            | MerkProofNodeViewer::KVValueHashFeatureType(key, ..)
+            | MerkProofNodeViewer::KVRefValueHash(key, ..) => Some(&key.bytes),
+        }
+    }
+
+    /// Value hash of the node if the proof includes it
+    pub(crate) fn value_hash(&self) -> Option<&[u8]> {
+        match self {
+            MerkProofNodeViewer::Hash(_) | MerkProofNodeViewer::KVHash(_) | MerkProofNodeViewer::KV(..) => {
+                None
+            }
+            MerkProofNodeViewer::KVDigest(_, hash)
+            | MerkProofNodeViewer::KVValueHash(_, _, hash)
+            | MerkProofNodeViewer::KVValueHashFeatureType(_, _, hash, _)
+            | MerkProofNodeViewer::KVRefValueHash(_, _, hash) => Some(&hash.bytes),
+        }
+    }
+
     pub(crate) fn draw(&mut self, ui: &mut egui::Ui) {
         ui.vertical(|ui| {
             match self {
@@ -492,7 +874,7 @@ impl ElementViewer {
                 sum,
                 element_flags,
             } => {
-                ui.label(format!("Sum tree: {sum}"));
+                ui.label(format!("Sum tree: {}", format::number(*sum)));
                 ui.horizontal(|line| {
                     line.label("Root key:");
                     key.draw(line);
@@ -509,7 +891,7 @@ impl ElementViewer {
                 sum,
                 element_flags,
             } => {
-                ui.label(format!("Empty sum tree: {sum}"));
+                ui.label(format!("Empty sum tree: {}", format::number(*sum)));
                 if let Some(flags) = element_flags {
                     ui.horizontal(|line| {
                         line.label("Flags:");
@@ -519,7 +901,11 @@ impl ElementViewer {
             }
             ElementViewer::Item { value, element_flags } => {
                 ui.label("Item");
-                value.draw(ui);
+                if redaction::enabled(ui.ctx()) {
+                    redaction::draw_redacted(ui, value.bytes.len());
+                } else {
+                    value.draw(ui);
+                }
                 if let Some(flags) = element_flags {
                     ui.horizontal(|line| {
                         line.label("Flags:");
@@ -528,7 +914,12 @@ impl ElementViewer {
                 }
             }
             ElementViewer::SumItem { value, element_flags } => {
-                ui.label(format!("Sum item: {value}"));
+                if redaction::enabled(ui.ctx()) {
+                    ui.label("Sum item");
+                    redaction::draw_redacted(ui, std::mem::size_of_val(value));
+                } else {
+                    ui.label(format!("Sum item: {}", format::number(*value)));
+                }
                 if let Some(flags) = element_flags {
                     ui.horizontal(|line| {
                         line.label("Flags:");
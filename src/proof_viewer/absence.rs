@@ -0,0 +1,188 @@
+//! Detection of keys a path query asked for that the proof shows to be
+//! missing: the proof carries only digests of the neighbouring keys instead
+//! of the requested one.
+
+use eframe::egui::{self, RichText};
+use grovedbg_types::{MerkProofNode, MerkProofOp, PathQuery, ProofLayer, Query, QueryItem};
+
+use crate::bytes_utils::{bytes_by_display_variant, BytesDisplayVariant};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Absence {
+    pub(crate) path: Vec<Vec<u8>>,
+    pub(crate) key: Vec<u8>,
+    /// Closest proved keys around the missing one, `None` is an edge of the
+    /// subtree
+    pub(crate) left: Option<Vec<u8>>,
+    pub(crate) right: Option<Vec<u8>>,
+}
+
+impl Absence {
+    pub(crate) fn draw(&self, ui: &mut egui::Ui) {
+        let bound = |key: &Option<Vec<u8>>, edge: &str| {
+            key.as_ref()
+                .map(|k| key_display(k))
+                .unwrap_or_else(|| edge.to_owned())
+        };
+        ui.label(
+            RichText::new(format!(
+                "{} Absence proof for key {}",
+                egui_phosphor::regular::PROHIBIT,
+                key_display(&self.key)
+            ))
+            .strong(),
+        )
+        .on_hover_text(format!(
+            "The proof has no node for the key, its neighbours {} and {} are adjacent in the subtree",
+            bound(&self.left, "subtree start"),
+            bound(&self.right, "subtree end"),
+        ));
+    }
+}
+
+fn key_display(key: &[u8]) -> String {
+    bytes_by_display_variant(key, &BytesDisplayVariant::guess(key))
+}
+
+/// Walks the proof layers the same way the path query does and collects keys
+/// it looked for by exact match that the proof shows to be absent
+pub(crate) fn collect_absences(root_layer: &ProofLayer, path_query: &PathQuery) -> Vec<Absence> {
+    let mut absences = Vec::new();
+    if let Some((layer, path)) = descend(root_layer, Vec::new(), &path_query.path, &mut absences) {
+        query_layer(layer, path, &path_query.query.query, &mut absences);
+    }
+    absences
+}
+
+/// Follows path segments through lower layers, stops at the first segment the
+/// proof doesn't continue into
+fn descend<'a>(
+    mut layer: &'a ProofLayer,
+    mut path: Vec<Vec<u8>>,
+    segments: &[Vec<u8>],
+    absences: &mut Vec<Absence>,
+) -> Option<(&'a ProofLayer, Vec<Vec<u8>>)> {
+    for segment in segments {
+        let Some(lower_layer) = layer.lower_layers.get(segment) else {
+            absences.extend(absence(layer, &path, segment));
+            return None;
+        };
+        path.push(segment.clone());
+        layer = lower_layer;
+    }
+    Some((layer, path))
+}
+
+fn query_layer(layer: &ProofLayer, path: Vec<Vec<u8>>, query: &Query, absences: &mut Vec<Absence>) {
+    for item in query.items.iter() {
+        if let QueryItem::Key(key) = item {
+            absences.extend(absence(layer, &path, key));
+        }
+    }
+
+    for (key, lower_layer) in layer.lower_layers.iter() {
+        let branch = query
+            .conditional_subquery_branches
+            .iter()
+            .find_map(|(item, branch)| item_contains(item, key).then_some(branch))
+            .unwrap_or(&query.default_subquery_branch);
+        let segments = branch.subquery_path.as_deref().unwrap_or_default();
+        let mut lower_path = path.clone();
+        lower_path.push(key.clone());
+
+        match &branch.subquery {
+            Some(subquery) => {
+                if let Some((layer, path)) = descend(lower_layer, lower_path, segments, absences) {
+                    query_layer(layer, path, subquery, absences);
+                }
+            }
+            // Without a subquery the last segment of the subquery path is the
+            // requested key
+            None => {
+                let Some((key, parents)) = segments.split_last() else {
+                    continue;
+                };
+                if let Some((layer, path)) = descend(lower_layer, lower_path, parents, absences) {
+                    absences.extend(absence(layer, &path, key));
+                }
+            }
+        }
+    }
+}
+
+/// The key is absent if the layer proves some keys but not this one; a layer
+/// with no keys but hashes proves nothing about it
+fn absence(layer: &ProofLayer, path: &[Vec<u8>], key: &[u8]) -> Option<Absence> {
+    let keys: Vec<&[u8]> = layer
+        .merk_proof
+        .iter()
+        .filter_map(|op| match op {
+            MerkProofOp::Push(node) | MerkProofOp::PushInverted(node) => node_key(node),
+            _ => None,
+        })
+        .collect();
+
+    if keys.contains(&key) || (keys.is_empty() && !layer.merk_proof.is_empty()) {
+        return None;
+    }
+
+    Some(Absence {
+        path: path.to_vec(),
+        key: key.to_vec(),
+        left: keys.iter().filter(|k| **k < key).max().map(|k| k.to_vec()),
+        right: keys.iter().filter(|k| **k > key).min().map(|k| k.to_vec()),
+    })
+}
+
+pub(super) fn node_key(node: &MerkProofNode) -> Option<&[u8]> {
+    match node {
+        MerkProofNode::Hash(_) | MerkProofNode::KVHash(_) => None,
+        MerkProofNode::KVDigest(key, _)
+        | MerkProofNode::KV(key, _)
+        | MerkProofNode::KVValueHash(key, ..)
+        | MerkProofNode::KVValueHashFeatureType(key, ..)
+        | MerkProofNode::KVRefValueHash(key, ..) => Some(key),
+    }
+}
+
+pub(super) fn item_contains(item: &QueryItem, key: &[u8]) -> bool {
+    match item {
+        QueryItem::Key(k) => k.as_slice() == key,
+        QueryItem::Range { start, end } => start.as_slice() <= key && key < end.as_slice(),
+        QueryItem::RangeInclusive { start, end } => start.as_slice() <= key && key <= end.as_slice(),
+        QueryItem::RangeFull => true,
+        QueryItem::RangeFrom(from) => key >= from.as_slice(),
+        QueryItem::RangeTo(to) => key < to.as_slice(),
+        QueryItem::RangeToInclusive(to) => key <= to.as_slice(),
+        QueryItem::RangeAfter(after) => key > after.as_slice(),
+        QueryItem::RangeAfterTo { after, to } => key > after.as_slice() && key < to.as_slice(),
+        QueryItem::RangeAfterToInclusive { after, to } => key > after.as_slice() && key <= to.as_slice(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(keys: &[&[u8]]) -> ProofLayer {
+        ProofLayer {
+            merk_proof: keys
+                .iter()
+                .map(|k| MerkProofOp::Push(MerkProofNode::KVDigest(k.to_vec(), [0; 32])))
+                .collect(),
+            lower_layers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn absent_key_is_bracketed_by_neighbours() {
+        let between = absence(&layer(&[b"a", b"c"]), &[], b"b").unwrap();
+        assert_eq!(between.left.as_deref(), Some(b"a".as_slice()));
+        assert_eq!(between.right.as_deref(), Some(b"c".as_slice()));
+
+        let after = absence(&layer(&[b"c"]), &[], b"d").unwrap();
+        assert_eq!(after.right, None);
+
+        assert!(absence(&layer(&[b"a", b"b"]), &[], b"b").is_none());
+    }
+}
@@ -0,0 +1,123 @@
+//! Filter of proof layers and operations for large proofs: layers by bytes in
+//! their path, operations by kind and nodes by bytes in their key.
+
+use eframe::egui::{self, ComboBox, Grid};
+
+use super::MerkProofOpViewer;
+use crate::bytes_utils::BytesInput;
+
+/// Kinds of proof nodes followed by kinds of the other operations
+const OP_KINDS: [&'static str; 11] = [
+    "Hash",
+    "KVHash",
+    "KVDigest",
+    "KV",
+    "KVValueHash",
+    "KVValueHashFeatureType",
+    "KVRefValueHash",
+    "Parent",
+    "Child",
+    "ParentInverted",
+    "ChildInverted",
+];
+
+pub(super) struct ProofFilter {
+    path_bytes: BytesInput,
+    kind: Option<&'static str>,
+    key_bytes: BytesInput,
+}
+
+impl Default for ProofFilter {
+    fn default() -> Self {
+        Self {
+            path_bytes: BytesInput::new(),
+            kind: None,
+            key_bytes: BytesInput::new(),
+        }
+    }
+}
+
+/// Parsed filter inputs, empty byte strings match anything
+pub(super) struct FilterCriteria {
+    path_bytes: Vec<u8>,
+    kind: Option<&'static str>,
+    key_bytes: Vec<u8>,
+}
+
+impl ProofFilter {
+    pub(super) fn draw(&mut self, ui: &mut egui::Ui) {
+        Grid::new("proof_filter").num_columns(2).show(ui, |grid| {
+            grid.label("Path contains:");
+            self.path_bytes.draw(grid);
+            grid.end_row();
+
+            grid.label("Operation:");
+            ComboBox::from_id_salt("proof_filter_kind")
+                .selected_text(self.kind.unwrap_or("any"))
+                .show_ui(grid, |combo| {
+                    combo.selectable_value(&mut self.kind, None, "any");
+                    for kind in OP_KINDS {
+                        combo.selectable_value(&mut self.kind, Some(kind), kind);
+                    }
+                });
+            grid.end_row();
+
+            grid.label("Key contains:");
+            self.key_bytes.draw(grid);
+            grid.end_row();
+        });
+        if ui.button("Clear").clicked() {
+            *self = Default::default();
+        }
+    }
+
+    pub(super) fn criteria(&self) -> FilterCriteria {
+        FilterCriteria {
+            path_bytes: self.path_bytes.get_bytes(),
+            kind: self.kind,
+            key_bytes: self.key_bytes.get_bytes(),
+        }
+    }
+}
+
+impl FilterCriteria {
+    pub(super) fn is_active(&self) -> bool {
+        !self.path_bytes.is_empty() || self.kind.is_some() || !self.key_bytes.is_empty()
+    }
+
+    pub(super) fn layer_matches(&self, path: &[Vec<u8>]) -> bool {
+        self.path_bytes.is_empty() || path.iter().any(|segment| contains(segment, &self.path_bytes))
+    }
+
+    pub(super) fn op_matches(&self, op: &MerkProofOpViewer) -> bool {
+        let kind_matches = self.kind.map(|kind| op.kind_name() == kind).unwrap_or(true);
+        let key_matches = self.key_bytes.is_empty()
+            || op
+                .node()
+                .and_then(|node| node.key())
+                .is_some_and(|key| contains(key, &self.key_bytes));
+        kind_matches && key_matches
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_segments_are_searched_for_bytes() {
+        let criteria = FilterCriteria {
+            path_bytes: vec![2, 3],
+            kind: None,
+            key_bytes: Vec::new(),
+        };
+        assert!(criteria.is_active());
+        assert!(criteria.layer_matches(&[vec![0], vec![1, 2, 3, 4]]));
+        assert!(!criteria.layer_matches(&[vec![2], vec![3]]));
+        assert!(!criteria.layer_matches(&[]));
+    }
+}
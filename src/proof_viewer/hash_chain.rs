@@ -0,0 +1,247 @@
+//! Links between proof layers: each layer's Merk root hash is recomputed from
+//! its operations and shown next to the value hash of the subtree element in
+//! the parent layer, which commits to that root hash.
+
+use eframe::egui::{self, CollapsingHeader, RichText};
+use grovedbg_types::{CryptoHash, MerkProofNode, MerkProofOp, ProofLayer};
+
+use super::{
+    result_set::path_display,
+    stack_debugger::{execute, OpKind, StackItem},
+};
+use crate::{bytes_utils::BytesView, merk_hash, theme::input_error_color};
+
+/// Layer indentation per level of depth
+const INDENT: f32 = 12.;
+
+/// Node of the tree reconstructed from proof operations, only hashes are kept
+enum HashNode {
+    /// Opaque subtree of the Merk tree known by its hash only
+    Hash(CryptoHash),
+    Node {
+        /// `None` if the proof has the value itself and not its hash, which
+        /// can't be hashed without the stored bytes
+        kv_hash: Option<CryptoHash>,
+        left: Option<CryptoHash>,
+        right: Option<CryptoHash>,
+    },
+}
+
+impl HashNode {
+    fn new(node: &MerkProofNode) -> Self {
+        let kv_hash = match node {
+            MerkProofNode::Hash(hash) => return HashNode::Hash(*hash),
+            MerkProofNode::KVHash(kv_hash) => Some(*kv_hash),
+            MerkProofNode::KVDigest(key, value_hash)
+            | MerkProofNode::KVValueHash(key, _, value_hash)
+            | MerkProofNode::KVValueHashFeatureType(key, _, value_hash, _) => {
+                Some(merk_hash::kv_digest_hash(key, value_hash))
+            }
+            MerkProofNode::KV(..) | MerkProofNode::KVRefValueHash(..) => None,
+        };
+        HashNode::Node {
+            kv_hash,
+            left: None,
+            right: None,
+        }
+    }
+
+    fn hash(&self) -> Result<CryptoHash, String> {
+        match self {
+            HashNode::Hash(hash) => Ok(*hash),
+            HashNode::Node {
+                kv_hash: Some(kv_hash),
+                left,
+                right,
+            } => Ok(merk_hash::node_hash(kv_hash, left.as_ref(), right.as_ref())),
+            HashNode::Node { kv_hash: None, .. } => Err("The proof carries a value without its hash, \
+                                                         stored bytes are needed to hash it"
+                .to_owned()),
+        }
+    }
+}
+
+impl StackItem for HashNode {
+    fn attach(&mut self, left: bool, child: HashNode) -> Result<(), String> {
+        let HashNode::Node {
+            left: left_hash,
+            right: right_hash,
+            ..
+        } = self
+        else {
+            return Err("A hash node can't have children".to_owned());
+        };
+        let side = if left { left_hash } else { right_hash };
+        if side.is_some() {
+            return Err("A node already has a child on this side".to_owned());
+        }
+        *side = Some(child.hash()?);
+        Ok(())
+    }
+}
+
+/// Merk root hash of a layer computed by executing its proof operations
+pub(super) fn layer_root_hash(ops: &[MerkProofOp]) -> Result<CryptoHash, String> {
+    let mut stack: Vec<HashNode> = Vec::new();
+    for op in ops {
+        let pushed = match op {
+            MerkProofOp::Push(node) | MerkProofOp::PushInverted(node) => Some(HashNode::new(node)),
+            _ => None,
+        };
+        execute(&mut stack, OpKind::of(op), pushed)?;
+    }
+    match stack.as_slice() {
+        [root] => root.hash(),
+        [] => Err("The proof has no operations".to_owned()),
+        _ => Err(format!(
+            "{} nodes are left on the stack instead of one",
+            stack.len()
+        )),
+    }
+}
+
+/// Value hash of the key's node if the layer's proof includes it
+fn proved_value_hash(ops: &[MerkProofOp], key: &[u8]) -> Option<CryptoHash> {
+    ops.iter().find_map(|op| match op {
+        MerkProofOp::Push(node) | MerkProofOp::PushInverted(node) => match node {
+            MerkProofNode::KVDigest(k, hash)
+            | MerkProofNode::KVValueHash(k, _, hash)
+            | MerkProofNode::KVValueHashFeatureType(k, _, hash, _)
+            | MerkProofNode::KVRefValueHash(k, _, hash)
+                if k == key =>
+            {
+                Some(*hash)
+            }
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+struct ChainLink {
+    path: Vec<Vec<u8>>,
+    root_hash: Result<BytesView, String>,
+    /// Value hash of the subtree element in the parent layer, it combines the
+    /// element's own hash with the layer's root hash
+    parent_value_hash: Option<BytesView>,
+}
+
+pub(super) struct HashChain {
+    /// Layers in depth-first order, starting from the root layer
+    links: Vec<ChainLink>,
+}
+
+impl HashChain {
+    pub(super) fn new(root_layer: &ProofLayer) -> Self {
+        let mut links = Vec::new();
+        collect_links(root_layer, Vec::new(), None, &mut links);
+        Self { links }
+    }
+
+    pub(super) fn draw(&mut self, ui: &mut egui::Ui) {
+        CollapsingHeader::new("Hash chain").show(ui, |collapsing| {
+            for (i, link) in self.links.iter_mut().enumerate() {
+                let indent = link.path.len() as f32 * INDENT;
+                if i > 0 {
+                    collapsing.separator();
+                }
+                collapsing.horizontal(|line| {
+                    line.add_space(indent);
+                    line.label(RichText::new(path_display(&link.path)).strong());
+                });
+                collapsing.horizontal(|line| {
+                    line.add_space(indent);
+                    line.label(if link.path.is_empty() {
+                        "GroveDB root hash:"
+                    } else {
+                        "Layer root hash:"
+                    });
+                    match &mut link.root_hash {
+                        Ok(hash) => hash_with_copy(line, hash),
+                        Err(e) => {
+                            line.label(RichText::new("unknown").color(input_error_color(line.ctx())))
+                                .on_hover_text(e.as_str());
+                        }
+                    }
+                });
+                if let Some(parent_value_hash) = &mut link.parent_value_hash {
+                    collapsing.horizontal(|line| {
+                        line.add_space(indent);
+                        line.label(format!("{} Parent value hash:", egui_phosphor::regular::ARROW_UP))
+                            .on_hover_text(
+                                "Value hash of the subtree element in the parent layer, combining the \
+                                 element's own hash with this layer's root hash",
+                            );
+                        hash_with_copy(line, parent_value_hash);
+                    });
+                }
+            }
+        });
+    }
+}
+
+fn hash_with_copy(ui: &mut egui::Ui, hash: &mut BytesView) {
+    if ui
+        .small_button(egui_phosphor::regular::COPY)
+        .on_hover_text("Copy as hex")
+        .clicked()
+    {
+        ui.ctx().copy_text(hex::encode(&hash.bytes));
+    }
+    hash.draw(ui);
+}
+
+fn collect_links(
+    layer: &ProofLayer,
+    path: Vec<Vec<u8>>,
+    parent_value_hash: Option<CryptoHash>,
+    links: &mut Vec<ChainLink>,
+) {
+    links.push(ChainLink {
+        path: path.clone(),
+        root_hash: layer_root_hash(&layer.merk_proof).map(|hash| BytesView::new_hash(hash.to_vec())),
+        parent_value_hash: parent_value_hash.map(|hash| BytesView::new_hash(hash.to_vec())),
+    });
+
+    for (key, lower_layer) in layer.lower_layers.iter() {
+        let mut lower_path = path.clone();
+        lower_path.push(key.clone());
+        collect_links(
+            lower_layer,
+            lower_path,
+            proved_value_hash(&layer.merk_proof, key),
+            links,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root_hash_of_a_parent_with_an_opaque_child() {
+        let child = [1; 32];
+        let value_hash = [2; 32];
+        let ops = vec![
+            MerkProofOp::Push(MerkProofNode::Hash(child)),
+            MerkProofOp::Push(MerkProofNode::KVDigest(b"key".to_vec(), value_hash)),
+            MerkProofOp::Parent,
+        ];
+        let kv_hash = merk_hash::kv_digest_hash(b"key", &value_hash);
+        assert_eq!(
+            layer_root_hash(&ops),
+            Ok(merk_hash::node_hash(&kv_hash, Some(&child), None))
+        );
+    }
+
+    #[test]
+    fn malformed_proofs_have_no_root_hash() {
+        assert!(layer_root_hash(&[MerkProofOp::Parent]).is_err());
+        assert!(layer_root_hash(&[
+            MerkProofOp::Push(MerkProofNode::Hash([0; 32])),
+            MerkProofOp::Push(MerkProofNode::Hash([1; 32])),
+        ])
+        .is_err());
+    }
+}
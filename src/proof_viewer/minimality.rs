@@ -0,0 +1,365 @@
+//! Minimal-form check of a proof: operations carrying more than the path query
+//! needs, such as values of keys the query doesn't select or nodes pushed
+//! twice, with an estimate of the bytes a minimal proof would save. Meant for
+//! tuning proof generation in GroveDB, a proof passing the check may still be
+//! larger than necessary.
+
+use eframe::egui::{self, CollapsingHeader, RichText};
+use grovedbg_types::{Element, MerkProofNode, MerkProofOp, PathQuery, ProofLayer, Query};
+
+use super::{
+    absence::{item_contains, node_key},
+    result_set::path_display,
+};
+use crate::{
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    merk_hash,
+    theme::success_color,
+};
+
+/// Size of a hash in a proof
+const HASH_SIZE: usize = 32;
+
+#[derive(Debug, PartialEq)]
+enum Redundancy {
+    /// Value of a key the query doesn't select, a KVDigest would do
+    UnqueriedValue(Vec<u8>),
+    /// Node pushed once already in the layer, `None` for nodes without a key
+    DuplicatePush(Option<Vec<u8>>),
+    /// Lower layer of a key the query doesn't continue into
+    UnqueriedLayer(Vec<u8>),
+}
+
+struct Finding {
+    path: Vec<Vec<u8>>,
+    redundancy: Redundancy,
+    /// Estimated bytes a minimal proof would save, `None` if the proof has an
+    /// element which size can't be estimated
+    savings: Option<usize>,
+}
+
+impl Finding {
+    fn description(&self) -> String {
+        match &self.redundancy {
+            Redundancy::UnqueriedValue(key) => {
+                format!(
+                    "Value of unqueried key {}, a KVDigest would suffice",
+                    key_display(key)
+                )
+            }
+            Redundancy::DuplicatePush(Some(key)) => {
+                format!("Key {} is pushed more than once", key_display(key))
+            }
+            Redundancy::DuplicatePush(None) => "The same hash is pushed more than once".to_owned(),
+            Redundancy::UnqueriedLayer(key) => {
+                format!(
+                    "Layer proof of {} the query doesn't continue into",
+                    key_display(key)
+                )
+            }
+        }
+    }
+}
+
+pub(super) struct Minimality {
+    findings: Vec<Finding>,
+}
+
+impl Minimality {
+    pub(super) fn new(root_layer: &ProofLayer, path_query: &PathQuery) -> Self {
+        let mut findings = Vec::new();
+        let query_layer = descend(root_layer, Vec::new(), &path_query.path, &mut findings);
+        if let Some((layer, path)) = query_layer {
+            check_query_layer(layer, path, &path_query.query.query, &mut findings);
+        }
+        Self { findings }
+    }
+
+    pub(super) fn draw(&self, ui: &mut egui::Ui) {
+        let header = if self.findings.is_empty() {
+            "Minimal form".to_owned()
+        } else {
+            format!("Minimal form ({} redundancies)", self.findings.len())
+        };
+        CollapsingHeader::new(header).show(ui, |collapsing| {
+            if self.findings.is_empty() {
+                collapsing.label(
+                    RichText::new(format!(
+                        "{} No redundant operations found",
+                        egui_phosphor::regular::CHECK
+                    ))
+                    .color(success_color(collapsing.ctx())),
+                );
+                return;
+            }
+
+            let known: usize = self.findings.iter().filter_map(|f| f.savings).sum();
+            let unknown = self.findings.iter().any(|f| f.savings.is_none());
+            collapsing
+                .label(format!(
+                    "About {known} bytes{} could be saved",
+                    if unknown { " and more" } else { "" }
+                ))
+                .on_hover_text("Estimated from the serialized sizes of operations and elements");
+            for finding in self.findings.iter() {
+                collapsing.horizontal(|line| {
+                    line.label(path_display(&finding.path));
+                    line.label(finding.description());
+                    match finding.savings {
+                        Some(savings) => line.label(RichText::new(format!("~{savings} bytes")).weak()),
+                        None => line.label(RichText::new("size unknown").weak()),
+                    };
+                });
+            }
+        });
+    }
+}
+
+fn key_display(key: &[u8]) -> String {
+    bytes_by_display_variant(key, &BytesDisplayVariant::guess(key))
+}
+
+/// Follows path segments through lower layers, each layer only needs the
+/// subtree element of its segment
+fn descend<'a>(
+    mut layer: &'a ProofLayer,
+    mut path: Vec<Vec<u8>>,
+    segments: &[Vec<u8>],
+    findings: &mut Vec<Finding>,
+) -> Option<(&'a ProofLayer, Vec<Vec<u8>>)> {
+    for segment in segments {
+        check_layer(layer, &path, |key| key == segment.as_slice(), findings);
+        let lower_layer = layer.lower_layers.get(segment)?;
+        path.push(segment.clone());
+        layer = lower_layer;
+    }
+    Some((layer, path))
+}
+
+fn check_query_layer(layer: &ProofLayer, path: Vec<Vec<u8>>, query: &Query, findings: &mut Vec<Finding>) {
+    let queried = |key: &[u8]| query.items.iter().any(|item| item_contains(item, key));
+    check_layer(layer, &path, queried, findings);
+
+    for (key, lower_layer) in layer.lower_layers.iter() {
+        if !queried(key) {
+            continue;
+        }
+        let branch = query
+            .conditional_subquery_branches
+            .iter()
+            .find_map(|(item, branch)| item_contains(item, key).then_some(branch))
+            .unwrap_or(&query.default_subquery_branch);
+        let segments = branch.subquery_path.as_deref().unwrap_or_default();
+        let mut lower_path = path.clone();
+        lower_path.push(key.clone());
+
+        match &branch.subquery {
+            Some(subquery) => {
+                if let Some((layer, path)) = descend(lower_layer, lower_path, segments, findings) {
+                    check_query_layer(layer, path, subquery, findings);
+                }
+            }
+            // Without a subquery the last segment of the subquery path is the
+            // requested key, and with no subquery path the subtree element is
+            // the result itself
+            None => {
+                let Some((key, parents)) = segments.split_last() else {
+                    findings.push(Finding {
+                        path: path.clone(),
+                        redundancy: Redundancy::UnqueriedLayer(key.clone()),
+                        savings: layer_size(lower_layer),
+                    });
+                    continue;
+                };
+                if let Some((layer, path)) = descend(lower_layer, lower_path, parents, findings) {
+                    check_layer(layer, &path, |k| k == key.as_slice(), findings);
+                }
+            }
+        }
+    }
+}
+
+/// Checks operations of a single layer and lower layers it has that aren't
+/// needed, `needed` tells which keys are expected to carry values
+fn check_layer(
+    layer: &ProofLayer,
+    path: &[Vec<u8>],
+    needed: impl Fn(&[u8]) -> bool,
+    findings: &mut Vec<Finding>,
+) {
+    let mut pushed: Vec<&MerkProofNode> = Vec::new();
+    for op in layer.merk_proof.iter() {
+        let (MerkProofOp::Push(node) | MerkProofOp::PushInverted(node)) = op else {
+            continue;
+        };
+        let key = node_key(node);
+        if pushed.iter().any(|p| same_node(p, node)) {
+            findings.push(Finding {
+                path: path.to_vec(),
+                redundancy: Redundancy::DuplicatePush(key.map(<[u8]>::to_vec)),
+                savings: node_size(node).map(|size| size + 1),
+            });
+            continue;
+        }
+        pushed.push(node);
+
+        let Some(key) = key.filter(|_| carries_value(node)) else {
+            continue;
+        };
+        if !needed(key) && !layer.lower_layers.contains_key(key) {
+            findings.push(Finding {
+                path: path.to_vec(),
+                redundancy: Redundancy::UnqueriedValue(key.to_vec()),
+                // The value and its hash are replaced with a KV digest hash
+                savings: node_size(node).map(|size| size.saturating_sub(key.len() + 1 + HASH_SIZE)),
+            });
+        }
+    }
+
+    for (key, lower_layer) in layer.lower_layers.iter() {
+        if !needed(key) {
+            findings.push(Finding {
+                path: path.to_vec(),
+                redundancy: Redundancy::UnqueriedLayer(key.clone()),
+                savings: layer_size(lower_layer),
+            });
+        }
+    }
+}
+
+/// Nodes are the same if they prove the same key, or the same hash for nodes
+/// without a key
+fn same_node(a: &MerkProofNode, b: &MerkProofNode) -> bool {
+    match (a, b) {
+        (MerkProofNode::Hash(a), MerkProofNode::Hash(b))
+        | (MerkProofNode::KVHash(a), MerkProofNode::KVHash(b)) => a == b,
+        _ => node_key(a).is_some() && node_key(a) == node_key(b),
+    }
+}
+
+fn carries_value(node: &MerkProofNode) -> bool {
+    !matches!(
+        node,
+        MerkProofNode::Hash(_) | MerkProofNode::KVHash(_) | MerkProofNode::KVDigest(..)
+    )
+}
+
+/// Approximate serialized size of a node without its operation byte
+fn node_size(node: &MerkProofNode) -> Option<usize> {
+    let keyed = |key: &[u8]| key.len() + 1;
+    Some(match node {
+        MerkProofNode::Hash(_) | MerkProofNode::KVHash(_) => HASH_SIZE,
+        MerkProofNode::KVDigest(key, _) => keyed(key) + HASH_SIZE,
+        MerkProofNode::KV(key, element) => keyed(key) + element_size(element)?,
+        MerkProofNode::KVValueHash(key, element, _) | MerkProofNode::KVRefValueHash(key, element, _) => {
+            keyed(key) + element_size(element)? + HASH_SIZE
+        }
+        // Feature type is a tag with an optional sum
+        MerkProofNode::KVValueHashFeatureType(key, element, ..) => {
+            keyed(key) + element_size(element)? + HASH_SIZE + 9
+        }
+    })
+}
+
+/// Serialized size of an element, references are not estimated
+fn element_size(element: &Element) -> Option<usize> {
+    let flags_size = |flags: &Option<Vec<u8>>| flags.as_ref().map(|f| f.len() + 2).unwrap_or(1);
+    match element {
+        Element::Item { value, element_flags } => {
+            Some(merk_hash::item_element_bytes(value, element_flags.as_deref()).len())
+        }
+        Element::SumItem { element_flags, .. } => Some(1 + 9 + flags_size(element_flags)),
+        Element::Subtree {
+            root_key,
+            element_flags,
+        } => {
+            Some(merk_hash::subtree_element_bytes(root_key.as_deref(), None, element_flags.as_deref()).len())
+        }
+        Element::Sumtree {
+            root_key,
+            sum,
+            element_flags,
+        } => Some(
+            merk_hash::subtree_element_bytes(root_key.as_deref(), Some(*sum), element_flags.as_deref()).len(),
+        ),
+        Element::Reference(_) => None,
+    }
+}
+
+/// Approximate size of a layer proof with its lower layers
+fn layer_size(layer: &ProofLayer) -> Option<usize> {
+    let ops = layer
+        .merk_proof
+        .iter()
+        .map(|op| match op {
+            MerkProofOp::Push(node) | MerkProofOp::PushInverted(node) => node_size(node).map(|size| size + 1),
+            _ => Some(1),
+        })
+        .sum::<Option<usize>>()?;
+    let lower = layer
+        .lower_layers
+        .iter()
+        .map(|(key, lower)| layer_size(lower).map(|size| size + key.len() + 1))
+        .sum::<Option<usize>>()?;
+    Some(ops + lower)
+}
+
+#[cfg(test)]
+mod tests {
+    use grovedbg_types::{QueryItem, SizedQuery, SubqueryBranch};
+
+    use super::*;
+
+    fn item(key: &[u8], value: &[u8]) -> MerkProofOp {
+        MerkProofOp::Push(MerkProofNode::KVValueHash(
+            key.to_vec(),
+            Element::Item {
+                value: value.to_vec(),
+                element_flags: None,
+            },
+            [0; 32],
+        ))
+    }
+
+    #[test]
+    fn redundant_operations_are_reported() {
+        let layer = ProofLayer {
+            merk_proof: vec![
+                item(b"a", b"value"),
+                item(b"b", b"value"),
+                MerkProofOp::Parent,
+                item(b"b", b"value"),
+                MerkProofOp::Push(MerkProofNode::KVDigest(b"c".to_vec(), [0; 32])),
+            ],
+            lower_layers: Default::default(),
+        };
+        let path_query = PathQuery {
+            path: Vec::new(),
+            query: SizedQuery {
+                query: Query {
+                    items: vec![QueryItem::Key(b"b".to_vec())],
+                    default_subquery_branch: SubqueryBranch {
+                        subquery_path: None,
+                        subquery: None,
+                    },
+                    conditional_subquery_branches: Vec::new(),
+                    left_to_right: true,
+                },
+                limit: None,
+                offset: None,
+            },
+        };
+
+        let minimality = Minimality::new(&layer, &path_query);
+        let redundancies: Vec<&Redundancy> = minimality.findings.iter().map(|f| &f.redundancy).collect();
+        assert_eq!(
+            redundancies,
+            vec![
+                &Redundancy::UnqueriedValue(b"a".to_vec()),
+                &Redundancy::DuplicatePush(Some(b"b".to_vec())),
+            ]
+        );
+        // Item element of the value: variant, length, bytes and no flags
+        assert_eq!(minimality.findings[0].savings, Some(1 + 1 + 5 + 1));
+    }
+}
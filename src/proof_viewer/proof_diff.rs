@@ -0,0 +1,250 @@
+//! Structural comparison of two proofs for the same query, e.g. generated by
+//! different nodes: layers present in one proof only, Merk root hashes and
+//! operations that differ, to pinpoint where proof generation diverged.
+
+use std::collections::BTreeMap;
+
+use eframe::egui::{self, CollapsingHeader, RichText};
+use grovedbg_types::{CryptoHash, MerkProofNode, MerkProofOp, ProofLayer};
+
+use super::{hash_chain::layer_root_hash, result_set::path_display};
+use crate::{
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    theme::input_error_color,
+};
+
+/// Hex digits of a hash shown in operation descriptions
+const SHORT_HASH_LEN: usize = 16;
+
+/// Operations at the same position of a layer that differ between proofs,
+/// `None` if the proof has fewer operations
+struct OpDiff {
+    idx: usize,
+    pinned: Option<String>,
+    current: Option<String>,
+}
+
+enum LayerDiff {
+    /// The layer is in one of the proofs only, its lower layers are not
+    /// compared
+    Missing { path: Vec<Vec<u8>>, in_pinned: bool },
+    Differs {
+        path: Vec<Vec<u8>>,
+        pinned_root: Result<CryptoHash, String>,
+        current_root: Result<CryptoHash, String>,
+        ops: Vec<OpDiff>,
+    },
+}
+
+pub(super) struct ProofDiff {
+    layers: Vec<LayerDiff>,
+}
+
+impl ProofDiff {
+    pub(super) fn new(pinned: &ProofLayer, current: &ProofLayer) -> Self {
+        let mut layers = Vec::new();
+        diff_layers(pinned, current, Vec::new(), &mut layers);
+        Self { layers }
+    }
+
+    pub(super) fn draw(&self, ui: &mut egui::Ui) {
+        CollapsingHeader::new("Comparison with the pinned proof")
+            .default_open(true)
+            .show(ui, |collapsing| {
+                if self.layers.is_empty() {
+                    collapsing.label("The proofs are identical");
+                }
+                for (i, layer) in self.layers.iter().enumerate() {
+                    if i > 0 {
+                        collapsing.separator();
+                    }
+                    layer.draw(collapsing);
+                }
+            });
+    }
+}
+
+impl LayerDiff {
+    fn draw(&self, ui: &mut egui::Ui) {
+        let error_color = input_error_color(ui.ctx());
+        match self {
+            LayerDiff::Missing { path, in_pinned } => {
+                ui.label(RichText::new(path_display(path)).strong());
+                ui.label(
+                    RichText::new(if *in_pinned {
+                        "Only in the pinned proof"
+                    } else {
+                        "Only in this proof"
+                    })
+                    .color(error_color),
+                );
+            }
+            LayerDiff::Differs {
+                path,
+                pinned_root,
+                current_root,
+                ops,
+            } => {
+                ui.label(RichText::new(path_display(path)).strong());
+                if pinned_root != current_root {
+                    ui.label(format!("Pinned root hash: {}", root_hash_text(pinned_root)));
+                    ui.label(format!("This root hash: {}", root_hash_text(current_root)));
+                }
+                for op in ops {
+                    ui.label(RichText::new(format!("Operation {}:", op.idx)).color(error_color));
+                    ui.label(
+                        RichText::new(format!("  pinned: {}", op.pinned.as_deref().unwrap_or("none")))
+                            .monospace(),
+                    );
+                    ui.label(
+                        RichText::new(format!("  this:   {}", op.current.as_deref().unwrap_or("none")))
+                            .monospace(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn diff_layers(pinned: &ProofLayer, current: &ProofLayer, path: Vec<Vec<u8>>, layers: &mut Vec<LayerDiff>) {
+    let len = pinned.merk_proof.len().max(current.merk_proof.len());
+    let ops: Vec<OpDiff> = (0..len)
+        .filter_map(|idx| {
+            let pinned_op = pinned.merk_proof.get(idx);
+            let current_op = current.merk_proof.get(idx);
+            (!same_op(pinned_op, current_op)).then(|| OpDiff {
+                idx,
+                pinned: pinned_op.map(op_text),
+                current: current_op.map(op_text),
+            })
+        })
+        .collect();
+    if !ops.is_empty() {
+        layers.push(LayerDiff::Differs {
+            path: path.clone(),
+            pinned_root: layer_root_hash(&pinned.merk_proof),
+            current_root: layer_root_hash(&current.merk_proof),
+            ops,
+        });
+    }
+
+    let pinned_lower: BTreeMap<&[u8], &ProofLayer> = pinned
+        .lower_layers
+        .iter()
+        .map(|(key, layer)| (key.as_slice(), layer))
+        .collect();
+    let current_lower: BTreeMap<&[u8], &ProofLayer> = current
+        .lower_layers
+        .iter()
+        .map(|(key, layer)| (key.as_slice(), layer))
+        .collect();
+
+    for (key, pinned_layer) in pinned_lower.iter() {
+        let mut lower_path = path.clone();
+        lower_path.push(key.to_vec());
+        match current_lower.get(key) {
+            Some(current_layer) => diff_layers(pinned_layer, current_layer, lower_path, layers),
+            None => layers.push(LayerDiff::Missing {
+                path: lower_path,
+                in_pinned: true,
+            }),
+        }
+    }
+    for key in current_lower
+        .keys()
+        .filter(|key| !pinned_lower.contains_key(*key))
+    {
+        let mut lower_path = path.clone();
+        lower_path.push(key.to_vec());
+        layers.push(LayerDiff::Missing {
+            path: lower_path,
+            in_pinned: false,
+        });
+    }
+}
+
+/// Operations are compared by their serialized form, which covers every byte
+/// the proof carries
+fn same_op(a: Option<&MerkProofOp>, b: Option<&MerkProofOp>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => serde_json::to_value(a).ok() == serde_json::to_value(b).ok(),
+        _ => false,
+    }
+}
+
+fn root_hash_text(hash: &Result<CryptoHash, String>) -> String {
+    match hash {
+        Ok(hash) => hex::encode(hash),
+        Err(e) => format!("unknown, {e}"),
+    }
+}
+
+fn op_text(op: &MerkProofOp) -> String {
+    match op {
+        MerkProofOp::Push(node) => format!("Push {}", node_text(node)),
+        MerkProofOp::PushInverted(node) => format!("Push inverted {}", node_text(node)),
+        MerkProofOp::Parent => "Parent".to_owned(),
+        MerkProofOp::Child => "Child".to_owned(),
+        MerkProofOp::ParentInverted => "Parent inverted".to_owned(),
+        MerkProofOp::ChildInverted => "Child inverted".to_owned(),
+    }
+}
+
+fn node_text(node: &MerkProofNode) -> String {
+    let key = |bytes: &[u8]| bytes_by_display_variant(bytes, &BytesDisplayVariant::guess(bytes));
+    let hash = |hash: &CryptoHash| {
+        let mut hex = hex::encode(hash);
+        hex.truncate(SHORT_HASH_LEN);
+        format!("{hex}…")
+    };
+
+    match node {
+        MerkProofNode::Hash(h) => format!("Hash {}", hash(h)),
+        MerkProofNode::KVHash(h) => format!("KVHash {}", hash(h)),
+        MerkProofNode::KVDigest(k, h) => format!("KVDigest {}, value hash {}", key(k), hash(h)),
+        MerkProofNode::KV(k, _) => format!("KV {}", key(k)),
+        MerkProofNode::KVValueHash(k, _, h) => format!("KVValueHash {}, value hash {}", key(k), hash(h)),
+        MerkProofNode::KVValueHashFeatureType(k, _, h, _) => {
+            format!("KVValueHashFeatureType {}, value hash {}", key(k), hash(h))
+        }
+        MerkProofNode::KVRefValueHash(k, _, h) => {
+            format!("KVRefValueHash {}, value hash {}", key(k), hash(h))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(ops: Vec<MerkProofOp>) -> ProofLayer {
+        ProofLayer {
+            merk_proof: ops,
+            lower_layers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn differing_operations_are_reported() {
+        let pinned = layer(vec![
+            MerkProofOp::Push(MerkProofNode::Hash([1; 32])),
+            MerkProofOp::Push(MerkProofNode::KVDigest(b"key".to_vec(), [2; 32])),
+            MerkProofOp::Parent,
+        ]);
+        let current = layer(vec![
+            MerkProofOp::Push(MerkProofNode::Hash([1; 32])),
+            MerkProofOp::Push(MerkProofNode::KVDigest(b"key".to_vec(), [3; 32])),
+            MerkProofOp::Parent,
+        ]);
+
+        assert!(ProofDiff::new(&pinned, &pinned).layers.is_empty());
+
+        let diff = ProofDiff::new(&pinned, &current);
+        let [LayerDiff::Differs { path, ops, .. }] = diff.layers.as_slice() else {
+            panic!("expected a single differing layer");
+        };
+        assert!(path.is_empty());
+        assert_eq!(ops.len(), 1);
+        assert_eq!(ops[0].idx, 1);
+    }
+}
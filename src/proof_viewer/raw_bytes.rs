@@ -0,0 +1,98 @@
+//! Proof response exactly as received from the endpoint, to paste it into
+//! other tooling after inspecting the proof.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use eframe::egui::{self, RichText, TextEdit};
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+
+use crate::budget::format_size;
+
+/// Longer responses are only shown in part, copy and export get all bytes
+const MAX_SHOWN_BYTES: usize = 16 * 1024;
+
+#[derive(Clone, Copy, PartialEq, AsRefStr, EnumIter)]
+enum Encoding {
+    #[strum(serialize = "Hex")]
+    Hex,
+    #[strum(serialize = "Base64")]
+    Base64,
+}
+
+impl Encoding {
+    fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Hex => hex::encode(bytes),
+            Encoding::Base64 => BASE64.encode(bytes),
+        }
+    }
+}
+
+pub(super) struct RawProof {
+    bytes: Vec<u8>,
+    encoding: Encoding,
+}
+
+impl RawProof {
+    pub(super) fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            encoding: Encoding::Hex,
+        }
+    }
+
+    pub(super) fn draw(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!("Total size: {}", format_size(self.bytes.len())))
+            .on_hover_text("Response body of the proof request as the endpoint serialized it");
+
+        ui.horizontal(|line| {
+            for encoding in Encoding::iter() {
+                line.selectable_value(&mut self.encoding, encoding, encoding.as_ref());
+            }
+            line.separator();
+
+            if line
+                .button(egui_phosphor::regular::COPY)
+                .on_hover_text(format!("Copy all bytes as {}", self.encoding.as_ref()))
+                .clicked()
+            {
+                line.ctx().copy_text(self.encoding.encode(&self.bytes));
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if line
+                .button(egui_phosphor::regular::FLOPPY_DISK)
+                .on_hover_text(
+                    "Save the proof to the working directory, it can be dropped back onto the window",
+                )
+                .clicked()
+            {
+                let file_name = format!(
+                    "grovedbg-proof-{}.json",
+                    chrono::Utc::now().format("%Y%m%d%H%M%S")
+                );
+                match std::fs::write(&file_name, &self.bytes) {
+                    Ok(_) => log::info!("Proof saved to {file_name}"),
+                    Err(e) => log::error!("Unable to save the proof: {e}"),
+                }
+            }
+        });
+
+        let shown = &self.bytes[..self.bytes.len().min(MAX_SHOWN_BYTES)];
+        let mut text = self.encoding.encode(shown);
+        ui.add(
+            TextEdit::multiline(&mut text)
+                .code_editor()
+                .interactive(false)
+                .desired_width(f32::INFINITY),
+        );
+        if shown.len() < self.bytes.len() {
+            ui.label(
+                RichText::new(format!(
+                    "Showing the first {} of the proof, copy or save to get all of it",
+                    format_size(shown.len())
+                ))
+                .weak(),
+            );
+        }
+    }
+}
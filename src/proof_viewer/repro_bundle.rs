@@ -0,0 +1,98 @@
+//! Reproduction bundle of a proof GroveDBG failed to process, to be attached
+//! to a GroveDB issue.
+
+use eframe::egui::{self, OpenUrl, RichText};
+use reqwest::Url;
+use serde::Serialize;
+
+use crate::{protocol::ProofFailure, theme::input_error_color};
+
+const GROVEDB_NEW_ISSUE_URL: &str = "https://github.com/dashpay/grovedb/issues/new";
+
+#[derive(Serialize)]
+struct Bundle<'a> {
+    grovedbg_version: &'static str,
+    #[serde(flatten)]
+    failure: &'a ProofFailure,
+}
+
+pub(super) struct ReproBundle {
+    failure: ProofFailure,
+}
+
+impl ReproBundle {
+    pub(super) fn new(failure: ProofFailure) -> Self {
+        Self { failure }
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&Bundle {
+            grovedbg_version: env!("CARGO_PKG_VERSION"),
+            failure: &self.failure,
+        })
+        .unwrap_or_else(|e| format!("Unable to serialize the bundle: {e}"))
+    }
+
+    fn issue_url(&self) -> Option<Url> {
+        let query = serde_json::to_string_pretty(&self.failure.path_query).unwrap_or_default();
+        let body = format!(
+            "### Description\n\n<!-- What was expected and what happened -->\n\n### \
+             Error\n\n```\n{}\n```\n\n### Path query\n\n```json\n{query}\n```\n\n### Reproduction \
+             bundle\n\n<!-- Attach the bundle saved from GroveDBG or paste it from the clipboard -->\n",
+            self.failure.error
+        );
+
+        Url::parse_with_params(
+            GROVEDB_NEW_ISSUE_URL,
+            &[("title", "Proof processing failure"), ("body", body.as_str())],
+        )
+        .ok()
+    }
+
+    pub(super) fn draw(&self, ui: &mut egui::Ui) {
+        ui.label(
+            RichText::new(format!("Unable to process the proof: {}", self.failure.error))
+                .color(input_error_color(ui.ctx())),
+        );
+        ui.label(format!(
+            "Collected {} nodes mentioned in the proof",
+            self.failure.nodes.len()
+        ));
+
+        ui.horizontal(|line| {
+            if line
+                .button(egui_phosphor::regular::COPY)
+                .on_hover_text("Copy reproduction bundle")
+                .clicked()
+            {
+                line.ctx().copy_text(self.to_json());
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if line
+                .button(egui_phosphor::regular::FLOPPY_DISK)
+                .on_hover_text("Save reproduction bundle to the working directory")
+                .clicked()
+            {
+                let file_name = format!(
+                    "grovedbg-proof-bundle-{}.json",
+                    chrono::Utc::now().format("%Y%m%d%H%M%S")
+                );
+                match std::fs::write(&file_name, self.to_json()) {
+                    Ok(_) => log::info!("Reproduction bundle saved to {file_name}"),
+                    Err(e) => log::error!("Unable to save reproduction bundle: {e}"),
+                }
+            }
+
+            if line
+                .button(egui_phosphor::regular::GITHUB_LOGO)
+                .on_hover_text("Open a GroveDB issue prefilled with the failure details")
+                .clicked()
+            {
+                if let Some(url) = self.issue_url() {
+                    line.ctx().open_url(OpenUrl::new_tab(url));
+                }
+            }
+        });
+    }
+}
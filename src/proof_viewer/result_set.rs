@@ -0,0 +1,114 @@
+//! Results demonstrated by a proof, gathered from all proof layers without
+//! the operations that prove them.
+
+use eframe::egui::{self, CollapsingHeader};
+use grovedbg_types::{MerkProofNode, MerkProofOp, ProofLayer};
+
+use super::ElementViewer;
+use crate::{
+    bus::{CommandBus, UserAction},
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant, BytesView},
+    path_ctx::PathCtx,
+};
+
+struct ProvedResult {
+    path: Vec<Vec<u8>>,
+    key: BytesView,
+    element: ElementViewer,
+    /// The element is a value of a reference rather than the stored one
+    resolved_reference: bool,
+}
+
+pub(super) struct ResultSet {
+    results: Vec<ProvedResult>,
+}
+
+impl ResultSet {
+    pub(super) fn new(root_layer: &ProofLayer) -> Self {
+        let mut results = Vec::new();
+        collect_results(root_layer, Vec::new(), &mut results);
+        Self { results }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub(super) fn draw<'pa>(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+        CollapsingHeader::new(format!("Proved results ({})", self.results.len()))
+            .default_open(true)
+            .show(ui, |collapsing| {
+                if self.results.is_empty() {
+                    collapsing.label("The proof demonstrates no elements");
+                }
+
+                for (i, result) in self.results.iter_mut().enumerate() {
+                    collapsing.horizontal(|line| {
+                        if line
+                            .button(egui_phosphor::regular::MAGNIFYING_GLASS)
+                            .on_hover_text("Reveal in tree")
+                            .clicked()
+                        {
+                            let path = path_ctx.add_path(result.path.clone());
+                            bus.fetch().node(path, result.key.bytes.clone());
+                            bus.user_action(UserAction::FocusSubtreeKey(path, result.key.bytes.clone()));
+                        }
+                        line.label(path_display(&result.path));
+                        result.key.draw(line);
+                    });
+                    CollapsingHeader::new(if result.resolved_reference {
+                        "Referenced value"
+                    } else {
+                        "Value"
+                    })
+                    .id_salt(("proved_result", i))
+                    .show(collapsing, |value_ui| {
+                        result.element.draw(value_ui);
+                    });
+                }
+            });
+    }
+}
+
+/// Nodes with elements are results unless the proof continues into them with
+/// a lower layer, then they are only a part of the path
+fn collect_results(layer: &ProofLayer, path: Vec<Vec<u8>>, results: &mut Vec<ProvedResult>) {
+    for op in layer.merk_proof.iter() {
+        let (MerkProofOp::Push(node) | MerkProofOp::PushInverted(node)) = op else {
+            continue;
+        };
+        let (key, element, resolved_reference) = match node {
+            MerkProofNode::KV(key, element)
+            | MerkProofNode::KVValueHash(key, element, _)
+            | MerkProofNode::KVValueHashFeatureType(key, element, ..) => (key, element, false),
+            MerkProofNode::KVRefValueHash(key, element, _) => (key, element, true),
+            MerkProofNode::Hash(_) | MerkProofNode::KVHash(_) | MerkProofNode::KVDigest(..) => continue,
+        };
+        if layer.lower_layers.contains_key(key) {
+            continue;
+        }
+        results.push(ProvedResult {
+            path: path.clone(),
+            key: BytesView::new(key.clone()),
+            element: ElementViewer::new(element.clone()),
+            resolved_reference,
+        });
+    }
+
+    for (key, lower_layer) in layer.lower_layers.iter() {
+        let mut lower_path = path.clone();
+        lower_path.push(key.clone());
+        collect_results(lower_layer, lower_path, results);
+    }
+}
+
+pub(super) fn path_display(path: &[Vec<u8>]) -> String {
+    if path.is_empty() {
+        return "Root subtree".to_owned();
+    }
+    let segments: Vec<_> = path
+        .iter()
+        .map(|s| bytes_by_display_variant(s, &BytesDisplayVariant::guess(s)))
+        .collect();
+    format!("[{}]", segments.join(", "))
+}
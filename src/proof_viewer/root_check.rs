@@ -0,0 +1,90 @@
+//! Local verification of a proof against the GroveDB root hash of the
+//! session: the Merk root hash of the root layer recomputed from the proof
+//! has to match it.
+
+use eframe::egui::{self, RichText};
+use grovedbg_types::{CryptoHash, ProofLayer};
+
+use super::hash_chain::layer_root_hash;
+use crate::theme::{input_error_color, success_color};
+
+pub(super) enum RootCheck {
+    Verified(CryptoHash),
+    Mismatch {
+        proved: CryptoHash,
+        session: CryptoHash,
+    },
+    /// The proof's root hash can't be computed
+    Malformed(String),
+    /// GroveDB root node isn't loaded to compare with
+    NoSessionRoot,
+}
+
+impl RootCheck {
+    pub(super) fn new(root_layer: &ProofLayer, session_root: Option<CryptoHash>) -> Self {
+        match (layer_root_hash(&root_layer.merk_proof), session_root) {
+            (Err(e), _) => RootCheck::Malformed(e),
+            (Ok(_), None) => RootCheck::NoSessionRoot,
+            (Ok(proved), Some(session)) if proved == session => RootCheck::Verified(proved),
+            (Ok(proved), Some(session)) => RootCheck::Mismatch { proved, session },
+        }
+    }
+
+    pub(super) fn summary(&self, results: usize) -> String {
+        match self {
+            RootCheck::Verified(_) => {
+                format!("Proof verified against the session root hash, {results} results proved")
+            }
+            RootCheck::Mismatch { .. } => {
+                "Proof verification failed: the root hash differs from the session root hash".to_owned()
+            }
+            RootCheck::Malformed(e) => format!("Proof verification failed: {e}"),
+            RootCheck::NoSessionRoot => {
+                "Proof is not verified: GroveDB root node of the session is not loaded".to_owned()
+            }
+        }
+    }
+
+    pub(super) fn draw(&self, ui: &mut egui::Ui, results: usize) {
+        let (icon, color) = match self {
+            RootCheck::Verified(_) => (egui_phosphor::regular::SEAL_CHECK, success_color(ui.ctx())),
+            _ => (egui_phosphor::regular::SEAL_WARNING, input_error_color(ui.ctx())),
+        };
+        ui.label(RichText::new(format!("{icon} {}", self.summary(results))).color(color));
+        match self {
+            RootCheck::Verified(hash) => {
+                ui.label(format!("Root hash: {}", hex::encode(hash)));
+            }
+            RootCheck::Mismatch { proved, session } => {
+                ui.label(format!("Proved root hash: {}", hex::encode(proved)));
+                ui.label(format!("Session root hash: {}", hex::encode(session)));
+            }
+            RootCheck::Malformed(_) | RootCheck::NoSessionRoot => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grovedbg_types::{MerkProofNode, MerkProofOp};
+
+    use super::*;
+
+    #[test]
+    fn proof_root_is_compared_with_the_session_one() {
+        let layer = ProofLayer {
+            merk_proof: vec![MerkProofOp::Push(MerkProofNode::Hash([1; 32]))],
+            lower_layers: Default::default(),
+        };
+
+        assert!(matches!(
+            RootCheck::new(&layer, Some([1; 32])),
+            RootCheck::Verified(_)
+        ));
+        assert!(matches!(
+            RootCheck::new(&layer, Some([2; 32])),
+            RootCheck::Mismatch { .. }
+        ));
+        assert!(matches!(RootCheck::new(&layer, None), RootCheck::NoSessionRoot));
+    }
+}
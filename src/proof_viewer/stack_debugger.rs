@@ -0,0 +1,288 @@
+//! Step by step execution of merk proof operations, showing how the stack
+//! machine reconstructs the tree from them.
+
+use eframe::egui::{self, CollapsingHeader, RichText};
+use grovedbg_types::MerkProofOp;
+
+use super::{MerkProofNodeViewer, MerkProofOpViewer};
+use crate::{
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    theme::input_error_color,
+};
+
+#[derive(Clone, Copy)]
+pub(super) enum OpKind {
+    Push,
+    PushInverted,
+    Parent,
+    Child,
+    ParentInverted,
+    ChildInverted,
+}
+
+impl OpKind {
+    pub(super) fn of(op: &MerkProofOp) -> Self {
+        match op {
+            MerkProofOp::Push(_) => OpKind::Push,
+            MerkProofOp::PushInverted(_) => OpKind::PushInverted,
+            MerkProofOp::Parent => OpKind::Parent,
+            MerkProofOp::Child => OpKind::Child,
+            MerkProofOp::ParentInverted => OpKind::ParentInverted,
+            MerkProofOp::ChildInverted => OpKind::ChildInverted,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            OpKind::Push => "Push",
+            OpKind::PushInverted => "Push inverted",
+            OpKind::Parent => "Parent",
+            OpKind::Child => "Child",
+            OpKind::ParentInverted => "Parent inverted",
+            OpKind::ChildInverted => "Child inverted",
+        }
+    }
+}
+
+/// Tree node the stack machine can assemble
+pub(super) trait StackItem: Sized {
+    fn attach(&mut self, left: bool, child: Self) -> Result<(), String>;
+}
+
+/// Executes a single operation of the stack machine, `pushed` is the node of
+/// push operations
+pub(super) fn execute<T: StackItem>(stack: &mut Vec<T>, op: OpKind, pushed: Option<T>) -> Result<(), String> {
+    match op {
+        OpKind::Push | OpKind::PushInverted => {
+            stack.push(pushed.ok_or_else(|| "Push operation without a node".to_owned())?);
+            Ok(())
+        }
+        // Parent is on top of the stack
+        OpKind::Parent | OpKind::ParentInverted => {
+            let (mut parent, child) = pop_two(stack)?;
+            parent.attach(matches!(op, OpKind::Parent), child)?;
+            stack.push(parent);
+            Ok(())
+        }
+        // Child is on top of the stack
+        OpKind::Child | OpKind::ChildInverted => {
+            let (child, mut parent) = pop_two(stack)?;
+            parent.attach(matches!(op, OpKind::ChildInverted), child)?;
+            stack.push(parent);
+            Ok(())
+        }
+    }
+}
+
+/// Pops the top of the stack and the item under it
+fn pop_two<T>(stack: &mut Vec<T>) -> Result<(T, T), String> {
+    if stack.len() < 2 {
+        return Err("Not enough items on the stack".to_owned());
+    }
+    let top = stack.pop().expect("checked above");
+    let below = stack.pop().expect("checked above");
+    Ok((top, below))
+}
+
+/// Tree node built on the stack, only labels are kept
+#[derive(Clone)]
+struct StackNode {
+    label: String,
+    left: Option<Box<StackNode>>,
+    right: Option<Box<StackNode>>,
+}
+
+impl StackItem for StackNode {
+    fn attach(&mut self, left: bool, child: StackNode) -> Result<(), String> {
+        let (side, name) = if left {
+            (&mut self.left, "left")
+        } else {
+            (&mut self.right, "right")
+        };
+        if side.is_some() {
+            return Err(format!("{} already has a {name} child", self.label));
+        }
+        *side = Some(Box::new(child));
+        Ok(())
+    }
+}
+
+pub(super) struct StackDebugger {
+    /// Operations with labels of nodes to push
+    ops: Vec<(OpKind, Option<String>)>,
+    /// Number of operations executed
+    position: usize,
+    stack: Vec<StackNode>,
+    error: Option<String>,
+}
+
+impl StackDebugger {
+    pub(super) fn new(merk_proof: &[MerkProofOpViewer]) -> Self {
+        let ops = merk_proof
+            .iter()
+            .map(|op| match op {
+                MerkProofOpViewer::Push(node) => (OpKind::Push, Some(node_label(node))),
+                MerkProofOpViewer::PushInverted(node) => (OpKind::PushInverted, Some(node_label(node))),
+                MerkProofOpViewer::Parent => (OpKind::Parent, None),
+                MerkProofOpViewer::Child => (OpKind::Child, None),
+                MerkProofOpViewer::ParentInverted => (OpKind::ParentInverted, None),
+                MerkProofOpViewer::ChildInverted => (OpKind::ChildInverted, None),
+            })
+            .collect();
+
+        Self {
+            ops,
+            position: 0,
+            stack: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.position = 0;
+        self.stack.clear();
+        self.error = None;
+    }
+
+    /// Executes the next operation, the debugger stops on the first error
+    fn step(&mut self) {
+        if self.error.is_some() {
+            return;
+        }
+        let Some((op, label)) = self.ops.get(self.position).cloned() else {
+            return;
+        };
+        self.position += 1;
+
+        let pushed = label.map(|label| StackNode {
+            label,
+            left: None,
+            right: None,
+        });
+        self.error = execute(&mut self.stack, op, pushed).err();
+    }
+
+    fn step_back(&mut self) {
+        let target = self.position.saturating_sub(1);
+        self.reset();
+        while self.position < target {
+            self.step();
+        }
+    }
+
+    fn run_to_end(&mut self) {
+        while self.position < self.ops.len() && self.error.is_none() {
+            self.step();
+        }
+    }
+
+    pub(super) fn draw(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|line| {
+            if line
+                .button(egui_phosphor::regular::ARROW_COUNTER_CLOCKWISE)
+                .on_hover_text("Reset")
+                .clicked()
+            {
+                self.reset();
+            }
+            if line
+                .add_enabled(
+                    self.position > 0,
+                    egui::Button::new(egui_phosphor::regular::ARROW_LEFT),
+                )
+                .on_hover_text("Step back")
+                .clicked()
+            {
+                self.step_back();
+            }
+            let can_step = self.position < self.ops.len() && self.error.is_none();
+            if line
+                .add_enabled(can_step, egui::Button::new(egui_phosphor::regular::ARROW_RIGHT))
+                .on_hover_text("Step")
+                .clicked()
+            {
+                self.step();
+            }
+            if line
+                .add_enabled(can_step, egui::Button::new(egui_phosphor::regular::FAST_FORWARD))
+                .on_hover_text("Run to the end")
+                .clicked()
+            {
+                self.run_to_end();
+            }
+            line.label(format!("{} / {}", self.position, self.ops.len()));
+        });
+
+        for (i, (op, label)) in self.ops.iter().enumerate() {
+            let text = if let Some(label) = label {
+                format!("{}: {label}", op.name())
+            } else {
+                op.name().to_owned()
+            };
+            if i + 1 == self.position {
+                ui.label(RichText::new(format!("{} {text}", egui_phosphor::regular::CARET_RIGHT)).strong());
+            } else if i < self.position {
+                ui.label(text);
+            } else {
+                ui.weak(text);
+            }
+        }
+
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(input_error_color(ui.ctx())));
+        }
+
+        ui.separator();
+        ui.label("Stack, top first:");
+        if self.stack.is_empty() {
+            ui.weak("empty");
+        }
+        let id = ui.id().with("proof_stack");
+        for (i, node) in self.stack.iter().rev().enumerate() {
+            draw_node(ui, "", node, id.with(self.stack.len() - i));
+        }
+        if self.position == self.ops.len() && self.error.is_none() && self.stack.len() != 1 {
+            ui.label(
+                RichText::new("A valid proof leaves exactly one tree on the stack")
+                    .color(input_error_color(ui.ctx())),
+            );
+        }
+    }
+}
+
+fn draw_node(ui: &mut egui::Ui, prefix: &str, node: &StackNode, id: egui::Id) {
+    let text = format!("{prefix}{}", node.label);
+    if node.left.is_none() && node.right.is_none() {
+        ui.label(text);
+        return;
+    }
+
+    CollapsingHeader::new(text)
+        .id_salt(id)
+        .default_open(true)
+        .show(ui, |collapsing| {
+            if let Some(left) = &node.left {
+                draw_node(collapsing, "L: ", left, id.with("left"));
+            }
+            if let Some(right) = &node.right {
+                draw_node(collapsing, "R: ", right, id.with("right"));
+            }
+        });
+}
+
+fn node_label(node: &MerkProofNodeViewer) -> String {
+    let key = |bytes: &[u8]| bytes_by_display_variant(bytes, &BytesDisplayVariant::guess(bytes));
+    let hash = |bytes: &[u8]| bytes_by_display_variant(bytes, &BytesDisplayVariant::Hex);
+
+    match node {
+        MerkProofNodeViewer::Hash(h) => format!("Hash {}", hash(&h.bytes)),
+        MerkProofNodeViewer::KVHash(h) => format!("KVHash {}", hash(&h.bytes)),
+        MerkProofNodeViewer::KVDigest(k, _) => format!("KVDigest {}", key(&k.bytes)),
+        MerkProofNodeViewer::KV(k, _) => format!("KV {}", key(&k.bytes)),
+        MerkProofNodeViewer::KVValueHash(k, ..) => format!("KVValueHash {}", key(&k.bytes)),
+        MerkProofNodeViewer::KVValueHashFeatureType(k, ..) => {
+            format!("KVValueHashFeatureType {}", key(&k.bytes))
+        }
+        MerkProofNodeViewer::KVRefValueHash(k, ..) => format!("KVRefValueHash {}", key(&k.bytes)),
+    }
+}
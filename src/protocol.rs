@@ -1,15 +1,28 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod disk_cache;
 mod proof_tree;
+pub mod traffic;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use futures::TryFutureExt;
+use chrono::{DateTime, Local, TimeDelta};
+use futures::{stream, StreamExt};
 use grovedbg_types::{
-    DropSessionRequest, Key, MerkProofNode, NewSessionResponse, NodeFetchRequest, NodeUpdate, Path,
-    PathQuery, Proof, RootFetchRequest, SessionId, WithSession,
+    CryptoHash, DropSessionRequest, Key, MerkProofNode, MerkProofOp, NewSessionResponse, NodeFetchRequest,
+    NodeUpdate, Path, PathQuery, Proof, ProofLayer, QueryItem, RootFetchRequest, SessionId, WithSession,
 };
 use proof_tree::ProofTree;
 use reqwest::{Client, StatusCode, Url};
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+use tokio::sync::mpsc::{Receiver, Sender};
+use traffic::{send_counted, ServerError};
+
+/// Maximal number of concurrent requests of a [FetchCommand::FetchNodes]
+const FETCH_NODES_CONCURRENCY: usize = 8;
+/// Maximal number of concurrent requests of a
+/// [FetchCommand::FetchWithPathQueries]
+const QUERY_BATCH_CONCURRENCY: usize = 4;
 
 /// Starts the data exchange process between GroveDBG application and GroveDB's
 /// debugger endpoint.
@@ -25,51 +38,189 @@ pub async fn start_grovedbg_protocol(
         address
     );
 
-    let (feedback_send, mut feedback_recv) = mpsc::channel(10);
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut disk_cache = disk_cache::DiskCache::from_env();
+
+    // The endpoint doesn't change during the task, so its info is requested once
+    let mut endpoint_info = None;
 
-    while let Some(cmd) = tokio::select! {
-        x = commands_receiver.recv() => x,
-        x = feedback_recv.recv() => x,
-    } {
+    while let Some(cmd) = commands_receiver.recv().await {
         if let Err(send_error) = updates_sender.send(GroveGdbUpdate::Block).await {
             log::error!("Unable to send update: {send_error}; terminating the protocol task");
             return;
         }
 
-        let updates = match process_command(&address, &client, cmd).await {
-            Ok(x) => x,
-            Err(e) => {
-                match e.downcast_ref::<reqwest::Error>() {
-                    Some(req_error) if req_error.status() == Some(StatusCode::UNAUTHORIZED) => {
-                        log::warn!("Session expired");
-                        feedback_send
-                            .send(ProtocolCommand::NewSession { old_session: None })
-                            .await
-                            .ok();
-                    }
-                    _ => log::error!("Error processing command: {e}"),
-                }
-                continue;
+        #[cfg(not(target_arch = "wasm32"))]
+        let is_new_session = matches!(cmd, ProtocolCommand::NewSession { .. });
+
+        let command = CommandKind::of(&cmd);
+        let retry = match &cmd {
+            ProtocolCommand::Fetch { command, .. } => Some(command.clone()),
+            _ => None,
+        };
+        let path_query = match &cmd {
+            ProtocolCommand::Fetch {
+                command: FetchCommand::ProvePathQuery { path_query },
+                ..
             }
+            | ProtocolCommand::Fetch {
+                command: FetchCommand::FetchWithPathQuery { path_query },
+                ..
+            } => Some(path_query.clone()),
+            _ => None,
         };
+        let started = Local::now();
+        let mut transfer = None;
+        let result = process_command(
+            &address,
+            &client,
+            cmd,
+            &mut transfer,
+            &mut endpoint_info,
+            &updates_sender,
+        )
+        .await
+        .map_err(ProtocolError::classify);
+        let mut activity = ActivityEvent::new(command, started, &result);
+        activity.transfer = transfer;
+        // Successful pings would only clutter the activity log
+        let quiet = command == CommandKind::KeepAlive && result.is_ok();
 
-        if let Err(send_error) = updates_sender
-            .send(updates)
-            .and_then(|_| updates_sender.send(GroveGdbUpdate::Unblock))
-            .await
+        let updates = match result {
+            Ok(x) => match path_query.zip(transfer) {
+                Some((path_query, transfer)) => vec![
+                    x,
+                    GroveGdbUpdate::QueryCost(QueryCost {
+                        command,
+                        path_query,
+                        transfer,
+                        items: activity.items,
+                    }),
+                ],
+                None => vec![x],
+            },
+            Err(e) => vec![GroveGdbUpdate::Error(e)],
+        };
+
+        if activity.severity == ActivitySeverity::Error {
+            activity.retry = retry;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let updates = match disk_cache.as_mut() {
+            Some(cache) => {
+                let updates = cache_updates(cache, updates, is_new_session);
+                // Keep alive pings come often enough to flush nodes stored last
+                cache.write_if_due();
+                updates
+            }
+            None => updates,
+        };
+
+        for update in updates
+            .into_iter()
+            .chain((!quiet).then_some(GroveGdbUpdate::Activity(activity)))
+            .chain([GroveGdbUpdate::Unblock])
         {
-            log::error!("Unable to send update: {send_error}; terminating the protocol task");
-            return;
+            if let Err(send_error) = updates_sender.send(update).await {
+                log::error!("Unable to send update: {send_error}; terminating the protocol task");
+                return;
+            }
+        }
+    }
+}
+
+/// Puts fetched nodes into the disk cache, adding updates with nodes restored
+/// from it. If a session couldn't be started the last cached state is used
+/// instead.
+#[cfg(not(target_arch = "wasm32"))]
+fn cache_updates(
+    cache: &mut disk_cache::DiskCache,
+    mut updates: Vec<GroveGdbUpdate>,
+    is_new_session: bool,
+) -> Vec<GroveGdbUpdate> {
+    if is_new_session && matches!(updates.as_slice(), [GroveGdbUpdate::Error(_)]) {
+        if let Some((root, nodes)) = cache.open_last() {
+            log::warn!("GroveDB is unreachable, showing the last cached state");
+            updates.push(GroveGdbUpdate::RootUpdate(Some(root)));
+            updates.push(GroveGdbUpdate::Node(NodeSource::Cache, nodes));
+        }
+        return updates;
+    }
+
+    let mut restored = Vec::new();
+    for update in updates.iter() {
+        match update {
+            GroveGdbUpdate::RootUpdate(Some(root)) => {
+                let nodes = cache.open_root(root);
+                if !nodes.is_empty() {
+                    restored.push(GroveGdbUpdate::Node(NodeSource::Cache, nodes));
+                }
+            }
+            GroveGdbUpdate::Node(_, nodes)
+            | GroveGdbUpdate::PartialNodes(nodes, _)
+            | GroveGdbUpdate::Proof(_, _, nodes, ..) => cache.store(nodes),
+            _ => {}
         }
     }
+    updates.append(&mut restored);
+    updates
 }
 
 /// Background tasks of GroveDBG application
+#[derive(Debug, Clone)]
 pub enum FetchCommand {
     FetchRoot,
-    FetchNode { path: Path, key: Key },
-    ProvePathQuery { path_query: PathQuery },
-    FetchWithPathQuery { path_query: PathQuery },
+    FetchNode {
+        path: Path,
+        key: Key,
+    },
+    ProvePathQuery {
+        path_query: PathQuery,
+    },
+    FetchWithPathQuery {
+        path_query: PathQuery,
+    },
+    /// Several path queries run concurrently, each one reported with
+    /// [GroveGdbUpdate::BatchProgress] as it finishes and their nodes merged
+    /// into one update
+    FetchWithPathQueries {
+        path_queries: Vec<PathQuery>,
+    },
+    /// Several nodes fetched concurrently, with a bounded number of requests
+    /// at once
+    FetchNodes {
+        nodes: Vec<(Path, Key)>,
+    },
+    /// Merk tree nodes of a subtree down to `levels` levels, fetched level by
+    /// level following children keys starting from the root node
+    FetchMerkLevels {
+        path: Path,
+        root_key: Key,
+        levels: u8,
+    },
+    /// Serialized element bytes exactly as stored by Merk, along with the
+    /// decoded node
+    FetchRawNode {
+        path: Path,
+        key: Key,
+    },
+    /// Number of elements in a subtree, exact or estimated by the endpoint
+    CountSubtree {
+        path: Path,
+    },
+    /// Node with the hash of the kind, looked up by the endpoint across the
+    /// whole session
+    FindNodeByHash {
+        hash: CryptoHash,
+        kind: HashLookupKind,
+    },
+    /// Elements with references pointing at the node, tracked by GroveDB
+    /// versions with bidirectional references
+    FetchReferrers {
+        path: Path,
+        key: Key,
+    },
 }
 
 pub enum ProtocolCommand {
@@ -80,71 +231,649 @@ pub enum ProtocolCommand {
         session_id: SessionId,
         command: FetchCommand,
     },
+    FetchComparison {
+        session_id: SessionId,
+        other_session_id: SessionId,
+        path: Path,
+        key: Key,
+    },
+    /// Starts a session kept apart from the current one to compare nodes
+    /// against
+    NewComparisonSession,
+    DropSession {
+        session_id: SessionId,
+    },
+    /// Checks if GroveDB root hash has changed since the session was started,
+    /// replacing the session with a new one if so
+    Follow {
+        session_id: SessionId,
+    },
+    /// Makes a cheap request within the session so GroveDB doesn't expire it
+    /// while idle
+    KeepAlive {
+        session_id: SessionId,
+    },
 }
 
 /// Updates and commands' results pushed to GroveDBG application
 #[derive(Debug)]
 pub enum GroveGdbUpdate {
     RootUpdate(Option<NodeUpdate>),
-    Node(Vec<NodeUpdate>),
+    Node(NodeSource, Vec<NodeUpdate>),
+    /// A query of all elements of the subtree returned nothing
+    EmptySubtree(Path),
+    /// Batch of nodes where some of them couldn't be parsed
+    PartialNodes(Vec<NodeUpdate>, Vec<UnparsedNode>),
     Proof(
         Proof,
+        PathQuery,
         Vec<NodeUpdate>,
         BTreeMap<Vec<Vec<u8>>, BTreeMap<Key, MerkProofNode>>,
+        /// Response body the proof was parsed from
+        Vec<u8>,
     ),
-    Session(SessionId),
+    /// Proof that couldn't be processed, with data to reproduce the issue
+    ProofFailure(Box<ProofFailure>),
+    /// New session with the endpoint info if the endpoint provides it
+    Session(SessionId, Option<EndpointInfo>),
+    Comparison(NodeComparison),
+    ComparisonSession(SessionId),
+    SessionDropped,
+    RawNode(RawNode),
+    SubtreeCount(SubtreeCount),
+    HashLookup(HashLookup),
+    /// A query of a path query batch has finished
+    BatchProgress(BatchProgress),
+    /// Measured cost of a path query fetch or proof, follows its result
+    QueryCost(QueryCost),
+    /// New session if GroveDB root hash has changed, the old one is dropped
+    Follow(Option<SessionId>),
+    /// The session was kept alive
+    KeepAlive,
+    /// Failure of a processed command, followed by its activity event
+    Error(ProtocolError),
+    /// Outcome of a processed command for the activity log
+    Activity(ActivityEvent),
     Block,
     Unblock,
 }
 
-impl From<Vec<NodeUpdate>> for GroveGdbUpdate {
-    fn from(value: Vec<NodeUpdate>) -> Self {
-        GroveGdbUpdate::Node(value)
+/// Command that brought node data
+#[derive(Debug, Clone, Copy, PartialEq, AsRefStr)]
+pub enum NodeSource {
+    #[strum(serialize = "root fetch")]
+    Root,
+    #[strum(serialize = "node fetch")]
+    Node,
+    #[strum(serialize = "path query")]
+    Query,
+    #[strum(serialize = "proof merge")]
+    Proof,
+    #[strum(serialize = "disk cache")]
+    Cache,
+    #[strum(serialize = "prefetch")]
+    Prefetch,
+    #[strum(serialize = "referrers fetch")]
+    Referrers,
+    #[strum(serialize = "snapshot import")]
+    Snapshot,
+    #[strum(serialize = "operation replay")]
+    Replay,
+}
+
+/// Kind of a processed protocol command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, AsRefStr, EnumIter)]
+pub enum CommandKind {
+    #[strum(serialize = "New session")]
+    NewSession,
+    #[strum(serialize = "Fetch root")]
+    FetchRoot,
+    #[strum(serialize = "Fetch node")]
+    FetchNode,
+    #[strum(serialize = "Prove path query")]
+    ProvePathQuery,
+    #[strum(serialize = "Fetch with path query")]
+    FetchWithPathQuery,
+    #[strum(serialize = "Fetch path query batch")]
+    FetchWithPathQueries,
+    #[strum(serialize = "Fetch nodes")]
+    FetchNodes,
+    #[strum(serialize = "Fetch Merk levels")]
+    FetchMerkLevels,
+    #[strum(serialize = "Fetch raw node")]
+    FetchRawNode,
+    #[strum(serialize = "Count subtree")]
+    CountSubtree,
+    #[strum(serialize = "Find node by hash")]
+    FindNodeByHash,
+    #[strum(serialize = "Fetch referrers")]
+    FetchReferrers,
+    #[strum(serialize = "Compare sessions")]
+    FetchComparison,
+    #[strum(serialize = "New comparison session")]
+    NewComparisonSession,
+    #[strum(serialize = "Drop session")]
+    DropSession,
+    #[strum(serialize = "Follow chain")]
+    Follow,
+    #[strum(serialize = "Keep session alive")]
+    KeepAlive,
+}
+
+impl CommandKind {
+    fn of(command: &ProtocolCommand) -> Self {
+        match command {
+            ProtocolCommand::NewSession { .. } => CommandKind::NewSession,
+            ProtocolCommand::Fetch { command, .. } => match command {
+                FetchCommand::FetchRoot => CommandKind::FetchRoot,
+                FetchCommand::FetchNode { .. } => CommandKind::FetchNode,
+                FetchCommand::ProvePathQuery { .. } => CommandKind::ProvePathQuery,
+                FetchCommand::FetchWithPathQuery { .. } => CommandKind::FetchWithPathQuery,
+                FetchCommand::FetchWithPathQueries { .. } => CommandKind::FetchWithPathQueries,
+                FetchCommand::FetchNodes { .. } => CommandKind::FetchNodes,
+                FetchCommand::FetchMerkLevels { .. } => CommandKind::FetchMerkLevels,
+                FetchCommand::FetchRawNode { .. } => CommandKind::FetchRawNode,
+                FetchCommand::CountSubtree { .. } => CommandKind::CountSubtree,
+                FetchCommand::FindNodeByHash { .. } => CommandKind::FindNodeByHash,
+                FetchCommand::FetchReferrers { .. } => CommandKind::FetchReferrers,
+            },
+            ProtocolCommand::FetchComparison { .. } => CommandKind::FetchComparison,
+            ProtocolCommand::NewComparisonSession => CommandKind::NewComparisonSession,
+            ProtocolCommand::DropSession { .. } => CommandKind::DropSession,
+            ProtocolCommand::Follow { .. } => CommandKind::Follow,
+            ProtocolCommand::KeepAlive { .. } => CommandKind::KeepAlive,
+        }
+    }
+}
+
+/// Severity of a command outcome, ordered from the least severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, AsRefStr, EnumIter)]
+pub enum ActivitySeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Structured record of a processed protocol command
+#[derive(Debug)]
+pub struct ActivityEvent {
+    pub command: CommandKind,
+    pub started: DateTime<Local>,
+    pub duration: TimeDelta,
+    /// Number of nodes returned, if the command fetches any
+    pub items: Option<usize>,
+    pub severity: ActivitySeverity,
+    pub message: Option<String>,
+    /// Command to re-issue if this one failed
+    pub retry: Option<FetchCommand>,
+    /// Measured request of a path query command
+    pub transfer: Option<Transfer>,
+}
+
+/// Round trip and response size of a single request
+#[derive(Debug, Clone, Copy)]
+pub struct Transfer {
+    pub round_trip: TimeDelta,
+    pub payload_bytes: usize,
+}
+
+/// Outcome of a single query of a batch by its index, the number of returned
+/// nodes or the error
+#[derive(Debug, Clone)]
+pub struct BatchProgress {
+    pub index: usize,
+    pub result: Result<usize, String>,
+}
+
+/// Cost of a path query to compare different query shapes
+#[derive(Debug, Clone)]
+pub struct QueryCost {
+    pub command: CommandKind,
+    pub path_query: PathQuery,
+    pub transfer: Transfer,
+    /// Number of nodes returned
+    pub items: Option<usize>,
+}
+
+impl ActivityEvent {
+    fn new(
+        command: CommandKind,
+        started: DateTime<Local>,
+        result: &Result<GroveGdbUpdate, ProtocolError>,
+    ) -> Self {
+        let (items, severity, message) = match result {
+            Ok(GroveGdbUpdate::RootUpdate(None)) => (
+                Some(0),
+                ActivitySeverity::Warning,
+                Some("No root node, GroveDB is empty".to_owned()),
+            ),
+            Ok(GroveGdbUpdate::RootUpdate(Some(_))) => (Some(1), ActivitySeverity::Info, None),
+            Ok(GroveGdbUpdate::Node(_, nodes)) if nodes.is_empty() => (
+                Some(0),
+                ActivitySeverity::Warning,
+                Some("No nodes returned".to_owned()),
+            ),
+            Ok(GroveGdbUpdate::Node(_, nodes)) => (Some(nodes.len()), ActivitySeverity::Info, None),
+            Ok(GroveGdbUpdate::EmptySubtree(_)) => (
+                Some(0),
+                ActivitySeverity::Info,
+                Some("The subtree is empty".to_owned()),
+            ),
+            Ok(GroveGdbUpdate::PartialNodes(nodes, unparsed)) => (
+                Some(nodes.len()),
+                ActivitySeverity::Warning,
+                Some(format!("{} nodes couldn't be parsed", unparsed.len())),
+            ),
+            Ok(GroveGdbUpdate::Proof(_, _, nodes, ..)) => (Some(nodes.len()), ActivitySeverity::Info, None),
+            Ok(GroveGdbUpdate::ProofFailure(failure)) => {
+                (None, ActivitySeverity::Error, Some(failure.error.clone()))
+            }
+            Ok(GroveGdbUpdate::Follow(Some(_))) => (
+                None,
+                ActivitySeverity::Info,
+                Some("Root hash changed, switched to a new session".to_owned()),
+            ),
+            Ok(GroveGdbUpdate::RawNode(RawNode { bytes: None, .. })) => (
+                Some(0),
+                ActivitySeverity::Warning,
+                Some("No stored bytes returned".to_owned()),
+            ),
+            Ok(GroveGdbUpdate::HashLookup(HashLookup { node: None, .. })) => (
+                Some(0),
+                ActivitySeverity::Warning,
+                Some("No node has the hash".to_owned()),
+            ),
+            Ok(GroveGdbUpdate::HashLookup(HashLookup { node: Some(_), .. })) => {
+                (Some(1), ActivitySeverity::Info, None)
+            }
+            Ok(GroveGdbUpdate::Comparison(comparison)) => (
+                Some(comparison.current.iter().chain(comparison.other.iter()).count()),
+                ActivitySeverity::Info,
+                None,
+            ),
+            Ok(_) => (None, ActivitySeverity::Info, None),
+            Err(ProtocolError::SessionExpired) => (
+                None,
+                ActivitySeverity::Warning,
+                Some("Session expired, starting a new one".to_owned()),
+            ),
+            Err(e) => (None, ActivitySeverity::Error, Some(e.to_string())),
+        };
+
+        ActivityEvent {
+            command,
+            started,
+            duration: Local::now() - started,
+            items,
+            severity,
+            message,
+            retry: None,
+            transfer: None,
+        }
     }
 }
 
+/// Failure of a protocol command, classified so the application can react to
+/// each kind differently
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ProtocolError {
+    /// GroveDB endpoint couldn't be reached or the connection broke
+    #[error("unable to reach GroveDB: {0}")]
+    Connection(String),
+    /// The response couldn't be decoded or didn't make sense
+    #[error("unable to decode GroveDB response: {0}")]
+    Decoding(String),
+    /// GroveDB doesn't know the session anymore, a new one is needed
+    #[error("session expired")]
+    SessionExpired,
+    /// GroveDB responded with an error status
+    #[error("GroveDB responded with {status}: {message}")]
+    Server { status: u16, message: String },
+}
+
+impl ProtocolError {
+    fn classify(error: anyhow::Error) -> Self {
+        if let Some(ServerError { status, body }) = error.downcast_ref::<ServerError>() {
+            if *status == StatusCode::UNAUTHORIZED {
+                return ProtocolError::SessionExpired;
+            }
+            let reason = status.canonical_reason().unwrap_or("unknown error");
+            return ProtocolError::Server {
+                status: status.as_u16(),
+                message: if body.is_empty() {
+                    reason.to_owned()
+                } else {
+                    format!("{reason}: {body}")
+                },
+            };
+        }
+        if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+            return if e.is_decode() {
+                ProtocolError::Decoding(e.to_string())
+            } else {
+                ProtocolError::Connection(e.to_string())
+            };
+        }
+        ProtocolError::Decoding(format!("{error:#}"))
+    }
+}
+
+/// Versions and capabilities advertised by GroveDB's debugger endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct EndpointInfo {
+    pub grovedb_version: String,
+    pub grovedbg_types_version: String,
+    #[serde(default)]
+    pub capabilities: BTreeSet<String>,
+}
+
+/// Endpoint features GroveDBG relies on
+#[derive(Debug, Clone, Copy, AsRefStr, EnumIter)]
+pub enum Capability {
+    #[strum(serialize = "prove_path_query")]
+    ProvePathQuery,
+    #[strum(serialize = "fetch_with_path_query")]
+    FetchWithPathQuery,
+    /// Keeping several sessions open to compare them
+    #[strum(serialize = "concurrent_sessions")]
+    ConcurrentSessions,
+    #[strum(serialize = "fetch_raw_node")]
+    FetchRawNode,
+    #[strum(serialize = "count_subtree")]
+    CountSubtree,
+    #[strum(serialize = "find_node_by_hash")]
+    FindNodeByHash,
+    #[strum(serialize = "fetch_referrers")]
+    FetchReferrers,
+}
+
+impl Capability {
+    /// Features GroveDBG used before endpoints started advertising their
+    /// capabilities
+    pub fn is_legacy(self) -> bool {
+        matches!(
+            self,
+            Capability::ProvePathQuery | Capability::FetchWithPathQuery | Capability::ConcurrentSessions
+        )
+    }
+
+    /// Whether this GroveDBG build knows how to use an advertised capability,
+    /// unknown ones usually mean element types or references it can't parse
+    pub fn is_known(name: &str) -> bool {
+        Capability::iter().any(|capability| capability.as_ref() == name)
+    }
+}
+
+/// The same node fetched from two different sessions
+#[derive(Debug)]
+pub struct NodeComparison {
+    pub path: Path,
+    pub key: Key,
+    pub current: Option<NodeUpdate>,
+    pub other: Option<NodeUpdate>,
+}
+
+/// Number of elements in a subtree as reported by the endpoint
+#[derive(Debug)]
+pub struct SubtreeCount {
+    pub path: Path,
+    pub count: u64,
+    /// Endpoints may estimate the count of large subtrees
+    pub exact: bool,
+}
+
+#[derive(Deserialize)]
+struct CountResponse {
+    count: u64,
+    #[serde(default)]
+    exact: bool,
+}
+
+/// Hash of a node a lookup is made by
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, AsRefStr, EnumIter)]
+#[serde(rename_all = "snake_case")]
+pub enum HashLookupKind {
+    #[default]
+    #[strum(serialize = "Node hash")]
+    Node,
+    #[strum(serialize = "Value hash")]
+    Value,
+}
+
+#[derive(Serialize)]
+struct HashLookupRequest {
+    hash: CryptoHash,
+    kind: HashLookupKind,
+}
+
+/// Result of a lookup by hash, `node` is `None` if no node has the hash
+#[derive(Debug)]
+pub struct HashLookup {
+    pub hash: CryptoHash,
+    pub kind: HashLookupKind,
+    pub node: Option<NodeUpdate>,
+}
+
+/// Stored bytes of a node's element with the decoded node to compare
+#[derive(Debug)]
+pub struct RawNode {
+    pub path: Path,
+    pub key: Key,
+    pub bytes: Option<Vec<u8>>,
+    pub decoded: Option<NodeUpdate>,
+}
+
+/// Everything needed to reproduce a proof that GroveDBG failed to process
+#[derive(Debug, Serialize)]
+pub struct ProofFailure {
+    pub error: String,
+    pub path_query: PathQuery,
+    pub proof: Proof,
+    pub root_node: Option<NodeUpdate>,
+    /// Nodes of the keys mentioned in the proof
+    pub nodes: Vec<NodeUpdate>,
+}
+
+impl ProofFailure {
+    async fn collect(
+        client: &Client,
+        address: &Url,
+        session_id: SessionId,
+        path_query: PathQuery,
+        proof: Proof,
+        error: String,
+    ) -> Self {
+        // Gathering is best effort, as the failure could be caused by the same
+        // data we're trying to fetch
+        let root_node = fetch_root_node(client, address, session_id).await.ok().flatten();
+
+        let mut keys = Vec::new();
+        proof_keys(&proof.root_layer, Vec::new(), &mut keys);
+
+        let mut nodes = Vec::new();
+        for (path, key) in keys {
+            if let Ok(Some(node)) = fetch_node(client, address, session_id, path, key).await {
+                nodes.push(node);
+            }
+        }
+
+        ProofFailure {
+            error,
+            path_query,
+            proof,
+            root_node,
+            nodes,
+        }
+    }
+}
+
+/// A query without offset and subqueries over all keys of a subtree, an empty
+/// result of which means the subtree is empty
+fn queries_whole_subtree(path_query: &PathQuery) -> bool {
+    let query = &path_query.query.query;
+    path_query.query.offset.unwrap_or_default() == 0
+        && matches!(query.items.as_slice(), [QueryItem::RangeFull])
+        && query.default_subquery_branch.subquery.is_none()
+        && query.conditional_subquery_branches.is_empty()
+}
+
+/// Collects paths and keys of all nodes of a proof that have keys
+fn proof_keys(layer: &ProofLayer, path: Path, keys: &mut Vec<(Path, Key)>) {
+    for op in layer.merk_proof.iter() {
+        let (MerkProofOp::Push(node) | MerkProofOp::PushInverted(node)) = op else {
+            continue;
+        };
+        match node {
+            MerkProofNode::KVDigest(key, ..)
+            | MerkProofNode::KV(key, ..)
+            | MerkProofNode::KVValueHash(key, ..)
+            | MerkProofNode::KVValueHashFeatureType(key, ..)
+            | MerkProofNode::KVRefValueHash(key, ..) => keys.push((path.clone(), key.clone())),
+            MerkProofNode::Hash(..) | MerkProofNode::KVHash(..) => {}
+        }
+    }
+
+    for (key, lower_layer) in layer.lower_layers.iter() {
+        let mut lower_path = path.clone();
+        lower_path.push(key.clone());
+        proof_keys(lower_layer, lower_path, keys);
+    }
+}
+
+/// Node received from GroveDB that couldn't be parsed as `NodeUpdate`
+#[derive(Debug)]
+pub struct UnparsedNode {
+    pub raw: String,
+    pub error: String,
+}
+
 async fn fetch_node(
     client: &Client,
     address: &Url,
     session_id: SessionId,
     path: Vec<Vec<u8>>,
     key: Vec<u8>,
-) -> Result<Option<NodeUpdate>, reqwest::Error> {
-    client
-        .post(format!("{address}fetch_node"))
-        .json(&WithSession {
-            session_id,
-            request: NodeFetchRequest { path, key },
-        })
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Option<NodeUpdate>>()
-        .await
+) -> anyhow::Result<Option<NodeUpdate>> {
+    let body = send_counted(client.post(format!("{address}fetch_node")).json(&WithSession {
+        session_id,
+        request: NodeFetchRequest { path, key },
+    }))
+    .await?;
+    Ok(serde_json::from_slice(&body)?)
 }
 
 async fn fetch_root_node(
     client: &Client,
     address: &Url,
     session_id: SessionId,
-) -> Result<Option<NodeUpdate>, reqwest::Error> {
-    client
-        .post(format!("{address}fetch_root_node"))
-        .json(&WithSession {
+) -> anyhow::Result<Option<NodeUpdate>> {
+    let body = send_counted(
+        client
+            .post(format!("{address}fetch_root_node"))
+            .json(&WithSession {
+                session_id,
+                request: RootFetchRequest,
+            }),
+    )
+    .await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Handshake part that not every endpoint supports, so a failure only means
+/// there is no info to show
+async fn fetch_endpoint_info(client: &Client, address: &Url) -> Option<EndpointInfo> {
+    let result = async {
+        let body = send_counted(client.post(format!("{address}info"))).await?;
+        anyhow::Ok(serde_json::from_slice::<EndpointInfo>(&body)?)
+    }
+    .await;
+
+    match result {
+        Ok(info) => {
+            log::info!(
+                "Connected to GroveDB {} with grovedbg-types {}",
+                info.grovedb_version,
+                info.grovedbg_types_version
+            );
+            Some(info)
+        }
+        Err(e) => {
+            log::info!("GroveDB endpoint doesn't advertise its version, assuming legacy capabilities: {e}");
+            None
+        }
+    }
+}
+
+/// Posts a request measuring its round trip and the response size
+async fn post_measured<R: Serialize, T: DeserializeOwned>(
+    client: &Client,
+    url: String,
+    request: &R,
+    transfer: &mut Option<Transfer>,
+) -> anyhow::Result<T> {
+    let payload = post_measured_raw(client, url, request, transfer).await?;
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+/// Same as [post_measured], but returns the response body as is
+async fn post_measured_raw<R: Serialize>(
+    client: &Client,
+    url: String,
+    request: &R,
+    transfer: &mut Option<Transfer>,
+) -> anyhow::Result<Vec<u8>> {
+    let started = Local::now();
+    let payload = send_counted(client.post(url).json(request)).await?;
+    *transfer = Some(Transfer {
+        round_trip: Local::now() - started,
+        payload_bytes: payload.len(),
+    });
+    Ok(payload)
+}
+
+/// Fetches nodes of a path query, they are parsed one by one so a single bad
+/// node won't cost the whole batch
+async fn fetch_path_query(
+    client: &Client,
+    address: &Url,
+    session_id: SessionId,
+    path_query: PathQuery,
+    transfer: &mut Option<Transfer>,
+) -> anyhow::Result<(Vec<NodeUpdate>, Vec<UnparsedNode>)> {
+    let raw_nodes: Vec<serde_json::Value> = post_measured(
+        client,
+        format!("{address}fetch_with_path_query"),
+        &WithSession {
             session_id,
-            request: RootFetchRequest,
-        })
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Option<NodeUpdate>>()
-        .await
+            request: path_query,
+        },
+        transfer,
+    )
+    .await?;
+
+    let mut nodes = Vec::with_capacity(raw_nodes.len());
+    let mut unparsed = Vec::new();
+    for raw in raw_nodes.into_iter() {
+        match serde_json::from_value::<NodeUpdate>(raw.clone()) {
+            Ok(node) => nodes.push(node),
+            Err(e) => unparsed.push(UnparsedNode {
+                raw: raw.to_string(),
+                error: e.to_string(),
+            }),
+        }
+    }
+    Ok((nodes, unparsed))
 }
 
+/// Processes a command, path query commands report their request's
+/// [Transfer] and batches report progress through `updates_sender`
 async fn process_command(
     address: &Url,
     client: &Client,
     command: ProtocolCommand,
+    transfer: &mut Option<Transfer>,
+    endpoint_info: &mut Option<Option<EndpointInfo>>,
+    updates_sender: &Sender<GroveGdbUpdate>,
 ) -> anyhow::Result<GroveGdbUpdate> {
     match command {
         ProtocolCommand::Fetch {
@@ -165,31 +894,203 @@ async fn process_command(
         } => {
             log::info!("Fetching a node...");
             if let Some(node_update) = fetch_node(client, address, session, path, key).await? {
-                Ok(vec![node_update].into())
+                Ok(GroveGdbUpdate::Node(NodeSource::Node, vec![node_update]))
             } else {
                 log::warn!("No node returned");
-                Ok(Vec::new().into())
+                Ok(GroveGdbUpdate::Node(NodeSource::Node, Vec::new()))
+            }
+        }
+        ProtocolCommand::Fetch {
+            command: FetchCommand::FetchNodes { nodes },
+            session_id: session,
+        } => {
+            log::info!("Fetching {} nodes...", nodes.len());
+            let results: Vec<anyhow::Result<Option<NodeUpdate>>> = stream::iter(nodes)
+                .map(|(path, key)| fetch_node(client, address, session, path, key))
+                .buffer_unordered(FETCH_NODES_CONCURRENCY)
+                .collect()
+                .await;
+            // A failed node shouldn't discard the others, so they are not requested again
+            let (node_updates, errors): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+            if node_updates.is_empty() {
+                if let Some(error) = errors.into_iter().find_map(Result::err) {
+                    return Err(error);
+                }
+            } else if !errors.is_empty() {
+                log::warn!("{} of the nodes couldn't be fetched", errors.len());
+            }
+            Ok(GroveGdbUpdate::Node(
+                NodeSource::Prefetch,
+                node_updates
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .flatten()
+                    .collect(),
+            ))
+        }
+        ProtocolCommand::Fetch {
+            command:
+                FetchCommand::FetchMerkLevels {
+                    path,
+                    root_key,
+                    levels,
+                },
+            session_id: session,
+        } => {
+            log::info!("Fetching {levels} levels of a subtree's Merk tree...");
+            let mut nodes = Vec::new();
+            let mut failed = Vec::new();
+            let mut level = vec![root_key];
+            for _ in 0..levels {
+                if level.is_empty() {
+                    break;
+                }
+                let results: Vec<(Key, anyhow::Result<Option<NodeUpdate>>)> = stream::iter(level)
+                    .map(|key| {
+                        let path = path.clone();
+                        async move {
+                            let result = fetch_node(client, address, session, path, key.clone()).await;
+                            (key, result)
+                        }
+                    })
+                    .buffer_unordered(FETCH_NODES_CONCURRENCY)
+                    .collect()
+                    .await;
+                // Children of a failed node are skipped, but what was fetched is kept
+                let mut fetched = Vec::new();
+                for (key, result) in results {
+                    match result {
+                        Ok(node) => fetched.extend(node),
+                        Err(error) => failed.push((key, error)),
+                    }
+                }
+                level = fetched
+                    .iter()
+                    .flat_map(|node| node.left_child.iter().chain(node.right_child.iter()).cloned())
+                    .collect();
+                nodes.extend(fetched);
+            }
+            if nodes.is_empty() {
+                if let Some((_, error)) = failed.into_iter().next() {
+                    return Err(error);
+                }
+            } else if !failed.is_empty() {
+                log::warn!(
+                    "{} of the Merk tree nodes couldn't be fetched: {}",
+                    failed.len(),
+                    failed
+                        .iter()
+                        .map(|(key, error)| format!("{} ({error})", hex::encode(key)))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
             }
+            Ok(GroveGdbUpdate::Node(NodeSource::Node, nodes))
+        }
+        ProtocolCommand::Fetch {
+            command: FetchCommand::FetchRawNode { path, key },
+            session_id,
+        } => {
+            log::info!("Fetching raw bytes of a node...");
+            let body = send_counted(
+                client
+                    .post(format!("{address}fetch_raw_node"))
+                    .json(&WithSession {
+                        session_id,
+                        request: NodeFetchRequest {
+                            path: path.clone(),
+                            key: key.clone(),
+                        },
+                    }),
+            )
+            .await?;
+            let bytes: Option<Vec<u8>> = serde_json::from_slice(&body)?;
+            let decoded = fetch_node(client, address, session_id, path.clone(), key.clone()).await?;
+            Ok(GroveGdbUpdate::RawNode(RawNode {
+                path,
+                key,
+                bytes,
+                decoded,
+            }))
+        }
+        ProtocolCommand::Fetch {
+            command: FetchCommand::CountSubtree { path },
+            session_id,
+        } => {
+            log::info!("Counting subtree elements...");
+            let body = send_counted(client.post(format!("{address}count_subtree")).json(&WithSession {
+                session_id,
+                request: path.clone(),
+            }))
+            .await?;
+            let CountResponse { count, exact } = serde_json::from_slice(&body)?;
+            Ok(GroveGdbUpdate::SubtreeCount(SubtreeCount { path, count, exact }))
+        }
+        ProtocolCommand::Fetch {
+            command: FetchCommand::FindNodeByHash { hash, kind },
+            session_id,
+        } => {
+            log::info!("Looking up a node by its {}...", kind.as_ref().to_lowercase());
+            let body = send_counted(
+                client
+                    .post(format!("{address}find_node_by_hash"))
+                    .json(&WithSession {
+                        session_id,
+                        request: HashLookupRequest { hash, kind },
+                    }),
+            )
+            .await?;
+            let node: Option<NodeUpdate> = serde_json::from_slice(&body)?;
+            Ok(GroveGdbUpdate::HashLookup(HashLookup { hash, kind, node }))
+        }
+        ProtocolCommand::Fetch {
+            command: FetchCommand::FetchReferrers { path, key },
+            session_id,
+        } => {
+            log::info!("Fetching referrers of a node...");
+            let body = send_counted(
+                client
+                    .post(format!("{address}fetch_referrers"))
+                    .json(&WithSession {
+                        session_id,
+                        request: NodeFetchRequest { path, key },
+                    }),
+            )
+            .await?;
+            let referrers: Vec<NodeUpdate> = serde_json::from_slice(&body)?;
+            Ok(GroveGdbUpdate::Node(NodeSource::Referrers, referrers))
         }
         ProtocolCommand::Fetch {
             command: FetchCommand::ProvePathQuery { path_query },
             session_id,
         } => {
             log::info!("Requesting a proof for a path query...");
-            let proof = client
-                .post(format!("{address}prove_path_query"))
-                .json(&WithSession {
+            let payload = post_measured_raw(
+                client,
+                format!("{address}prove_path_query"),
+                &WithSession {
                     session_id,
-                    request: path_query,
-                })
-                .send()
-                .await?
-                .error_for_status()?
-                .json::<grovedbg_types::Proof>()
-                .await?;
+                    request: path_query.clone(),
+                },
+                transfer,
+            )
+            .await?;
+            let proof: grovedbg_types::Proof = serde_json::from_slice(&payload)?;
 
-            let mut proof_tree = ProofTree::new(client, address, proof.clone(), session_id).await?;
-            proof_tree.fetch_additional_data().await?;
+            let proof_tree = match ProofTree::new(client, address, proof.clone(), session_id).await {
+                Ok(mut proof_tree) => proof_tree.fetch_additional_data().await.map(|_| proof_tree),
+                Err(e) => Err(e),
+            };
+            let proof_tree = match proof_tree {
+                Ok(proof_tree) => proof_tree,
+                Err(e) => {
+                    log::error!("Unable to process the proof: {e}");
+                    let failure =
+                        ProofFailure::collect(client, address, session_id, path_query, proof, e.to_string())
+                            .await;
+                    return Ok(GroveGdbUpdate::ProofFailure(Box::new(failure)));
+                }
+            };
 
             let updates = proof_tree
                 .tree
@@ -205,7 +1106,13 @@ async fn process_command(
                 .map(|(k, v)| (k, v.to_proof_tree_data()))
                 .collect();
 
-            Ok(GroveGdbUpdate::Proof(proof, updates, tree_proof_data))
+            Ok(GroveGdbUpdate::Proof(
+                proof,
+                path_query,
+                updates,
+                tree_proof_data,
+                payload,
+            ))
         }
         ProtocolCommand::Fetch {
             command: FetchCommand::FetchWithPathQuery { path_query },
@@ -219,38 +1126,155 @@ async fn process_command(
                     .map(|n| n.to_string())
                     .unwrap_or_else(|| "all".to_owned())
             );
-            Ok(client
-                .post(format!("{address}fetch_with_path_query"))
-                .json(&WithSession {
-                    session_id,
-                    request: path_query,
+            let path = queries_whole_subtree(&path_query).then(|| path_query.path.clone());
+            let (nodes, unparsed) =
+                fetch_path_query(client, address, session_id, path_query, transfer).await?;
+
+            if let (Some(path), true) = (path, nodes.is_empty() && unparsed.is_empty()) {
+                return Ok(GroveGdbUpdate::EmptySubtree(path));
+            }
+
+            if unparsed.is_empty() {
+                Ok(GroveGdbUpdate::Node(NodeSource::Query, nodes))
+            } else {
+                log::warn!("{} nodes of the batch couldn't be parsed", unparsed.len());
+                Ok(GroveGdbUpdate::PartialNodes(nodes, unparsed))
+            }
+        }
+        ProtocolCommand::Fetch {
+            command: FetchCommand::FetchWithPathQueries { path_queries },
+            session_id,
+        } => {
+            log::info!("Running a batch of {} path queries...", path_queries.len());
+            let mut finished = stream::iter(path_queries.into_iter().enumerate())
+                .map(|(index, path_query)| async move {
+                    let mut transfer = None;
+                    let result =
+                        fetch_path_query(client, address, session_id, path_query, &mut transfer).await;
+                    (index, result)
                 })
-                .send()
-                .await?
-                .error_for_status()?
-                .json::<Vec<grovedbg_types::NodeUpdate>>()
-                .await?
-                .into())
+                .buffer_unordered(QUERY_BATCH_CONCURRENCY);
+
+            // Queries may overlap, so nodes are merged by their path and key
+            let mut merged = BTreeMap::new();
+            let mut unparsed = Vec::new();
+            while let Some((index, result)) = finished.next().await {
+                let result = match result {
+                    Ok((nodes, mut query_unparsed)) => {
+                        let count = nodes.len();
+                        merged.extend(
+                            nodes
+                                .into_iter()
+                                .map(|node| ((node.path.clone(), node.key.clone()), node)),
+                        );
+                        unparsed.append(&mut query_unparsed);
+                        Ok(count)
+                    }
+                    Err(e) => Err(ProtocolError::classify(e).to_string()),
+                };
+                // The batch goes on even if the application can't be notified
+                let _ = updates_sender
+                    .send(GroveGdbUpdate::BatchProgress(BatchProgress { index, result }))
+                    .await;
+            }
+
+            let nodes = merged.into_values().collect();
+            if unparsed.is_empty() {
+                Ok(GroveGdbUpdate::Node(NodeSource::Query, nodes))
+            } else {
+                log::warn!("{} nodes of the batch couldn't be parsed", unparsed.len());
+                Ok(GroveGdbUpdate::PartialNodes(nodes, unparsed))
+            }
+        }
+        ProtocolCommand::FetchComparison {
+            session_id,
+            other_session_id,
+            path,
+            key,
+        } => {
+            log::info!("Fetching a node from two sessions for comparison...");
+            let current = fetch_node(client, address, session_id, path.clone(), key.clone()).await?;
+            let other = fetch_node(client, address, other_session_id, path.clone(), key.clone()).await?;
+            Ok(GroveGdbUpdate::Comparison(NodeComparison {
+                path,
+                key,
+                current,
+                other,
+            }))
         }
         ProtocolCommand::NewSession { old_session } => {
+            traffic::reset();
             if let Some(old) = old_session {
                 log::info!("Terminating old session: {}", old);
-                client
-                    .post(format!("{address}drop_session"))
-                    .json(&DropSessionRequest { session_id: old })
-                    .send()
-                    .await?
-                    .error_for_status()?;
+                // An expired session is gone already and shall not block a new one
+                if let Err(e) = drop_session(client, address, old).await {
+                    log::warn!("Unable to terminate old session {old}: {e}");
+                }
             }
             log::info!("Starting new session");
-            let NewSessionResponse { session_id } = client
-                .post(format!("{address}new_session"))
-                .send()
-                .await?
-                .error_for_status()?
-                .json::<NewSessionResponse>()
-                .await?;
-            Ok(GroveGdbUpdate::Session(session_id))
+            let session_id = new_session(client, address).await?;
+            if endpoint_info.is_none() {
+                *endpoint_info = Some(fetch_endpoint_info(client, address).await);
+            }
+            let info = endpoint_info.clone().flatten();
+            Ok(GroveGdbUpdate::Session(session_id, info))
+        }
+        ProtocolCommand::NewComparisonSession => {
+            log::info!("Starting new comparison session");
+            let session_id = new_session(client, address).await?;
+            Ok(GroveGdbUpdate::ComparisonSession(session_id))
+        }
+        ProtocolCommand::DropSession { session_id } => {
+            log::info!("Terminating session: {}", session_id);
+            drop_session(client, address, session_id).await?;
+            Ok(GroveGdbUpdate::SessionDropped)
+        }
+        ProtocolCommand::KeepAlive { session_id } => {
+            fetch_root_node(client, address, session_id).await?;
+            Ok(GroveGdbUpdate::KeepAlive)
+        }
+        ProtocolCommand::Follow { session_id } => {
+            let current = fetch_root_node(client, address, session_id).await?;
+            let latest_session_id = new_session(client, address).await?;
+            let latest = fetch_root_node(client, address, latest_session_id).await?;
+
+            if root_hash_parts(&current) == root_hash_parts(&latest) {
+                drop_session(client, address, latest_session_id).await?;
+                Ok(GroveGdbUpdate::Follow(None))
+            } else {
+                log::info!("GroveDB root hash has changed, following with session {latest_session_id}");
+                drop_session(client, address, session_id).await?;
+                traffic::reset();
+                Ok(GroveGdbUpdate::Follow(Some(latest_session_id)))
+            }
         }
     }
 }
+
+async fn new_session(client: &Client, address: &Url) -> anyhow::Result<SessionId> {
+    let body = send_counted(client.post(format!("{address}new_session"))).await?;
+    let NewSessionResponse { session_id } = serde_json::from_slice(&body)?;
+    Ok(session_id)
+}
+
+async fn drop_session(client: &Client, address: &Url, session_id: SessionId) -> anyhow::Result<()> {
+    send_counted(
+        client
+            .post(format!("{address}drop_session"))
+            .json(&DropSessionRequest { session_id }),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Root hash is derived from these, so comparing them is enough to tell if it
+/// has changed
+fn root_hash_parts(root: &Option<NodeUpdate>) -> Option<(&[u8], Option<&[u8]>, Option<&[u8]>)> {
+    root.as_ref().map(|node| {
+        (
+            node.kv_digest_hash.as_slice(),
+            node.left_merk_hash.as_ref().map(|h| h.as_slice()),
+            node.right_merk_hash.as_ref().map(|h| h.as_slice()),
+        )
+    })
+}
@@ -0,0 +1,184 @@
+//! Desktop only cache of fetched nodes on disk, enabled by setting
+//! `GROVEDBG_CACHE_DIR` env variable.
+//!
+//! Nodes are stored per GroveDB state identified by the root node hashes, so
+//! reopening the app against an unchanged database gets everything fetched
+//! before at once, and the last state can be browsed with GroveDB offline.
+//!
+//! States are written by a separate thread at most once per
+//! [WRITE_INTERVAL], as each write serializes all nodes of the state.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use grovedbg_types::{Key, NodeUpdate, Path};
+use serde::{Deserialize, Serialize};
+
+const CACHE_DIR_ENV: &str = "GROVEDBG_CACHE_DIR";
+const LAST_STATE_FILE: &str = "last";
+const WRITE_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize)]
+struct CachedState {
+    root: NodeUpdate,
+    nodes: Vec<NodeUpdate>,
+}
+
+pub(super) struct DiskCache {
+    dir: PathBuf,
+    state_id: Option<String>,
+    root: Option<NodeUpdate>,
+    nodes: BTreeMap<(Path, Key), NodeUpdate>,
+    /// Nodes were added since the state was last written
+    dirty: bool,
+    last_write: Option<Instant>,
+    writer: mpsc::Sender<(PathBuf, CachedState)>,
+}
+
+impl DiskCache {
+    pub(super) fn from_env() -> Option<Self> {
+        let dir = PathBuf::from(std::env::var_os(CACHE_DIR_ENV)?);
+        if let Err(e) = fs::create_dir_all(&dir) {
+            log::error!("Unable to create cache directory {}: {e}", dir.display());
+            return None;
+        }
+        log::info!("Using disk cache at {}", dir.display());
+
+        let (writer, states) = mpsc::channel::<(PathBuf, CachedState)>();
+        thread::spawn(move || {
+            for (file, state) in states {
+                if let Err(e) = serde_json::to_vec(&state)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|bytes| Ok(fs::write(file, bytes)?))
+                {
+                    log::error!("Unable to write disk cache: {e}");
+                }
+            }
+        });
+
+        Some(DiskCache {
+            dir,
+            state_id: None,
+            root: None,
+            nodes: BTreeMap::new(),
+            dirty: false,
+            last_write: None,
+            writer,
+        })
+    }
+
+    /// Switches the cache to the GroveDB state of the `root` node, returning
+    /// nodes cached for it previously
+    pub(super) fn open_root(&mut self, root: &NodeUpdate) -> Vec<NodeUpdate> {
+        let state_id = state_id(root);
+        if self.state_id.as_ref() == Some(&state_id) {
+            return Vec::new();
+        }
+
+        // Nodes of the previous state are not to be lost to the debounce
+        self.write();
+
+        let cached = self.read_state(&state_id).map(|s| s.nodes).unwrap_or_default();
+        if !cached.is_empty() {
+            log::info!("Restored {} nodes from disk cache", cached.len());
+        }
+
+        if let Err(e) = fs::write(self.dir.join(LAST_STATE_FILE), &state_id) {
+            log::error!("Unable to update disk cache: {e}");
+        }
+
+        self.nodes = cached
+            .iter()
+            .map(|n| ((n.path.clone(), n.key.clone()), n.clone()))
+            .collect();
+        self.root = Some(root.clone());
+        self.state_id = Some(state_id);
+        self.dirty = true;
+        self.write();
+
+        cached
+    }
+
+    /// Loads the last cached GroveDB state, returns its root node and the rest
+    /// of nodes
+    pub(super) fn open_last(&mut self) -> Option<(NodeUpdate, Vec<NodeUpdate>)> {
+        let state_id = fs::read_to_string(self.dir.join(LAST_STATE_FILE)).ok()?;
+        let CachedState { root, nodes } = self.read_state(&state_id)?;
+        Some((root, nodes))
+    }
+
+    /// Adds nodes to the currently opened state, does nothing if no root node
+    /// was fetched yet
+    pub(super) fn store<'a>(&mut self, nodes: impl IntoIterator<Item = &'a NodeUpdate>) {
+        if self.state_id.is_none() {
+            return;
+        }
+
+        for node in nodes {
+            self.nodes
+                .insert((node.path.clone(), node.key.clone()), node.clone());
+            self.dirty = true;
+        }
+        self.write_if_due();
+    }
+
+    /// Writes added nodes unless the state was written recently, expected to
+    /// be called regularly so the last nodes are written eventually
+    pub(super) fn write_if_due(&mut self) {
+        if self.last_write.map_or(true, |at| at.elapsed() >= WRITE_INTERVAL) {
+            self.write();
+        }
+    }
+
+    fn write(&mut self) {
+        let (Some(state_id), Some(root)) = (&self.state_id, &self.root) else {
+            return;
+        };
+        if !self.dirty {
+            return;
+        }
+
+        let state = CachedState {
+            root: root.clone(),
+            nodes: self.nodes.values().cloned().collect(),
+        };
+        if self.writer.send((self.dir.join(state_id), state)).is_err() {
+            log::error!("Disk cache writer has stopped");
+        }
+        self.dirty = false;
+        self.last_write = Some(Instant::now());
+    }
+
+    fn read_state(&self, state_id: &str) -> Option<CachedState> {
+        let bytes = fs::read(self.dir.join(state_id)).ok()?;
+        serde_json::from_slice(&bytes)
+            .inspect_err(|e| log::warn!("Discarding broken disk cache entry {state_id}: {e}"))
+            .ok()
+    }
+}
+
+/// Root node hashes are enough to tell one GroveDB state from another, as the
+/// root hash is computed from them
+fn state_id(root: &NodeUpdate) -> String {
+    [
+        root.kv_digest_hash.as_slice(),
+        root.left_merk_hash
+            .as_ref()
+            .map(|h| h.as_slice())
+            .unwrap_or_default(),
+        root.right_merk_hash
+            .as_ref()
+            .map(|h| h.as_slice())
+            .unwrap_or_default(),
+    ]
+    .into_iter()
+    .map(hex::encode)
+    .collect::<Vec<_>>()
+    .join("_")
+}
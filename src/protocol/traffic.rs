@@ -0,0 +1,121 @@
+//! Requests made to GroveDB's debugger endpoint within the current session,
+//! counted over every HTTP request of the protocol task, to notice when the
+//! debugger itself puts load on a shared endpoint.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::TimeDelta;
+use reqwest::{RequestBuilder, StatusCode};
+
+/// Longest part of an error response body kept to be shown to the user
+const ERROR_BODY_LIMIT: usize = 300;
+
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static LATENCY_MICROS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TrafficStats {
+    pub requests: u64,
+    /// Downloaded response bodies
+    pub bytes: u64,
+    pub total_latency: TimeDelta,
+}
+
+impl TrafficStats {
+    pub fn average_latency(&self) -> Option<TimeDelta> {
+        (self.requests > 0).then(|| self.total_latency / self.requests as i32)
+    }
+}
+
+/// Traffic since the current session was started
+pub fn current() -> TrafficStats {
+    TrafficStats {
+        requests: REQUESTS.load(Ordering::Relaxed),
+        bytes: BYTES.load(Ordering::Relaxed),
+        total_latency: TimeDelta::microseconds(LATENCY_MICROS.load(Ordering::Relaxed) as i64),
+    }
+}
+
+pub(super) fn reset() {
+    REQUESTS.store(0, Ordering::Relaxed);
+    BYTES.store(0, Ordering::Relaxed);
+    LATENCY_MICROS.store(0, Ordering::Relaxed);
+}
+
+fn record(bytes: usize, latency: TimeDelta) {
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+    BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+    LATENCY_MICROS.fetch_add(
+        latency.num_microseconds().unwrap_or_default() as u64,
+        Ordering::Relaxed,
+    );
+}
+
+/// GroveDB responded with an error status, the body usually tells why
+#[derive(Debug, thiserror::Error)]
+#[error("{status}: {body}")]
+pub(super) struct ServerError {
+    pub(super) status: StatusCode,
+    /// Response body text, truncated to [ERROR_BODY_LIMIT] characters
+    pub(super) body: String,
+}
+
+/// Sends the request counting it in the session traffic, returns the response
+/// body
+pub(super) async fn send_counted(request: RequestBuilder) -> anyhow::Result<Vec<u8>> {
+    let started = chrono::Local::now();
+    let result = async {
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+        if status.is_client_error() || status.is_server_error() {
+            return Err(ServerError {
+                status,
+                body: error_body_text(&body),
+            }
+            .into());
+        }
+        anyhow::Ok(body)
+    }
+    .await;
+    record(
+        result.as_ref().map(|body| body.len()).unwrap_or_default(),
+        chrono::Local::now() - started,
+    );
+    result.map(|body| body.to_vec())
+}
+
+fn error_body_text(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    let text = text.trim();
+    match text.char_indices().nth(ERROR_BODY_LIMIT) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn average_latency_of_requests() {
+        let stats = TrafficStats {
+            requests: 4,
+            bytes: 100,
+            total_latency: TimeDelta::milliseconds(200),
+        };
+        assert_eq!(stats.average_latency(), Some(TimeDelta::milliseconds(50)));
+        assert_eq!(TrafficStats::default().average_latency(), None);
+    }
+
+    #[test]
+    fn error_body_is_truncated() {
+        assert_eq!(error_body_text(b"  no such session\n"), "no such session");
+        let long = "é".repeat(ERROR_BODY_LIMIT + 10);
+        let text = error_body_text(long.as_bytes());
+        assert_eq!(text.chars().count(), ERROR_BODY_LIMIT + 1);
+        assert!(text.ends_with('…'));
+    }
+}
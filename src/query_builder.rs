@@ -1,14 +1,25 @@
-use eframe::egui::{self, CollapsingHeader, Color32, Frame, Margin, RadioButton, RichText};
-use grovedbg_types::{PathQuery, Query, QueryItem, SubqueryBranch};
-use integer_encoding::VarInt;
+mod batch;
+mod cost_history;
+mod key_list;
+mod preview;
+mod validation;
+
+use batch::QueryBatch;
+pub(crate) use cost_history::transfer_summary;
+use cost_history::CostHistory;
+use eframe::egui::{self, Button, CollapsingHeader, Color32, Frame, Margin, RadioButton, RichText};
+use grovedbg_types::{PathQuery, Query, QueryItem, SizedQuery, SubqueryBranch};
+use key_list::KeyListInput;
 use strum::IntoEnumIterator;
 
 use crate::{
-    bus::CommandBus,
+    bus::{CommandBus, UserAction},
     bytes_utils::BytesInputVariant,
     path_ctx::{path_label, Path, PathCtx},
     profiles::RootActiveProfileContext,
-    protocol::FetchCommand,
+    protocol::{BatchProgress, Capability, QueryCost},
+    theme::input_error_color,
+    tree_data::TreeData,
 };
 
 const MARGIN: f32 = 20.;
@@ -17,6 +28,11 @@ pub(crate) struct QueryBuilder {
     limit_input: OptionalNumberInput,
     offset_input: OptionalNumberInput,
     query: QueryInput,
+    /// Path the query was last prefilled for, a profile default query is
+    /// applied once a different path is selected
+    prefilled_path: Option<Vec<Vec<u8>>>,
+    costs: CostHistory,
+    batch: QueryBatch,
 }
 
 impl QueryBuilder {
@@ -25,60 +41,174 @@ impl QueryBuilder {
             limit_input: OptionalNumberInput::new("Limit".to_owned()),
             offset_input: OptionalNumberInput::new("Offset".to_owned()),
             query: QueryInput::new(0),
+            prefilled_path: None,
+            costs: Default::default(),
+            batch: Default::default(),
         }
     }
 
-    pub fn draw<'pf>(
+    fn sized_query(&self) -> SizedQuery {
+        SizedQuery {
+            query: self.query.get_query(),
+            limit: self.limit_input.number,
+            offset: self.offset_input.number,
+        }
+    }
+
+    fn prefill(&mut self, sized_query: SizedQuery) {
+        self.limit_input.set(sized_query.limit);
+        self.offset_input.set(sized_query.offset);
+        self.query = QueryInput::from_query(0, sized_query.query);
+    }
+
+    pub fn draw<'pa, 'pf>(
         &mut self,
         ui: &mut egui::Ui,
-        path_ctx: &PathCtx,
+        path_ctx: &'pa PathCtx,
         profile_ctx: RootActiveProfileContext<'pf>,
-        bus: &CommandBus,
+        bus: &CommandBus<'pa>,
+        tree_data: &TreeData<'pa>,
     ) {
         if let Some(path) = path_ctx.get_selected_for_query() {
             let profile_ctx = profile_ctx.fast_forward(path);
+            if self.prefilled_path.as_ref() != Some(&path.to_vec()) {
+                self.prefilled_path = Some(path.to_vec());
+                if let Some(default_query) = profile_ctx.default_query() {
+                    self.prefill(default_query.clone());
+                }
+            }
             path_label(ui, path, &profile_ctx);
             self.limit_input.draw(ui);
             self.offset_input.draw(ui);
             self.query.draw(ui);
 
+            let query = self.query.get_query();
+            preview::draw(ui, &query);
+
+            let warnings = validation::query_warnings(&query, self.limit_input.number, path, tree_data);
+            for warning in warnings.iter() {
+                ui.label(
+                    RichText::new(format!("{} {warning}", egui_phosphor::regular::WARNING))
+                        .color(input_error_color(ui.ctx())),
+                );
+            }
+
             ui.horizontal(|line| {
-                if line.button("Prove").clicked() {
-                    self.prove_query(&path, bus);
+                if line
+                    .add_enabled(bus.supports(Capability::ProvePathQuery), Button::new("Prove"))
+                    .on_disabled_hover_text("GroveDB endpoint doesn't support proofs")
+                    .clicked()
+                {
+                    self.prove_query(&path, bus, false);
+                }
+                if line
+                    .add_enabled(
+                        bus.supports(Capability::ProvePathQuery),
+                        Button::new(format!("{} Prove and verify", egui_phosphor::regular::SEAL_CHECK)),
+                    )
+                    .on_hover_text("Prove the query and verify the proof against the session root hash")
+                    .on_disabled_hover_text("GroveDB endpoint doesn't support proofs")
+                    .clicked()
+                {
+                    self.prove_query(&path, bus, true);
                 }
-                if line.button("Fetch").clicked() {
+                if line
+                    .add_enabled(bus.supports(Capability::FetchWithPathQuery), Button::new("Fetch"))
+                    .on_disabled_hover_text("GroveDB endpoint doesn't support fetching with path queries")
+                    .clicked()
+                {
                     self.fetch_query(&path, bus);
                 }
+                if line
+                    .button(egui_phosphor::regular::PLUS_CIRCLE)
+                    .on_hover_text("Add the query to the batch to run it along with others")
+                    .clicked()
+                {
+                    self.batch.push(PathQuery {
+                        path: path.to_vec(),
+                        query: self.sized_query(),
+                    });
+                }
+                if line
+                    .button(egui_phosphor::regular::BOOKMARK_SIMPLE)
+                    .on_hover_text("Save as the default query of the path's profile entry")
+                    .clicked()
+                {
+                    bus.user_action(UserAction::SetDefaultQuery(path, Some(self.sized_query())));
+                }
+                if line
+                    .button(egui_phosphor::regular::STAR)
+                    .on_hover_text("Add a top panel shortcut running the query to the profile")
+                    .clicked()
+                {
+                    bus.user_action(UserAction::AddShortcut(path, Some(self.sized_query())));
+                }
+                if let Some(cost) = self.costs.last() {
+                    line.label(transfer_summary(&cost.transfer, cost.items))
+                        .on_hover_text(format!("Last {}", cost.command.as_ref().to_lowercase()));
+                }
             });
         } else {
             ui.label("No query path selected, click on a subtree header with path first");
         }
+
+        let batch_load = self.batch.draw(ui, bus);
+        if let Some(path_query) = self.costs.draw(ui).or(batch_load) {
+            path_ctx.add_path(path_query.path.clone()).select_for_query();
+            self.prefilled_path = Some(path_query.path);
+            self.prefill(path_query.query);
+        }
     }
 
-    fn prove_query(&self, path: &Path, bus: &CommandBus) {
+    /// Records the measured cost of a sent path query
+    pub(crate) fn push_cost(&mut self, cost: QueryCost) {
+        self.costs.push(cost);
+    }
+
+    /// Records the outcome of a query of the running batch
+    pub(crate) fn batch_progress(&mut self, progress: BatchProgress) {
+        self.batch.set_progress(progress);
+    }
+
+    /// Replaces the query items with the provided ones, other query parts are
+    /// reset as well
+    pub(crate) fn prefill_items(&mut self, path: Path, items: Vec<QueryItem>) {
+        self.prefilled_path = Some(path.to_vec());
+        self.query = QueryInput::new(0);
+        self.query.items = items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| QueryItemInput::from_query_item(0, i, item))
+            .collect();
+    }
+
+    /// Query for exactly one key, to prove a known element
+    pub(crate) fn prefill_key(&mut self, path: Path, key: Vec<u8>) {
+        self.prefill_items(path, vec![QueryItem::Key(key)]);
+        self.limit_input.set(Some(1));
+        self.offset_input.set(None);
+    }
+
+    fn prove_query(&self, path: &Path, bus: &CommandBus, verify: bool) {
         let path_query = PathQuery {
             path: path.to_vec(),
-            query: grovedbg_types::SizedQuery {
-                query: self.query.get_query(),
-                limit: self.limit_input.number,
-                offset: self.offset_input.number,
-            },
+            query: self.sized_query(),
         };
 
-        bus.fetch_command(FetchCommand::ProvePathQuery { path_query });
+        if verify {
+            bus.fetch().prove_and_verify(path_query);
+        } else {
+            bus.fetch().prove(path_query);
+        }
     }
 
     fn fetch_query(&self, path: &Path, bus: &CommandBus) {
         let path_query = PathQuery {
             path: path.to_vec(),
-            query: grovedbg_types::SizedQuery {
-                query: self.query.get_query(),
-                limit: self.limit_input.number,
-                offset: self.offset_input.number,
-            },
+            query: self.sized_query(),
         };
 
-        bus.fetch_command(FetchCommand::FetchWithPathQuery { path_query });
+        bus.fetch().path_query(path_query);
     }
 }
 
@@ -99,6 +229,12 @@ impl OptionalNumberInput {
         }
     }
 
+    fn set(&mut self, number: Option<u16>) {
+        self.number = number;
+        self.input = number.map(|n| n.to_string()).unwrap_or_default();
+        self.err = false;
+    }
+
     fn draw(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|line| {
             let label = line.label(RichText::new(&self.label).color(if self.err {
@@ -143,6 +279,14 @@ impl BytesInput {
         }
     }
 
+    fn from_bytes(label: String, bytes: Vec<u8>) -> Self {
+        Self {
+            input: hex::encode(&bytes),
+            bytes,
+            ..Self::new(label)
+        }
+    }
+
     fn draw(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|line| {
             let label = line.label(RichText::new(&self.label).color(if self.err {
@@ -161,61 +305,10 @@ impl BytesInput {
 
             if response.lost_focus() {
                 self.err = false;
-                self.bytes = match self.display_variant {
-                    BytesInputVariant::U8 => self
-                        .input
-                        .split_whitespace()
-                        .map(|int| int.parse::<u8>())
-                        .collect::<Result<Vec<u8>, _>>()
-                        .inspect_err(|_| self.err = true)
-                        .unwrap_or_default(),
-                    BytesInputVariant::String => self.input.as_bytes().to_vec(),
-                    BytesInputVariant::Hex => hex::decode(&self.input)
-                        .inspect_err(|_| self.err = true)
-                        .unwrap_or_default(),
-                    BytesInputVariant::VarInt => self
-                        .input
-                        .parse::<i64>()
-                        .map(|int| int.encode_var_vec())
-                        .inspect_err(|_| self.err = true)
-                        .unwrap_or_default(),
-                    BytesInputVariant::I16 => self
-                        .input
-                        .parse::<i16>()
-                        .map(|int| int.to_be_bytes().to_vec())
-                        .inspect_err(|_| self.err = true)
-                        .unwrap_or_default(),
-                    BytesInputVariant::I32 => self
-                        .input
-                        .parse::<i32>()
-                        .map(|int| int.to_be_bytes().to_vec())
-                        .inspect_err(|_| self.err = true)
-                        .unwrap_or_default(),
-                    BytesInputVariant::I64 => self
-                        .input
-                        .parse::<i64>()
-                        .map(|int| int.to_be_bytes().to_vec())
-                        .inspect_err(|_| self.err = true)
-                        .unwrap_or_default(),
-                    BytesInputVariant::U16 => self
-                        .input
-                        .parse::<u16>()
-                        .map(|int| int.to_be_bytes().to_vec())
-                        .inspect_err(|_| self.err = true)
-                        .unwrap_or_default(),
-                    BytesInputVariant::U32 => self
-                        .input
-                        .parse::<u32>()
-                        .map(|int| int.to_be_bytes().to_vec())
-                        .inspect_err(|_| self.err = true)
-                        .unwrap_or_default(),
-                    BytesInputVariant::U64 => self
-                        .input
-                        .parse::<u64>()
-                        .map(|int| int.to_be_bytes().to_vec())
-                        .inspect_err(|_| self.err = true)
-                        .unwrap_or_default(),
-                }
+                self.bytes = self.display_variant.parse(&self.input).unwrap_or_else(|| {
+                    self.err = true;
+                    Vec::new()
+                });
             }
         });
     }
@@ -249,6 +342,40 @@ impl QueryItemInput {
         }
     }
 
+    fn from_query_item(subquery_idx: usize, item_idx: usize, item: QueryItem) -> Self {
+        let input = |label: &str, bytes| BytesInput::from_bytes(label.to_owned(), bytes);
+        let input_type = match item {
+            QueryItem::Key(key) => QueryInputType::Key(input("Key", key)),
+            QueryItem::Range { start, end } => QueryInputType::Range {
+                start: input("Start", start),
+                end: input("End", end),
+            },
+            QueryItem::RangeInclusive { start, end } => QueryInputType::RangeInclusive {
+                start: input("Start", start),
+                end: input("End", end),
+            },
+            QueryItem::RangeFull => QueryInputType::RangeFull,
+            QueryItem::RangeFrom(from) => QueryInputType::RangeFrom(input("From", from)),
+            QueryItem::RangeTo(to) => QueryInputType::RangeTo(input("To", to)),
+            QueryItem::RangeToInclusive(to) => QueryInputType::RangeToInclusive(input("To", to)),
+            QueryItem::RangeAfter(after) => QueryInputType::RangeAfter(input("After", after)),
+            QueryItem::RangeAfterTo { after, to } => QueryInputType::RangeAfterTo {
+                after: input("After", after),
+                to: input("To", to),
+            },
+            QueryItem::RangeAfterToInclusive { after, to } => QueryInputType::RangeAfterToInclusive {
+                after: input("After", after),
+                to: input("To", to),
+            },
+        };
+
+        Self {
+            input_type,
+            subquery_idx,
+            item_idx,
+        }
+    }
+
     fn draw(&mut self, ui: &mut egui::Ui) {
         CollapsingHeader::new("Query item type")
             .id_salt(self.subquery_idx * 1000 + self.item_idx)
@@ -428,6 +555,7 @@ struct QueryInput {
     conditional_subquery_branches: Vec<ConditionalSubqueryBranchInput>,
     left_to_right: bool,
     subquery_idx: usize,
+    key_list: KeyListInput,
 }
 
 impl QueryInput {
@@ -438,9 +566,33 @@ impl QueryInput {
             conditional_subquery_branches: Vec::new(),
             left_to_right: true,
             subquery_idx,
+            key_list: KeyListInput::new(),
         }
     }
 
+    fn from_query(subquery_idx: usize, query: Query) -> Self {
+        let mut input = Self::new(subquery_idx);
+        input.items = query
+            .items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| QueryItemInput::from_query_item(subquery_idx, i, item))
+            .collect();
+        input.default_subquery_branch =
+            SubqueryBranchInput::from_subquery_branch(subquery_idx + 1, query.default_subquery_branch);
+        let branches_idx = subquery_idx + input.default_subquery_branch.iter().count();
+        input.conditional_subquery_branches = query
+            .conditional_subquery_branches
+            .into_iter()
+            .enumerate()
+            .map(|(i, (item, branch))| {
+                ConditionalSubqueryBranchInput::from_pair(branches_idx + i, item, branch)
+            })
+            .collect();
+        input.left_to_right = query.left_to_right;
+        input
+    }
+
     fn draw(&mut self, ui: &mut egui::Ui) {
         ui.checkbox(&mut self.left_to_right, "Left to right");
         ui.horizontal(|line| {
@@ -453,6 +605,19 @@ impl QueryInput {
                 self.items.pop();
             }
         });
+        CollapsingHeader::new("Paste keys")
+            .id_salt(("paste_keys", self.subquery_idx))
+            .show(ui, |collapsing| {
+                if let Some(keys) = self.key_list.draw(collapsing, self.subquery_idx) {
+                    for key in keys {
+                        self.items.push(QueryItemInput::from_query_item(
+                            self.subquery_idx,
+                            self.items.len(),
+                            QueryItem::Key(key),
+                        ));
+                    }
+                }
+            });
         for item in self.items.iter_mut() {
             item.draw(ui);
         }
@@ -540,6 +705,15 @@ impl SubqueryBranchInput {
         }
     }
 
+    /// `None` if the branch has no subquery
+    fn from_subquery_branch(subquery_idx: usize, branch: SubqueryBranch) -> Option<Self> {
+        let subquery = branch.subquery?;
+        Some(Self {
+            relative_path: PathInput::from_path(branch.subquery_path.unwrap_or_default()),
+            subquery: Box::new(QueryInput::from_query(subquery_idx, *subquery)),
+        })
+    }
+
     fn draw(&mut self, ui: &mut egui::Ui) {
         ui.vertical(|layout| {
             self.relative_path.draw(layout);
@@ -568,6 +742,14 @@ impl ConditionalSubqueryBranchInput {
         }
     }
 
+    fn from_pair(subquery_idx: usize, item: QueryItem, branch: SubqueryBranch) -> Self {
+        Self {
+            query_item: QueryItemInput::from_query_item(subquery_idx * 10, 0, item),
+            subquery_branch: SubqueryBranchInput::from_subquery_branch(subquery_idx * 100, branch)
+                .unwrap_or_else(|| SubqueryBranchInput::new(subquery_idx * 100)),
+        }
+    }
+
     fn draw(&mut self, ui: &mut egui::Ui) {
         ui.label("Condition:");
         self.query_item.draw(ui);
@@ -592,6 +774,16 @@ impl PathInput {
         Self { path: Vec::new() }
     }
 
+    fn from_path(path: Vec<Vec<u8>>) -> Self {
+        Self {
+            path: path
+                .into_iter()
+                .enumerate()
+                .map(|(i, segment)| BytesInput::from_bytes(i.to_string(), segment))
+                .collect(),
+        }
+    }
+
     fn draw(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|line| {
             line.label("Path");
@@ -0,0 +1,151 @@
+//! Path queries queued in the builder and run as one batch, the protocol task
+//! runs them concurrently and reports each one as it finishes, to populate a
+//! working set at the start of a session.
+
+use eframe::egui::{self, RichText};
+use grovedbg_types::PathQuery;
+
+use crate::{
+    bus::CommandBus,
+    path_ctx::path_as_json_hex,
+    protocol::{BatchProgress, Capability},
+    theme::{input_error_color, success_color},
+};
+
+enum QueryStatus {
+    Queued,
+    Running,
+    /// Number of returned nodes
+    Done(usize),
+    Failed(String),
+}
+
+struct QueuedQuery {
+    path_query: PathQuery,
+    status: QueryStatus,
+}
+
+#[derive(Default)]
+pub(super) struct QueryBatch {
+    queries: Vec<QueuedQuery>,
+}
+
+impl QueryBatch {
+    pub(super) fn push(&mut self, path_query: PathQuery) {
+        self.queries.push(QueuedQuery {
+            path_query,
+            status: QueryStatus::Queued,
+        });
+    }
+
+    pub(super) fn set_progress(&mut self, progress: BatchProgress) {
+        // The queue could have been cleared while the batch was running
+        if let Some(query) = self.queries.get_mut(progress.index) {
+            query.status = match progress.result {
+                Ok(count) => QueryStatus::Done(count),
+                Err(e) => QueryStatus::Failed(e),
+            };
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.queries
+            .iter()
+            .any(|query| matches!(query.status, QueryStatus::Running))
+    }
+
+    /// Draws queued queries with their progress, returns a query picked to be
+    /// loaded back into the builder
+    pub(super) fn draw(&mut self, ui: &mut egui::Ui, bus: &CommandBus) -> Option<PathQuery> {
+        let mut load = None;
+        let running = self.is_running();
+        egui::CollapsingHeader::new(format!("Query batch ({})", self.queries.len())).show(ui, |collapsing| {
+            collapsing.horizontal(|line| {
+                if line
+                    .add_enabled(
+                        !self.queries.is_empty() && bus.supports(Capability::FetchWithPathQuery),
+                        egui::Button::new(format!("{} Run batch", egui_phosphor::regular::PLAY)),
+                    )
+                    .on_hover_text("Fetch all queued queries concurrently")
+                    .clicked()
+                {
+                    self.queries
+                        .iter_mut()
+                        .for_each(|query| query.status = QueryStatus::Running);
+                    bus.fetch()
+                        .path_queries(self.queries.iter().map(|q| q.path_query.clone()).collect());
+                }
+                if line
+                    .add_enabled(!self.queries.is_empty(), egui::Button::new("Clear"))
+                    .clicked()
+                {
+                    self.queries.clear();
+                }
+                let done = self
+                    .queries
+                    .iter()
+                    .filter(|query| !matches!(query.status, QueryStatus::Queued | QueryStatus::Running))
+                    .count();
+                if running {
+                    line.spinner();
+                    line.label(format!("{done} / {} finished", self.queries.len()));
+                }
+            });
+
+            let mut remove = None;
+            for (i, query) in self.queries.iter().enumerate() {
+                collapsing.horizontal(|line| {
+                    if line
+                        .add_enabled(!running, egui::Button::new(egui_phosphor::regular::TRASH))
+                        .on_hover_text("Remove from the batch")
+                        .clicked()
+                    {
+                        remove = Some(i);
+                    }
+                    if line
+                        .button(egui_phosphor::regular::ARROW_COUNTER_CLOCKWISE)
+                        .on_hover_text("Load the query into the builder")
+                        .clicked()
+                    {
+                        load = Some(query.path_query.clone());
+                    }
+                    line.label(format!(
+                        "{} · {} items, limit {}",
+                        path_as_json_hex(&query.path_query.path),
+                        query.path_query.query.query.items.len(),
+                        query
+                            .path_query
+                            .query
+                            .limit
+                            .map(|l| l.to_string())
+                            .unwrap_or_else(|| "none".to_owned()),
+                    ))
+                    .on_hover_text(serde_json::to_string_pretty(&query.path_query).unwrap_or_default());
+                    match &query.status {
+                        QueryStatus::Queued => {}
+                        QueryStatus::Running => {
+                            line.spinner();
+                        }
+                        QueryStatus::Done(count) => {
+                            line.label(
+                                RichText::new(format!("{} {count} nodes", egui_phosphor::regular::CHECK))
+                                    .color(success_color(line.ctx())),
+                            );
+                        }
+                        QueryStatus::Failed(e) => {
+                            line.label(
+                                RichText::new(egui_phosphor::regular::X_CIRCLE)
+                                    .color(input_error_color(line.ctx())),
+                            )
+                            .on_hover_text(e);
+                        }
+                    }
+                });
+            }
+            if let Some(i) = remove {
+                self.queries.remove(i);
+            }
+        });
+        load
+    }
+}
@@ -0,0 +1,78 @@
+//! Measured costs of path queries, to compare different query shapes against
+//! the same data.
+
+use std::collections::VecDeque;
+
+use eframe::egui;
+use grovedbg_types::PathQuery;
+
+use crate::{
+    budget::format_size,
+    protocol::{QueryCost, Transfer},
+};
+
+const MAX_COSTS: usize = 50;
+
+#[derive(Default)]
+pub(super) struct CostHistory {
+    /// Oldest first
+    costs: VecDeque<QueryCost>,
+}
+
+impl CostHistory {
+    pub(super) fn push(&mut self, cost: QueryCost) {
+        if self.costs.len() >= MAX_COSTS {
+            self.costs.pop_front();
+        }
+        self.costs.push_back(cost);
+    }
+
+    pub(super) fn last(&self) -> Option<&QueryCost> {
+        self.costs.back()
+    }
+
+    /// Draws costs newest first, returns a query picked to be loaded back into
+    /// the builder
+    pub(super) fn draw(&self, ui: &mut egui::Ui) -> Option<PathQuery> {
+        let mut load = None;
+        egui::CollapsingHeader::new(format!("Query history ({})", self.costs.len())).show(ui, |collapsing| {
+            for cost in self.costs.iter().rev() {
+                collapsing.horizontal(|line| {
+                    if line
+                        .button(egui_phosphor::regular::ARROW_COUNTER_CLOCKWISE)
+                        .on_hover_text("Load the query into the builder")
+                        .clicked()
+                    {
+                        load = Some(cost.path_query.clone());
+                    }
+                    let query = &cost.path_query.query;
+                    line.label(format!(
+                        "{} · {} items, limit {} · {}",
+                        cost.command.as_ref(),
+                        query.query.items.len(),
+                        query
+                            .limit
+                            .map(|l| l.to_string())
+                            .unwrap_or_else(|| "none".to_owned()),
+                        transfer_summary(&cost.transfer, cost.items)
+                    ))
+                    .on_hover_text(serde_json::to_string_pretty(&cost.path_query).unwrap_or_default());
+                });
+            }
+        });
+        load
+    }
+}
+
+/// Round trip, payload size and number of returned nodes
+pub(crate) fn transfer_summary(transfer: &Transfer, items: Option<usize>) -> String {
+    let mut summary = format!(
+        "{} ms · {}",
+        transfer.round_trip.num_milliseconds(),
+        format_size(transfer.payload_bytes)
+    );
+    if let Some(items) = items {
+        summary.push_str(&format!(" · {items} nodes"));
+    }
+    summary
+}
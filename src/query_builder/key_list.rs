@@ -0,0 +1,108 @@
+//! Bulk input of query keys, to prove a batch of known IDs without adding
+//! items one by one.
+
+use eframe::egui::{self, ComboBox, RichText, TextEdit};
+use strum::IntoEnumIterator;
+
+use crate::{bytes_utils::BytesInputVariant, theme::input_error_color};
+
+pub(super) struct KeyListInput {
+    input: String,
+    variant: BytesInputVariant,
+    /// Lines that couldn't be parsed on the last attempt, 1-based
+    bad_lines: Vec<usize>,
+}
+
+impl KeyListInput {
+    pub(super) fn new() -> Self {
+        Self {
+            input: String::new(),
+            variant: BytesInputVariant::Base58,
+            bad_lines: Vec::new(),
+        }
+    }
+
+    /// Draws the input, returns parsed keys once they are added
+    pub(super) fn draw(&mut self, ui: &mut egui::Ui, id_salt: usize) -> Option<Vec<Vec<u8>>> {
+        ui.add(
+            TextEdit::multiline(&mut self.input)
+                .hint_text("One key per line")
+                .desired_rows(3),
+        );
+
+        let mut added = None;
+        ui.horizontal(|line| {
+            ComboBox::from_id_salt(("key_list_variant", id_salt))
+                .selected_text(self.variant.as_ref())
+                .show_ui(line, |combo| {
+                    for variant in BytesInputVariant::iter() {
+                        combo.selectable_value(&mut self.variant, variant, variant.as_ref());
+                    }
+                });
+            if line
+                .add_enabled(!self.input.trim().is_empty(), egui::Button::new("Add keys"))
+                .clicked()
+            {
+                match parse_keys(&self.input, self.variant) {
+                    Ok(keys) => {
+                        self.input.clear();
+                        self.bad_lines.clear();
+                        added = Some(keys);
+                    }
+                    Err(bad_lines) => self.bad_lines = bad_lines,
+                }
+            }
+        });
+
+        if !self.bad_lines.is_empty() {
+            let lines: Vec<_> = self.bad_lines.iter().map(|l| l.to_string()).collect();
+            ui.label(
+                RichText::new(format!(
+                    "Lines {} are not valid {}",
+                    lines.join(", "),
+                    self.variant.as_ref()
+                ))
+                .color(input_error_color(ui.ctx())),
+            );
+        }
+
+        added
+    }
+}
+
+/// Parses non-empty lines as keys, returns numbers of lines that couldn't be
+/// parsed if any
+fn parse_keys(input: &str, variant: BytesInputVariant) -> Result<Vec<Vec<u8>>, Vec<usize>> {
+    let mut keys = Vec::new();
+    let mut bad_lines = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match variant.parse(line) {
+            Some(key) => keys.push(key),
+            None => bad_lines.push(i + 1),
+        }
+    }
+    if bad_lines.is_empty() {
+        Ok(keys)
+    } else {
+        Err(bad_lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keys_per_line() {
+        assert_eq!(
+            parse_keys("0102\n\n  ff  \n", BytesInputVariant::Hex),
+            Ok(vec![vec![1, 2], vec![255]])
+        );
+        assert_eq!(parse_keys("0102\nzz\n03", BytesInputVariant::Hex), Err(vec![2]));
+        assert_eq!(parse_keys("2g", BytesInputVariant::Base58), Ok(vec![vec![97]]));
+    }
+}
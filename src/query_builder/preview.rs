@@ -0,0 +1,140 @@
+//! Read-only outline of the query being built, so the structure of nested
+//! subqueries can be checked at a glance.
+
+use eframe::egui::{self, CollapsingHeader, RichText};
+use grovedbg_types::{Query, QueryItem, SubqueryBranch};
+
+use crate::bytes_utils::{bytes_by_display_variant, BytesDisplayVariant};
+
+/// Indentation per level of nesting
+const INDENT: f32 = 14.;
+
+fn bytes(bytes: &[u8]) -> String {
+    bytes_by_display_variant(bytes, &BytesDisplayVariant::guess(bytes))
+}
+
+/// Query item in interval notation
+fn item_text(item: &QueryItem) -> String {
+    match item {
+        QueryItem::Key(key) => bytes(key),
+        QueryItem::Range { start, end } => format!("[{}, {})", bytes(start), bytes(end)),
+        QueryItem::RangeInclusive { start, end } => format!("[{}, {}]", bytes(start), bytes(end)),
+        QueryItem::RangeFull => "all keys".to_owned(),
+        QueryItem::RangeFrom(start) => format!("[{}, ...)", bytes(start)),
+        QueryItem::RangeTo(end) => format!("(..., {})", bytes(end)),
+        QueryItem::RangeToInclusive(end) => format!("(..., {}]", bytes(end)),
+        QueryItem::RangeAfter(after) => format!("({}, ...)", bytes(after)),
+        QueryItem::RangeAfterTo { after, to } => format!("({}, {})", bytes(after), bytes(to)),
+        QueryItem::RangeAfterToInclusive { after, to } => format!("({}, {}]", bytes(after), bytes(to)),
+    }
+}
+
+/// Lines of the outline with their nesting depth
+fn outline(query: &Query) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    push_query(query, 0, &mut lines);
+    lines
+}
+
+fn push_query(query: &Query, depth: usize, lines: &mut Vec<(usize, String)>) {
+    let direction = if query.left_to_right {
+        "left to right"
+    } else {
+        "right to left"
+    };
+    if query.items.is_empty() {
+        lines.push((depth, format!("No items, {direction}")));
+    } else {
+        lines.push((depth, format!("Items, {direction}:")));
+        lines.extend(query.items.iter().map(|item| (depth + 1, item_text(item))));
+    }
+
+    for (item, branch) in query.conditional_subquery_branches.iter() {
+        lines.push((depth, format!("When {}:", item_text(item))));
+        push_branch(branch, depth + 1, lines);
+    }
+
+    if !query.conditional_subquery_branches.is_empty() {
+        lines.push((depth, "Otherwise:".to_owned()));
+        push_branch(&query.default_subquery_branch, depth + 1, lines);
+    } else if has_subquery(&query.default_subquery_branch) {
+        lines.push((depth, "For each subtree:".to_owned()));
+        push_branch(&query.default_subquery_branch, depth + 1, lines);
+    }
+}
+
+fn has_subquery(branch: &SubqueryBranch) -> bool {
+    branch.subquery_path.is_some() || branch.subquery.is_some()
+}
+
+fn push_branch(branch: &SubqueryBranch, depth: usize, lines: &mut Vec<(usize, String)>) {
+    if let Some(path) = &branch.subquery_path {
+        lines.push((
+            depth,
+            format!(
+                "Go to [{}]",
+                path.iter().map(|s| bytes(s)).collect::<Vec<_>>().join(", ")
+            ),
+        ));
+    }
+    match &branch.subquery {
+        Some(subquery) => push_query(subquery, depth, lines),
+        None if branch.subquery_path.is_some() => lines.push((depth, "Take the element".to_owned())),
+        None => lines.push((depth, "No subquery".to_owned())),
+    }
+}
+
+pub(super) fn draw(ui: &mut egui::Ui, query: &Query) {
+    CollapsingHeader::new("Structure preview").show(ui, |collapsing| {
+        for (depth, line) in outline(query) {
+            collapsing.horizontal(|line_ui| {
+                line_ui.add_space(depth as f32 * INDENT);
+                line_ui.label(RichText::new(line).monospace());
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(items: Vec<QueryItem>, default_subquery_branch: SubqueryBranch) -> Query {
+        Query {
+            items,
+            default_subquery_branch,
+            conditional_subquery_branches: Vec::new(),
+            left_to_right: true,
+        }
+    }
+
+    #[test]
+    fn nested_subquery_is_indented() {
+        let subquery = query(
+            vec![QueryItem::RangeFull],
+            SubqueryBranch {
+                subquery_path: None,
+                subquery: None,
+            },
+        );
+        let outer = query(
+            vec![QueryItem::Key(b"abc".to_vec())],
+            SubqueryBranch {
+                subquery_path: Some(vec![b"def".to_vec()]),
+                subquery: Some(Box::new(subquery)),
+            },
+        );
+
+        assert_eq!(
+            outline(&outer),
+            vec![
+                (0, "Items, left to right:".to_owned()),
+                (1, "str: abc".to_owned()),
+                (0, "For each subtree:".to_owned()),
+                (1, "Go to [str: def]".to_owned()),
+                (1, "Items, left to right:".to_owned()),
+                (2, "all keys".to_owned()),
+            ]
+        );
+    }
+}
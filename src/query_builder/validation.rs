@@ -0,0 +1,162 @@
+//! Checks of a query against locally known data to warn about queries that
+//! would silently return nothing.
+
+use std::collections::BTreeSet;
+
+use grovedbg_types::{Query, QueryItem};
+
+use crate::{
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    path_ctx::Path,
+    tree_data::TreeData,
+};
+
+pub(super) fn query_warnings<'pa>(
+    query: &Query,
+    limit: Option<u16>,
+    path: Path<'pa>,
+    tree_data: &TreeData<'pa>,
+) -> BTreeSet<String> {
+    let mut warnings = BTreeSet::new();
+
+    if limit == Some(0) {
+        warnings.insert("Limit is zero, nothing will be returned".to_owned());
+    }
+
+    check_items(query, &mut warnings);
+    check_subquery_paths(query, path, tree_data, &mut warnings);
+
+    warnings
+}
+
+/// Checks query items of the query and its subqueries for empty ranges
+fn check_items(query: &Query, warnings: &mut BTreeSet<String>) {
+    let conditions = query.conditional_subquery_branches.iter().map(|(item, _)| item);
+    for (i, item) in query.items.iter().chain(conditions).enumerate() {
+        if is_empty_range(item) {
+            warnings.insert(format!("Query item {i} is an empty range"));
+        }
+    }
+
+    let subqueries = query
+        .conditional_subquery_branches
+        .iter()
+        .map(|(_, branch)| branch)
+        .chain([&query.default_subquery_branch])
+        .filter_map(|branch| branch.subquery.as_deref());
+    for subquery in subqueries {
+        check_items(subquery, warnings);
+    }
+}
+
+/// Follows subquery paths from fetched child subtrees matched by the query
+/// and reports segments not found among fetched elements
+fn check_subquery_paths<'pa>(
+    query: &Query,
+    path: Path<'pa>,
+    tree_data: &TreeData<'pa>,
+    warnings: &mut BTreeSet<String>,
+) {
+    let Some(children): Option<Vec<_>> = tree_data.get(&path).map(|subtree| {
+        subtree
+            .subtree_keys
+            .iter()
+            .filter(|key| query.items.iter().any(|item| item_contains(item, key)))
+            .cloned()
+            .collect()
+    }) else {
+        return;
+    };
+
+    for key in children {
+        let branch = query
+            .conditional_subquery_branches
+            .iter()
+            .find(|(item, _)| item_contains(item, &key))
+            .map(|(_, branch)| branch)
+            .unwrap_or(&query.default_subquery_branch);
+
+        let mut current = path.child(key);
+        let mut resolved = true;
+        for segment in branch.subquery_path.iter().flatten() {
+            let Some(subtree) = tree_data.get(&current).filter(|s| !s.elements.is_empty()) else {
+                // Nothing is known about this subtree
+                resolved = false;
+                break;
+            };
+            if !subtree.elements.contains_key(segment) {
+                warnings.insert(format!(
+                    "Subquery path key {} was not found in fetched subtree {}",
+                    bytes_by_display_variant(segment, &BytesDisplayVariant::guess(segment)),
+                    current
+                        .for_last_segment(|s| s.view_by_display())
+                        .unwrap_or_default(),
+                ));
+                resolved = false;
+                break;
+            }
+            drop(subtree);
+            current = current.child(segment.clone());
+        }
+
+        if let (true, Some(subquery)) = (resolved, &branch.subquery) {
+            check_subquery_paths(subquery, current, tree_data, warnings);
+        }
+    }
+}
+
+fn is_empty_range(item: &QueryItem) -> bool {
+    match item {
+        QueryItem::Range { start, end } => start >= end,
+        QueryItem::RangeInclusive { start, end } => start > end,
+        QueryItem::RangeAfterTo { after, to } | QueryItem::RangeAfterToInclusive { after, to } => after >= to,
+        _ => false,
+    }
+}
+
+fn item_contains(item: &QueryItem, key: &[u8]) -> bool {
+    match item {
+        QueryItem::Key(k) => k.as_slice() == key,
+        QueryItem::Range { start, end } => start.as_slice() <= key && key < end.as_slice(),
+        QueryItem::RangeInclusive { start, end } => start.as_slice() <= key && key <= end.as_slice(),
+        QueryItem::RangeFull => true,
+        QueryItem::RangeFrom(start) => start.as_slice() <= key,
+        QueryItem::RangeTo(end) => key < end.as_slice(),
+        QueryItem::RangeToInclusive(end) => key <= end.as_slice(),
+        QueryItem::RangeAfter(after) => after.as_slice() < key,
+        QueryItem::RangeAfterTo { after, to } => after.as_slice() < key && key < to.as_slice(),
+        QueryItem::RangeAfterToInclusive { after, to } => after.as_slice() < key && key <= to.as_slice(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_ranges() {
+        assert!(is_empty_range(&QueryItem::Range {
+            start: vec![1],
+            end: vec![1],
+        }));
+        assert!(!is_empty_range(&QueryItem::RangeInclusive {
+            start: vec![1],
+            end: vec![1],
+        }));
+        assert!(is_empty_range(&QueryItem::RangeAfterToInclusive {
+            after: vec![2],
+            to: vec![1],
+        }));
+    }
+
+    #[test]
+    fn items_contain_keys() {
+        let item = QueryItem::RangeAfterToInclusive {
+            after: vec![1],
+            to: vec![3],
+        };
+        assert!(!item_contains(&item, &[1]));
+        assert!(item_contains(&item, &[3]));
+        assert!(item_contains(&QueryItem::RangeFull, &[]));
+    }
+}
@@ -0,0 +1,120 @@
+//! Element bytes exactly as stored by Merk shown next to the decoded element,
+//! to debug encoding issues.
+
+use eframe::egui::{self, RichText, ScrollArea};
+
+use crate::{
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    protocol::RawNode,
+};
+
+const BYTES_PER_ROW: usize = 16;
+
+pub(crate) struct RawNodeView {
+    raw_node: RawNode,
+}
+
+impl RawNodeView {
+    pub(crate) fn new(raw_node: RawNode) -> Self {
+        Self { raw_node }
+    }
+
+    pub(crate) fn draw(&self, ui: &mut egui::Ui) {
+        let RawNode {
+            path,
+            key,
+            bytes,
+            decoded,
+        } = &self.raw_node;
+
+        ui.label(format!(
+            "Path: [{}]",
+            path.iter().map(hex::encode).collect::<Vec<_>>().join(", ")
+        ));
+        ui.label(format!(
+            "Key: {}",
+            bytes_by_display_variant(key, &BytesDisplayVariant::guess(key))
+        ));
+        ui.separator();
+
+        let Some(bytes) = bytes else {
+            ui.label("GroveDB returned no stored bytes for the key");
+            return;
+        };
+
+        ui.horizontal(|line| {
+            line.label(format!("Stored: {} bytes", bytes.len()));
+            if line
+                .button(egui_phosphor::regular::COPY)
+                .on_hover_text("Copy as hex")
+                .clicked()
+            {
+                line.ctx().copy_text(hex::encode(bytes));
+            }
+        });
+        if let Some(version) = bytes.first() {
+            ui.label(format!("Serialization version byte: 0x{version:02x}"))
+                .on_hover_text("The leading byte of the serialized element");
+        }
+
+        ui.columns(2, |columns| {
+            columns[0].label(RichText::new("Stored bytes").strong());
+            ScrollArea::vertical()
+                .id_salt("raw_node_bytes")
+                .show(&mut columns[0], |scroll| {
+                    scroll.label(RichText::new(hex_dump(bytes)).monospace());
+                });
+
+            columns[1].label(RichText::new("Decoded element").strong());
+            ScrollArea::vertical().id_salt("raw_node_decoded").show(
+                &mut columns[1],
+                |scroll| match decoded {
+                    Some(node) => {
+                        scroll.label(RichText::new(format!("{:#?}", node.element)).monospace());
+                    }
+                    None => {
+                        scroll.label("No node returned");
+                    }
+                },
+            );
+        });
+    }
+}
+
+/// Offset, hex and ASCII columns, one row per [BYTES_PER_ROW] bytes
+fn hex_dump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(BYTES_PER_ROW)
+        .enumerate()
+        .map(|(i, row)| {
+            let hex: Vec<_> = row.iter().map(|b| format!("{b:02x}")).collect();
+            let ascii: String = row
+                .iter()
+                .map(|b| if b.is_ascii_graphic() { *b as char } else { '.' })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  {ascii}",
+                i * BYTES_PER_ROW,
+                hex.join(" "),
+                width = BYTES_PER_ROW * 3 - 1
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_dump_rows() {
+        let dump = hex_dump(b"0123456789abcdefXY\x00");
+        let rows: Vec<_> = dump.lines().collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].starts_with("00000000  30 31"));
+        assert!(rows[0].ends_with("0123456789abcdef"));
+        assert!(rows[1].starts_with("00000010  58 59 00 "));
+        assert!(rows[1].ends_with("XY."));
+    }
+}
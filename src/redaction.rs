@@ -0,0 +1,27 @@
+//! Redaction mode for screen sharing: item values are masked in every view
+//! while keys, types and hashes stay visible. Profile entries can exempt
+//! subtrees from it.
+
+use eframe::egui::{self, RichText};
+
+const REDACTION_ID: &'static str = "redaction";
+
+pub(crate) fn enabled(ctx: &egui::Context) -> bool {
+    ctx.data(|data| data.get_temp::<bool>(egui::Id::new(REDACTION_ID)))
+        .unwrap_or(false)
+}
+
+pub(crate) fn set_enabled(ctx: &egui::Context, enabled: bool) {
+    ctx.data_mut(|data| data.insert_temp(egui::Id::new(REDACTION_ID), enabled));
+}
+
+/// Drawn in place of a masked value, only its size is shown
+pub(crate) fn draw_redacted(ui: &mut egui::Ui, len: usize) {
+    ui.label(
+        RichText::new(format!(
+            "{} Value redacted, {len} bytes",
+            egui_phosphor::regular::EYE_SLASH
+        ))
+        .weak(),
+    );
+}
@@ -0,0 +1,235 @@
+//! Replay of a GroveDB operation log over the loaded data, so the evolution
+//! of the state can be watched without a live database. Operations are
+//! applied as simulated node updates: hashes of touched nodes are dropped
+//! since they can't be recomputed without the rest of the Merk tree.
+
+use eframe::egui::{self, DragValue, TextEdit};
+use grovedbg_types::{Element, Key};
+use serde::Deserialize;
+
+use crate::{
+    bus::{CommandBus, UserAction},
+    path_ctx::PathCtx,
+    tree_data::TreeData,
+};
+
+const DEFAULT_SPEED: f64 = 2.;
+
+/// A line of the operation log, elements use the same JSON layout as
+/// snapshots
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub(crate) enum Operation {
+    Insert {
+        path: Vec<Vec<u8>>,
+        key: Key,
+        element: Element,
+    },
+    Delete {
+        path: Vec<Vec<u8>>,
+        key: Key,
+    },
+}
+
+impl Operation {
+    fn path(&self) -> &[Vec<u8>] {
+        match self {
+            Operation::Insert { path, .. } | Operation::Delete { path, .. } => path,
+        }
+    }
+
+    fn key(&self) -> &Key {
+        match self {
+            Operation::Insert { key, .. } | Operation::Delete { key, .. } => key,
+        }
+    }
+}
+
+/// Parses a JSON-lines operation log, blank lines are skipped
+pub(crate) fn parse_log(text: &str) -> Result<Vec<Operation>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {e}", idx + 1)))
+        .collect()
+}
+
+pub(crate) struct Replay {
+    operations: Vec<Operation>,
+    /// Index of the next operation to apply
+    position: usize,
+    playing: bool,
+    /// Operations applied per second while playing
+    speed: f64,
+    last_step_time: f64,
+    /// Focus the subtree of each applied operation
+    follow: bool,
+    pasted: String,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            operations: Vec::new(),
+            position: 0,
+            playing: false,
+            speed: DEFAULT_SPEED,
+            last_step_time: 0.,
+            follow: true,
+            pasted: String::new(),
+        }
+    }
+}
+
+impl Replay {
+    pub(crate) fn load(&mut self, operations: Vec<Operation>) {
+        log::info!("Loaded {} operations to replay", operations.len());
+        self.operations = operations;
+        self.position = 0;
+        self.playing = false;
+    }
+
+    pub(crate) fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Seconds between operations while playing
+    pub(crate) fn step_interval(&self) -> f64 {
+        1. / self.speed
+    }
+
+    fn step<'pa>(&mut self, tree_data: &mut TreeData<'pa>, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+        let Some(operation) = self.operations.get(self.position) else {
+            self.playing = false;
+            return;
+        };
+        let path = path_ctx.add_path(operation.path().to_vec());
+        let key = operation.key().clone();
+        match operation {
+            Operation::Insert { element, .. } => {
+                tree_data.simulate_insert(path, key.clone(), element.clone())
+            }
+            Operation::Delete { .. } => tree_data.delete_element(path, key.clone()),
+        }
+        if self.follow {
+            bus.user_action(UserAction::FocusSubtreeKey(path, key));
+        }
+
+        self.position += 1;
+        if self.position == self.operations.len() {
+            self.playing = false;
+        }
+    }
+
+    /// Applies the next operation once it's time to while playing
+    pub(crate) fn process<'pa>(
+        &mut self,
+        time: f64,
+        tree_data: &mut TreeData<'pa>,
+        bus: &CommandBus<'pa>,
+        path_ctx: &'pa PathCtx,
+    ) {
+        if self.playing && time - self.last_step_time >= self.step_interval() {
+            self.last_step_time = time;
+            self.step(tree_data, bus, path_ctx);
+        }
+    }
+
+    pub(crate) fn draw<'pa>(
+        &mut self,
+        ui: &mut egui::Ui,
+        tree_data: &mut TreeData<'pa>,
+        bus: &CommandBus<'pa>,
+        path_ctx: &'pa PathCtx,
+    ) {
+        ui.label("Paste a JSON-lines log of inserts and deletes, or drop its file onto the window:");
+        ui.add(
+            TextEdit::multiline(&mut self.pasted)
+                .code_editor()
+                .desired_rows(4)
+                .hint_text(r#"{"op": "delete", "path": [[32]], "key": [1, 2, 3]}"#),
+        );
+        if ui
+            .add_enabled(!self.pasted.trim().is_empty(), egui::Button::new("Load"))
+            .clicked()
+        {
+            match parse_log(&self.pasted) {
+                Ok(operations) => {
+                    self.load(operations);
+                    self.pasted.clear();
+                }
+                Err(e) => log::error!("Unable to parse the operation log: {e}"),
+            }
+        }
+        ui.separator();
+
+        if self.operations.is_empty() {
+            ui.label("No operations loaded");
+            return;
+        }
+
+        let finished = self.position == self.operations.len();
+        ui.horizontal(|line| {
+            let play_icon = if self.playing {
+                egui_phosphor::regular::PAUSE
+            } else {
+                egui_phosphor::regular::PLAY
+            };
+            if line
+                .add_enabled(!finished, egui::Button::new(play_icon))
+                .on_hover_text("Play or pause the replay")
+                .clicked()
+            {
+                self.playing = !self.playing;
+            }
+            if line
+                .add_enabled(
+                    !finished && !self.playing,
+                    egui::Button::new(egui_phosphor::regular::SKIP_FORWARD),
+                )
+                .on_hover_text("Apply the next operation")
+                .clicked()
+            {
+                self.step(tree_data, bus, path_ctx);
+            }
+            line.label(format!("{} / {}", self.position, self.operations.len()));
+        });
+        ui.horizontal(|line| {
+            line.label("Operations per second:");
+            line.add(DragValue::new(&mut self.speed).range(0.1..=50.).speed(0.1));
+        });
+        ui.checkbox(&mut self.follow, "Focus the subtree of each operation");
+
+        if let Some(next) = self.operations.get(self.position) {
+            let op = match next {
+                Operation::Insert { .. } => "insert",
+                Operation::Delete { .. } => "delete",
+            };
+            ui.label(format!(
+                "Next: {op} {} at [{}]",
+                hex::encode(next.key()),
+                next.path().iter().map(hex::encode).collect::<Vec<_>>().join(", ")
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_lines_are_parsed() {
+        let log = "{\"op\": \"delete\", \"path\": [[32]], \"key\": [1]}\n\n{\"op\": \"delete\", \"path\": \
+                   [], \"key\": [2]}\n";
+        let operations = parse_log(log).unwrap();
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations[0].path(), &[vec![32]]);
+        assert_eq!(operations[1].key(), &vec![2]);
+
+        let error = parse_log("{\"op\": \"delete\", \"path\": [], \"key\": [2]}\n{\"op\": \"move\"}")
+            .err()
+            .unwrap();
+        assert!(error.starts_with("line 2:"));
+    }
+}
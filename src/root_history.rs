@@ -0,0 +1,181 @@
+//! Root keys and root hashes of pinned subtrees recorded per session. With
+//! sessions recreated on each new block in follow mode this shows in which of
+//! them a subtree actually changed.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use chrono::{DateTime, Local};
+use eframe::egui::{self, CollapsingHeader, Grid, RichText, Sense, Vec2};
+use grovedbg_types::{CryptoHash, Key, SessionId};
+
+use crate::{
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    path_ctx::{path_label, Path},
+    profiles::ProfilesView,
+    tree_data::TreeData,
+};
+
+/// Samples kept per subtree, older ones are dropped
+const MAX_SAMPLES: usize = 100;
+
+/// Hex digits of a root hash shown in the list
+const SHORT_HASH_LEN: usize = 16;
+
+/// Side of a session square in the history strip
+const STRIP_CELL: f32 = 10.;
+
+struct RootSample {
+    session_id: SessionId,
+    at: DateTime<Local>,
+    root_key: Option<Key>,
+    root_hash: Option<CryptoHash>,
+}
+
+impl RootSample {
+    fn same_root(&self, other: &RootSample) -> bool {
+        self.root_key == other.root_key && self.root_hash == other.root_hash
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct RootHistory<'pa> {
+    subtrees: BTreeMap<Path<'pa>, VecDeque<RootSample>>,
+}
+
+impl<'pa> RootHistory<'pa> {
+    /// Records roots of pinned subtrees for the session, a sample of the same
+    /// session is updated as refetched data arrives
+    pub(crate) fn record(&mut self, session_id: Option<SessionId>, tree_data: &TreeData<'pa>) {
+        let Some(session_id) = session_id else {
+            return;
+        };
+
+        for (path, subtree_data) in tree_data.data.iter() {
+            let subtree_data = subtree_data.borrow();
+            if !subtree_data.pinned {
+                continue;
+            }
+            let root_key = subtree_data.root_key.clone();
+            let root_hash = subtree_data.root_hash();
+            if root_key.is_none() && root_hash.is_none() {
+                continue;
+            }
+
+            let samples = self.subtrees.entry(*path).or_default();
+            match samples.back_mut() {
+                Some(last) if last.session_id == session_id => {
+                    last.root_key = root_key;
+                    last.root_hash = root_hash;
+                }
+                _ => {
+                    samples.push_back(RootSample {
+                        session_id,
+                        at: Local::now(),
+                        root_key,
+                        root_hash,
+                    });
+                    if samples.len() > MAX_SAMPLES {
+                        samples.pop_front();
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn draw(&mut self, ui: &mut egui::Ui, profiles_view: &ProfilesView) {
+        if self.subtrees.is_empty() {
+            ui.label("Pin subtrees to record their roots across sessions");
+            return;
+        }
+
+        let mut forget = None;
+        for (path, samples) in self.subtrees.iter() {
+            ui.horizontal(|line| {
+                path_label(
+                    line,
+                    *path,
+                    &profiles_view.active_profile_root_ctx().fast_forward(*path),
+                );
+                if line
+                    .small_button(egui_phosphor::regular::TRASH)
+                    .on_hover_text("Forget the history of the subtree")
+                    .clicked()
+                {
+                    forget = Some(*path);
+                }
+            });
+            draw_strip(ui, samples);
+
+            CollapsingHeader::new(format!("{} sessions", samples.len()))
+                .id_salt(path.id().with("root_history"))
+                .show(ui, |collapsing| {
+                    Grid::new(path.id().with("root_history_grid"))
+                        .striped(true)
+                        .show(collapsing, |grid| {
+                            let mut previous: Option<&RootSample> = None;
+                            for sample in samples.iter() {
+                                let changed = previous.is_some_and(|p| !p.same_root(sample));
+                                grid.label(format!("Session {}", sample.session_id));
+                                grid.label(sample.at.format("%H:%M:%S").to_string());
+                                grid.label(RichText::new(root_key_text(&sample.root_key)).monospace());
+                                grid.label(RichText::new(root_hash_text(&sample.root_hash)).monospace());
+                                if changed {
+                                    grid.label(RichText::new("changed").color(grid.visuals().warn_fg_color));
+                                }
+                                grid.end_row();
+                                previous = Some(sample);
+                            }
+                        });
+                });
+            ui.separator();
+        }
+
+        if let Some(path) = forget {
+            self.subtrees.remove(&path);
+        }
+    }
+}
+
+/// A square per session, highlighted when the root differs from the previous
+/// session
+fn draw_strip(ui: &mut egui::Ui, samples: &VecDeque<RootSample>) {
+    let (rect, response) = ui.allocate_exact_size(
+        Vec2::new(samples.len() as f32 * STRIP_CELL, STRIP_CELL),
+        Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    let mut previous: Option<&RootSample> = None;
+    for (i, sample) in samples.iter().enumerate() {
+        let color = if previous.is_some_and(|p| !p.same_root(sample)) {
+            ui.visuals().warn_fg_color
+        } else {
+            ui.visuals().weak_text_color()
+        };
+        let cell = egui::Rect::from_min_size(
+            rect.min + Vec2::new(i as f32 * STRIP_CELL, 0.),
+            Vec2::splat(STRIP_CELL),
+        )
+        .shrink(1.);
+        painter.rect_filled(cell, 1., color);
+        previous = Some(sample);
+    }
+    response.on_hover_text("A square per session, highlighted ones changed the subtree root");
+}
+
+fn root_key_text(key: &Option<Key>) -> String {
+    match key {
+        Some(key) => bytes_by_display_variant(key, &BytesDisplayVariant::guess(key)),
+        None => "no root key".to_owned(),
+    }
+}
+
+fn root_hash_text(hash: &Option<CryptoHash>) -> String {
+    match hash {
+        Some(hash) => {
+            let mut hex = hex::encode(hash);
+            hex.truncate(SHORT_HASH_LEN);
+            format!("{hex}…")
+        }
+        None => "unknown hash".to_owned(),
+    }
+}
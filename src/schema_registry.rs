@@ -0,0 +1,156 @@
+//! JSON schemas registered per path prefix. Item values under a prefix are
+//! parsed as JSON documents and validated against the schema of the longest
+//! matching prefix, to catch malformed documents stored in a tree. The
+//! registry is global since element views have no access to the app state.
+
+mod json_schema;
+
+use std::sync::RwLock;
+
+use eframe::{
+    egui::{self, RichText, TextEdit},
+    Storage,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{go_to_path::parse_path, path_ctx::path_as_json_hex, theme::input_error_color};
+
+const SCHEMA_REGISTRY_KEY: &'static str = "schema_registry";
+
+static REGISTRY: RwLock<Vec<SchemaEntry>> = RwLock::new(Vec::new());
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SchemaEntry {
+    prefix: Vec<Vec<u8>>,
+    schema: Value,
+}
+
+pub(crate) fn persist(storage: &mut dyn Storage) {
+    let Ok(registry) = REGISTRY.read() else {
+        return;
+    };
+    if let Ok(s) = serde_json::to_string(&*registry) {
+        storage.set_string(SCHEMA_REGISTRY_KEY, s);
+    }
+}
+
+pub(crate) fn restore(storage: Option<&dyn Storage>) {
+    let entries: Vec<SchemaEntry> = storage
+        .and_then(|s| s.get_string(SCHEMA_REGISTRY_KEY))
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    if let Ok(mut registry) = REGISTRY.write() {
+        *registry = entries;
+    }
+}
+
+/// Violations of the schema registered for the item's path, `None` if no
+/// schema applies
+fn validate_item(path: &[Vec<u8>], value: &[u8]) -> Option<Vec<String>> {
+    let registry = REGISTRY.read().ok()?;
+    let entry = registry
+        .iter()
+        .filter(|entry| path.starts_with(&entry.prefix))
+        .max_by_key(|entry| entry.prefix.len())?;
+    Some(match serde_json::from_slice::<Value>(value) {
+        Ok(document) => json_schema::validate(&entry.schema, &document),
+        Err(e) => vec![format!("Not a JSON document: {e}")],
+    })
+}
+
+/// Marks an item value that doesn't match its registered schema, the
+/// violations are listed on hover
+pub(crate) fn draw_item_validation(ui: &mut egui::Ui, path: &[Vec<u8>], value: &[u8]) {
+    let Some(violations) = validate_item(path, value).filter(|v| !v.is_empty()) else {
+        return;
+    };
+    ui.label(
+        RichText::new(format!(
+            "{} {} schema violations",
+            egui_phosphor::regular::WARNING,
+            violations.len()
+        ))
+        .color(input_error_color(ui.ctx())),
+    )
+    .on_hover_text(violations.join("\n"));
+}
+
+/// Registered schemas and inputs to add more
+#[derive(Default)]
+pub(crate) struct SchemaRegistryView {
+    prefix_input: String,
+    schema_input: String,
+    error: Option<String>,
+}
+
+impl SchemaRegistryView {
+    pub(crate) fn draw(&mut self, ui: &mut egui::Ui) {
+        let Ok(mut registry) = REGISTRY.write() else {
+            return;
+        };
+
+        if registry.is_empty() {
+            ui.label("No schemas registered");
+        }
+        let mut remove = None;
+        for (i, entry) in registry.iter().enumerate() {
+            ui.horizontal(|line| {
+                if line
+                    .button(egui_phosphor::regular::TRASH)
+                    .on_hover_text("Remove the schema")
+                    .clicked()
+                {
+                    remove = Some(i);
+                }
+                line.label(RichText::new(path_as_json_hex(&entry.prefix)).monospace())
+                    .on_hover_text(serde_json::to_string_pretty(&entry.schema).unwrap_or_default());
+            });
+        }
+        if let Some(i) = remove {
+            registry.remove(i);
+        }
+        ui.separator();
+
+        ui.label("Path prefix:");
+        ui.add(TextEdit::singleline(&mut self.prefix_input).hint_text(r#"["40", "01"]"#));
+        ui.label("JSON schema:");
+        ui.add(
+            TextEdit::multiline(&mut self.schema_input)
+                .code_editor()
+                .desired_rows(6)
+                .hint_text(r#"{"type": "object", "required": ["$id"]}"#),
+        );
+        if ui
+            .add_enabled(
+                !self.schema_input.trim().is_empty(),
+                egui::Button::new("Register"),
+            )
+            .on_hover_text("Items under the prefix are validated, the longest matching prefix wins")
+            .clicked()
+        {
+            match self.parse_entry() {
+                Ok(entry) => {
+                    registry.retain(|e| e.prefix != entry.prefix);
+                    registry.push(entry);
+                    self.schema_input.clear();
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        if let Some(error) = &self.error {
+            ui.label(RichText::new(error).color(input_error_color(ui.ctx())));
+        }
+    }
+
+    fn parse_entry(&self) -> Result<SchemaEntry, String> {
+        let prefix = parse_path(&self.prefix_input).map_err(|e| format!("Path prefix: {e}"))?;
+        let schema: Value =
+            serde_json::from_str(&self.schema_input).map_err(|e| format!("JSON schema: {e}"))?;
+        if !schema.is_object() && !schema.is_boolean() {
+            return Err("JSON schema shall be an object or a boolean".to_owned());
+        }
+        Ok(SchemaEntry { prefix, schema })
+    }
+}
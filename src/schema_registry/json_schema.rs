@@ -0,0 +1,199 @@
+//! Subset of JSON Schema covering document shapes: types, object properties,
+//! array items, enums and bounds. Other keywords are ignored, so a document
+//! passing here may still be rejected by a complete validator.
+
+use serde_json::{Map, Value};
+
+/// Violations of the schema, each one prefixed with a JSON pointer to the
+/// offending value
+pub(super) fn validate(schema: &Value, document: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, document, "", &mut errors);
+    errors
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|f| f.fract() == 0.),
+        "null" | "boolean" | "number" | "string" | "array" | "object" => expected == type_name(value),
+        // Unknown types are not ours to judge
+        _ => true,
+    }
+}
+
+fn validate_at(schema: &Value, value: &Value, pointer: &str, errors: &mut Vec<String>) {
+    let at = if pointer.is_empty() { "/" } else { pointer };
+    let Some(schema) = schema.as_object() else {
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{at}: no value is allowed here"));
+        }
+        return;
+    };
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|t| type_matches(t, value)) {
+        errors.push(format!(
+            "{at}: expected {}, found {}",
+            types.join(" or "),
+            type_name(value)
+        ));
+        return;
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            errors.push(format!("{at}: {value} is not one of the allowed values"));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{at}: expected {expected}, found {value}"));
+        }
+    }
+
+    let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+    match value {
+        Value::Object(object) => validate_object(schema, object, pointer, errors),
+        Value::Array(items) => {
+            check_len(
+                at,
+                "items",
+                items.len(),
+                bound("minItems"),
+                bound("maxItems"),
+                errors,
+            );
+            if let Some(items_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(items_schema, item, &format!("{pointer}/{i}"), errors);
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count();
+            check_len(
+                at,
+                "characters",
+                len,
+                bound("minLength"),
+                bound("maxLength"),
+                errors,
+            );
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if bound("minimum").is_some_and(|min| n < min)
+                || bound("exclusiveMinimum").is_some_and(|min| n <= min)
+            {
+                errors.push(format!("{at}: {n} is below the minimum"));
+            }
+            if bound("maximum").is_some_and(|max| n > max)
+                || bound("exclusiveMaximum").is_some_and(|max| n >= max)
+            {
+                errors.push(format!("{at}: {n} is above the maximum"));
+            }
+        }
+        Value::Null | Value::Bool(_) => {}
+    }
+}
+
+fn check_len(at: &str, unit: &str, len: usize, min: Option<f64>, max: Option<f64>, errors: &mut Vec<String>) {
+    if let Some(min) = min.filter(|min| (len as f64) < *min) {
+        errors.push(format!("{at}: {len} {unit}, at least {min} expected"));
+    }
+    if let Some(max) = max.filter(|max| (len as f64) > *max) {
+        errors.push(format!("{at}: {len} {unit}, at most {max} expected"));
+    }
+}
+
+fn validate_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    pointer: &str,
+    errors: &mut Vec<String>,
+) {
+    let at = if pointer.is_empty() { "/" } else { pointer };
+    for required in schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if let Some(name) = required.as_str().filter(|name| !object.contains_key(*name)) {
+            errors.push(format!("{at}: missing required property \"{name}\""));
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let additional = schema.get("additionalProperties");
+    for (name, value) in object {
+        let property_pointer = format!("{pointer}/{}", name.replace('~', "~0").replace('/', "~1"));
+        match (properties.and_then(|p| p.get(name)), additional) {
+            (Some(property_schema), _) => validate_at(property_schema, value, &property_pointer, errors),
+            (None, Some(Value::Bool(false))) => errors.push(format!("{at}: unexpected property \"{name}\"")),
+            (None, Some(additional_schema)) => {
+                validate_at(additional_schema, value, &property_pointer, errors)
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn documents_are_checked_against_the_schema() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name", "tags"],
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "age": {"type": "integer", "minimum": 0},
+                "tags": {"type": "array", "items": {"enum": ["a", "b"]}, "maxItems": 2},
+            },
+            "additionalProperties": false,
+        });
+
+        assert!(validate(&schema, &json!({"name": "x", "age": 3, "tags": ["a"]})).is_empty());
+        // Properties may be visited in insertion order or sorted depending on
+        // serde_json features
+        let mut errors = validate(
+            &schema,
+            &json!({"name": "", "age": -1.5, "tags": ["a", "c", "b"], "extra": 1}),
+        );
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "/: unexpected property \"extra\"",
+                "/age: expected integer, found number",
+                "/name: 0 characters, at least 1 expected",
+                "/tags/1: \"c\" is not one of the allowed values",
+                "/tags: 3 items, at most 2 expected",
+            ]
+        );
+        assert_eq!(
+            validate(&schema, &json!([])),
+            vec!["/: expected object, found array"]
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! Navigation scripts: high level actions recorded while browsing, saved as
+//! JSON and replayed step by step by another GroveDBG instance against its
+//! own endpoint, to hand over a reproduction path along with a snapshot.
+
+use std::cell::{Cell, RefCell};
+
+use eframe::egui::{self, RichText, TextEdit};
+use grovedbg_types::{Key, PathQuery};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bus::{CommandBus, UserAction},
+    path_ctx::PathCtx,
+    protocol::FetchCommand,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub(crate) enum Step {
+    Focus {
+        path: Vec<Vec<u8>>,
+        #[serde(default)]
+        key: Option<Key>,
+    },
+    FetchRoot,
+    FetchNode {
+        path: Vec<Vec<u8>>,
+        key: Key,
+    },
+    FetchNodes {
+        nodes: Vec<(Vec<Vec<u8>>, Key)>,
+    },
+    FetchMerkLevels {
+        path: Vec<Vec<u8>>,
+        root_key: Key,
+        levels: u8,
+    },
+    Query {
+        path_query: PathQuery,
+    },
+    QueryBatch {
+        path_queries: Vec<PathQuery>,
+    },
+    /// Proofs are opened in the proof viewer once received
+    Prove {
+        path_query: PathQuery,
+    },
+}
+
+impl Step {
+    fn from_command(command: &FetchCommand) -> Option<Self> {
+        match command {
+            FetchCommand::FetchRoot => Some(Step::FetchRoot),
+            FetchCommand::FetchNode { path, key } => Some(Step::FetchNode {
+                path: path.clone(),
+                key: key.clone(),
+            }),
+            FetchCommand::FetchNodes { nodes } => Some(Step::FetchNodes { nodes: nodes.clone() }),
+            FetchCommand::FetchMerkLevels {
+                path,
+                root_key,
+                levels,
+            } => Some(Step::FetchMerkLevels {
+                path: path.clone(),
+                root_key: root_key.clone(),
+                levels: *levels,
+            }),
+            FetchCommand::FetchWithPathQuery { path_query } => Some(Step::Query {
+                path_query: path_query.clone(),
+            }),
+            FetchCommand::FetchWithPathQueries { path_queries } => Some(Step::QueryBatch {
+                path_queries: path_queries.clone(),
+            }),
+            FetchCommand::ProvePathQuery { path_query } => Some(Step::Prove {
+                path_query: path_query.clone(),
+            }),
+            // Inspection of a node's bytes or count is not a part of navigation,
+            // a node found by hash is recorded as it's focused
+            FetchCommand::FetchRawNode { .. }
+            | FetchCommand::CountSubtree { .. }
+            | FetchCommand::FindNodeByHash { .. }
+            | FetchCommand::FetchReferrers { .. } => None,
+        }
+    }
+
+    /// Steps repeated by redraws or clicks, queries are not compared as they
+    /// are meant to be sent again
+    fn repeats(&self, previous: &Step) -> bool {
+        match (self, previous) {
+            (Step::Focus { path, key }, Step::Focus { path: p, key: k }) => path == p && key == k,
+            (Step::FetchNode { path, key }, Step::FetchNode { path: p, key: k }) => path == p && key == k,
+            (Step::FetchRoot, Step::FetchRoot) => true,
+            _ => false,
+        }
+    }
+
+    fn description(&self) -> String {
+        let path_text = |path: &[Vec<u8>]| {
+            format!(
+                "[{}]",
+                path.iter().map(hex::encode).collect::<Vec<_>>().join(", ")
+            )
+        };
+        match self {
+            Step::Focus { path, key: None } => format!("Focus {}", path_text(path)),
+            Step::Focus { path, key: Some(key) } => {
+                format!("Focus {} at {}", path_text(path), hex::encode(key))
+            }
+            Step::FetchRoot => "Fetch the root node".to_owned(),
+            Step::FetchNode { path, key } => format!("Fetch {} at {}", hex::encode(key), path_text(path)),
+            Step::FetchNodes { nodes } => format!("Fetch {} nodes", nodes.len()),
+            Step::FetchMerkLevels { path, levels, .. } => {
+                format!("Fetch {levels} Merk levels of {}", path_text(path))
+            }
+            Step::Query { path_query } => format!("Query {}", path_text(&path_query.path)),
+            Step::QueryBatch { path_queries } => format!("Run a batch of {} queries", path_queries.len()),
+            Step::Prove { path_query } => format!("Prove a query of {}", path_text(&path_query.path)),
+        }
+    }
+
+    fn run<'pa>(&self, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+        match self {
+            Step::Focus { path, key } => {
+                let path = path_ctx.add_path(path.clone());
+                match key {
+                    Some(key) => bus.user_action(UserAction::FocusSubtreeKey(path, key.clone())),
+                    None => bus.user_action(UserAction::FocusSubtree(path)),
+                }
+            }
+            Step::FetchRoot => bus.fetch().root(),
+            Step::FetchNode { path, key } => bus.fetch().node(path_ctx.add_path(path.clone()), key.clone()),
+            Step::FetchNodes { nodes } => bus.fetch().nodes(
+                nodes
+                    .iter()
+                    .map(|(path, key)| (path_ctx.add_path(path.clone()), key.clone())),
+            ),
+            Step::FetchMerkLevels {
+                path,
+                root_key,
+                levels,
+            } => bus
+                .fetch()
+                .merk_levels(path_ctx.add_path(path.clone()), root_key.clone(), *levels),
+            Step::Query { path_query } => {
+                bus.fetch().path_query(path_query.clone());
+            }
+            Step::QueryBatch { path_queries } => bus.fetch().path_queries(path_queries.clone()),
+            Step::Prove { path_query } => bus.fetch().prove(path_query.clone()),
+        }
+    }
+}
+
+pub(crate) fn parse_script(text: &str) -> Result<Vec<Step>, String> {
+    serde_json::from_str(text).map_err(|e| e.to_string())
+}
+
+/// Captures steps while recording is on, shared through the command bus to
+/// see every fetch and focus change
+#[derive(Default)]
+pub(crate) struct Recorder {
+    recording: Cell<bool>,
+    steps: RefCell<Vec<Step>>,
+}
+
+impl Recorder {
+    pub(crate) fn is_recording(&self) -> bool {
+        self.recording.get()
+    }
+
+    fn record(&self, step: Step) {
+        if !self.recording.get() {
+            return;
+        }
+        let mut steps = self.steps.borrow_mut();
+        if !steps.last().is_some_and(|previous| step.repeats(previous)) {
+            steps.push(step);
+        }
+    }
+
+    pub(crate) fn record_command(&self, command: &FetchCommand) {
+        if let Some(step) = Step::from_command(command) {
+            self.record(step);
+        }
+    }
+
+    pub(crate) fn record_focus(&self, path: Vec<Vec<u8>>, key: Option<Key>) {
+        self.record(Step::Focus { path, key });
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&*self.steps.borrow()).unwrap_or_default()
+    }
+}
+
+/// Recording controls and a step-by-step player of a loaded script
+#[derive(Default)]
+pub(crate) struct ScriptPlayer {
+    steps: Vec<Step>,
+    /// Index of the next step to run
+    position: usize,
+    pasted: String,
+}
+
+impl ScriptPlayer {
+    pub(crate) fn load(&mut self, steps: Vec<Step>) {
+        log::info!("Loaded a navigation script of {} steps", steps.len());
+        self.steps = steps;
+        self.position = 0;
+    }
+
+    pub(crate) fn draw<'pa>(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, path_ctx: &'pa PathCtx) {
+        self.draw_recorder(ui, bus.recorder());
+        ui.separator();
+
+        ui.label("Paste a recorded script to replay:");
+        ui.add(
+            TextEdit::multiline(&mut self.pasted)
+                .code_editor()
+                .desired_rows(4)
+                .hint_text(r#"[{"step": "focus", "path": [[32]]}]"#),
+        );
+        if ui
+            .add_enabled(!self.pasted.trim().is_empty(), egui::Button::new("Load"))
+            .clicked()
+        {
+            match parse_script(&self.pasted) {
+                Ok(steps) => {
+                    self.load(steps);
+                    self.pasted.clear();
+                }
+                Err(e) => log::error!("Unable to parse the navigation script: {e}"),
+            }
+        }
+        ui.separator();
+
+        if self.steps.is_empty() {
+            ui.label("No script loaded");
+            return;
+        }
+
+        ui.horizontal(|line| {
+            if line
+                .add_enabled(
+                    self.position < self.steps.len(),
+                    egui::Button::new(egui_phosphor::regular::SKIP_FORWARD),
+                )
+                .on_hover_text("Run the next step")
+                .clicked()
+            {
+                self.steps[self.position].run(bus, path_ctx);
+                self.position += 1;
+            }
+            if line
+                .add_enabled(
+                    self.position > 0,
+                    egui::Button::new(egui_phosphor::regular::REWIND),
+                )
+                .on_hover_text("Start over")
+                .clicked()
+            {
+                self.position = 0;
+            }
+            line.label(format!("{} / {}", self.position, self.steps.len()));
+        });
+
+        egui::ScrollArea::vertical().show(ui, |scroll| {
+            for (i, step) in self.steps.iter().enumerate() {
+                let text = RichText::new(format!("{}. {}", i + 1, step.description()));
+                let text = if i < self.position {
+                    text.weak()
+                } else if i == self.position {
+                    text.strong()
+                } else {
+                    text
+                };
+                scroll.label(text);
+            }
+        });
+    }
+
+    fn draw_recorder(&self, ui: &mut egui::Ui, recorder: &Recorder) {
+        ui.horizontal(|line| {
+            let recording = recorder.is_recording();
+            let text = if recording {
+                format!("{} Stop recording", egui_phosphor::regular::STOP)
+            } else {
+                format!("{} Record", egui_phosphor::regular::RECORD)
+            };
+            if line.button(text).clicked() {
+                if !recording {
+                    recorder.steps.borrow_mut().clear();
+                }
+                recorder.recording.set(!recording);
+            }
+
+            let recorded = recorder.steps.borrow().len();
+            line.label(format!("{recorded} steps recorded"));
+
+            if line
+                .add_enabled(recorded > 0, egui::Button::new(egui_phosphor::regular::COPY))
+                .on_hover_text("Copy the recorded script as JSON")
+                .clicked()
+            {
+                line.ctx().copy_text(recorder.to_json());
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            if line
+                .add_enabled(
+                    recorded > 0,
+                    egui::Button::new(egui_phosphor::regular::FLOPPY_DISK),
+                )
+                .on_hover_text("Save the recorded script to the working directory")
+                .clicked()
+            {
+                let file_name = format!(
+                    "grovedbg-script-{}.json",
+                    chrono::Utc::now().format("%Y%m%d%H%M%S")
+                );
+                match std::fs::write(&file_name, recorder.to_json()) {
+                    Ok(_) => log::info!("Navigation script saved to {file_name}"),
+                    Err(e) => log::error!("Unable to save the navigation script: {e}"),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_steps_round_trip() {
+        let recorder = Recorder::default();
+        recorder.record_focus(vec![b"a".to_vec()], None);
+        recorder.recording.set(true);
+        recorder.record_focus(vec![b"b".to_vec()], None);
+        recorder.record_focus(vec![b"b".to_vec()], None);
+        recorder.record_command(&FetchCommand::FetchNode {
+            path: vec![b"b".to_vec()],
+            key: b"k".to_vec(),
+        });
+        recorder.record_command(&FetchCommand::CountSubtree { path: vec![] });
+
+        let steps = parse_script(&recorder.to_json()).unwrap();
+        assert_eq!(
+            steps.iter().map(Step::description).collect::<Vec<_>>(),
+            vec!["Focus [62]", "Fetch 6b at [62]"]
+        );
+        assert!(parse_script(r#"[{"step": "jump"}]"#).is_err());
+    }
+}
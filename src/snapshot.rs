@@ -0,0 +1,136 @@
+//! Export of the loaded GroveDB state together with notes, so findings can be
+//! shared or revisited after the session is gone.
+
+use eframe::egui;
+use grovedbg_types::{CryptoHash, Element, Key};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bus::CommandBus,
+    notes::{Note, Notes},
+    tree_data::TreeData,
+    tree_view::ElementOrPlaceholder,
+};
+
+/// Version of the exported JSON layout, bumped on incompatible changes
+const SCHEMA_VERSION: u32 = 1;
+
+/// A loaded element, placeholders of not yet fetched nodes are skipped
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SnapshotNode {
+    pub(crate) path: Vec<Vec<u8>>,
+    pub(crate) key: Key,
+    pub(crate) element: Element,
+    pub(crate) left_child: Option<Key>,
+    pub(crate) right_child: Option<Key>,
+    pub(crate) kv_digest_hash: Option<CryptoHash>,
+    pub(crate) value_hash: Option<CryptoHash>,
+    pub(crate) node_hash: Option<CryptoHash>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SessionSnapshot {
+    #[serde(default)]
+    pub(crate) version: u32,
+    pub(crate) grovedbg_version: String,
+    pub(crate) grovedb_version: Option<String>,
+    pub(crate) exported_at: String,
+    pub(crate) nodes: Vec<SnapshotNode>,
+    #[serde(default)]
+    pub(crate) notes: Vec<Note>,
+}
+
+impl SessionSnapshot {
+    fn collect(tree_data: &TreeData, notes: &Notes, bus: &CommandBus) -> Self {
+        let mut nodes = Vec::new();
+        for (path, subtree) in tree_data.data.iter() {
+            let path = path.to_vec();
+            for element_view in subtree.borrow().elements.values() {
+                let ElementOrPlaceholder::Element(element) = &element_view.value else {
+                    continue;
+                };
+                nodes.push(SnapshotNode {
+                    path: path.clone(),
+                    key: element_view.key.clone(),
+                    element: element.clone(),
+                    left_child: element_view.left_child.clone(),
+                    right_child: element_view.right_child.clone(),
+                    kv_digest_hash: element_view.kv_digest_hash,
+                    value_hash: element_view.value_hash,
+                    node_hash: element_view.node_hash,
+                });
+            }
+        }
+
+        SessionSnapshot {
+            version: SCHEMA_VERSION,
+            grovedbg_version: env!("CARGO_PKG_VERSION").to_owned(),
+            grovedb_version: bus.endpoint_info().map(|info| info.grovedb_version),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            nodes,
+            notes: notes.notes().to_vec(),
+        }
+    }
+
+    /// Loads nodes and notes of the snapshot into the current state
+    pub(crate) fn import(self, tree_data: &mut TreeData, notes: &mut Notes) {
+        if self.version > SCHEMA_VERSION {
+            log::warn!(
+                "Snapshot schema version {} is newer than supported {SCHEMA_VERSION}, some data may be \
+                 missing",
+                self.version
+            );
+        }
+        let count = self.nodes.len();
+        for node in self.nodes {
+            tree_data.import_snapshot_node(node);
+        }
+        notes.import(self.notes);
+        log::info!(
+            "Imported {count} nodes of a snapshot exported at {}",
+            self.exported_at
+        );
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|e| format!("Unable to serialize snapshot: {e}"))
+    }
+}
+
+/// Copies loaded nodes and notes to the clipboard as JSON
+pub(crate) fn copy_snapshot(ctx: &egui::Context, tree_data: &TreeData, notes: &Notes, bus: &CommandBus) {
+    ctx.copy_text(SessionSnapshot::collect(tree_data, notes, bus).to_json());
+}
+
+/// Draws buttons to copy or save a snapshot of the loaded data and notes
+pub(crate) fn draw_export(ui: &mut egui::Ui, tree_data: &TreeData, notes: &Notes, bus: &CommandBus) {
+    ui.horizontal(|line| {
+        line.label("Export snapshot:");
+        if line
+            .button(egui_phosphor::regular::COPY)
+            .on_hover_text("Copy loaded nodes and notes as JSON")
+            .clicked()
+        {
+            copy_snapshot(line.ctx(), tree_data, notes, bus);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if line
+            .button(egui_phosphor::regular::FLOPPY_DISK)
+            .on_hover_text("Save loaded nodes and notes to the working directory")
+            .clicked()
+        {
+            let file_name = format!(
+                "grovedbg-snapshot-{}.json",
+                chrono::Utc::now().format("%Y%m%d%H%M%S")
+            );
+            match std::fs::write(
+                &file_name,
+                SessionSnapshot::collect(tree_data, notes, bus).to_json(),
+            ) {
+                Ok(_) => log::info!("Snapshot saved to {file_name}"),
+                Err(e) => log::error!("Unable to save snapshot: {e}"),
+            }
+        }
+    });
+}
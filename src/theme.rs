@@ -1,4 +1,4 @@
-use eframe::egui::{Color32, Context};
+use eframe::egui::{ecolor::Hsva, Color32, Context};
 use grovedbg_types::Element;
 
 use crate::tree_view::ElementOrPlaceholder;
@@ -15,6 +15,11 @@ const REFERENCE_COLOR_DARK: Color32 = Color32::LIGHT_BLUE;
 const PROOF_NODE_COLOR_LIGHT: Color32 = Color32::from_rgb(143, 0, 179);
 const PROOF_NODE_COLOR_DARK: Color32 = Color32::from_rgb(215, 119, 240);
 
+/// Data fetched within this number of seconds is considered fresh
+const FRESH_DATA_SECONDS: i64 = 60;
+/// Data fetched earlier than this number of seconds ago is considered stale
+const STALE_DATA_SECONDS: i64 = 600;
+
 pub(crate) fn element_to_color(ctx: &Context, element: &ElementOrPlaceholder) -> Color32 {
     if ctx.style().visuals.dark_mode {
         // Dark theme
@@ -70,3 +75,47 @@ pub(crate) fn proof_node_color(ctx: &Context) -> Color32 {
         PROOF_NODE_COLOR_LIGHT
     }
 }
+
+pub(crate) fn success_color(ctx: &Context) -> Color32 {
+    if ctx.style().visuals.dark_mode {
+        Color32::GREEN
+    } else {
+        Color32::from_rgb(0, 150, 0)
+    }
+}
+
+/// Hashes that would change after a local what-if edit
+pub(crate) fn changed_hash_color(ctx: &Context) -> Color32 {
+    if ctx.style().visuals.dark_mode {
+        Color32::from_rgb(255, 170, 60)
+    } else {
+        Color32::from_rgb(200, 110, 0)
+    }
+}
+
+/// Color of a profile category's group, a hue derived from the name so the
+/// same category looks the same across subtrees and sessions
+pub(crate) fn category_color(ctx: &Context, category: &str) -> Color32 {
+    let hash = category
+        .bytes()
+        .fold(0x811c9dc5u32, |h, b| (h ^ b as u32).wrapping_mul(0x01000193));
+    let hue = (hash % 360) as f32 / 360.;
+    let value = if ctx.style().visuals.dark_mode { 0.9 } else { 0.6 };
+    Hsva::new(hue, 0.6, value, 1.).into()
+}
+
+/// Tint of fetched data by its age in seconds
+pub(crate) fn data_age_color(ctx: &Context, age: i64) -> Color32 {
+    let dark_mode = ctx.style().visuals.dark_mode;
+    if age < FRESH_DATA_SECONDS {
+        success_color(ctx)
+    } else if age < STALE_DATA_SECONDS {
+        if dark_mode {
+            Color32::YELLOW
+        } else {
+            Color32::from_rgb(180, 120, 0)
+        }
+    } else {
+        Color32::GRAY
+    }
+}
@@ -0,0 +1,106 @@
+//! Notifications about failed commands shown on top of everything else.
+
+use chrono::{DateTime, Local};
+use eframe::egui::{self, RichText};
+
+use crate::{
+    bus::CommandBus,
+    format,
+    protocol::{ActivityEvent, ActivitySeverity, CommandKind, FetchCommand},
+};
+
+const MAX_TOASTS: usize = 5;
+
+struct Toast {
+    /// Identity of the failed request used to merge repeated failures
+    dedup_key: String,
+    command: CommandKind,
+    message: String,
+    retry: Option<FetchCommand>,
+    count: usize,
+    last: DateTime<Local>,
+}
+
+#[derive(Default)]
+pub(crate) struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    /// Shows a toast if the event is an error, repeated failures of the same
+    /// request bump the counter of an existing toast instead
+    pub(crate) fn notify(&mut self, event: &ActivityEvent) {
+        if event.severity != ActivitySeverity::Error {
+            return;
+        }
+
+        let message = event
+            .message
+            .clone()
+            .unwrap_or_else(|| "Unknown error".to_owned());
+        let dedup_key = format!("{:?} {:?} {message}", event.command, event.retry);
+
+        if let Some(toast) = self.toasts.iter_mut().find(|t| t.dedup_key == dedup_key) {
+            toast.count += 1;
+            toast.last = event.started;
+            return;
+        }
+
+        if self.toasts.len() >= MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push(Toast {
+            dedup_key,
+            command: event.command,
+            message,
+            retry: event.retry.clone(),
+            count: 1,
+            last: event.started,
+        });
+    }
+
+    pub(crate) fn draw(&mut self, ctx: &egui::Context, bus: &CommandBus) {
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_TOP, [-10., 40.])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_max_width(350.);
+                self.toasts.retain(|toast| {
+                    let mut keep = true;
+                    egui::Frame::popup(ui.style()).show(ui, |frame| {
+                        frame.horizontal(|line| {
+                            line.label(
+                                RichText::new(egui_phosphor::regular::X_CIRCLE)
+                                    .color(line.visuals().error_fg_color),
+                            );
+                            line.label(RichText::new(toast.command.as_ref()).strong());
+                            if toast.count > 1 {
+                                line.label(format!("×{}", toast.count));
+                            }
+                            line.label(format::time_of_day(&toast.last));
+                        });
+                        frame.label(&toast.message);
+                        frame.horizontal(|line| {
+                            if let Some(retry) = &toast.retry {
+                                if line
+                                    .button(format!("{} Retry", egui_phosphor::regular::ARROW_CLOCKWISE))
+                                    .clicked()
+                                {
+                                    bus.fetch_command(retry.clone());
+                                    keep = false;
+                                }
+                            }
+                            if line.button("Dismiss").clicked() {
+                                keep = false;
+                            }
+                        });
+                    });
+                    keep
+                });
+            });
+    }
+}
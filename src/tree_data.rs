@@ -1,25 +1,86 @@
+mod refetch_diff;
+mod saved_layout;
+
 use std::{
-    cell::{Ref, RefCell, RefMut},
+    cell::{Cell, Ref, RefCell, RefMut},
     collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    fmt,
 };
 
-use grovedbg_types::{Key, NodeUpdate};
+use chrono::{DateTime, Local};
+use grovedbg_types::{CryptoHash, Key, NodeUpdate, QueryItem, Reference, SessionId};
+use refetch_diff::{RefetchSnapshot, SubtreeDiff};
+pub(crate) use saved_layout::SavedLayout;
 
 use crate::{
+    budget,
+    bus::CommandBus,
+    density::kv_per_page,
+    display_defaults::DisplayDefaults,
+    format,
+    merk_hash::{self, HashCheck, Verification},
     path_ctx::{Path, PathCtx},
-    proof_viewer::MerkProofNodeViewer,
-    tree_view::{ElementOrPlaceholder, ElementView, SubtreeElements},
+    proof_viewer::{Absence, MerkProofNodeViewer},
+    protocol::NodeSource,
+    snapshot::SnapshotNode,
+    tree_view::{reference_target, ElementOrPlaceholder, ElementView, FetchInfo, SubtreeElements},
 };
 
 pub(crate) type SubtreeProofData = BTreeMap<Key, MerkProofNodeViewer>;
 pub(crate) type ProofData<'pa> = BTreeMap<Path<'pa>, SubtreeProofData>;
+/// Fetched nodes with value hashes different from the proved ones
+pub(crate) type ProofMismatches<'pa> = BTreeSet<(Path<'pa>, Key)>;
 pub(crate) type SubtreeDataMap<'pa> = BTreeMap<Path<'pa>, RefCell<SubtreeData>>;
+/// Paths and keys of loaded references to an element
+pub(crate) type Referrers = BTreeSet<(Vec<Vec<u8>>, Key)>;
 
 pub(crate) struct TreeData<'pa> {
     path_ctx: &'pa PathCtx,
     pub(crate) data: SubtreeDataMap<'pa>,
     pub(crate) proof_data: ProofData<'pa>,
+    pub(crate) proof_mismatches: ProofMismatches<'pa>,
+    /// Keys the last proof shows to be absent, by subtree
+    pub(crate) proof_absences: BTreeMap<Path<'pa>, Vec<Absence>>,
     pub(crate) merk_selected: Path<'pa>,
+    /// Subtrees being expanded, child subtrees learned later are expanded as
+    /// well
+    pending_expansions: BTreeMap<Path<'pa>, PendingExpansion>,
+    /// Subtree roots already received by prefetching
+    prefetched: BTreeSet<(Path<'pa>, Key)>,
+    display_defaults: DisplayDefaults,
+    session: Option<SessionId>,
+    /// Session the last proof was received in
+    proof_session: Option<SessionId>,
+    /// Proved nodes fetched with a different value in the proof's session
+    pub(crate) update_conflicts: Vec<UpdateConflict<'pa>>,
+    /// Estimated memory used by loaded elements in bytes
+    usage: Cell<usize>,
+    /// Referrers of elements of subtrees that are not created yet, moved to
+    /// the subtree once it is
+    detached_referrers: BTreeMap<Path<'pa>, BTreeMap<Key, Referrers>>,
+}
+
+/// Expansion of a subtree waiting for its child subtrees to be learned
+struct PendingExpansion {
+    depth: usize,
+    /// Child subtrees the expansion has shown, hiding one afterwards is not
+    /// undone by later passes
+    shown: BTreeSet<Key>,
+    /// A page of the subtree was requested and hasn't arrived yet
+    awaiting_page: bool,
+}
+
+/// A node fetched in the session of a proof with a value hash that differs
+/// from the proved one. Sessions work on snapshots, so this shouldn't happen
+/// unless the session is broken on the server side.
+pub(crate) struct UpdateConflict<'pa> {
+    pub(crate) session_id: SessionId,
+    pub(crate) path: Path<'pa>,
+    pub(crate) key: Key,
+    pub(crate) proved_value_hash: Vec<u8>,
+    pub(crate) fetched_value_hash: CryptoHash,
+    pub(crate) source: NodeSource,
+    pub(crate) at: DateTime<Local>,
 }
 
 #[derive(Default)]
@@ -28,28 +89,577 @@ pub(crate) struct SubtreeData {
     pub(crate) root_key: Option<Key>,
     pub(crate) subtree_keys: BTreeSet<Key>,
     pub(crate) visible_keys: BTreeSet<Key>,
+    /// Keys selected to build a path query from
+    pub(crate) selected_keys: BTreeSet<Key>,
+    refetch_snapshot: Option<RefetchSnapshot>,
+    /// Changes found by the last refetch
+    pub(crate) last_diff: Option<SubtreeDiff>,
+    /// Loaded references pointing at elements of the subtree
+    pub(crate) back_references: BTreeMap<Key, Referrers>,
+    pub(crate) pinned: bool,
+    /// Number of elements reported by the endpoint
+    pub(crate) element_count: Option<ElementCount>,
+    /// The subtree element was fetched from the parent subtree, so a missing
+    /// root key means the subtree is empty
+    root_key_known: bool,
+    /// A query of the whole subtree returned no elements
+    confirmed_empty: bool,
+}
+
+/// What is known about a subtree's elements
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SubtreeState {
+    Loaded,
+    Empty,
+    NotFetched,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ElementCount {
+    pub(crate) count: u64,
+    pub(crate) exact: bool,
+}
+
+impl fmt::Display for ElementCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let approx = if self.exact { "" } else { "~" };
+        write!(f, "{approx}{} keys", format::number(self.count))
+    }
 }
 
 impl SubtreeData {
+    /// Root key learned from the subtree element in the parent subtree
+    pub(crate) fn set_root_key(&mut self, root_key: Option<Key>) {
+        if root_key.is_some() {
+            self.confirmed_empty = false;
+        }
+        self.root_key = root_key;
+        self.root_key_known = true;
+    }
+
+    /// Whether the subtree has no elements or they are not fetched yet
+    pub(crate) fn state(&self) -> SubtreeState {
+        if !self.elements.is_empty() {
+            SubtreeState::Loaded
+        } else if self.confirmed_empty || (self.root_key_known && self.root_key.is_none()) {
+            SubtreeState::Empty
+        } else {
+            SubtreeState::NotFetched
+        }
+    }
+
+    /// Whether a missing root key is known to mean the subtree is empty
+    pub(crate) fn is_root_key_known(&self) -> bool {
+        self.root_key_known
+    }
+
     pub(crate) fn get_root(&mut self) -> Option<&mut ElementView> {
         self.root_key.as_ref().and_then(|k| self.elements.get_mut(k))
     }
+
+    /// Root hash of the subtree's Merk computed from the root node and the
+    /// hashes of its children, `None` until they are fetched
+    pub(crate) fn root_hash(&self) -> Option<CryptoHash> {
+        let root = self.root_key.as_ref().and_then(|k| self.elements.get(k))?;
+        let child_hash = |child: &Option<Key>| match child {
+            Some(child_key) => self.elements.get(child_key).and_then(|c| c.node_hash).map(Some),
+            None => Some(None),
+        };
+        Some(merk_hash::node_hash(
+            &root.kv_digest_hash?,
+            child_hash(&root.left_child)?.as_ref(),
+            child_hash(&root.right_child)?.as_ref(),
+        ))
+    }
+
+    /// Keeps current elements to compare with once refetched data arrives
+    pub(crate) fn start_refetch(&mut self, limit: Option<u16>, left_to_right: bool) {
+        self.refetch_snapshot = RefetchSnapshot::new(&self.elements, limit, left_to_right);
+    }
+
+    /// Query items for selected keys. With `as_ranges` selected keys that are
+    /// neighbours among loaded elements are merged into inclusive ranges.
+    pub(crate) fn selected_query_items(&self, as_ranges: bool) -> Vec<QueryItem> {
+        if !as_ranges {
+            return self.selected_keys.iter().cloned().map(QueryItem::Key).collect();
+        }
+
+        let mut runs: Vec<(Key, Key)> = Vec::new();
+        let mut prev_selected = false;
+        for key in self.elements.keys() {
+            let selected = self.selected_keys.contains(key);
+            if selected {
+                match runs.last_mut() {
+                    Some((_, end)) if prev_selected => *end = key.clone(),
+                    _ => runs.push((key.clone(), key.clone())),
+                }
+            }
+            prev_selected = selected;
+        }
+
+        // Selected keys could be cleared from loaded data meanwhile
+        for key in &self.selected_keys {
+            if !self.elements.contains_key(key) {
+                runs.push((key.clone(), key.clone()));
+            }
+        }
+        runs.sort();
+
+        runs.into_iter()
+            .map(|(start, end)| {
+                if start == end {
+                    QueryItem::Key(start)
+                } else {
+                    QueryItem::RangeInclusive { start, end }
+                }
+            })
+            .collect()
+    }
 }
 
 impl<'pa> TreeData<'pa> {
-    pub(crate) fn new(path_ctx: &'pa PathCtx) -> Self {
+    pub(crate) fn new(path_ctx: &'pa PathCtx, display_defaults: DisplayDefaults) -> Self {
+        path_ctx.set_key_display(display_defaults.keys);
+        format::set_utc_timestamps(display_defaults.utc_timestamps);
         Self {
             path_ctx,
             data: Default::default(),
             merk_selected: path_ctx.get_root(),
             proof_data: Default::default(),
+            proof_mismatches: Default::default(),
+            proof_absences: Default::default(),
+            pending_expansions: Default::default(),
+            prefetched: Default::default(),
+            display_defaults,
+            session: None,
+            proof_session: None,
+            update_conflicts: Vec::new(),
+            usage: Cell::new(0),
+            detached_referrers: Default::default(),
         }
     }
 
+    /// Estimated memory used by loaded elements in bytes, kept up to date as
+    /// elements are added and dropped
+    pub(crate) fn usage(&self) -> usize {
+        self.usage.get()
+    }
+
+    fn account_usage(&self, added: usize, removed: usize) {
+        self.usage.set((self.usage.get() + added).saturating_sub(removed));
+    }
+
+    /// Drops all elements of a subtree
+    pub(crate) fn clear_elements(&self, subtree_data: &mut SubtreeData) {
+        let removed = elements_usage(&subtree_data.elements);
+        subtree_data.elements.clear();
+        self.account_usage(0, removed);
+    }
+
+    /// Session of the data fetched from now on, to tell fetches done in the
+    /// session of a proof
+    pub(crate) fn set_session(&mut self, session_id: SessionId) {
+        self.session = Some(session_id);
+        // Roots prefetched in another session may be outdated
+        self.prefetched.clear();
+    }
+
+    pub(crate) fn display_defaults(&self) -> DisplayDefaults {
+        self.display_defaults
+    }
+
+    /// Sets display defaults for data fetched later
+    pub(crate) fn set_display_defaults(&mut self, display_defaults: DisplayDefaults) {
+        self.path_ctx.set_key_display(display_defaults.keys);
+        format::set_utc_timestamps(display_defaults.utc_timestamps);
+        self.display_defaults = display_defaults;
+    }
+
+    /// Resets display variants of all loaded data to the defaults
+    pub(crate) fn reapply_display_defaults(&self) {
+        self.path_ctx.reset_key_displays();
+        self.data.values().for_each(|subtree| {
+            subtree
+                .borrow_mut()
+                .elements
+                .values_mut()
+                .for_each(|element| element.apply_display_defaults(&self.display_defaults));
+        });
+    }
+
     pub(crate) fn select_for_merk(&mut self, path: Path<'pa>) {
         self.merk_selected = path;
     }
 
+    /// Requests fresh data for pinned subtrees: the parent node to get an
+    /// actual root key and as many elements as were loaded before.
+    pub(crate) fn refetch_pinned(&self, bus: &CommandBus<'pa>) {
+        for (path, subtree_data) in self.data.iter() {
+            let mut subtree_data = subtree_data.borrow_mut();
+            if subtree_data.pinned {
+                refetch_subtree(*path, &mut subtree_data, bus, false);
+            }
+        }
+    }
+
+    /// Refetches pinned and shown subtrees after the session was switched to
+    /// a newer GroveDB state, keeping snapshots to mark what has changed.
+    pub(crate) fn refetch_followed(&self, bus: &CommandBus<'pa>) {
+        let mut to_refetch: BTreeSet<Path<'pa>> = self
+            .data
+            .iter()
+            .filter_map(|(path, subtree_data)| subtree_data.borrow().pinned.then_some(*path))
+            .collect();
+        to_refetch.append(&mut self.shown_subtrees());
+
+        for path in to_refetch {
+            let Some(mut subtree_data) = self.data.get(&path).map(RefCell::borrow_mut) else {
+                continue;
+            };
+            if subtree_data.elements.is_empty() {
+                continue;
+            }
+            refetch_subtree(path, &mut subtree_data, bus, true);
+        }
+    }
+
+    /// Shown subtrees and the Merk view selection to restore on the next
+    /// start
+    pub(crate) fn saved_layout(&self) -> SavedLayout {
+        SavedLayout {
+            visible: self
+                .shown_subtrees()
+                .into_iter()
+                .filter_map(|path| {
+                    let subtree_data = self.get(&path)?;
+                    (!subtree_data.visible_keys.is_empty())
+                        .then(|| (path.to_vec(), subtree_data.visible_keys.iter().cloned().collect()))
+                })
+                .collect(),
+            merk_selected: self.merk_selected.to_vec(),
+        }
+    }
+
+    /// Shows subtrees of a saved layout again and fetches the first page of
+    /// each along with its subtree element
+    pub(crate) fn restore_layout(&mut self, layout: SavedLayout, bus: &CommandBus<'pa>) {
+        for path in self.apply_layout(layout) {
+            if let Some((parent_path, parent_key)) = path.parent_with_key() {
+                bus.fetch().throttled().node(parent_path, parent_key);
+            }
+            bus.fetch()
+                .throttled()
+                .subtree(path)
+                .limit(kv_per_page() as u16)
+                .send();
+        }
+    }
+
+    /// Sets visible keys and the Merk view selection of a saved layout,
+    /// returns the subtrees it shows
+    fn apply_layout(&mut self, layout: SavedLayout) -> BTreeSet<Path<'pa>> {
+        for (path, keys) in layout.visible {
+            let path = self.path_ctx.add_path(path);
+            self.get_or_create_mut(path).visible_keys.extend(keys);
+        }
+        self.merk_selected = self.path_ctx.add_path(layout.merk_selected);
+        self.shown_subtrees()
+    }
+
+    /// Subtrees reachable from the root through visible keys
+    pub(crate) fn shown_subtrees(&self) -> BTreeSet<Path<'pa>> {
+        let mut shown_subtrees = BTreeSet::new();
+        let mut shown = vec![self.path_ctx.get_root()];
+        while let Some(path) = shown.pop() {
+            let Some(subtree_data) = self.get(&path) else {
+                continue;
+            };
+            shown.extend(
+                subtree_data
+                    .visible_keys
+                    .iter()
+                    .map(|key| path.child(key.clone())),
+            );
+            shown_subtrees.insert(path);
+        }
+        shown_subtrees
+    }
+
+    /// Drops loaded elements of a subtree, keeping placeholders of its root
+    /// node and child subtrees to fetch them again
+    pub(crate) fn unload_subtree(&mut self, path: Path<'pa>) {
+        let references = {
+            let Some(mut subtree_data) = self.get_mut(&path) else {
+                return;
+            };
+            let subtree_data = &mut *subtree_data;
+            let mut references = Vec::new();
+            let removed = elements_usage(&subtree_data.elements);
+            for (key, element) in std::mem::take(&mut subtree_data.elements) {
+                if subtree_data.subtree_keys.contains(&key) || subtree_data.root_key.as_ref() == Some(&key) {
+                    subtree_data
+                        .elements
+                        .insert(key.clone(), ElementView::new_placeholder(key.clone()));
+                }
+                if let ElementOrPlaceholder::Element(grovedbg_types::Element::Reference(reference)) =
+                    element.value
+                {
+                    references.push((key, reference));
+                }
+            }
+            subtree_data.selected_keys.clear();
+            subtree_data.refetch_snapshot = None;
+            subtree_data.last_diff = None;
+            self.account_usage(elements_usage(&subtree_data.elements), removed);
+            references
+        };
+
+        for (key, reference) in references {
+            self.update_back_references(path, &key, Some(reference), None);
+        }
+        self.proof_mismatches.retain(|(p, _)| *p != path);
+        self.prefetched.retain(|(p, _)| *p != path);
+    }
+
+    /// Unloads subtrees that are neither shown nor pinned, least recently
+    /// fetched first, until about `to_free` bytes are freed
+    pub(crate) fn evict_hidden(&mut self, to_free: usize) {
+        let shown = self.shown_subtrees();
+        let mut candidates: Vec<_> = self
+            .data
+            .iter()
+            .filter(|(path, _)| !shown.contains(path))
+            .filter_map(|(path, subtree_data)| {
+                let subtree_data = subtree_data.borrow();
+                let last_fetched = subtree_data
+                    .elements
+                    .values()
+                    .filter(|e| matches!(e.value, ElementOrPlaceholder::Element(_)))
+                    .map(|e| e.fetched.map(|f| f.at))
+                    .max()?;
+                (!subtree_data.pinned).then(|| (last_fetched, budget::subtree_usage(&subtree_data), *path))
+            })
+            .collect();
+        candidates.sort_by_key(|(last_fetched, ..)| *last_fetched);
+
+        let mut freed = 0;
+        for (_, size, path) in candidates {
+            if freed >= to_free {
+                break;
+            }
+            self.unload_subtree(path);
+            freed += size;
+        }
+        if freed > 0 {
+            log::info!(
+                "Unloaded hidden subtrees to free about {}",
+                budget::format_size(freed)
+            );
+        }
+    }
+
+    /// Recomputes hashes of a fetched node from its key, value and children
+    /// hashes, storing the outcome in the element view
+    pub(crate) fn verify_node_hash(&mut self, path: Path<'pa>, key: Key) {
+        let Some(mut subtree_data) = self.data.get(&path).map(RefCell::borrow_mut) else {
+            return;
+        };
+        let Some(element) = subtree_data.elements.get(&key) else {
+            return;
+        };
+
+        // Only an item's value hash depends on the element alone, subtree
+        // elements combine it with the root hash of the child subtree
+        let computed_value_hash = match &element.value {
+            ElementOrPlaceholder::Element(grovedbg_types::Element::Item { value, element_flags }) => Some(
+                merk_hash::value_hash(&merk_hash::item_element_bytes(value, element_flags.as_deref())),
+            ),
+            _ => None,
+        };
+        let value_hash = match (computed_value_hash, element.value_hash) {
+            (Some(computed), Some(reported)) => Verification::compare(computed, &reported),
+            (None, _) => Verification::Unknown("Only value hashes of items are recomputed"),
+            (_, None) => Verification::Unknown("The node is not fetched"),
+        };
+
+        let kv_digest_hash = match (computed_value_hash.or(element.value_hash), element.kv_digest_hash) {
+            (Some(value_hash), Some(reported)) => {
+                Verification::compare(merk_hash::kv_digest_hash(&key, &value_hash), &reported)
+            }
+            _ => Verification::Unknown("The node is not fetched"),
+        };
+
+        let child_hash = |child: &Option<Key>| match child {
+            Some(child_key) => subtree_data
+                .elements
+                .get(child_key)
+                .and_then(|c| c.node_hash)
+                .map(Some)
+                .ok_or("A child hash is unknown"),
+            None => Ok(None),
+        };
+        let node_hash = match (
+            element.kv_digest_hash,
+            element.node_hash,
+            child_hash(&element.left_child),
+            child_hash(&element.right_child),
+        ) {
+            (Some(kv), Some(reported), Ok(left), Ok(right)) => Verification::compare(
+                merk_hash::node_hash(&kv, left.as_ref(), right.as_ref()),
+                &reported,
+            ),
+            (_, None, ..) => {
+                Verification::Unknown("Node hash is reported by the parent node, fetch it first")
+            }
+            (_, _, Err(e), _) | (_, _, _, Err(e)) => Verification::Unknown(e),
+            (None, ..) => Verification::Unknown("The node is not fetched"),
+        };
+
+        if let Some(element) = subtree_data.elements.get_mut(&key) {
+            element.hash_check = Some(HashCheck {
+                value_hash,
+                kv_digest_hash,
+                node_hash,
+            });
+        }
+    }
+
+    /// Makes child subtrees visible down to `depth` levels, fetching their
+    /// root nodes (and first pages for intermediate levels to learn deeper
+    /// subtrees) using throttled commands.
+    pub(crate) fn expand_subtrees(&mut self, path: Path<'pa>, depth: usize, bus: &CommandBus<'pa>) {
+        self.start_expansion(path, depth, false, bus);
+    }
+
+    fn start_expansion(&mut self, path: Path<'pa>, depth: usize, awaiting_page: bool, bus: &CommandBus<'pa>) {
+        if depth == 0 {
+            return;
+        }
+        self.pending_expansions.insert(
+            path,
+            PendingExpansion {
+                depth,
+                shown: BTreeSet::new(),
+                awaiting_page,
+            },
+        );
+        self.continue_expansion(path, bus);
+    }
+
+    /// Shows child subtrees learned since the previous pass of the expansion.
+    /// The expansion is done once a pass over the fetched subtree finds none.
+    fn continue_expansion(&mut self, path: Path<'pa>, bus: &CommandBus<'pa>) {
+        let Some(pending) = self.pending_expansions.get_mut(&path) else {
+            return;
+        };
+        let depth = pending.depth;
+        let new_keys: Vec<Key> = {
+            let mut subtree_data =
+                subtree_entry(&mut self.data, &mut self.detached_referrers, path).borrow_mut();
+            let new_keys: Vec<Key> = subtree_data
+                .subtree_keys
+                .difference(&pending.shown)
+                .cloned()
+                .collect();
+            pending.shown.extend(new_keys.iter().cloned());
+            subtree_data.visible_keys.extend(new_keys.iter().cloned());
+            new_keys
+        };
+        if new_keys.is_empty() && !pending.awaiting_page {
+            self.pending_expansions.remove(&path);
+            return;
+        }
+
+        for key in new_keys {
+            let child_path = path.child(key);
+            let root_key = self.get(&child_path).and_then(|s| s.root_key.clone());
+            let fetch_page = depth > 1 && root_key.is_some();
+            if let Some(root_key) = root_key {
+                bus.fetch().throttled().node(child_path, root_key);
+                if fetch_page {
+                    bus.fetch()
+                        .throttled()
+                        .subtree(child_path)
+                        .limit(kv_per_page() as u16)
+                        .send();
+                }
+            }
+            self.start_expansion(child_path, depth - 1, fetch_page, bus);
+        }
+    }
+
+    /// Expands subtrees that were learned after an expansion was requested.
+    pub(crate) fn continue_expansions(&mut self, bus: &CommandBus<'pa>) {
+        let pending: Vec<_> = self.pending_expansions.keys().copied().collect();
+        for path in pending {
+            self.continue_expansion(path, bus);
+        }
+    }
+
+    /// A page requested by an expansion has arrived
+    fn page_arrived(&mut self, path: Path<'pa>) {
+        if let Some(pending) = self.pending_expansions.get_mut(&path) {
+            pending.awaiting_page = false;
+        }
+    }
+
+    /// Fetches root nodes of child subtrees of all shown subtrees that are not
+    /// loaded yet, so expanding a subtree shows its root right away.
+    pub(crate) fn prefetch_child_roots(&self, bus: &CommandBus<'pa>) {
+        let mut to_fetch = Vec::new();
+        let mut shown = vec![self.path_ctx.get_root()];
+
+        while let Some(path) = shown.pop() {
+            let Some(subtree_data) = self.get(&path) else {
+                continue;
+            };
+            for key in subtree_data.subtree_keys.iter() {
+                let child_path = path.child(key.clone());
+                if subtree_data.visible_keys.contains(key) {
+                    shown.push(child_path);
+                }
+                let Some(child_data) = self.get(&child_path) else {
+                    continue;
+                };
+                let Some(root_key) = child_data.root_key.clone() else {
+                    continue;
+                };
+                let loaded = child_data
+                    .elements
+                    .get(&root_key)
+                    .is_some_and(|e| matches!(e.value, ElementOrPlaceholder::Element(_)));
+                if !loaded
+                    && !self.prefetched.contains(&(child_path, root_key.clone()))
+                    && !bus.is_node_pending(&child_path.to_vec(), &root_key)
+                {
+                    to_fetch.push((child_path, root_key));
+                }
+            }
+        }
+
+        bus.fetch().nodes(to_fetch);
+    }
+
+    /// Hides all child subtrees recursively and cancels pending expansions.
+    pub(crate) fn collapse_subtrees(&mut self, path: Path<'pa>) {
+        let is_descendant = |p: &Path<'pa>| {
+            let mut current = Some(*p);
+            while let Some(c) = current {
+                if c == path {
+                    return true;
+                }
+                current = c.parent();
+            }
+            false
+        };
+
+        self.pending_expansions.retain(|p, _| !is_descendant(p));
+        self.data
+            .iter()
+            .filter(|(p, _)| is_descendant(p))
+            .for_each(|(_, subtree_data)| subtree_data.borrow_mut().visible_keys.clear());
+    }
+
     pub(crate) fn get_or_create_mut(&mut self, path: Path<'pa>) -> RefMut<SubtreeData> {
         // NLL issue
         if self.data.contains_key(&path) {
@@ -76,24 +686,63 @@ impl<'pa> TreeData<'pa> {
         self.data.get(path).map(RefCell::borrow)
     }
 
+    /// Loaded references pointing at the subtree element itself or at any
+    /// element of the subtree
+    pub(crate) fn subtree_referrers(&self, path: Path<'pa>) -> Referrers {
+        let mut referrers = Referrers::new();
+        if let Some((parent, key)) = path.parent_with_key() {
+            if let Some(to_subtree) = self
+                .get(&parent)
+                .and_then(|p| p.back_references.get(&key).cloned())
+            {
+                referrers.extend(to_subtree);
+            }
+        }
+        if let Some(subtree) = self.get(&path) {
+            referrers.extend(subtree.back_references.values().flatten().cloned());
+        }
+        referrers
+    }
+
+    /// GroveDB root hash, which is the root hash of the root subtree
+    pub(crate) fn root_hash(&self) -> Option<CryptoHash> {
+        self.get(&self.path_ctx.get_root())
+            .and_then(|root| root.root_hash())
+    }
+
     fn get_create_missing_parents(&mut self, path: Path<'pa>) -> &RefCell<SubtreeData> {
         let mut current_path = path;
         while let Some((parent, key)) = current_path.parent_with_key() {
-            let parent_value = self.data.entry(parent).or_default();
-            RefCell::borrow_mut(parent_value)
-                .elements
-                .entry(key.clone())
-                .or_insert_with(|| ElementView::new_placeholder(key));
+            let parent_value = subtree_entry(&mut self.data, &mut self.detached_referrers, parent);
+            if let Entry::Vacant(e) = RefCell::borrow_mut(parent_value).elements.entry(key.clone()) {
+                self.usage
+                    .set(self.usage.get() + budget::element_usage(&key, &ElementOrPlaceholder::Placeholder));
+                e.insert(ElementView::new_placeholder(key));
+            }
 
             current_path = parent;
         }
 
-        self.data.entry(path).or_default()
+        subtree_entry(&mut self.data, &mut self.detached_referrers, path)
+    }
+
+    /// Marks the subtree empty as the endpoint returned nothing for a query of
+    /// all of its elements
+    pub(crate) fn confirm_empty(&mut self, path: Path<'pa>) {
+        self.page_arrived(path);
+        let mut subtree = self.get_or_create_mut(path);
+        if subtree.elements.is_empty() {
+            subtree.confirmed_empty = true;
+        }
+        if let Some(snapshot) = subtree.refetch_snapshot.as_mut() {
+            snapshot.empty = true;
+        }
     }
 
     pub(crate) fn apply_root_node_update(&mut self, node_update: NodeUpdate) {
-        self.get_or_create_mut(self.path_ctx.get_root()).root_key = Some(node_update.key.clone());
-        self.apply_node_update(node_update);
+        self.get_or_create_mut(self.path_ctx.get_root())
+            .set_root_key(Some(node_update.key.clone()));
+        self.apply_node_update(node_update, NodeSource::Root);
     }
 
     pub(crate) fn apply_node_update(
@@ -110,52 +759,83 @@ impl<'pa> TreeData<'pa> {
             kv_digest_hash,
             ..
         }: NodeUpdate,
+        source: NodeSource,
     ) {
         let subtree_path = self.path_ctx.add_path(path);
+        if source == NodeSource::Query {
+            self.page_arrived(subtree_path);
+        }
+        if source == NodeSource::Prefetch {
+            self.prefetched.insert((subtree_path, key.clone()));
+        }
 
         if let grovedbg_types::Element::Subtree { root_key, .. }
         | grovedbg_types::Element::Sumtree { root_key, .. } = &element
         {
             let child_subtree_path = subtree_path.child(key.clone());
-            self.get_or_create_mut(child_subtree_path).root_key = root_key.clone();
+            self.get_or_create_mut(child_subtree_path)
+                .set_root_key(root_key.clone());
             self.get_or_create_mut(subtree_path)
                 .subtree_keys
                 .insert(key.clone());
         }
 
+        let node_key = key.clone();
+        let new_reference = match &element {
+            grovedbg_types::Element::Reference(reference) => Some(reference.clone()),
+            _ => None,
+        };
+        let mut old_reference = None;
+        let display_defaults = self.display_defaults;
+        let (mut added, mut removed) = (0, 0);
         let mut subtree = self.get_or_create_mut(subtree_path);
 
         match subtree.elements.entry(key.clone()) {
             Entry::Vacant(e) => {
-                e.insert(ElementView::new(
+                let e = e.insert(ElementView::new(
                     key,
                     ElementOrPlaceholder::Element(element),
                     left_child.clone(),
                     right_child.clone(),
                     Some(kv_digest_hash),
                     Some(value_hash),
+                    &display_defaults,
                 ));
+                added += budget::element_usage(&e.key, &e.value);
             }
             Entry::Occupied(mut o) => {
                 let e = o.get_mut();
+                let was_placeholder = matches!(e.value, ElementOrPlaceholder::Placeholder);
+                if let ElementOrPlaceholder::Element(grovedbg_types::Element::Reference(reference)) = &e.value
+                {
+                    old_reference = Some(reference.clone());
+                }
 
+                removed += budget::element_usage(&e.key, &e.value);
                 e.value = ElementOrPlaceholder::Element(element);
+                added += budget::element_usage(&e.key, &e.value);
+                if was_placeholder {
+                    e.apply_display_defaults(&display_defaults);
+                }
                 e.left_child = left_child.clone();
                 e.right_child = right_child.clone();
                 e.kv_digest_hash = Some(kv_digest_hash);
                 e.value_hash = Some(value_hash);
+                e.hash_check = None;
             }
         };
 
         if let (Some(left_hash), Some(left_key)) = (left_merk_hash, left_child) {
             match subtree.elements.entry(left_key.clone()) {
                 Entry::Vacant(e) => {
+                    added += budget::element_usage(&left_key, &ElementOrPlaceholder::Placeholder);
                     let element = e.insert(ElementView::new_placeholder(left_key));
                     element.node_hash = Some(left_hash);
                 }
                 Entry::Occupied(mut o) => {
                     let e = o.get_mut();
                     e.node_hash = Some(left_hash);
+                    e.hash_check = None;
                 }
             };
         }
@@ -163,21 +843,319 @@ impl<'pa> TreeData<'pa> {
         if let (Some(right_hash), Some(right_key)) = (right_merk_hash, right_child) {
             match subtree.elements.entry(right_key.clone()) {
                 Entry::Vacant(e) => {
+                    added += budget::element_usage(&right_key, &ElementOrPlaceholder::Placeholder);
                     let element = e.insert(ElementView::new_placeholder(right_key));
                     element.node_hash = Some(right_hash);
                 }
                 Entry::Occupied(mut o) => {
                     let e = o.get_mut();
                     e.node_hash = Some(right_hash);
+                    e.hash_check = None;
                 }
             };
         }
+
+        if let Some(e) = subtree.elements.get_mut(&node_key) {
+            e.fetched = Some(FetchInfo::now(source));
+        }
+
+        if let Some(snapshot) = subtree
+            .refetch_snapshot
+            .as_mut()
+            .filter(|_| source == NodeSource::Query)
+        {
+            snapshot.fetched.insert(node_key.clone());
+        }
+
+        drop(subtree);
+        self.account_usage(added, removed);
+        self.update_back_references(subtree_path, &node_key, old_reference, new_reference);
+        self.update_proof_mismatch(subtree_path, node_key.clone());
+        if matches!(
+            source,
+            NodeSource::Root
+                | NodeSource::Node
+                | NodeSource::Query
+                | NodeSource::Prefetch
+                | NodeSource::Referrers
+        ) {
+            self.detect_conflict(subtree_path, node_key, source);
+        }
+    }
+
+    /// Loads a node of an exported snapshot, replacing the loaded element with
+    /// the same key
+    pub(crate) fn import_snapshot_node(&mut self, node: SnapshotNode) {
+        let subtree_path = self.path_ctx.add_path(node.path);
+
+        if let grovedbg_types::Element::Subtree { root_key, .. }
+        | grovedbg_types::Element::Sumtree { root_key, .. } = &node.element
+        {
+            self.get_or_create_mut(subtree_path.child(node.key.clone()))
+                .set_root_key(root_key.clone());
+            self.get_or_create_mut(subtree_path)
+                .subtree_keys
+                .insert(node.key.clone());
+        }
+
+        let new_reference = match &node.element {
+            grovedbg_types::Element::Reference(reference) => Some(reference.clone()),
+            _ => None,
+        };
+        let display_defaults = self.display_defaults;
+        let mut element_view = ElementView::new(
+            node.key.clone(),
+            ElementOrPlaceholder::Element(node.element),
+            node.left_child,
+            node.right_child,
+            node.kv_digest_hash,
+            node.value_hash,
+            &display_defaults,
+        );
+        element_view.node_hash = node.node_hash;
+        element_view.fetched = Some(FetchInfo::now(NodeSource::Snapshot));
+
+        let old_reference = self
+            .get_or_create_mut(subtree_path)
+            .elements
+            .insert(node.key.clone(), element_view)
+            .and_then(|old| match old.value {
+                ElementOrPlaceholder::Element(grovedbg_types::Element::Reference(reference)) => {
+                    Some(reference)
+                }
+                _ => None,
+            });
+        self.update_back_references(subtree_path, &node.key, old_reference, new_reference);
+        self.update_proof_mismatch(subtree_path, node.key);
+    }
+
+    /// Applies an insert of a replayed operation, hashes of the node are
+    /// dropped as they can't be recomputed without the rest of the Merk tree
+    pub(crate) fn simulate_insert(&mut self, path: Path<'pa>, key: Key, element: grovedbg_types::Element) {
+        let is_subtree = if let grovedbg_types::Element::Subtree { root_key, .. }
+        | grovedbg_types::Element::Sumtree { root_key, .. } = &element
+        {
+            self.get_or_create_mut(path.child(key.clone()))
+                .set_root_key(root_key.clone());
+            true
+        } else {
+            false
+        };
+
+        let new_reference = match &element {
+            grovedbg_types::Element::Reference(reference) => Some(reference.clone()),
+            _ => None,
+        };
+        let display_defaults = self.display_defaults;
+        let mut subtree = self.get_or_create_mut(path);
+        if is_subtree {
+            subtree.subtree_keys.insert(key.clone());
+        } else {
+            subtree.subtree_keys.remove(&key);
+            subtree.visible_keys.remove(&key);
+        }
+
+        let element_view = subtree
+            .elements
+            .entry(key.clone())
+            .or_insert_with(|| ElementView::new_placeholder(key.clone()));
+        let removed = budget::element_usage(&key, &element_view.value);
+        let old_reference = match &element_view.value {
+            ElementOrPlaceholder::Element(grovedbg_types::Element::Reference(reference)) => {
+                Some(reference.clone())
+            }
+            _ => None,
+        };
+        let was_placeholder = matches!(element_view.value, ElementOrPlaceholder::Placeholder);
+        element_view.value = ElementOrPlaceholder::Element(element);
+        if was_placeholder {
+            element_view.apply_display_defaults(&display_defaults);
+        }
+        element_view.kv_digest_hash = None;
+        element_view.value_hash = None;
+        element_view.node_hash = None;
+        element_view.hash_check = None;
+        element_view.fetched = Some(FetchInfo::now(NodeSource::Replay));
+        let added = budget::element_usage(&key, &element_view.value);
+
+        drop(subtree);
+        self.account_usage(added, removed);
+        self.update_back_references(path, &key, old_reference, new_reference);
+        self.update_proof_mismatch(path, key);
+    }
+
+    /// Removes an element deleted from GroveDB or by a replayed operation, a
+    /// deleted subtree is unloaded and hidden
+    pub(crate) fn delete_element(&mut self, path: Path<'pa>, key: Key) {
+        let removed = {
+            let Some(mut subtree) = self.get_mut(&path) else {
+                return;
+            };
+            subtree.visible_keys.remove(&key);
+            subtree.selected_keys.remove(&key);
+            subtree.subtree_keys.remove(&key);
+            subtree.elements.remove(&key)
+        };
+        if let Some(old) = &removed {
+            self.account_usage(0, budget::element_usage(&key, &old.value));
+        }
+
+        self.unload_subtree(path.child(key.clone()));
+        let old_reference = removed.and_then(|old| match old.value {
+            ElementOrPlaceholder::Element(grovedbg_types::Element::Reference(reference)) => Some(reference),
+            _ => None,
+        });
+        self.update_back_references(path, &key, old_reference, None);
+        self.update_proof_mismatch(path, key);
+    }
+
+    /// Moves the element from referrers of its old reference target to the new
+    /// one's
+    fn update_back_references(
+        &mut self,
+        path: Path<'pa>,
+        key: &Key,
+        old_reference: Option<Reference>,
+        new_reference: Option<Reference>,
+    ) {
+        let referrer = (path.to_vec(), key.clone());
+
+        if let Some((target_path, target_key)) =
+            old_reference.and_then(|reference| reference_target(path, key, &reference))
+        {
+            let mut target = self.data.get(&target_path).map(RefCell::borrow_mut);
+            let back_references = match target.as_deref_mut() {
+                Some(target) => Some(&mut target.back_references),
+                None => self.detached_referrers.get_mut(&target_path),
+            };
+            if let Some(back_references) = back_references {
+                if let Some(referrers) = back_references.get_mut(&target_key) {
+                    referrers.remove(&referrer);
+                    if referrers.is_empty() {
+                        back_references.remove(&target_key);
+                    }
+                }
+            }
+        }
+
+        if let Some((target_path, target_key)) =
+            new_reference.and_then(|reference| reference_target(path, key, &reference))
+        {
+            // Loading a reference doesn't create the subtree it points into
+            let mut target = self.data.get(&target_path).map(RefCell::borrow_mut);
+            let back_references = match target.as_deref_mut() {
+                Some(target) => &mut target.back_references,
+                None => self.detached_referrers.entry(target_path).or_default(),
+            };
+            back_references.entry(target_key).or_default().insert(referrer);
+        }
+    }
+
+    /// Computes changes of a subtree once its refetch is processed, elements
+    /// missing from the refetched range are dropped. Nothing is computed if
+    /// the refetch failed.
+    pub(crate) fn finish_refetch(&mut self, path: Path<'pa>) {
+        let diff = {
+            let Some(mut subtree) = self.get_mut(&path) else {
+                return;
+            };
+            match subtree.refetch_snapshot.take() {
+                Some(snapshot) if snapshot.answered() => snapshot.diff(&subtree.elements),
+                _ => return,
+            }
+        };
+        for key in diff.removed.iter() {
+            self.delete_element(path, key.clone());
+        }
+        if let Some(mut subtree) = self.get_mut(&path) {
+            subtree.last_diff = Some(diff);
+        }
+    }
+
+    /// Compares the value hash of a fetched node with the proved one, if both
+    /// are known
+    fn update_proof_mismatch(&mut self, path: Path<'pa>, key: Key) {
+        let proved = self
+            .proof_data
+            .get(&path)
+            .and_then(|proof_subtree| proof_subtree.get(&key))
+            .and_then(MerkProofNodeViewer::value_hash);
+        let fetched = self.data.get(&path).and_then(|subtree| {
+            let subtree = subtree.borrow();
+            subtree.elements.get(&key).and_then(|e| e.value_hash)
+        });
+
+        if let (Some(proved), Some(fetched)) = (proved, fetched) {
+            if proved != fetched.as_slice() {
+                self.proof_mismatches.insert((path, key));
+                return;
+            }
+        }
+        self.proof_mismatches.remove(&(path, key));
+    }
+
+    /// Records a proof mismatch of a node fetched in the session of the proof
+    fn detect_conflict(&mut self, path: Path<'pa>, key: Key, source: NodeSource) {
+        let Some(session_id) = self.session.filter(|s| self.proof_session == Some(*s)) else {
+            return;
+        };
+        if !self.proof_mismatches.contains(&(path, key.clone())) {
+            return;
+        }
+        let Some(proved_value_hash) = self
+            .proof_data
+            .get(&path)
+            .and_then(|proof_subtree| proof_subtree.get(&key))
+            .and_then(MerkProofNodeViewer::value_hash)
+            .map(<[u8]>::to_vec)
+        else {
+            return;
+        };
+        let Some(fetched_value_hash) = self
+            .get(&path)
+            .and_then(|subtree| subtree.elements.get(&key).and_then(|e| e.value_hash))
+        else {
+            return;
+        };
+
+        if self.update_conflicts.iter().any(|c| {
+            c.session_id == session_id
+                && c.path == path
+                && c.key == key
+                && c.fetched_value_hash == fetched_value_hash
+        }) {
+            return;
+        }
+
+        log::warn!(
+            "A node fetched by {} differs from the proof of the same session {session_id}",
+            source.as_ref()
+        );
+        self.update_conflicts.push(UpdateConflict {
+            session_id,
+            path,
+            key,
+            proved_value_hash,
+            fetched_value_hash,
+            source,
+            at: Local::now(),
+        });
     }
 
     pub(crate) fn set_proof_tree(
         &mut self,
         proof_tree: BTreeMap<Vec<Vec<u8>>, BTreeMap<Vec<u8>, grovedbg_types::MerkProofNode>>,
+        absences: &[Absence],
     ) {
+        self.proof_session = self.session;
+        self.proof_absences.clear();
+        for absence in absences {
+            self.proof_absences
+                .entry(self.path_ctx.add_path(absence.path.clone()))
+                .or_default()
+                .push(absence.clone());
+        }
+
         self.proof_data = proof_tree
             .into_iter()
             .map(|(path_vec, proof_subtree)| {
@@ -190,5 +1168,223 @@ impl<'pa> TreeData<'pa> {
                 )
             })
             .collect();
+
+        self.proof_mismatches.clear();
+        let proved_nodes: Vec<(Path<'pa>, Key)> = self
+            .proof_data
+            .iter()
+            .flat_map(|(path, proof_subtree)| proof_subtree.keys().map(|key| (*path, key.clone())))
+            .collect();
+        for (path, key) in proved_nodes {
+            self.update_proof_mismatch(path, key);
+        }
+    }
+}
+
+/// Fetches the parent node to get an actual root key and as many elements as
+/// were loaded before, optionally keeping a snapshot to find changes
+fn refetch_subtree<'pa>(
+    path: Path<'pa>,
+    subtree_data: &mut SubtreeData,
+    bus: &CommandBus<'pa>,
+    track_changes: bool,
+) {
+    if let Some((parent_path, parent_key)) = path.parent_with_key() {
+        bus.fetch().node(parent_path, parent_key);
+    }
+
+    let loaded = subtree_data
+        .elements
+        .values()
+        .filter(|e| matches!(e.value, ElementOrPlaceholder::Element(_)))
+        .count();
+    let limit = loaded.clamp(1, u16::MAX as usize) as u16;
+    let fetch = bus.fetch().subtree(path).limit(limit);
+    if track_changes {
+        if fetch.refetch().send() {
+            subtree_data.start_refetch(bus.effective_limit(Some(limit)), true);
+        }
+    } else {
+        fetch.send();
+    }
+}
+
+fn elements_usage(elements: &SubtreeElements) -> usize {
+    elements
+        .iter()
+        .map(|(key, element)| budget::element_usage(key, &element.value))
+        .sum()
+}
+
+/// Subtree data by path, a new one gets referrers loaded before it. Takes the
+/// fields apart from [TreeData] so other fields stay usable meanwhile.
+fn subtree_entry<'a, 'pa>(
+    data: &'a mut SubtreeDataMap<'pa>,
+    detached_referrers: &mut BTreeMap<Path<'pa>, BTreeMap<Key, Referrers>>,
+    path: Path<'pa>,
+) -> &'a RefCell<SubtreeData> {
+    data.entry(path).or_insert_with(|| {
+        RefCell::new(SubtreeData {
+            back_references: detached_referrers.remove(&path).unwrap_or_default(),
+            ..Default::default()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::DEFAULT_MAX_IN_FLIGHT;
+
+    fn test_bus<'pa>() -> CommandBus<'pa> {
+        let (sender, _) = tokio::sync::mpsc::channel(1);
+        CommandBus::new(sender, Default::default(), DEFAULT_MAX_IN_FLIGHT, false, false, 0)
+    }
+
+    #[test]
+    fn expansion_does_not_show_hidden_subtrees_again() {
+        let path_ctx = PathCtx::new();
+        let root = path_ctx.get_root();
+        let mut tree_data = TreeData::new(&path_ctx, DisplayDefaults::default());
+        let bus = test_bus();
+        tree_data
+            .get_or_create_mut(root)
+            .subtree_keys
+            .extend([b"a".to_vec(), b"b".to_vec()]);
+        tree_data.expand_subtrees(root, 1, &bus);
+        assert_eq!(tree_data.get(&root).unwrap().visible_keys.len(), 2);
+
+        // Subtrees learned later are shown, the hidden one is not
+        {
+            let mut subtree = tree_data.get_or_create_mut(root);
+            subtree.visible_keys.remove(b"a".as_slice());
+            subtree.subtree_keys.insert(b"c".to_vec());
+        }
+        tree_data.continue_expansions(&bus);
+        let visible = tree_data.get(&root).unwrap().visible_keys.clone();
+        assert_eq!(visible, BTreeSet::from([b"b".to_vec(), b"c".to_vec()]));
+
+        // A pass with nothing new finishes the expansion
+        tree_data.continue_expansions(&bus);
+        assert!(tree_data.pending_expansions.is_empty());
+        tree_data.get_or_create_mut(root).visible_keys.clear();
+        tree_data.continue_expansions(&bus);
+        assert!(tree_data.get(&root).unwrap().visible_keys.is_empty());
+    }
+
+    #[test]
+    fn usage_follows_added_and_dropped_elements() {
+        let path_ctx = PathCtx::new();
+        let root = path_ctx.get_root();
+        let mut tree_data = TreeData::new(&path_ctx, DisplayDefaults::default());
+        let recomputed = |tree_data: &TreeData| -> usize {
+            tree_data
+                .data
+                .values()
+                .map(|subtree| elements_usage(&subtree.borrow().elements))
+                .sum()
+        };
+
+        let item = grovedbg_types::Element::Item {
+            value: vec![0; 100],
+            element_flags: None,
+        };
+        tree_data.simulate_insert(root.child(b"a".to_vec()), b"k".to_vec(), item);
+        assert_eq!(tree_data.usage(), recomputed(&tree_data));
+
+        tree_data.delete_element(root.child(b"a".to_vec()), b"k".to_vec());
+        assert_eq!(tree_data.usage(), recomputed(&tree_data));
+
+        tree_data.unload_subtree(root);
+        assert_eq!(tree_data.usage(), recomputed(&tree_data));
+    }
+
+    #[test]
+    fn refetch_drops_elements_missing_from_the_range() {
+        let path_ctx = PathCtx::new();
+        let root = path_ctx.get_root();
+        let mut tree_data = TreeData::new(&path_ctx, DisplayDefaults::default());
+        let item = || grovedbg_types::Element::Item {
+            value: b"v".to_vec(),
+            element_flags: None,
+        };
+        tree_data.simulate_insert(root, b"a".to_vec(), item());
+        tree_data.simulate_insert(root, b"b".to_vec(), item());
+
+        tree_data.get_or_create_mut(root).start_refetch(None, true);
+        // A failed refetch leaves the elements as they are
+        tree_data.finish_refetch(root);
+        assert_eq!(tree_data.get(&root).unwrap().elements.len(), 2);
+
+        tree_data.get_or_create_mut(root).start_refetch(None, true);
+        if let Some(snapshot) = tree_data.get_or_create_mut(root).refetch_snapshot.as_mut() {
+            snapshot.fetched.insert(b"a".to_vec());
+        }
+        tree_data.finish_refetch(root);
+        let subtree = tree_data.get(&root).unwrap();
+        assert!(subtree.elements.contains_key(b"a".as_slice()));
+        assert!(!subtree.elements.contains_key(b"b".as_slice()));
+        assert_eq!(subtree.last_diff.as_ref().unwrap().removed, vec![b"b".to_vec()]);
+    }
+
+    #[test]
+    fn saved_layout_shows_the_same_subtrees() {
+        let path_ctx = PathCtx::new();
+        let root = path_ctx.get_root();
+        let mut tree_data = TreeData::new(&path_ctx, DisplayDefaults::default());
+        tree_data
+            .get_or_create_mut(root)
+            .visible_keys
+            .insert(b"a".to_vec());
+        tree_data
+            .get_or_create_mut(root.child(b"a".to_vec()))
+            .visible_keys
+            .insert(b"b".to_vec());
+        // Not reachable from the root, so not shown
+        tree_data
+            .get_or_create_mut(root.child(b"c".to_vec()))
+            .visible_keys
+            .insert(b"d".to_vec());
+        tree_data.select_for_merk(root.child(b"a".to_vec()));
+        let layout = tree_data.saved_layout();
+        assert_eq!(layout.visible.len(), 2);
+
+        let restored_ctx = PathCtx::new();
+        let mut restored = TreeData::new(&restored_ctx, DisplayDefaults::default());
+        let saved = serde_json::to_string(&layout).unwrap();
+        let shown = restored.apply_layout(serde_json::from_str(&saved).unwrap());
+        assert_eq!(
+            shown.iter().map(Path::to_vec).collect::<BTreeSet<_>>(),
+            tree_data.shown_subtrees().iter().map(Path::to_vec).collect()
+        );
+        assert_eq!(restored.merk_selected.to_vec(), vec![b"a".to_vec()]);
+    }
+
+    #[test]
+    fn element_count_display() {
+        let count = |count, exact| ElementCount { count, exact }.to_string();
+        assert_eq!(count(12430, false), "~12,430 keys");
+        assert_eq!(count(999, true), "999 keys");
+        assert_eq!(count(1000000, true), "1,000,000 keys");
+    }
+
+    #[test]
+    fn empty_subtrees_are_told_from_unfetched() {
+        let mut subtree = SubtreeData::default();
+        assert_eq!(subtree.state(), SubtreeState::NotFetched);
+
+        subtree.set_root_key(Some(b"root".to_vec()));
+        assert_eq!(subtree.state(), SubtreeState::NotFetched);
+
+        subtree.set_root_key(None);
+        assert_eq!(subtree.state(), SubtreeState::Empty);
+
+        let mut subtree = SubtreeData::default();
+        subtree.confirmed_empty = true;
+        assert_eq!(subtree.state(), SubtreeState::Empty);
+        subtree
+            .elements
+            .insert(b"a".to_vec(), ElementView::new_placeholder(b"a".to_vec()));
+        assert_eq!(subtree.state(), SubtreeState::Loaded);
     }
 }
@@ -0,0 +1,100 @@
+//! Changes of a subtree found by refetching it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use grovedbg_types::{CryptoHash, Key};
+
+use crate::tree_view::{ElementOrPlaceholder, SubtreeElements};
+
+/// Subtree elements as they were before a refetch was requested
+pub(crate) struct RefetchSnapshot {
+    value_hashes: BTreeMap<Key, Option<CryptoHash>>,
+    /// Limit of the refetch, keys past the last fetched one are unknown if
+    /// the limit was reached
+    limit: Option<u16>,
+    /// The refetch goes from the last key, so keys before the first fetched
+    /// one are unknown instead
+    left_to_right: bool,
+    pub(super) fetched: BTreeSet<Key>,
+    /// The refetch found the subtree empty
+    pub(super) empty: bool,
+}
+
+impl RefetchSnapshot {
+    /// Returns `None` if there is nothing loaded to compare with
+    pub(super) fn new(elements: &SubtreeElements, limit: Option<u16>, left_to_right: bool) -> Option<Self> {
+        let value_hashes: BTreeMap<_, _> = elements
+            .iter()
+            .filter(|(_, e)| matches!(e.value, ElementOrPlaceholder::Element(_)))
+            .map(|(key, e)| (key.clone(), e.value_hash))
+            .collect();
+
+        (!value_hashes.is_empty()).then(|| Self {
+            value_hashes,
+            limit,
+            left_to_right,
+            fetched: BTreeSet::new(),
+            empty: false,
+        })
+    }
+
+    /// The refetch got a response, a failed one leaves nothing to compare
+    pub(super) fn answered(&self) -> bool {
+        self.empty || !self.fetched.is_empty()
+    }
+
+    pub(super) fn diff(self, elements: &SubtreeElements) -> SubtreeDiff {
+        let complete = self
+            .limit
+            .map(|limit| self.fetched.len() < limit as usize)
+            .unwrap_or(true);
+        let is_covered = |key: &Key| {
+            complete
+                || if self.left_to_right {
+                    Some(key) < self.fetched.last()
+                } else {
+                    self.fetched.first().is_some_and(|first| key > first)
+                }
+        };
+
+        let mut diff = SubtreeDiff::default();
+        for key in self.fetched.iter() {
+            match self.value_hashes.get(key) {
+                None => diff.added.push(key.clone()),
+                Some(old_hash) if *old_hash != elements.get(key).and_then(|e| e.value_hash) => {
+                    diff.changed.push(key.clone())
+                }
+                _ => {}
+            }
+        }
+        diff.removed = self
+            .value_hashes
+            .keys()
+            .filter(|key| !self.fetched.contains(*key) && is_covered(key))
+            .cloned()
+            .collect();
+
+        diff
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct SubtreeDiff {
+    pub(crate) added: Vec<Key>,
+    pub(crate) removed: Vec<Key>,
+    pub(crate) changed: Vec<Key>,
+    /// Time the summary was first shown at, to hide it later
+    pub(crate) shown_since: Option<f64>,
+    pub(crate) expanded: bool,
+}
+
+impl SubtreeDiff {
+    pub(crate) fn summary(&self) -> String {
+        format!(
+            "+{} / -{} / ~{}",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )
+    }
+}
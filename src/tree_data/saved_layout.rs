@@ -0,0 +1,32 @@
+//! Layout of the tree kept across restarts: visible child subtrees of shown
+//! subtrees and the subtree selected for the Merk view. Only paths are saved,
+//! the data is fetched again once the first session is started.
+
+use eframe::Storage;
+use grovedbg_types::Key;
+use serde::{Deserialize, Serialize};
+
+const SAVED_LAYOUT_KEY: &'static str = "saved_layout";
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedLayout {
+    /// Shown subtrees with keys of their visible child subtrees
+    pub(super) visible: Vec<(Vec<Vec<u8>>, Vec<Key>)>,
+    pub(super) merk_selected: Vec<Vec<u8>>,
+}
+
+impl SavedLayout {
+    pub(crate) fn persist(&self, storage: &mut dyn Storage) {
+        if let Ok(s) = serde_json::to_string(self) {
+            storage.set_string(SAVED_LAYOUT_KEY, s);
+        }
+    }
+
+    /// `None` if nothing beyond the root subtree was shown
+    pub(crate) fn restore(storage: Option<&dyn Storage>) -> Option<Self> {
+        storage
+            .and_then(|s| s.get_string(SAVED_LAYOUT_KEY))
+            .and_then(|s| serde_json::from_str::<SavedLayout>(&s).ok())
+            .filter(|layout| !layout.visible.is_empty() || !layout.merk_selected.is_empty())
+    }
+}
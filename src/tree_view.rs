@@ -1,5 +1,11 @@
+mod csv_export;
 mod element_view;
+mod hash_link;
+mod key_range;
+mod layout;
+mod prefix_breakdown;
 mod subtree_view;
+mod sum_chart;
 
 use std::collections::BTreeMap;
 
@@ -7,24 +13,35 @@ use eframe::{
     egui::{self, Context, Rect},
     emath::TSTransform,
 };
-pub(crate) use element_view::{ElementOrPlaceholder, ElementView};
+pub(crate) use element_view::{reference_target, ElementOrPlaceholder, ElementView, FetchInfo};
+use layout::{LayoutNode, SubtreesLayout};
 pub(crate) use subtree_view::SubtreeElements;
 use subtree_view::SubtreeView;
 
 use crate::{
     bus::{CommandBus, UserAction},
+    canvas,
+    image_export::{Diagram, ImageExport},
     path_ctx::{Path, PathCtx},
     profiles::{ActiveProfileSubtreeContext, RootActiveProfileContext},
     tree_data::TreeData,
     FocusedSubree,
 };
 
-pub(crate) const NODE_WIDTH: f32 = 300.;
-
 pub(crate) struct TreeView<'pa> {
     transform: TSTransform,
+    /// Canvas area of the previous frame
+    viewport: Option<Rect>,
     pub(super) subtrees: BTreeMap<Path<'pa>, SubtreeView<'pa>>,
     path_ctx: &'pa PathCtx,
+    /// Salt of canvas areas of the split pane, `None` for the main one
+    pane: Option<egui::Id>,
+    /// Last focused subtree to center on it again after panning away
+    last_focus: Option<Path<'pa>>,
+    image_export: ImageExport,
+    collapse_chains: bool,
+    /// Tint subtrees by how often they were accessed
+    heatmap: bool,
 }
 
 impl<'pa> TreeView<'pa> {
@@ -35,11 +52,33 @@ impl<'pa> TreeView<'pa> {
 
         Self {
             transform: TSTransform::default(),
+            viewport: None,
             subtrees,
             path_ctx,
+            pane: None,
+            last_focus: None,
+            image_export: Default::default(),
+            collapse_chains: true,
+            heatmap: false,
+        }
+    }
+
+    /// Tree view of the second pane, with its own pan, zoom and focus
+    pub(crate) fn new_split(path_ctx: &'pa PathCtx) -> Self {
+        Self {
+            pane: Some(egui::Id::new("split_pane")),
+            ..Self::new(path_ctx)
         }
     }
 
+    fn drop_focus(&self, bus: &CommandBus<'pa>) {
+        bus.user_action(if self.pane.is_some() {
+            UserAction::DropSplitFocus
+        } else {
+            UserAction::DropFocus
+        });
+    }
+
     pub(crate) fn draw<'pf, 'b, 'af>(
         &mut self,
         ui: &mut egui::Ui,
@@ -49,41 +88,74 @@ impl<'pa> TreeView<'pa> {
         tree_data: &mut TreeData<'pa>,
         focused_subtree: &'af Option<FocusedSubree<'pa>>,
     ) {
+        let mut fit = false;
+        let mut center = false;
+        ui.horizontal(|line| {
+            fit = line
+                .button(egui_phosphor::regular::ARROWS_OUT)
+                .on_hover_text("Fit all shown subtrees into the view (F)")
+                .clicked();
+            center = line
+                .add_enabled(
+                    self.last_focus.is_some(),
+                    egui::Button::new(egui_phosphor::regular::CROSSHAIR_SIMPLE),
+                )
+                .on_hover_text("Center on the focused subtree (C)")
+                .clicked();
+            line.toggle_value(
+                &mut self.collapse_chains,
+                egui_phosphor::regular::ARROWS_IN_LINE_VERTICAL,
+            )
+            .on_hover_text("Collapse chains of subtrees with nothing fetched but the way down");
+            line.toggle_value(&mut self.heatmap, egui_phosphor::regular::FIRE)
+                .on_hover_text("Tint subtrees by how often they were fetched or focused in the session");
+            let root_path = self.path_ctx.get_root();
+            self.image_export.draw_menu(line, "subtrees", |depth| {
+                subtrees_diagram(root_path, tree_data, depth)
+            });
+        });
+
         let (id, rect) = ui.allocate_space(ui.available_size());
+        canvas::follow_resize(&mut self.transform, &mut self.viewport, rect);
 
-        let pointer_response = ui.interact(rect, id, egui::Sense::click_and_drag());
+        #[cfg(not(target_arch = "wasm32"))]
+        self.image_export.process_screenshot(ui.ctx(), rect, "subtrees");
 
-        let transform_before = self.transform;
+        let pointer_response = ui.interact(rect, id, egui::Sense::click_and_drag());
 
-        if pointer_response.dragged() {
-            self.transform.translation += pointer_response.drag_delta();
-        }
-        if pointer_response.double_clicked() {
-            self.transform = TSTransform::default();
+        if canvas::handle_pan_zoom(ui, &pointer_response, &mut self.transform) {
+            self.drop_focus(bus);
         }
 
-        if let Some(pointer) = ui.ctx().input(|i| i.pointer.hover_pos()) {
-            if pointer_response.hovered() {
-                let pointer_in_layer = self.transform.inverse() * pointer;
-                let zoom_delta = ui.ctx().input(|i| i.zoom_delta());
-                let pan_delta = ui.ctx().input(|i| i.smooth_scroll_delta);
-
-                // Zoom in on pointer:
-                self.transform = self.transform
-                    * TSTransform::from_translation(pointer_in_layer.to_vec2())
-                    * TSTransform::from_scaling(zoom_delta)
-                    * TSTransform::from_translation(-pointer_in_layer.to_vec2());
+        fit |= pointer_response.double_clicked()
+            || canvas::shortcut_pressed(ui, &pointer_response, egui::Key::F);
+        center |= canvas::shortcut_pressed(ui, &pointer_response, egui::Key::C);
 
-                // Pan:
-                self.transform = TSTransform::from_translation(pan_delta) * self.transform;
+        // Opened with a right click or a long press on touch screens
+        pointer_response.context_menu(|menu| {
+            if menu.button("Fit view").clicked() {
+                fit = true;
+                menu.close_menu();
             }
-        }
+            if menu.button("Reset view").clicked() {
+                self.transform = TSTransform::default();
+                self.drop_focus(bus);
+                menu.close_menu();
+            }
+        });
 
-        if transform_before != self.transform {
-            bus.user_action(UserAction::DropFocus);
+        if let Some(path) = self.last_focus.filter(|_| center) {
+            if let Some(target) = ui
+                .ctx()
+                .memory(|mem| mem.area_rect(area_id(self.pane, path)).map(|rect| rect.center()))
+            {
+                self.transform = canvas::center_transform(self.transform, target, rect);
+                self.drop_focus(bus);
+            }
         }
 
         if let Some(FocusedSubree { path, key }) = focused_subtree {
+            self.last_focus = Some(*path);
             // Show focused subtree
             path.for_segments(|segments_iter| {
                 let mut current_path = path.get_root();
@@ -101,9 +173,12 @@ impl<'pa> TreeView<'pa> {
 
             let context = ui.ctx();
 
-            let self_pos = context.memory(|mem| mem.area_rect(path.id()).map(|rect| rect.center()));
-            let root_pos =
-                context.memory(|mem| mem.area_rect(path.get_root().id()).map(|rect| rect.center()));
+            let self_pos =
+                context.memory(|mem| mem.area_rect(area_id(self.pane, *path)).map(|rect| rect.center()));
+            let root_pos = context.memory(|mem| {
+                mem.area_rect(area_id(self.pane, path.get_root()))
+                    .map(|rect| rect.center())
+            });
 
             if let (Some(self_pos), Some(root_pos)) = (self_pos, root_pos) {
                 self.transform =
@@ -111,8 +186,31 @@ impl<'pa> TreeView<'pa> {
             }
         }
 
-        let subtree_view_ctx =
-            SubtreeViewContext::new_root(ui.ctx().clone(), self.transform, rect, root_profile_ctx, bus);
+        let mut subtree_view_ctx = SubtreeViewContext::new_root(
+            ui.ctx().clone(),
+            self.transform,
+            rect,
+            self.pane,
+            root_profile_ctx,
+            bus,
+            self.collapse_chains,
+            self.heatmap,
+        );
+
+        let root_path = self.path_ctx.get_root();
+        let mut layout = SubtreesLayout::default();
+        subtree_view::collect_layout(
+            root_path,
+            &mut subtree_view_ctx,
+            tree_data,
+            &self.subtrees,
+            &mut layout,
+        );
+        layout.compute(
+            ui.ctx(),
+            LayoutNode::Subtree(root_path),
+            area_id(self.pane, root_path),
+        );
 
         if let Some(mut root) = self.subtrees.remove(&self.path_ctx.get_root()) {
             root.draw(
@@ -120,20 +218,76 @@ impl<'pa> TreeView<'pa> {
                 ui,
                 tree_data,
                 &mut self.subtrees,
+                &layout,
                 None,
                 merk_panel_width,
             );
             self.subtrees.insert(self.path_ctx.get_root(), root);
         };
+
+        // Uses sizes of the subtrees drawn this frame, so the new transform
+        // applies starting from the next one
+        if fit {
+            let bounds = ui.ctx().memory(|mem| {
+                layout
+                    .subtrees()
+                    .filter_map(|path| mem.area_rect(area_id(self.pane, path)))
+                    .reduce(|a, b| a.union(b))
+            });
+            if let Some(bounds) = bounds {
+                self.transform = canvas::fit_transform(bounds, rect);
+                self.drop_focus(bus);
+                ui.ctx().request_repaint();
+            }
+        }
+    }
+}
+
+/// Diagram of loaded subtrees from `path` down to `depth` levels
+fn subtrees_diagram<'pa>(path: Path<'pa>, tree_data: &TreeData<'pa>, depth: usize) -> Diagram {
+    let mut diagram = Diagram::default();
+    add_subtree_node(&mut diagram, None, path, tree_data, depth);
+    diagram
+}
+
+fn add_subtree_node<'pa>(
+    diagram: &mut Diagram,
+    parent: Option<usize>,
+    path: Path<'pa>,
+    tree_data: &TreeData<'pa>,
+    depth: usize,
+) {
+    let Some(subtree_data) = tree_data.get(&path) else {
+        return;
+    };
+    let loaded = subtree_data
+        .elements
+        .values()
+        .filter(|e| matches!(e.value, ElementOrPlaceholder::Element(_)))
+        .count();
+    let idx = diagram.add(
+        parent,
+        path.for_last_segment(|segment| segment.view_by_display())
+            .unwrap_or_else(|| "root".to_owned()),
+        format!("{loaded} elements loaded"),
+    );
+    if depth > 1 {
+        for key in subtree_data.subtree_keys.iter() {
+            add_subtree_node(diagram, Some(idx), path.child(key.clone()), tree_data, depth - 1);
+        }
     }
 }
 
 pub(crate) struct SubtreeViewContext<'pf, 'pa, 'b> {
     transform: TSTransform,
     rect: Rect,
+    pane: Option<egui::Id>,
     context: Context,
     profile_ctx: ActiveProfileSubtreeContext<'pf>,
     bus: &'b CommandBus<'pa>,
+    /// Collapse chains of placeholder-only subtrees into one widget
+    collapse_chains: bool,
+    heatmap: bool,
 }
 
 impl<'pf, 'pa, 'b> SubtreeViewContext<'pf, 'pa, 'b> {
@@ -141,15 +295,21 @@ impl<'pf, 'pa, 'b> SubtreeViewContext<'pf, 'pa, 'b> {
         context: Context,
         transform: TSTransform,
         rect: Rect,
+        pane: Option<egui::Id>,
         root_profile_ctx: RootActiveProfileContext<'pf>,
         bus: &'b CommandBus<'pa>,
+        collapse_chains: bool,
+        heatmap: bool,
     ) -> Self {
         Self {
             transform,
             rect,
+            pane,
             context,
             profile_ctx: root_profile_ctx.into_inner(),
             bus,
+            collapse_chains,
+            heatmap,
         }
     }
 
@@ -157,38 +317,62 @@ impl<'pf, 'pa, 'b> SubtreeViewContext<'pf, 'pa, 'b> {
         SubtreeViewContext {
             rect: self.rect,
             transform: self.transform,
+            pane: self.pane,
             context: self.context.clone(),
             profile_ctx: self.profile_ctx.child(key),
             bus: self.bus,
+            collapse_chains: self.collapse_chains,
+            heatmap: self.heatmap,
         }
     }
 
+    /// Context of a descendant subtree by keys of the path to it
+    pub(crate) fn descendant(&mut self, keys: &[Vec<u8>]) -> SubtreeViewContext<'pf, 'pa, 'b> {
+        let (first, rest) = keys.split_first().expect("at least one key");
+        let mut ctx = self.child(first.clone());
+        for key in rest {
+            ctx = ctx.child(key.clone());
+        }
+        ctx
+    }
+
     pub(crate) fn element_view_context<'sc>(
         &'sc mut self,
         path: Path<'pa>,
     ) -> ElementViewContext<'sc, 'pa, 'pf, 'b> {
         ElementViewContext {
             path,
+            pane: self.pane,
             profile_ctx: &mut self.profile_ctx,
             bus: self.bus,
+            keys_only: false,
         }
     }
+
+    pub(crate) fn area_id(&self, path: Path<'pa>) -> egui::Id {
+        area_id(self.pane, path)
+    }
 }
 
 pub(crate) struct ElementViewContext<'af, 'pa, 'pf, 'b> {
     pub(crate) path: Path<'pa>,
+    pub(crate) pane: Option<egui::Id>,
     pub(crate) profile_ctx: &'af mut ActiveProfileSubtreeContext<'pf>,
     pub(crate) bus: &'b CommandBus<'pa>,
+    /// Values of items and references are hidden, only keys are listed
+    pub(crate) keys_only: bool,
 }
 
 impl<'af, 'pa, 'pf, 'cs> ElementViewContext<'af, 'pa, 'pf, 'cs> {
     pub(crate) fn focus_child_subtree(&mut self, key: Vec<u8>) {
-        self.bus
-            .user_action(UserAction::FocusSubtree(self.path.child(key)));
+        self.focus(self.path.child(key), None);
     }
 
+    /// Focus stays within the pane the element is drawn in
     pub(crate) fn focus(&mut self, path: Path<'pa>, key: Option<Vec<u8>>) {
-        if let Some(key) = key {
+        if self.pane.is_some() {
+            self.bus.user_action(UserAction::FocusSplitSubtree(path, key));
+        } else if let Some(key) = key {
             self.bus.user_action(UserAction::FocusSubtreeKey(path, key));
         } else {
             self.bus.user_action(UserAction::FocusSubtree(path));
@@ -199,7 +383,20 @@ impl<'af, 'pa, 'pf, 'cs> ElementViewContext<'af, 'pa, 'pf, 'cs> {
         self.path
     }
 
+    pub(crate) fn area_id(&self, path: Path<'pa>) -> egui::Id {
+        area_id(self.pane, path)
+    }
+
     pub(crate) fn profile_ctx(&self) -> &ActiveProfileSubtreeContext {
         &self.profile_ctx
     }
 }
+
+/// Canvas area of a subtree, areas of the split pane are salted so both panes
+/// can show the same subtree
+fn area_id(pane: Option<egui::Id>, path: Path) -> egui::Id {
+    match pane {
+        Some(pane) => path.id().with(pane),
+        None => path.id(),
+    }
+}
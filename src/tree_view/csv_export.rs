@@ -0,0 +1,154 @@
+//! Export of fetched subtree elements to CSV for spreadsheet analysis.
+
+use eframe::egui;
+use grovedbg_types::Element;
+
+use super::{
+    element_view::{reference_flags, FlagsView},
+    reference_target, ElementOrPlaceholder, ElementView,
+};
+use crate::{
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    flags_decoder::FlagsDecoder,
+    path_ctx::Path,
+    tree_data::SubtreeData,
+};
+
+const HEADER: &str = "key,type,value,flags";
+
+/// Quotes a CSV field if it contains a separator, a quote or a line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn segment_display(bytes: &[u8]) -> String {
+    bytes_by_display_variant(bytes, &BytesDisplayVariant::guess(bytes))
+}
+
+fn element_type_and_value(path: Path, element_view: &ElementView) -> (&'static str, String) {
+    match &element_view.value {
+        ElementOrPlaceholder::Element(Element::Item { value, .. }) => (
+            "Item",
+            bytes_by_display_variant(value, &element_view.value_display),
+        ),
+        ElementOrPlaceholder::Element(Element::SumItem { value, .. }) => ("SumItem", value.to_string()),
+        ElementOrPlaceholder::Element(Element::Reference(reference)) => (
+            "Reference",
+            reference_target(path, &element_view.key, reference)
+                .map(|(target_path, target_key)| {
+                    let segments: Vec<_> = target_path
+                        .to_vec()
+                        .iter()
+                        .chain([&target_key])
+                        .map(|s| segment_display(s))
+                        .collect();
+                    format!("[{}]", segments.join(", "))
+                })
+                .unwrap_or_else(|| "Bad reference".to_owned()),
+        ),
+        ElementOrPlaceholder::Element(Element::Sumtree { sum, .. }) => ("Sumtree", sum.to_string()),
+        ElementOrPlaceholder::Element(Element::Subtree { .. }) => ("Subtree", String::new()),
+        ElementOrPlaceholder::Placeholder => ("Placeholder", String::new()),
+    }
+}
+
+fn element_flags(element_view: &ElementView, decoders: &[FlagsDecoder]) -> String {
+    let flags = match &element_view.value {
+        ElementOrPlaceholder::Element(
+            Element::Item { element_flags, .. }
+            | Element::SumItem { element_flags, .. }
+            | Element::Sumtree { element_flags, .. }
+            | Element::Subtree { element_flags, .. },
+        ) => element_flags.as_deref(),
+        ElementOrPlaceholder::Element(Element::Reference(reference)) => reference_flags(reference).as_deref(),
+        ElementOrPlaceholder::Placeholder => None,
+    };
+    let Some(flags) = flags else {
+        return String::new();
+    };
+
+    let decoded = match element_view.flags_view {
+        FlagsView::Auto => decoders.iter().find_map(|d| d.decode(flags)),
+        FlagsView::Decoder(decoder) => decoder.decode(flags),
+        FlagsView::Raw => None,
+    };
+    decoded.unwrap_or_else(|| bytes_by_display_variant(flags, &element_view.flags_display))
+}
+
+/// Fetched elements of a subtree as CSV, keys are shown the same way as in
+/// the subtree view
+pub(super) fn subtree_csv(path: Path, subtree_data: &SubtreeData, decoders: &[FlagsDecoder]) -> String {
+    let mut csv = String::from(HEADER);
+    csv.push('\n');
+
+    for (key, element_view) in subtree_data.elements.iter() {
+        let key_display = path
+            .child(key.clone())
+            .get_display_variant()
+            .unwrap_or_else(|| BytesDisplayVariant::guess(key));
+        let (element_type, value) = element_type_and_value(path, element_view);
+        let row = [
+            bytes_by_display_variant(key, &key_display),
+            element_type.to_owned(),
+            value,
+            element_flags(element_view, decoders),
+        ];
+        csv.push_str(&row.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Where an exported CSV goes
+#[derive(Clone, Copy)]
+pub(super) enum ExportTarget {
+    Clipboard,
+    /// A file in the working directory
+    #[cfg(not(target_arch = "wasm32"))]
+    File,
+}
+
+pub(super) fn export_csv(ctx: &egui::Context, path: Path, csv: String, target: ExportTarget) {
+    let name = path
+        .for_last_segment(|segment| segment.view_by_display())
+        .unwrap_or_else(|| "root".to_owned());
+
+    match target {
+        ExportTarget::Clipboard => {
+            ctx.copy_text(csv);
+            log::info!("Subtree {name} copied to the clipboard as CSV");
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        ExportTarget::File => {
+            let file_name = format!(
+                "grovedbg-{}-{}.csv",
+                name.chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                    .collect::<String>(),
+                chrono::Utc::now().format("%Y%m%d%H%M%S")
+            );
+            match std::fs::write(&file_name, csv) {
+                Ok(_) => log::info!("Subtree {name} exported to {file_name}"),
+                Err(e) => log::error!("Unable to export subtree {name}: {e}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
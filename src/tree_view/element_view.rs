@@ -2,25 +2,47 @@ mod reference_view;
 
 use std::collections::BTreeSet;
 
-use eframe::egui::{self, Context, Label, Layout, RichText, Vec2};
-use grovedb_epoch_based_storage_flags::StorageFlags;
+use chrono::{DateTime, Local};
+use eframe::egui::{self, CollapsingHeader, ComboBox, Context, Label, Layout, RichText, Vec2};
 use grovedbg_types::{CryptoHash, Element, Key};
 use reference_view::draw_reference;
+pub(crate) use reference_view::{reference_flags, reference_target};
+use strum::IntoEnumIterator;
 
-use super::{ElementViewContext, NODE_WIDTH};
+use super::ElementViewContext;
 use crate::{
+    bus::UserAction,
     bytes_utils::{
-        binary_label, binary_label_colored, bytes_as_dpp_vote_poll, bytes_by_display_variant,
-        BytesDisplayVariant,
+        binary_label, binary_label_colored, bytes_as_dpp_vote_poll, bytes_as_structure,
+        bytes_by_display_variant, bytes_preview, BytesDisplayVariant,
     },
-    path_ctx::{full_path_display, full_path_display_iter},
-    protocol::FetchCommand,
-    theme::element_to_color,
-    tree_data::SubtreeDataMap,
+    density::node_width,
+    display_defaults::DisplayDefaults,
+    flags_decoder::FlagsDecoder,
+    format,
+    merk_hash::{HashCheck, Verification},
+    notes::NoteTarget,
+    path_ctx::{full_path_display, full_path_display_iter, Path},
+    permalink,
+    proof_viewer::MerkProofNodeViewer,
+    protocol::{Capability, NodeSource},
+    redaction, schema_registry,
+    theme::{data_age_color, element_to_color, input_error_color, proof_node_color, success_color},
+    tree_data::{Referrers, SubtreeDataMap},
 };
 
 const ELEMENT_HEIGHT: f32 = 20.;
 
+/// How element flags are shown
+#[derive(Clone, Copy, PartialEq, Default)]
+pub(crate) enum FlagsView {
+    /// First profile decoder that succeeds, raw bytes otherwise
+    #[default]
+    Auto,
+    Decoder(FlagsDecoder),
+    Raw,
+}
+
 /// Same as `Element` of `grovedbg-types` except with an addition of
 /// `SubtreePlaceholder` to represent known but incomplete subtree mentions.
 pub(crate) enum ElementOrPlaceholder {
@@ -28,6 +50,35 @@ pub(crate) enum ElementOrPlaceholder {
     Placeholder,
 }
 
+impl ElementOrPlaceholder {
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            ElementOrPlaceholder::Element(Element::Item { .. }) => "Item",
+            ElementOrPlaceholder::Element(Element::SumItem { .. }) => "SumItem",
+            ElementOrPlaceholder::Element(Element::Reference(_)) => "Reference",
+            ElementOrPlaceholder::Element(Element::Sumtree { .. }) => "Sumtree",
+            ElementOrPlaceholder::Element(Element::Subtree { .. }) => "Subtree",
+            ElementOrPlaceholder::Placeholder => "Placeholder",
+        }
+    }
+}
+
+/// When and by which command an element was fetched
+#[derive(Clone, Copy)]
+pub(crate) struct FetchInfo {
+    pub(crate) source: NodeSource,
+    pub(crate) at: DateTime<Local>,
+}
+
+impl FetchInfo {
+    pub(crate) fn now(source: NodeSource) -> Self {
+        Self {
+            source,
+            at: Local::now(),
+        }
+    }
+}
+
 pub(crate) struct ElementView {
     pub(crate) key: Key,
     pub(crate) value: ElementOrPlaceholder,
@@ -36,14 +87,22 @@ pub(crate) struct ElementView {
     pub(crate) kv_digest_hash: Option<CryptoHash>,
     pub(crate) value_hash: Option<CryptoHash>,
     pub(crate) value_display: BytesDisplayVariant,
+    pub(crate) flags_view: FlagsView,
     pub(crate) flags_display: BytesDisplayVariant,
     pub(crate) kv_digest_hash_display: BytesDisplayVariant,
     pub(crate) value_hash_display: BytesDisplayVariant,
     pub(crate) node_hash: Option<CryptoHash>,
     pub(crate) node_hash_display: BytesDisplayVariant,
+    /// Hashes recomputed locally, reset once the node or its children change
+    pub(crate) hash_check: Option<HashCheck>,
     pub(crate) show_hashes: bool,
     pub(crate) show_reference_details: bool,
+    pub(crate) show_referrers: bool,
     pub(crate) merk_visible: bool,
+    pub(crate) fetched: Option<FetchInfo>,
+    /// Item values longer than this are shown truncated
+    pub(crate) large_value_bytes: usize,
+    pub(crate) show_full_value: bool,
 }
 
 impl ElementView {
@@ -56,14 +115,20 @@ impl ElementView {
             kv_digest_hash: None,
             value_hash: None,
             value_display: Default::default(),
+            flags_view: Default::default(),
             flags_display: Default::default(),
             kv_digest_hash_display: BytesDisplayVariant::Hex,
             value_hash_display: BytesDisplayVariant::Hex,
             node_hash: None,
             node_hash_display: BytesDisplayVariant::Hex,
+            hash_check: None,
             show_hashes: Default::default(),
             show_reference_details: Default::default(),
+            show_referrers: false,
             merk_visible: false,
+            fetched: None,
+            large_value_bytes: DisplayDefaults::default().large_value_bytes,
+            show_full_value: false,
         }
     }
 
@@ -74,29 +139,44 @@ impl ElementView {
         right_child: Option<Key>,
         kv_digest_hash: Option<CryptoHash>,
         value_hash: Option<CryptoHash>,
+        display_defaults: &DisplayDefaults,
     ) -> Self {
-        let value_display = if let ElementOrPlaceholder::Element(Element::Item { value, .. }) = &value {
-            BytesDisplayVariant::guess(&value)
-        } else {
-            BytesDisplayVariant::Hex
-        };
-        Self {
+        let mut element_view = Self {
             key,
             value,
             left_child,
             right_child,
-            value_display,
+            value_display: BytesDisplayVariant::Hex,
             kv_digest_hash,
             value_hash,
+            flags_view: Default::default(),
             flags_display: BytesDisplayVariant::U8,
             kv_digest_hash_display: BytesDisplayVariant::Hex,
             value_hash_display: BytesDisplayVariant::Hex,
             node_hash: None,
             node_hash_display: BytesDisplayVariant::Hex,
+            hash_check: None,
             show_hashes: false,
             show_reference_details: false,
+            show_referrers: false,
             merk_visible: false,
+            fetched: None,
+            large_value_bytes: display_defaults.large_value_bytes,
+            show_full_value: false,
+        };
+        element_view.apply_display_defaults(display_defaults);
+        element_view
+    }
+
+    pub(crate) fn apply_display_defaults(&mut self, display_defaults: &DisplayDefaults) {
+        if let ElementOrPlaceholder::Element(Element::Item { value, .. }) = &self.value {
+            self.value_display = display_defaults.item_value(value);
         }
+        self.flags_display = display_defaults.flags;
+        self.kv_digest_hash_display = display_defaults.hashes;
+        self.value_hash_display = display_defaults.hashes;
+        self.node_hash_display = display_defaults.hashes;
+        self.large_value_bytes = display_defaults.large_value_bytes;
     }
 
     pub(crate) fn draw<'af, 'pa, 'pf, 'b>(
@@ -104,6 +184,9 @@ impl ElementView {
         ui: &mut egui::Ui,
         element_view_context: &mut ElementViewContext<'af, 'pa, 'pf, 'b>,
         visibility: &mut BTreeSet<Key>,
+        selection: &mut BTreeSet<Key>,
+        referrers: Option<&Referrers>,
+        proof_node: Option<&MerkProofNodeViewer>,
         subtrees_map: &SubtreeDataMap<'pa>,
     ) {
         let ctx: Context = ui.ctx().clone();
@@ -112,15 +195,27 @@ impl ElementView {
 
         // Draw key
         ui.horizontal(|key_line| {
+            let mut selected = selection.contains(&self.key);
+            if key_line
+                .toggle_value(&mut selected, egui_phosphor::regular::CHECK_SQUARE)
+                .on_hover_text("Select the key for a path query")
+                .changed()
+            {
+                if selected {
+                    selection.insert(self.key.clone());
+                } else {
+                    selection.remove(&self.key);
+                }
+            }
             if key_line
                 .button(egui_phosphor::regular::ARROW_CLOCKWISE)
                 .on_hover_text("Refetch the node")
                 .clicked()
             {
-                element_view_context.bus.fetch_command(FetchCommand::FetchNode {
-                    path: element_view_context.path().to_vec(),
-                    key: self.key.clone(),
-                });
+                element_view_context
+                    .bus
+                    .fetch()
+                    .node(element_view_context.path(), self.key.clone());
             }
             if key_line
                 .button(egui_phosphor::regular::HASH)
@@ -129,6 +224,105 @@ impl ElementView {
             {
                 self.show_hashes = !self.show_hashes;
             }
+            if key_line
+                .add_enabled(
+                    element_view_context.bus.supports(Capability::FetchRawNode),
+                    egui::Button::new(egui_phosphor::regular::BINARY),
+                )
+                .on_hover_text("Show element bytes as stored by Merk")
+                .on_disabled_hover_text("The endpoint doesn't provide raw node bytes")
+                .clicked()
+            {
+                element_view_context
+                    .bus
+                    .fetch()
+                    .raw_node(element_view_context.path(), self.key.clone());
+            }
+            if key_line
+                .button(egui_phosphor::regular::LIST_CHECKS)
+                .on_hover_text("Build a path query for this key only")
+                .clicked()
+            {
+                element_view_context.bus.user_action(UserAction::QueryKey(
+                    element_view_context.path(),
+                    self.key.clone(),
+                ));
+            }
+            permalink::draw_copy_link(key_line, &element_view_context.path().to_vec(), &self.key);
+            if key_line
+                .button(egui_phosphor::regular::NOTE_PENCIL)
+                .on_hover_text("Add a note to the element")
+                .clicked()
+            {
+                element_view_context
+                    .bus
+                    .user_action(UserAction::OpenNote(NoteTarget::Key(
+                        element_view_context.path().to_vec(),
+                        self.key.clone(),
+                    )));
+            }
+            if element_view_context.bus.comparison_session().is_some()
+                && key_line
+                    .button(egui_phosphor::regular::ARROWS_LEFT_RIGHT)
+                    .on_hover_text("Compare the node with the kept session")
+                    .clicked()
+            {
+                element_view_context
+                    .bus
+                    .fetch_comparison(element_view_context.path().to_vec(), self.key.clone());
+            }
+
+            if key_line
+                .add_enabled(
+                    element_view_context.bus.supports(Capability::FetchReferrers),
+                    egui::Button::new(egui_phosphor::regular::ARROW_BEND_DOWN_LEFT),
+                )
+                .on_hover_text("Fetch elements with references pointing at this element")
+                .on_disabled_hover_text("The endpoint doesn't track references pointing at elements")
+                .clicked()
+            {
+                element_view_context
+                    .bus
+                    .fetch()
+                    .referrers(element_view_context.path(), self.key.clone());
+                self.show_referrers = true;
+            }
+            if let Some(referrers) = referrers {
+                key_line
+                    .toggle_value(
+                        &mut self.show_referrers,
+                        format!(
+                            "{} {}",
+                            egui_phosphor::regular::ARROW_BEND_DOWN_RIGHT,
+                            referrers.len()
+                        ),
+                    )
+                    .on_hover_text("Show loaded references pointing at this element");
+            }
+
+            if let Some(fetched) = &self.fetched {
+                let age = (Local::now() - fetched.at).num_seconds();
+                key_line
+                    .label(RichText::new(egui_phosphor::regular::CLOCK).color(data_age_color(&ctx, age)))
+                    .on_hover_text(format!(
+                        "Fetched by {} at {}, {} ago",
+                        fetched.source.as_ref(),
+                        format::time_of_day(&fetched.at),
+                        age_display(age)
+                    ));
+            }
+
+            if let Some(proof_node) = proof_node {
+                key_line
+                    .label(RichText::new(egui_phosphor::regular::SHIELD_CHECK).color(proof_node_color(&ctx)))
+                    .on_hover_text(format!("Covered by the last proof as {}", proof_node.kind_name()));
+            }
+
+            if self.hash_check.is_some_and(|check| check.has_mismatch()) {
+                key_line
+                    .label(RichText::new(egui_phosphor::regular::WARNING).color(input_error_color(&ctx)))
+                    .on_hover_text("Hashes reported by GroveDB differ from the locally computed ones");
+            }
 
             if let Some(alias) = element_view_context.profile_ctx().key_view(&self.key) {
                 key_line.add(
@@ -156,49 +350,105 @@ impl ElementView {
         // Draw value
         let layout = Layout::top_down(egui::Align::Min);
         ui.allocate_ui_with_layout(
-            Vec2::new(NODE_WIDTH, ELEMENT_HEIGHT),
+            Vec2::new(node_width(), ELEMENT_HEIGHT),
             layout,
             |value_ui: &mut egui::Ui| {
+                let value_hidden = element_view_context.keys_only
+                    && matches!(
+                        self.value,
+                        ElementOrPlaceholder::Element(
+                            Element::Item { .. } | Element::SumItem { .. } | Element::Reference(_)
+                        )
+                    );
                 match &self.value {
+                    // Subtrees keep their controls to be shown or focused
+                    _ if value_hidden => {
+                        value_ui.weak("Value hidden");
+                    }
                     ElementOrPlaceholder::Element(Element::Item { value, element_flags }) => {
-                        let mut profile_display = element_view_context.profile_ctx().value_display(&self.key);
+                        if element_view_context.profile_ctx().values_redacted(value_ui.ctx()) {
+                            redaction::draw_redacted(value_ui, value.len());
+                        } else {
+                            let mut profile_display =
+                                element_view_context.profile_ctx().value_display(&self.key);
+
+                            let mut value = value.as_slice();
+                            if let Some(prefix) = element_view_context.profile_ctx().version_prefix() {
+                                match prefix.split(value) {
+                                    Some((version, payload)) => {
+                                        value_ui.label(RichText::new(format!("Version: {version}")).strong());
+                                        value = payload;
+                                    }
+                                    None => {
+                                        value_ui.label(
+                                            RichText::new("No valid version prefix")
+                                                .color(input_error_color(value_ui.ctx())),
+                                        );
+                                    }
+                                }
+                            }
+
+                            schema_registry::draw_item_validation(value_ui, &path.to_vec(), value);
+
+                            let display = profile_display.as_mut().unwrap_or(&mut self.value_display);
+
+                            let truncated = value.len() > self.large_value_bytes && !self.show_full_value;
+                            if truncated {
+                                binary_label(value_ui, &value[..self.large_value_bytes], display);
+                            } else {
+                                binary_label(value_ui, value, display);
+                            }
 
-                        let display = profile_display.as_mut().unwrap_or(&mut self.value_display);
+                            if value.len() > self.large_value_bytes {
+                                draw_large_value_notice(value_ui, value.len(), &mut self.show_full_value);
+                            }
 
-                        binary_label(value_ui, value, display);
-                        if matches!(display, BytesDisplayVariant::DppVotePoll) {
-                            if let Some(json) =
-                                bytes_as_dpp_vote_poll(value).and_then(|v| serde_json::to_value(v).ok())
-                            {
-                                egui_json_tree::JsonTree::new("json-view", &json).show(value_ui);
+                            if !truncated {
+                                bytes_preview(value_ui, value, display);
+                                if matches!(display, BytesDisplayVariant::DppVotePoll) {
+                                    if let Some(json) = bytes_as_dpp_vote_poll(value)
+                                        .and_then(|v| serde_json::to_value(v).ok())
+                                    {
+                                        egui_json_tree::JsonTree::new("json-view", &json).show(value_ui);
+                                    }
+                                }
+                                if matches!(display, BytesDisplayVariant::StructuredGuess) {
+                                    if let Some(json) = bytes_as_structure(value) {
+                                        egui_json_tree::JsonTree::new("structure-view", &json).show(value_ui);
+                                    }
+                                }
                             }
                         }
 
                         if let Some(flags) = element_flags {
-                            value_ui.horizontal(|line| {
-                                line.label("Flags:");
-                                if let Some(storage_flags) = StorageFlags::deserialize(&flags).ok().flatten()
-                                {
-                                    line.label(format!("{storage_flags}"));
-                                } else {
-                                    binary_label(line, flags, &mut self.flags_display);
-                                }
-                            });
+                            draw_flags(
+                                value_ui,
+                                path,
+                                &self.key,
+                                flags,
+                                element_view_context.profile_ctx().flags_decoders(),
+                                &mut self.flags_view,
+                                &mut self.flags_display,
+                            );
                         }
                     }
                     ElementOrPlaceholder::Element(Element::SumItem { value, element_flags }) => {
-                        value_ui.label(format!("Value: {value}"));
+                        if element_view_context.profile_ctx().values_redacted(value_ui.ctx()) {
+                            redaction::draw_redacted(value_ui, std::mem::size_of_val(value));
+                        } else {
+                            value_ui.label(format!("Value: {}", format::number(*value)));
+                        }
 
                         if let Some(flags) = element_flags {
-                            value_ui.horizontal(|line| {
-                                line.label("Flags:");
-                                if let Some(storage_flags) = StorageFlags::deserialize(&flags).ok().flatten()
-                                {
-                                    line.label(format!("{storage_flags}"));
-                                } else {
-                                    binary_label(line, flags, &mut self.flags_display);
-                                }
-                            });
+                            draw_flags(
+                                value_ui,
+                                path,
+                                &self.key,
+                                flags,
+                                element_view_context.profile_ctx().flags_decoders(),
+                                &mut self.flags_view,
+                                &mut self.flags_display,
+                            );
                         }
                     }
                     ElementOrPlaceholder::Element(Element::Reference(reference)) => {
@@ -208,6 +458,7 @@ impl ElementView {
                             &self.key,
                             reference,
                             &mut self.show_reference_details,
+                            &mut self.flags_view,
                             &mut self.flags_display,
                             subtrees_map,
                         )
@@ -247,6 +498,7 @@ impl ElementView {
                             if checkbox_before != checkbox {
                                 if checkbox {
                                     visibility.insert(self.key.clone());
+                                    element_view_context.bus.request_prefetch();
                                 } else {
                                     visibility.remove(&self.key);
                                 }
@@ -255,18 +507,18 @@ impl ElementView {
                             if line.button(egui_phosphor::regular::MAGNIFYING_GLASS).clicked() {
                                 element_view_context.focus_child_subtree(self.key.clone());
                             }
-                            line.label(format!("Sum: {sum}"));
+                            line.label(format!("Sum: {}", format::number(*sum)));
                         });
                         if let Some(flags) = element_flags {
-                            value_ui.horizontal(|line| {
-                                line.label("Flags:");
-                                if let Some(storage_flags) = StorageFlags::deserialize(&flags).ok().flatten()
-                                {
-                                    line.label(format!("{storage_flags}"));
-                                } else {
-                                    binary_label(line, flags, &mut self.flags_display);
-                                }
-                            });
+                            draw_flags(
+                                value_ui,
+                                path,
+                                &self.key,
+                                flags,
+                                element_view_context.profile_ctx().flags_decoders(),
+                                &mut self.flags_view,
+                                &mut self.flags_display,
+                            );
                         }
                     }
                     ElementOrPlaceholder::Element(Element::Subtree { element_flags, .. }) => {
@@ -279,6 +531,7 @@ impl ElementView {
                             if checkbox_before != checkbox {
                                 if checkbox {
                                     visibility.insert(self.key.clone());
+                                    element_view_context.bus.request_prefetch();
                                 } else {
                                     visibility.remove(&self.key);
                                 }
@@ -289,38 +542,71 @@ impl ElementView {
                             line.label("Subtree");
                         });
                         if let Some(flags) = element_flags {
-                            value_ui.horizontal(|line| {
-                                line.label("Flags:");
-                                if let Some(storage_flags) = StorageFlags::deserialize(&flags).ok().flatten()
-                                {
-                                    line.label(format!("{storage_flags}"));
-                                } else {
-                                    binary_label(line, flags, &mut self.flags_display);
-                                }
-                            });
+                            draw_flags(
+                                value_ui,
+                                path,
+                                &self.key,
+                                flags,
+                                element_view_context.profile_ctx().flags_decoders(),
+                                &mut self.flags_view,
+                                &mut self.flags_display,
+                            );
                         }
                     }
                     ElementOrPlaceholder::Placeholder => {
                         value_ui.label("Placeholder");
                     }
                 };
+                if let Some(referrers) = referrers.filter(|_| self.show_referrers) {
+                    draw_referrers(value_ui, element_view_context, referrers);
+                }
                 if self.show_hashes {
+                    if value_ui
+                        .button(format!("{} Verify hashes", egui_phosphor::regular::SEAL_CHECK))
+                        .on_hover_text("Recompute value, KV digest and node hashes locally from loaded data")
+                        .clicked()
+                    {
+                        element_view_context
+                            .bus
+                            .user_action(UserAction::VerifyNodeHash(path, self.key.clone()));
+                    }
+                    if matches!(self.value, ElementOrPlaceholder::Element(Element::Item { .. }))
+                        && value_ui
+                            .button(format!("{} What if", egui_phosphor::regular::FLASK))
+                            .on_hover_text(
+                                "Edit the value locally to see which hashes would change up to the root",
+                            )
+                            .clicked()
+                    {
+                        element_view_context
+                            .bus
+                            .user_action(UserAction::OpenWhatIf(path, self.key.clone()));
+                    }
                     value_ui.horizontal(|line| {
                         if let Some(hash) = &self.node_hash {
                             line.label("Node hash:");
                             binary_label(line, hash, &mut self.node_hash_display);
+                            if let Some(check) = &self.hash_check {
+                                draw_verification(line, &check.node_hash);
+                            }
                         }
                     });
                     value_ui.horizontal(|line| {
                         if let Some(hash) = &self.kv_digest_hash {
                             line.label("KV digest hash:");
                             binary_label(line, hash, &mut self.kv_digest_hash_display);
+                            if let Some(check) = &self.hash_check {
+                                draw_verification(line, &check.kv_digest_hash);
+                            }
                         }
                     });
                     value_ui.horizontal(|line| {
                         if let Some(hash) = &self.value_hash {
                             line.label("Value hash:");
                             binary_label(line, hash, &mut self.value_hash_display);
+                            if let Some(check) = &self.hash_check {
+                                draw_verification(line, &check.value_hash);
+                            }
                         }
                     });
                 }
@@ -328,3 +614,138 @@ impl ElementView {
         );
     }
 }
+
+pub(super) fn draw_verification(ui: &mut egui::Ui, verification: &Verification) {
+    match verification {
+        Verification::Match => {
+            ui.label(RichText::new(egui_phosphor::regular::CHECK_CIRCLE).color(success_color(ui.ctx())))
+                .on_hover_text("Matches the locally computed hash");
+        }
+        Verification::Mismatch { computed } => {
+            ui.label(RichText::new(egui_phosphor::regular::X_CIRCLE).color(input_error_color(ui.ctx())))
+                .on_hover_text(format!(
+                    "Locally computed hash differs: {}",
+                    hex::encode(computed)
+                ));
+        }
+        Verification::Unknown(reason) => {
+            ui.label(egui_phosphor::regular::QUESTION).on_hover_text(*reason);
+        }
+    }
+}
+
+/// List of references to the element, each can be fetched and focused on
+fn draw_referrers(ui: &mut egui::Ui, element_view_context: &mut ElementViewContext, referrers: &Referrers) {
+    ui.label("Referenced by:");
+    for (path, key) in referrers.iter() {
+        ui.horizontal(|line| {
+            if line
+                .button(egui_phosphor::regular::MAGNIFYING_GLASS)
+                .on_hover_text("Fetch and focus on the reference")
+                .clicked()
+            {
+                let path = element_view_context.path().get_ctx().add_path(path.clone());
+                element_view_context.bus.fetch().node(path, key.clone());
+                element_view_context.focus(path, Some(key.clone()));
+            }
+            let segments: Vec<_> = path
+                .iter()
+                .chain([key])
+                .map(|s| bytes_by_display_variant(s, &BytesDisplayVariant::guess(s)))
+                .collect();
+            line.label(format!("[{}]", segments.join(", ")));
+        });
+    }
+}
+
+/// Size of a large value with a toggle between a truncated and a full view
+fn draw_large_value_notice(ui: &mut egui::Ui, len: usize, show_full_value: &mut bool) {
+    ui.horizontal(|line| {
+        line.label(
+            RichText::new(format!("{} {len} bytes", egui_phosphor::regular::WARNING))
+                .color(line.visuals().warn_fg_color),
+        )
+        .on_hover_text("Large values may slow down rendering");
+        let text = if *show_full_value {
+            "Truncate"
+        } else {
+            "Load full value"
+        };
+        if line.button(text).clicked() {
+            *show_full_value = !*show_full_value;
+        }
+    });
+}
+
+fn draw_flags(
+    ui: &mut egui::Ui,
+    path: Path,
+    key: &[u8],
+    flags: &[u8],
+    decoders: &[FlagsDecoder],
+    flags_view: &mut FlagsView,
+    flags_display: &mut BytesDisplayVariant,
+) {
+    // Flags are decoded only once drawn, errors are not kept with the element
+    let decoded = match flags_view {
+        FlagsView::Auto => FlagsDecoder::decode_first(decoders, flags),
+        FlagsView::Decoder(decoder) => decoder.try_decode(flags).map_err(|e| vec![(*decoder, e)]),
+        FlagsView::Raw => Err(Vec::new()),
+    };
+
+    ui.horizontal(|line| {
+        // Auto ids shift as elements are added and removed, so the open menu would
+        // jump between elements
+        ComboBox::from_id_salt(("element_flags", path.to_vec(), key))
+            .selected_text("Flags:")
+            .show_ui(line, |menu| {
+                menu.selectable_value(flags_view, FlagsView::Auto, "Auto");
+                for decoder in decoders {
+                    menu.selectable_value(flags_view, FlagsView::Decoder(*decoder), decoder.as_ref());
+                }
+                menu.selectable_value(flags_view, FlagsView::Raw, "Raw bytes");
+                menu.separator();
+                menu.label(RichText::new("Not in the profile").weak());
+                for decoder in FlagsDecoder::iter().filter(|d| !decoders.contains(d)) {
+                    menu.selectable_value(flags_view, FlagsView::Decoder(decoder), decoder.as_ref());
+                }
+            })
+            .response
+            .on_hover_text("Flags decoder");
+
+        match &decoded {
+            Ok(decoded) => {
+                line.label(decoded);
+            }
+            Err(_) => {
+                binary_label(line, flags, flags_display);
+            }
+        }
+    });
+
+    let errors = match decoded {
+        Err(errors) if !errors.is_empty() => errors,
+        _ => return,
+    };
+    CollapsingHeader::new(
+        RichText::new(format!(
+            "{} Flags are not decoded",
+            egui_phosphor::regular::WARNING
+        ))
+        .weak(),
+    )
+    .id_salt(ui.next_auto_id())
+    .show(ui, |collapsing| {
+        for (decoder, error) in errors {
+            collapsing.label(format!("{}: {error}", decoder.as_ref()));
+        }
+    });
+}
+
+fn age_display(seconds: i64) -> String {
+    match seconds {
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m", s / 60),
+        s => format!("{}h {}m", s / 3600, s % 3600 / 60),
+    }
+}
@@ -1,11 +1,11 @@
 use std::{borrow::Cow, cmp, fmt::Write};
 
 use eframe::egui::{self, Painter, Pos2, Stroke, Vec2};
-use grovedb_epoch_based_storage_flags::StorageFlags;
-use grovedbg_types::Reference;
+use grovedbg_types::{Key, Reference};
 
+use super::{draw_flags, FlagsView};
 use crate::{
-    bytes_utils::{binary_label, bytes_by_display_variant, BytesDisplayVariant},
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
     path_ctx::{path_label, Path},
     theme::reference_line_color,
     tree_data::SubtreeDataMap,
@@ -20,6 +20,7 @@ pub(super) fn draw_reference(
     key: &[u8],
     reference: &Reference,
     show_details: &mut bool,
+    flags_view: &mut FlagsView,
     flags_display: &mut BytesDisplayVariant,
     subtrees_map: &SubtreeDataMap,
 ) -> Result<(), ReferenceError> {
@@ -72,25 +73,16 @@ pub(super) fn draw_reference(
         ));
     });
 
-    let flags = match reference {
-        Reference::AbsolutePathReference { element_flags, .. } => element_flags,
-        Reference::UpstreamRootHeightReference { element_flags, .. } => element_flags,
-        Reference::UpstreamRootHeightWithParentPathAdditionReference { element_flags, .. } => element_flags,
-        Reference::UpstreamFromElementHeightReference { element_flags, .. } => element_flags,
-        Reference::CousinReference { element_flags, .. } => element_flags,
-        Reference::RemovedCousinReference { element_flags, .. } => element_flags,
-        Reference::SiblingReference { element_flags, .. } => element_flags,
-    };
-
-    if let Some(flags) = flags {
-        ui.horizontal(|line| {
-            line.label("Flags:");
-            if let Some(storage_flags) = StorageFlags::deserialize(&flags).ok().flatten() {
-                line.label(format!("{storage_flags}"));
-            } else {
-                binary_label(line, flags, flags_display);
-            }
-        });
+    if let Some(flags) = reference_flags(reference) {
+        draw_flags(
+            ui,
+            element_view_context.path(),
+            key,
+            flags,
+            element_view_context.profile_ctx().flags_decoders(),
+            flags_view,
+            flags_display,
+        );
     }
 
     if *show_details {
@@ -109,9 +101,9 @@ pub(super) fn draw_reference(
             .unwrap_or_default())
     .then(|| {
         ui.memory(|mem| {
-            mem.area_rect(element_view_context.path().id())
+            mem.area_rect(element_view_context.area_id(element_view_context.path()))
                 .and_then(|rect_from| {
-                    mem.area_rect(referenced_path.id())
+                    mem.area_rect(element_view_context.area_id(referenced_path))
                         .map(|rect_to| (rect_from, rect_to))
                 })
         })
@@ -229,8 +221,31 @@ fn hex_array(byte_slices: &[impl AsRef<[u8]>]) -> String {
     buf
 }
 
+pub(crate) fn reference_flags(reference: &Reference) -> &Option<Vec<u8>> {
+    match reference {
+        Reference::AbsolutePathReference { element_flags, .. } => element_flags,
+        Reference::UpstreamRootHeightReference { element_flags, .. } => element_flags,
+        Reference::UpstreamRootHeightWithParentPathAdditionReference { element_flags, .. } => element_flags,
+        Reference::UpstreamFromElementHeightReference { element_flags, .. } => element_flags,
+        Reference::CousinReference { element_flags, .. } => element_flags,
+        Reference::RemovedCousinReference { element_flags, .. } => element_flags,
+        Reference::SiblingReference { element_flags, .. } => element_flags,
+    }
+}
+
 pub(super) struct ReferenceError(pub(super) &'static str);
 
+/// Absolute path and key of the element a reference points at
+pub(crate) fn reference_target<'a>(
+    current_path: Path<'a>,
+    current_key: &[u8],
+    reference: &Reference,
+) -> Option<(Path<'a>, Key)> {
+    get_absolute_path_key(current_path, current_key, reference)
+        .ok()
+        .map(|(path, key)| (path, key.into_owned()))
+}
+
 fn get_absolute_path_key<'a, 'b>(
     current_path: Path<'a>,
     current_key: &'b [u8],
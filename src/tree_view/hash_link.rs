@@ -0,0 +1,134 @@
+//! How a child subtree's root hash is embedded into its parent: the value
+//! hash of the subtree element combines the hash of the element's stored
+//! bytes with the child's Merk root hash.
+
+use eframe::egui::{self, Grid, Label, RichText};
+use grovedbg_types::{CryptoHash, Element};
+
+use super::{element_view::draw_verification, ElementOrPlaceholder};
+use crate::{
+    merk_hash::{self, Verification},
+    path_ctx::Path,
+    tree_data::TreeData,
+};
+
+/// Hashes of the link recomputed from loaded data
+struct HashLink {
+    element_bytes: Vec<u8>,
+    element_hash: CryptoHash,
+    child_root_hash: Option<CryptoHash>,
+    combined: Option<CryptoHash>,
+    reported: Option<CryptoHash>,
+}
+
+impl HashLink {
+    fn new(path: Path, tree_data: &TreeData) -> Result<Self, &'static str> {
+        let (parent_path, key) = path
+            .parent_with_key()
+            .ok_or("The root subtree's root hash is the GroveDB root hash")?;
+        let parent = tree_data
+            .get(&parent_path)
+            .ok_or("The parent subtree is not loaded")?;
+        let element = parent
+            .elements
+            .get(&key)
+            .ok_or("The subtree element is not loaded")?;
+        let (root_key, sum, flags) = match &element.value {
+            ElementOrPlaceholder::Element(Element::Subtree {
+                root_key,
+                element_flags,
+            }) => (root_key, None, element_flags),
+            ElementOrPlaceholder::Element(Element::Sumtree {
+                root_key,
+                sum,
+                element_flags,
+            }) => (root_key, Some(*sum), element_flags),
+            _ => return Err("Fetch the subtree element in the parent subtree first"),
+        };
+
+        let element_bytes = merk_hash::subtree_element_bytes(root_key.as_deref(), sum, flags.as_deref());
+        let element_hash = merk_hash::value_hash(&element_bytes);
+        let child_root_hash = if root_key.is_some() {
+            tree_data.get(&path).and_then(|subtree| subtree.root_hash())
+        } else {
+            // An empty Merk has a null root hash
+            Some(merk_hash::NULL_HASH)
+        };
+
+        Ok(HashLink {
+            combined: child_root_hash.map(|root| merk_hash::combine_hash(&element_hash, &root)),
+            element_bytes,
+            element_hash,
+            child_root_hash,
+            reported: element.value_hash,
+        })
+    }
+
+    fn verification(&self) -> Verification {
+        match (self.combined, self.reported) {
+            (Some(combined), Some(reported)) => Verification::compare(combined, &reported),
+            (None, _) => Verification::Unknown("Root hash of the subtree is unknown"),
+            (_, None) => Verification::Unknown("Value hash of the subtree element is not loaded"),
+        }
+    }
+}
+
+/// Hex is truncated to fit the subtree, the full one is shown on hover
+fn hex_label(ui: &mut egui::Ui, bytes: &[u8]) {
+    let hex = hex::encode(bytes);
+    ui.add(Label::new(RichText::new(&hex).monospace()).truncate())
+        .on_hover_text(hex);
+}
+
+fn hash_label(ui: &mut egui::Ui, hash: Option<&CryptoHash>, missing: &str) {
+    match hash {
+        Some(hash) => hex_label(ui, hash),
+        None => {
+            ui.label(RichText::new(missing).weak());
+        }
+    }
+}
+
+pub(super) fn draw(ui: &mut egui::Ui, path: Path, tree_data: &TreeData) {
+    let link = match HashLink::new(path, tree_data) {
+        Ok(link) => link,
+        Err(reason) => {
+            ui.label(reason);
+            return;
+        }
+    };
+
+    Grid::new(path.id().with("hash_link"))
+        .num_columns(2)
+        .show(ui, |grid| {
+            grid.label("Element bytes:")
+                .on_hover_text("Serialized as GroveDB stores the subtree element in the parent subtree");
+            hex_label(grid, &link.element_bytes);
+            grid.end_row();
+
+            grid.label("Element bytes hash:");
+            hash_label(grid, Some(&link.element_hash), "");
+            grid.end_row();
+
+            grid.label("Subtree root hash:")
+                .on_hover_text("Node hash of the root node, computed from it and its children");
+            hash_label(
+                grid,
+                link.child_root_hash.as_ref(),
+                "unknown, fetch the root node and its children",
+            );
+            grid.end_row();
+
+            grid.label("Combined value hash:")
+                .on_hover_text("Hash of the element bytes hash followed by the subtree root hash");
+            hash_label(grid, link.combined.as_ref(), "unknown");
+            grid.end_row();
+
+            grid.label("Reported value hash:");
+            grid.horizontal(|line| {
+                hash_label(line, link.reported.as_ref(), "not loaded");
+                draw_verification(line, &link.verification());
+            });
+            grid.end_row();
+        });
+}
@@ -0,0 +1,143 @@
+//! Window of a huge subtree limited to a key range, moved over adjacent
+//! ranges once they are fetched.
+
+use std::ops::Bound;
+
+use grovedbg_types::{Key, Query, QueryItem, SizedQuery, SubqueryBranch};
+
+use super::SubtreeElements;
+
+/// Inclusive range of keys shown in a subtree
+#[derive(Clone, PartialEq, Debug)]
+pub(super) struct KeyRange {
+    pub(super) start: Key,
+    pub(super) end: Key,
+}
+
+/// Side of the window to move it to
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum RangeShift {
+    Before,
+    After,
+}
+
+impl KeyRange {
+    pub(super) fn new(start: Key, end: Key) -> Self {
+        if start <= end {
+            KeyRange { start, end }
+        } else {
+            KeyRange {
+                start: end,
+                end: start,
+            }
+        }
+    }
+
+    pub(super) fn contains(&self, key: &[u8]) -> bool {
+        self.start.as_slice() <= key && key <= self.end.as_slice()
+    }
+
+    /// Number of loaded keys before and after the window
+    pub(super) fn outside(&self, elements: &SubtreeElements) -> (usize, usize) {
+        let before = elements
+            .range::<Key, _>((Bound::Unbounded, Bound::Excluded(&self.start)))
+            .count();
+        let after = elements
+            .range::<Key, _>((Bound::Excluded(&self.end), Bound::Unbounded))
+            .count();
+        (before, after)
+    }
+
+    /// Window of up to `len` loaded keys next to this one, `None` if no keys
+    /// are loaded on that side
+    pub(super) fn shifted(
+        &self,
+        elements: &SubtreeElements,
+        shift: RangeShift,
+        len: usize,
+    ) -> Option<KeyRange> {
+        let keys: Vec<&Key> = match shift {
+            RangeShift::Before => elements
+                .range::<Key, _>((Bound::Unbounded, Bound::Excluded(&self.start)))
+                .rev()
+                .take(len)
+                .map(|(key, _)| key)
+                .collect(),
+            RangeShift::After => elements
+                .range::<Key, _>((Bound::Excluded(&self.end), Bound::Unbounded))
+                .take(len)
+                .map(|(key, _)| key)
+                .collect(),
+        };
+        let (first, last) = (keys.first()?, keys.last()?);
+        Some(KeyRange::new((*first).clone(), (*last).clone()))
+    }
+
+    pub(super) fn query(&self, limit: u16) -> SizedQuery {
+        sized_query(
+            QueryItem::RangeInclusive {
+                start: self.start.clone(),
+                end: self.end.clone(),
+            },
+            true,
+            limit,
+        )
+    }
+
+    /// Query of up to `limit` keys next to the window
+    pub(super) fn adjacent_query(&self, shift: RangeShift, limit: u16) -> SizedQuery {
+        match shift {
+            RangeShift::Before => sized_query(QueryItem::RangeTo(self.start.clone()), false, limit),
+            RangeShift::After => sized_query(QueryItem::RangeAfter(self.end.clone()), true, limit),
+        }
+    }
+}
+
+fn sized_query(item: QueryItem, left_to_right: bool, limit: u16) -> SizedQuery {
+    SizedQuery {
+        query: Query {
+            items: vec![item],
+            default_subquery_branch: SubqueryBranch {
+                subquery_path: None,
+                subquery: None,
+            },
+            conditional_subquery_branches: Vec::new(),
+            left_to_right,
+        },
+        limit: Some(limit),
+        offset: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_view::ElementView;
+
+    fn elements(keys: &[u8]) -> SubtreeElements {
+        keys.iter()
+            .map(|k| (vec![*k], ElementView::new_placeholder(vec![*k])))
+            .collect()
+    }
+
+    #[test]
+    fn window_moves_over_loaded_keys() {
+        let elements = elements(&[1, 2, 3, 4, 5, 6, 7]);
+        let range = KeyRange::new(vec![5], vec![3]);
+        assert_eq!(range, KeyRange::new(vec![3], vec![5]));
+        assert_eq!(range.outside(&elements), (2, 2));
+
+        assert_eq!(
+            range.shifted(&elements, RangeShift::Before, 3),
+            Some(KeyRange::new(vec![1], vec![2]))
+        );
+        assert_eq!(
+            range.shifted(&elements, RangeShift::After, 1),
+            Some(KeyRange::new(vec![6], vec![6]))
+        );
+        assert_eq!(
+            KeyRange::new(vec![1], vec![7]).shifted(&elements, RangeShift::After, 3),
+            None
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Placement of subtrees using a Reingold–Tilford layout, so a wide branch
+//! doesn't push apart everything else on its level.
+//!
+//! The layout separates nodes of the same level only, so levels are made rows
+//! as tall as their tallest node. Heights are measured on the previous frame,
+//! otherwise a tall subtree would reach into the next level of its
+//! neighbours.
+
+use std::collections::{HashMap, HashSet};
+
+use eframe::egui;
+use reingold_tilford::{Coordinate, Dimensions, NodeInfo};
+
+use crate::{
+    density::{margin_horizontal, margin_vertical, node_width},
+    path_ctx::Path,
+};
+
+const ANIMATION_SECONDS: f32 = 0.3;
+
+/// Node of the subtrees layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum LayoutNode<'pa> {
+    Subtree(Path<'pa>),
+    /// A collapsed group of child subtrees by its index among the groups of
+    /// the parent subtree
+    CollapsedGroup(Path<'pa>, usize),
+    /// A chain of placeholder-only subtrees collapsed into one widget, by the
+    /// path of its first subtree
+    Chain(Path<'pa>),
+}
+
+impl LayoutNode<'_> {
+    fn id(&self) -> egui::Id {
+        match self {
+            LayoutNode::Subtree(path) => path.id().with("layout"),
+            LayoutNode::CollapsedGroup(path, idx) => path.id().with(("layout", idx)),
+            LayoutNode::Chain(path) => path.id().with(("layout", "chain")),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct SubtreesLayout<'pa> {
+    children: HashMap<LayoutNode<'pa>, Vec<LayoutNode<'pa>>>,
+    /// Canvas areas the nodes are drawn in, to measure their heights
+    areas: HashMap<LayoutNode<'pa>, egui::Id>,
+    positions: HashMap<LayoutNode<'pa>, f32>,
+    /// Space between the bottom of a node and the bottom of its row
+    row_paddings: HashMap<LayoutNode<'pa>, f32>,
+}
+
+impl<'pa> NodeInfo<LayoutNode<'pa>> for SubtreesLayout<'pa> {
+    type Key = LayoutNode<'pa>;
+
+    fn key(&self, node: LayoutNode<'pa>) -> Self::Key {
+        node
+    }
+
+    fn children(&self, node: LayoutNode<'pa>) -> reingold_tilford::SmallVec<LayoutNode<'pa>> {
+        self.children.get(&node).into_iter().flatten().copied().collect()
+    }
+
+    fn dimensions(&self, _node: LayoutNode<'pa>) -> Dimensions {
+        Dimensions::all(0.5)
+    }
+
+    /// Borders are in node width units, vertical placement doesn't come from
+    /// the layout so only horizontal margins matter
+    fn border(&self, _node: LayoutNode<'pa>) -> Dimensions {
+        let margin = (margin_horizontal() / node_width() / 2.) as f64;
+        Dimensions {
+            top: 0.,
+            right: margin,
+            bottom: 0.,
+            left: margin,
+        }
+    }
+}
+
+impl<'pa> SubtreesLayout<'pa> {
+    /// Adds children of a node with the areas they are drawn in
+    pub(super) fn add_children(
+        &mut self,
+        parent: LayoutNode<'pa>,
+        children: Vec<(LayoutNode<'pa>, egui::Id)>,
+    ) {
+        if children.is_empty() {
+            return;
+        }
+        self.areas.extend(children.iter().copied());
+        self.children
+            .insert(parent, children.into_iter().map(|(node, _)| node).collect());
+    }
+
+    pub(super) fn compute(&mut self, ctx: &egui::Context, root: LayoutNode<'pa>, root_area: egui::Id) {
+        self.areas.insert(root, root_area);
+        self.positions = reingold_tilford::layout(self, root)
+            .into_iter()
+            .map(|(node, Coordinate { x, .. })| (node, x as f32 * node_width()))
+            .collect();
+
+        let height = |node: &LayoutNode<'pa>| {
+            self.areas
+                .get(node)
+                .and_then(|area| ctx.memory(|mem| mem.area_rect(*area)))
+                .map_or(margin_vertical(), |rect| rect.height())
+        };
+        self.row_paddings.clear();
+        let mut visited = HashSet::new();
+        let mut row = vec![root];
+        while !row.is_empty() {
+            let heights: Vec<f32> = row.iter().map(height).collect();
+            let row_height = heights.iter().copied().fold(0., f32::max);
+            for (node, height) in row.iter().zip(heights) {
+                self.row_paddings.insert(*node, row_height - height);
+            }
+            row = row
+                .iter()
+                .flat_map(|node| self.children.get(node).into_iter().flatten().copied())
+                .filter(|node| visited.insert(*node))
+                .collect();
+        }
+    }
+
+    /// Subtrees placed by the last computation
+    pub(super) fn subtrees(&self) -> impl Iterator<Item = Path<'pa>> + '_ {
+        self.positions.keys().filter_map(|node| match node {
+            LayoutNode::Subtree(path) => Some(*path),
+            LayoutNode::CollapsedGroup(..) | LayoutNode::Chain(_) => None,
+        })
+    }
+
+    /// Horizontal offset of a child from its parent, animated to move
+    /// smoothly when the layout changes
+    pub(super) fn offset(&self, ctx: &egui::Context, parent: LayoutNode<'pa>, child: LayoutNode<'pa>) -> f32 {
+        let offset = self
+            .positions
+            .get(&child)
+            .zip(self.positions.get(&parent))
+            .map(|(child_x, parent_x)| child_x - parent_x)
+            .unwrap_or_default();
+        ctx.animate_value_with_time(child.id(), offset, ANIMATION_SECONDS)
+    }
+
+    /// Vertical offset of the children of a node from its bottom, so children
+    /// of all nodes of its row start at the same height
+    pub(super) fn row_padding(&self, ctx: &egui::Context, node: LayoutNode<'pa>) -> f32 {
+        let padding = self.row_paddings.get(&node).copied().unwrap_or_default();
+        ctx.animate_value_with_time(node.id().with("row_padding"), padding, ANIMATION_SECONDS)
+    }
+}
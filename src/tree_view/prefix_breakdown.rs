@@ -0,0 +1,113 @@
+//! Fetched keys grouped by a prefix of configurable length, to see how a
+//! composite index subtree is partitioned, e.g. by a type byte in front of
+//! 32-byte identifiers.
+
+use std::collections::BTreeMap;
+
+use eframe::egui::{self, DragValue, Grid, RichText};
+use grovedbg_types::Key;
+
+use super::SubtreeElements;
+use crate::path_ctx::Path;
+
+const MAX_PREFIX_LEN: usize = 64;
+const MAX_SHOWN_PREFIXES: usize = 32;
+
+/// Number of fetched keys per prefix in key order, keys shorter than the
+/// prefix length are grouped by themselves
+pub(super) fn prefix_counts(elements: &SubtreeElements, prefix_len: usize) -> BTreeMap<&[u8], usize> {
+    let mut counts = BTreeMap::new();
+    for key in elements.keys() {
+        *counts.entry(&key[..prefix_len.min(key.len())]).or_default() += 1;
+    }
+    counts
+}
+
+/// Draws counts per prefix, a click on one lists only the keys with it.
+/// Returns `true` if the filter has changed.
+pub(super) fn draw(
+    ui: &mut egui::Ui,
+    path: Path,
+    elements: &SubtreeElements,
+    prefix_len: &mut usize,
+    filter: &mut Option<Key>,
+) -> bool {
+    let mut changed = false;
+    ui.horizontal(|line| {
+        line.label("Prefix length:");
+        line.add(DragValue::new(prefix_len).range(1..=MAX_PREFIX_LEN))
+            .on_hover_text("Number of leading key bytes to group by");
+        if let Some(prefix) = filter {
+            line.label(format!("Filter: {}", hex::encode(prefix)));
+            if line
+                .button(egui_phosphor::regular::X)
+                .on_hover_text("List all keys")
+                .clicked()
+            {
+                *filter = None;
+                changed = true;
+            }
+        }
+    });
+
+    let counts = prefix_counts(elements, *prefix_len);
+    if counts.is_empty() {
+        ui.label("No keys are fetched");
+        return changed;
+    }
+    ui.label(format!(
+        "{} prefixes among {} fetched keys",
+        counts.len(),
+        elements.len()
+    ));
+
+    Grid::new(path.id().with("prefix_breakdown"))
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |grid| {
+            for (prefix, count) in counts.iter().take(MAX_SHOWN_PREFIXES) {
+                let selected = filter.as_deref() == Some(*prefix);
+                if grid
+                    .selectable_label(selected, RichText::new(hex::encode(prefix)).monospace())
+                    .on_hover_text("Only list keys with the prefix")
+                    .clicked()
+                {
+                    *filter = (!selected).then(|| prefix.to_vec());
+                    changed = true;
+                }
+                grid.label(count.to_string());
+                grid.label(format!("{:.1}%", *count as f64 / elements.len() as f64 * 100.));
+                grid.end_row();
+            }
+        });
+    if counts.len() > MAX_SHOWN_PREFIXES {
+        ui.label(RichText::new(format!("and {} more prefixes", counts.len() - MAX_SHOWN_PREFIXES)).weak());
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_view::ElementView;
+
+    #[test]
+    fn keys_are_counted_by_prefix() {
+        let elements: SubtreeElements = [vec![0, 1, 2], vec![0, 1, 3], vec![0, 2], vec![1], vec![1, 5]]
+            .into_iter()
+            .map(|key| (key.clone(), ElementView::new_placeholder(key)))
+            .collect();
+
+        let counts = prefix_counts(&elements, 2);
+        assert_eq!(
+            counts.into_iter().collect::<Vec<_>>(),
+            vec![
+                (&[0, 1][..], 2),
+                (&[0, 2][..], 1),
+                (&[1][..], 1),
+                (&[1, 5][..], 1)
+            ]
+        );
+        assert_eq!(prefix_counts(&elements, 1).len(), 2);
+    }
+}
@@ -1,27 +1,134 @@
-use std::{cell::RefCell, collections::BTreeMap};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+};
 
-use eframe::egui::{self, Align2, Color32, Pos2, Stroke};
+use chrono::{DateTime, Local};
+use eframe::egui::{self, Align2, Button, Color32, DragValue, Pos2, ScrollArea, Sense, Stroke, Vec2};
 use grovedbg_types::{Key, PathQuery, Query, QueryItem, SizedQuery, SubqueryBranch};
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
 
-use super::{element_view::ElementView, SubtreeViewContext, NODE_WIDTH};
+use super::{
+    csv_export::{export_csv, subtree_csv, ExportTarget},
+    element_view::{ElementOrPlaceholder, ElementView},
+    hash_link,
+    key_range::{KeyRange, RangeShift},
+    layout::{LayoutNode, SubtreesLayout},
+    prefix_breakdown, sum_chart, SubtreeViewContext,
+};
 use crate::{
+    access_heatmap,
     bus::{CommandBus, UserAction},
-    path_ctx::{path_label, Path},
-    protocol::FetchCommand,
-    theme::subtree_line_color,
-    tree_data::{SubtreeData, SubtreeDataMap, TreeData},
+    bytes_utils::{bytes_as_number, bytes_by_display_variant, BytesDisplayVariant, BytesInput},
+    density::{kv_per_page, margin_vertical, node_width},
+    flags_decoder::FlagsDecoder,
+    notes::NoteTarget,
+    path_ctx::{draw_copy_path_menu, path_label, Path},
+    profiles::ActiveProfileSubtreeContext,
+    protocol::Capability,
+    theme::{category_color, subtree_line_color},
+    tree_data::{SubtreeData, SubtreeDataMap, SubtreeProofData, SubtreeState, TreeData},
 };
 
-const KV_PER_PAGE: usize = 10;
-const NODE_MARGIN_HORIZONTAL: f32 = 50.;
-const NODE_MARGIN_VERTICAL: f32 = 400.;
+const GROUP_MARGIN: f32 = 16.;
+const GROUP_HEADER_HEIGHT: f32 = 30.;
+const REFETCH_DIFF_SECONDS: f64 = 10.;
+const REFETCH_DIFF_LIST_HEIGHT: f32 = 150.;
+/// Minimum number of placeholder-only levels worth collapsing
+const MIN_CHAIN_LEVELS: usize = 2;
+/// Deepest level the fetch level control goes to, beyond it fetching all
+/// nodes is cheaper
+const MAX_MERK_LEVELS: u8 = 10;
 
 pub(crate) type SubtreeElements = BTreeMap<Key, ElementView>;
 
 pub(crate) struct SubtreeView<'pa> {
     pub(super) path: Path<'pa>,
     page_index: usize,
-    width: usize,
+    expand_depth: usize,
+    collapsed_groups: BTreeSet<String>,
+    /// Number of items to fetch with query shortcuts
+    shortcut_limit: u16,
+    /// Only keys are listed, values of items and references are hidden
+    keys_only: bool,
+    /// Fetch the whole subtree before exporting to CSV
+    export_fetch_all: bool,
+    /// Export to be done once the whole subtree is fetched
+    pending_export: Option<ExportTarget>,
+    /// Key prefix to jump to among fetched elements
+    quick_find: BytesInput,
+    /// Key the quick find last jumped to, so pagination is left alone until
+    /// a different key matches
+    quick_find_match: Option<Key>,
+    key_order: KeyOrder,
+    sorted: Option<SortedKeys>,
+    /// Keys of child subtrees whose placeholder chains were expanded
+    expanded_chains: BTreeSet<Key>,
+    /// Only keys of the range are listed
+    key_range: Option<KeyRange>,
+    range_start: BytesInput,
+    range_end: BytesInput,
+    /// Window move to do once keys next to it are fetched
+    pending_shift: Option<RangeShift>,
+    show_sum_chart: bool,
+    show_prefix_breakdown: bool,
+    /// Number of leading key bytes the breakdown groups keys by
+    prefix_len: usize,
+    /// Only keys with the prefix are listed
+    prefix_filter: Option<Key>,
+    /// Show how the root hash is committed to by the parent element
+    show_hash_link: bool,
+    /// Number of Merk tree levels fetched by the fetch level control
+    merk_levels: u8,
+}
+
+/// Order of the subtree's elements listing
+#[derive(Clone, Copy, PartialEq, Default, AsRefStr, EnumIter)]
+enum KeyOrder {
+    #[default]
+    #[strum(serialize = "Key bytes")]
+    Key,
+    /// Earliest fetched first, placeholders last
+    #[strum(serialize = "Fetch order")]
+    Fetch,
+    /// Keys decoded as numbers by their display format, the rest after them
+    #[strum(serialize = "Numeric value")]
+    Numeric,
+}
+
+/// Keys sorted in a listing order other than by key bytes, sorted again once
+/// elements change
+struct SortedKeys {
+    order: KeyOrder,
+    len: usize,
+    last_fetched: Option<DateTime<Local>>,
+    keys: Vec<Key>,
+}
+
+/// Position of a child subtree and the point its line to the parent starts at
+#[derive(Clone, Copy)]
+pub(super) struct Placement {
+    pos: Pos2,
+    parent_anchor: Pos2,
+}
+
+/// Visible child subtrees that share a profile category, or a single
+/// uncategorized one
+struct SiblingGroup {
+    category: Option<String>,
+    /// Color set in the profile for the category
+    custom_color: Option<Color32>,
+    keys: Vec<Key>,
+}
+
+impl SiblingGroup {
+    fn color(&self, ctx: &egui::Context) -> Color32 {
+        match (&self.category, self.custom_color) {
+            (_, Some(color)) => color,
+            (Some(category), None) => category_color(ctx, category),
+            (None, None) => Color32::GRAY,
+        }
+    }
 }
 
 impl<'pa> SubtreeView<'pa> {
@@ -29,61 +136,379 @@ impl<'pa> SubtreeView<'pa> {
         Self {
             path,
             page_index: 0,
-            width: 1,
+            expand_depth: 1,
+            collapsed_groups: BTreeSet::new(),
+            shortcut_limit: 10,
+            keys_only: false,
+            export_fetch_all: false,
+            pending_export: None,
+            quick_find: BytesInput::new(),
+            quick_find_match: None,
+            key_order: KeyOrder::default(),
+            sorted: None,
+            expanded_chains: BTreeSet::new(),
+            key_range: None,
+            range_start: BytesInput::new(),
+            range_end: BytesInput::new(),
+            pending_shift: None,
+            show_sum_chart: false,
+            show_prefix_breakdown: false,
+            prefix_len: 1,
+            prefix_filter: None,
+            show_hash_link: false,
+            merk_levels: 3,
         }
     }
 
+    fn is_collapsed(&self, group: &SiblingGroup) -> bool {
+        group
+            .category
+            .as_ref()
+            .is_some_and(|c| self.collapsed_groups.contains(c))
+    }
+
     pub(super) fn scroll_to(&mut self, key: &[u8], tree_data: &mut TreeData<'pa>) {
         let Some(subtree_data) = tree_data.get(&self.path) else {
             self.page_index = 0;
             return;
         };
-        let index = subtree_data
-            .elements
-            .iter()
-            .enumerate()
-            .find_map(|(i, (k, _))| (k.as_slice() == key).then_some(i))
-            .unwrap_or_default();
-
-        self.page_index = index / KV_PER_PAGE;
-    }
-
-    fn fetch(&self, bus: &CommandBus, limit: Option<u16>) {
-        bus.fetch_command(FetchCommand::FetchWithPathQuery {
-            path_query: PathQuery {
-                path: self.path.to_vec(),
-                query: SizedQuery {
-                    query: Query {
-                        items: vec![QueryItem::RangeFull],
-                        default_subquery_branch: SubqueryBranch {
-                            subquery_path: None,
-                            subquery: None,
-                        },
-                        conditional_subquery_branches: Vec::new(),
-                        left_to_right: true,
-                    },
-                    limit,
-                    offset: None,
-                },
-            },
+        if self.key_range.as_ref().is_some_and(|range| !range.contains(key)) {
+            self.key_range = None;
+        }
+        if self
+            .prefix_filter
+            .as_ref()
+            .is_some_and(|prefix| !key.starts_with(prefix))
+        {
+            self.prefix_filter = None;
+        }
+        self.page_index = self.listing_index(&subtree_data.elements, key) / kv_per_page();
+    }
+
+    /// Keys in the listing order, `None` if it's the order of the elements map
+    fn sorted_keys(&mut self, elements: &SubtreeElements) -> Option<&[Key]> {
+        if self.key_order == KeyOrder::Key {
+            return None;
+        }
+        let last_fetched = elements.values().filter_map(|e| e.fetched.map(|f| f.at)).max();
+        let is_stale = !self.sorted.as_ref().is_some_and(|sorted| {
+            sorted.order == self.key_order
+                && sorted.len == elements.len()
+                && sorted.last_fetched == last_fetched
         });
+        if is_stale {
+            self.sorted = Some(SortedKeys {
+                order: self.key_order,
+                len: elements.len(),
+                last_fetched,
+                keys: sort_keys(self.path, elements, self.key_order),
+            });
+        }
+        self.sorted.as_ref().map(|sorted| sorted.keys.as_slice())
     }
 
-    fn fetch_n(&self, bus: &CommandBus, n: u16) {
-        self.fetch(bus, Some(n))
+    /// Whether a key is listed with the key range and the prefix filter
+    fn listing_filter(&self) -> impl Fn(&&Key) -> bool {
+        let range = self.key_range.clone();
+        let prefix = self.prefix_filter.clone();
+        move |k: &&Key| {
+            range.as_ref().map(|r| r.contains(k)).unwrap_or(true)
+                && prefix.as_ref().map(|p| k.starts_with(p)).unwrap_or(true)
+        }
     }
 
-    fn fetch_all(&self, bus: &CommandBus) {
-        self.fetch(bus, None)
+    /// Position of the key in the listing
+    fn listing_index(&mut self, elements: &SubtreeElements, key: &[u8]) -> usize {
+        let listed = self.listing_filter();
+        match self.sorted_keys(elements) {
+            Some(keys) => keys.iter().filter(listed).position(|k| k.as_slice() == key),
+            None => elements.keys().filter(listed).position(|k| k.as_slice() == key),
+        }
+        .unwrap_or_default()
     }
 
-    fn fetch_key(&self, bus: &CommandBus, key: Vec<u8>) {
-        bus.fetch_command(FetchCommand::FetchNode {
+    /// Keys of the listing page, limited to the key range and the prefix if
+    /// set
+    fn page_keys(&mut self, elements: &SubtreeElements) -> Vec<Key> {
+        let skip = self.page_index * kv_per_page();
+        let listed = self.listing_filter();
+        match self.sorted_keys(elements) {
+            Some(keys) => keys
+                .iter()
+                .filter(listed)
+                .skip(skip)
+                .take(kv_per_page())
+                .cloned()
+                .collect(),
+            None => elements
+                .keys()
+                .filter(listed)
+                .skip(skip)
+                .take(kv_per_page())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn show_range(&mut self, bus: &CommandBus<'pa>, range: KeyRange) {
+        bus.fetch().path_query(PathQuery {
+            path: self.path.to_vec(),
+            query: range.query(kv_per_page() as u16),
+        });
+        self.range_start = BytesInput::new_from_bytes(range.start.clone());
+        self.range_end = BytesInput::new_from_bytes(range.end.clone());
+        self.key_range = Some(range);
+        self.page_index = 0;
+    }
+
+    /// Fetches keys next to the window and moves it there once they arrive
+    fn shift_range(&mut self, bus: &CommandBus<'pa>, shift: RangeShift) {
+        let Some(range) = &self.key_range else {
+            return;
+        };
+        bus.fetch().path_query(PathQuery {
+            path: self.path.to_vec(),
+            query: range.adjacent_query(shift, kv_per_page() as u16),
+        });
+        self.pending_shift = Some(shift);
+    }
+
+    /// Key range the subtree is limited to with buttons to move it over
+    /// adjacent keys, which are fetched first
+    fn draw_key_range(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, tree_data: &TreeData<'pa>) {
+        let Some(range) = self.key_range.clone() else {
+            return;
+        };
+        let Some(subtree_data) = tree_data.get(&self.path) else {
+            return;
+        };
+
+        if let Some(shift) = self.pending_shift.filter(|_| bus.is_idle()) {
+            self.pending_shift = None;
+            match range.shifted(&subtree_data.elements, shift, kv_per_page()) {
+                Some(shifted) => {
+                    self.key_range = Some(shifted);
+                    self.page_index = 0;
+                }
+                None => log::info!("No more keys in this direction"),
+            }
+        }
+
+        let (before, after) = range.outside(&subtree_data.elements);
+        ui.horizontal(|line| {
+            line.label(format!(
+                "Keys {} ..= {}",
+                bytes_by_display_variant(&range.start, &BytesDisplayVariant::guess(&range.start)),
+                bytes_by_display_variant(&range.end, &BytesDisplayVariant::guess(&range.end)),
+            ));
+            if line
+                .small_button(egui_phosphor::regular::X)
+                .on_hover_text("Show all keys")
+                .clicked()
+            {
+                self.key_range = None;
+                self.pending_shift = None;
+            }
+        });
+        if self.pending_shift.is_some() {
+            ui.spinner().on_hover_text("Fetching adjacent keys");
+            return;
+        }
+        ui.horizontal(|line| {
+            if line
+                .button(format!("{} More before", egui_phosphor::regular::CARET_UP))
+                .on_hover_text(format!(
+                    "Fetch keys before the range and move to them, {before} are loaded"
+                ))
+                .clicked()
+            {
+                self.shift_range(bus, RangeShift::Before);
+            }
+            if line
+                .button(format!("{} More after", egui_phosphor::regular::CARET_DOWN))
+                .on_hover_text(format!(
+                    "Fetch keys after the range and move to them, {after} are loaded"
+                ))
+                .clicked()
+            {
+                self.shift_range(bus, RangeShift::After);
+            }
+        });
+    }
+
+    /// Range inputs of the header menu, the range can be typed or taken from
+    /// the listed page
+    fn draw_range_menu(&mut self, menu: &mut egui::Ui, bus: &CommandBus<'pa>, subtree_data: &SubtreeData) {
+        menu.menu_button("Key range", |submenu| {
+            submenu.horizontal(|line| {
+                line.label("From:");
+                self.range_start.draw(line);
+            });
+            submenu.horizontal(|line| {
+                line.label("To:");
+                self.range_end.draw(line);
+            });
+            if submenu
+                .button("Show the range")
+                .on_hover_text("Fetch the range and list only its keys")
+                .clicked()
+            {
+                let range = KeyRange::new(self.range_start.get_bytes(), self.range_end.get_bytes());
+                self.show_range(bus, range);
+                submenu.close_menu();
+            }
+
+            let page_keys = self.page_keys(&subtree_data.elements);
+            if submenu
+                .add_enabled(!page_keys.is_empty(), Button::new("Limit to the listed page"))
+                .on_hover_text("Take the range from the first and the last key of the page")
+                .clicked()
+            {
+                let start = page_keys.iter().min().cloned().unwrap_or_default();
+                let end = page_keys.iter().max().cloned().unwrap_or_default();
+                self.show_range(bus, KeyRange::new(start, end));
+                submenu.close_menu();
+            }
+        });
+    }
+
+    /// Draws the quick find input, jumping to the page of the first fetched
+    /// key that starts with the typed prefix
+    fn draw_quick_find(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, tree_data: &TreeData<'pa>) {
+        ui.horizontal(|line| {
+            line.menu_button(egui_phosphor::regular::SORT_ASCENDING, |menu| {
+                for order in KeyOrder::iter() {
+                    if menu
+                        .radio_value(&mut self.key_order, order, order.as_ref())
+                        .clicked()
+                    {
+                        menu.close_menu();
+                    }
+                }
+            })
+            .response
+            .on_hover_text(format!("Listed by {}", self.key_order.as_ref().to_lowercase()));
+
+            line.label(egui_phosphor::regular::MAGNIFYING_GLASS)
+                .on_hover_text("Find a fetched key by prefix, right click the input to change its format");
+            self.quick_find.draw(line);
+
+            let prefix = self.quick_find.get_bytes();
+            if prefix.is_empty() {
+                self.quick_find_match = None;
+                return;
+            }
+            let Some(subtree_data) = tree_data.get(&self.path) else {
+                return;
+            };
+
+            match first_with_prefix(&subtree_data.elements, &prefix) {
+                Some(key) => {
+                    if self.quick_find_match.as_ref() != Some(key) {
+                        self.quick_find_match = Some(key.clone());
+                        self.page_index = self.listing_index(&subtree_data.elements, key) / kv_per_page();
+                    }
+                }
+                None => {
+                    self.quick_find_match = None;
+                    if line
+                        .small_button(egui_phosphor::regular::DOWNLOAD_SIMPLE)
+                        .on_hover_text("No fetched key matches, fetch the typed key exactly")
+                        .clicked()
+                    {
+                        self.fetch_key(bus, prefix);
+                    }
+                }
+            }
+        });
+    }
+
+    // The snapshot is taken only once the fetch is queued, a refused fetch
+    // would leave it waiting forever
+    fn fetch_n(&self, bus: &CommandBus, subtree_data: &mut SubtreeData, n: u16) {
+        if bus.fetch().subtree(self.path).limit(n).refetch().send() {
+            subtree_data.start_refetch(bus.effective_limit(Some(n)), true);
+        }
+    }
+
+    fn fetch_all(&self, bus: &CommandBus, subtree_data: &mut SubtreeData) {
+        if bus.fetch().subtree(self.path).refetch().send() {
+            subtree_data.start_refetch(bus.effective_limit(None), true);
+        }
+    }
+
+    /// Fetch level choices: the root node only, the upper Merk tree levels or
+    /// all nodes of the subtree
+    fn draw_fetch_level_menu(
+        &mut self,
+        menu: &mut egui::Ui,
+        bus: &CommandBus,
+        subtree_data: &mut SubtreeData,
+        root_key: Option<&Key>,
+    ) {
+        let Some(root_key) = root_key else {
+            menu.label("Root key is unknown, fetch the subtree element in the parent first");
+            return;
+        };
+        if menu.button("Root node only").clicked() {
+            self.fetch_key(bus, root_key.clone());
+            menu.close_menu();
+        }
+        menu.horizontal(|line| {
+            if line
+                .button("First levels:")
+                .on_hover_text("Fetch Merk tree nodes level by level starting from the root node")
+                .clicked()
+            {
+                bus.fetch()
+                    .merk_levels(self.path, root_key.clone(), self.merk_levels);
+                line.close_menu();
+            }
+            line.add(DragValue::new(&mut self.merk_levels).range(1..=MAX_MERK_LEVELS))
+                .on_hover_text(format!("Up to {} nodes", (1usize << self.merk_levels) - 1));
+        });
+        if menu.button("All nodes").clicked() {
+            self.fetch_all(bus, subtree_data);
+            menu.close_menu();
+        }
+    }
+
+    fn fetch_last_n(&self, bus: &CommandBus, subtree_data: &mut SubtreeData, n: u16) {
+        if bus
+            .fetch()
+            .subtree(self.path)
+            .limit(n)
+            .reversed()
+            .refetch()
+            .send()
+        {
+            subtree_data.start_refetch(bus.effective_limit(Some(n)), false);
+        }
+    }
+
+    fn fetch_keys(&self, bus: &CommandBus, keys: impl IntoIterator<Item = Key>) {
+        bus.fetch().path_query(PathQuery {
             path: self.path.to_vec(),
-            key,
+            query: SizedQuery {
+                query: Query {
+                    items: keys.into_iter().map(QueryItem::Key).collect(),
+                    default_subquery_branch: SubqueryBranch {
+                        subquery_path: None,
+                        subquery: None,
+                    },
+                    conditional_subquery_branches: Vec::new(),
+                    left_to_right: true,
+                },
+                limit: None,
+                offset: None,
+            },
         });
     }
 
+    fn fetch_key(&self, bus: &CommandBus, key: Vec<u8>) {
+        bus.fetch().node(self.path, key);
+    }
+
     fn next_page(&mut self, ctx: &mut SubtreeViewContext) {
         ctx.bus.user_action(UserAction::DropFocus);
         self.page_index += 1;
@@ -94,8 +519,190 @@ impl<'pa> SubtreeView<'pa> {
         self.page_index = self.page_index.saturating_sub(1);
     }
 
+    /// Context menu of the subtree header with canned queries and navigation
+    /// to the parent subtree and references to the subtree
+    fn draw_header_menu(
+        &mut self,
+        header: egui::Response,
+        bus: &CommandBus<'pa>,
+        tree_data: &TreeData<'pa>,
+        profile_ctx: &ActiveProfileSubtreeContext,
+    ) {
+        header.interact(Sense::click()).context_menu(|menu| {
+            self.draw_navigation(menu, bus, tree_data);
+            draw_copy_path_menu(menu, self.path, profile_ctx);
+            menu.add_enabled(
+                self.path.parent().is_some(),
+                egui::Checkbox::new(&mut self.show_hash_link, "Root hash link to the parent"),
+            )
+            .on_hover_text("Show how the parent element's value hash commits to the subtree root hash");
+            menu.separator();
+
+            let Some(mut subtree_data) = tree_data.get_mut(&self.path) else {
+                return;
+            };
+
+            menu.horizontal(|line| {
+                line.label("N:");
+                line.add(DragValue::new(&mut self.shortcut_limit).range(1..=u16::MAX));
+            });
+
+            if menu.button("First N").clicked() {
+                self.fetch_n(bus, &mut subtree_data, self.shortcut_limit);
+                menu.close_menu();
+            }
+
+            if menu.button("Last N").clicked() {
+                self.fetch_last_n(bus, &mut subtree_data, self.shortcut_limit);
+                menu.close_menu();
+            }
+
+            // GroveDB queries always return values, so they are hidden instead
+            if menu
+                .button("Keys only (no values)")
+                .on_hover_text("Fetch the first N elements and list their keys without values")
+                .clicked()
+            {
+                self.fetch_n(bus, &mut subtree_data, self.shortcut_limit);
+                self.keys_only = true;
+                menu.close_menu();
+            }
+            menu.checkbox(&mut self.keys_only, "Hide values");
+
+            self.draw_range_menu(menu, bus, &subtree_data);
+
+            if menu
+                .add_enabled(
+                    !subtree_data.subtree_keys.is_empty(),
+                    Button::new("Children that are subtrees"),
+                )
+                .on_hover_text("Fetch elements known to be subtrees")
+                .on_disabled_hover_text("No child subtrees are known, fetch some elements first")
+                .clicked()
+            {
+                self.fetch_keys(bus, subtree_data.subtree_keys.iter().cloned());
+                menu.close_menu();
+            }
+        });
+    }
+
+    fn draw_navigation(&self, menu: &mut egui::Ui, bus: &CommandBus<'pa>, tree_data: &TreeData<'pa>) {
+        let parent = self.path.parent_with_key();
+        if menu
+            .add_enabled(
+                parent.is_some(),
+                Button::new(format!(
+                    "{} Go to parent subtree",
+                    egui_phosphor::regular::ARROW_UP
+                )),
+            )
+            .on_disabled_hover_text("The root subtree has no parent")
+            .clicked()
+        {
+            if let Some((parent_path, key)) = parent {
+                bus.user_action(UserAction::FocusSubtreeKey(parent_path, key));
+            }
+            menu.close_menu();
+        }
+
+        let referrers = tree_data.subtree_referrers(self.path);
+        menu.add_enabled_ui(!referrers.is_empty(), |enabled| {
+            enabled
+                .menu_button(
+                    format!(
+                        "{} Referencing elements ({})",
+                        egui_phosphor::regular::ARROW_BEND_DOWN_RIGHT,
+                        referrers.len()
+                    ),
+                    |submenu| {
+                        for (path, key) in referrers.iter() {
+                            let segments: Vec<_> = path
+                                .iter()
+                                .chain([key])
+                                .map(|s| bytes_by_display_variant(s, &BytesDisplayVariant::guess(s)))
+                                .collect();
+                            if submenu.button(format!("[{}]", segments.join(", "))).clicked() {
+                                let path = self.path.get_ctx().add_path(path.clone());
+                                bus.fetch().node(path, key.clone());
+                                bus.user_action(UserAction::FocusSubtreeKey(path, key.clone()));
+                                submenu.close_menu();
+                            }
+                        }
+                    },
+                )
+                .response
+                .on_disabled_hover_text("No loaded references point at the subtree or its elements");
+        });
+    }
+
+    /// CSV export menu, the export may wait for the whole subtree to be
+    /// fetched first
+    fn draw_export(
+        &mut self,
+        ui: &mut egui::Ui,
+        bus: &CommandBus<'pa>,
+        subtree_data: &mut SubtreeData,
+        decoders: &[FlagsDecoder],
+    ) {
+        if let Some(target) = self.pending_export.filter(|_| bus.is_idle()) {
+            self.pending_export = None;
+            export_csv(
+                ui.ctx(),
+                self.path,
+                subtree_csv(self.path, subtree_data, decoders),
+                target,
+            );
+        }
+
+        if self.pending_export.is_some() {
+            ui.spinner().on_hover_text("Fetching the subtree to export it");
+            return;
+        }
+
+        ui.menu_button(egui_phosphor::regular::FILE_CSV, |menu| {
+            menu.checkbox(&mut self.export_fetch_all, "Fetch all pages first");
+
+            let mut target = None;
+            if menu.button("Copy as CSV").clicked() {
+                target = Some(ExportTarget::Clipboard);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if menu
+                .button("Save as CSV")
+                .on_hover_text("Save to the working directory")
+                .clicked()
+            {
+                target = Some(ExportTarget::File);
+            }
+
+            if let Some(target) = target {
+                if self.export_fetch_all {
+                    self.fetch_all(bus, subtree_data);
+                    self.pending_export = Some(target);
+                } else {
+                    export_csv(
+                        menu.ctx(),
+                        self.path,
+                        subtree_csv(self.path, subtree_data, decoders),
+                        target,
+                    );
+                }
+                menu.close_menu();
+            }
+        })
+        .response
+        .on_hover_text("Export fetched elements to CSV");
+    }
+
     /// Draw subtree control buttons
-    fn draw_controls(&mut self, ui: &mut egui::Ui, bus: &CommandBus<'pa>, tree_data: &TreeData<'pa>) {
+    fn draw_controls(
+        &mut self,
+        ui: &mut egui::Ui,
+        bus: &CommandBus<'pa>,
+        tree_data: &TreeData<'pa>,
+        decoders: &[FlagsDecoder],
+        default_query: Option<&SizedQuery>,
+    ) {
         ui.horizontal(|controls_ui| {
             let Some(mut subtree_data) = tree_data.get_mut(&self.path) else {
                 return;
@@ -103,7 +710,7 @@ impl<'pa> SubtreeView<'pa> {
             let root_key = subtree_data.root_key.clone();
 
             if controls_ui.button("10").on_hover_text("Fetch 10 items").clicked() {
-                self.fetch_n(bus, 10);
+                self.fetch_n(bus, &mut subtree_data, 10);
             }
 
             if controls_ui
@@ -111,15 +718,54 @@ impl<'pa> SubtreeView<'pa> {
                 .on_hover_text("Fetch 100 items")
                 .clicked()
             {
-                self.fetch_n(bus, 100);
+                self.fetch_n(bus, &mut subtree_data, 100);
             }
 
             if controls_ui
                 .button(egui_phosphor::regular::DATABASE)
-                .on_hover_text("Fetch whole subtree")
+                .on_hover_text(if bus.is_guarded() {
+                    "Fetch whole subtree, limited while expensive operations are guarded"
+                } else {
+                    "Fetch whole subtree"
+                })
                 .clicked()
             {
-                self.fetch_all(bus);
+                self.fetch_all(bus, &mut subtree_data);
+            }
+
+            controls_ui
+                .menu_button(egui_phosphor::regular::CARET_DOUBLE_DOWN, |menu| {
+                    self.draw_fetch_level_menu(menu, bus, &mut subtree_data, root_key.as_ref())
+                })
+                .response
+                .on_hover_text("Fetch the root node, the first Merk tree levels or all nodes");
+
+            // Released GroveDB versions have no counting endpoint, so the button is shown
+            // only when the endpoint advertises it
+            if bus.supports(Capability::CountSubtree) {
+                let count_button = match subtree_data.element_count {
+                    Some(count) => controls_ui.button(count.to_string()),
+                    None => controls_ui.button(egui_phosphor::regular::LIST_NUMBERS),
+                };
+                if count_button
+                    .on_hover_text("Request the number of elements in the subtree")
+                    .clicked()
+                {
+                    bus.fetch().subtree_count(self.path);
+                }
+            }
+
+            if let Some(query) = default_query {
+                if controls_ui
+                    .button(egui_phosphor::regular::BOOKMARK_SIMPLE)
+                    .on_hover_text("Fetch with the default query of the profile entry")
+                    .clicked()
+                {
+                    bus.fetch().path_query(PathQuery {
+                        path: self.path.to_vec(),
+                        query: query.clone(),
+                    });
+                }
             }
 
             if let Some(key) = subtree_data.root_key.as_ref() {
@@ -138,10 +784,30 @@ impl<'pa> SubtreeView<'pa> {
                     .on_hover_text("Clear subtree data")
                     .clicked()
                 {
-                    subtree_data.elements.clear();
+                    tree_data.clear_elements(&mut subtree_data);
                 }
             }
 
+            controls_ui
+                .toggle_value(&mut subtree_data.pinned, egui_phosphor::regular::PUSH_PIN)
+                .on_hover_text("Pin the subtree to refetch its data on a new session");
+
+            if controls_ui
+                .button(egui_phosphor::regular::SQUARE_SPLIT_HORIZONTAL)
+                .on_hover_text("Open the subtree in the split pane")
+                .clicked()
+            {
+                bus.user_action(UserAction::FocusSplitSubtree(self.path, None));
+            }
+
+            if controls_ui
+                .button(egui_phosphor::regular::NOTE_PENCIL)
+                .on_hover_text("Add a note to the subtree")
+                .clicked()
+            {
+                bus.user_action(UserAction::OpenNote(NoteTarget::Path(self.path.to_vec())));
+            }
+
             if controls_ui
                 .button(egui_phosphor::regular::LIST_MAGNIFYING_GLASS)
                 .on_hover_text("Select this subtree for a path query")
@@ -150,6 +816,75 @@ impl<'pa> SubtreeView<'pa> {
                 self.path.select_for_query();
             }
 
+            if !subtree_data.selected_keys.is_empty() {
+                let count = subtree_data.selected_keys.len();
+                if controls_ui
+                    .button(format!("{} {count}", egui_phosphor::regular::LIST_CHECKS))
+                    .on_hover_text("Build a path query for the selected keys")
+                    .clicked()
+                {
+                    bus.user_action(UserAction::QuerySelectedKeys(
+                        self.path,
+                        subtree_data.selected_query_items(false),
+                    ));
+                }
+                if controls_ui
+                    .button(egui_phosphor::regular::BRACKETS_SQUARE)
+                    .on_hover_text(
+                        "Build a path query with ranges covering the selected keys, neighbouring keys that \
+                         are not loaded may be included",
+                    )
+                    .clicked()
+                {
+                    bus.user_action(UserAction::QuerySelectedKeys(
+                        self.path,
+                        subtree_data.selected_query_items(true),
+                    ));
+                }
+                if controls_ui
+                    .button(egui_phosphor::regular::X_SQUARE)
+                    .on_hover_text("Clear selection")
+                    .clicked()
+                {
+                    subtree_data.selected_keys.clear();
+                }
+            }
+
+            if !subtree_data.subtree_keys.is_empty() {
+                if controls_ui
+                    .button(egui_phosphor::regular::ARROWS_OUT_SIMPLE)
+                    .on_hover_text("Expand child subtrees down to the selected depth")
+                    .clicked()
+                {
+                    bus.user_action(UserAction::ExpandSubtrees(self.path, self.expand_depth));
+                }
+                controls_ui
+                    .add(egui::DragValue::new(&mut self.expand_depth).range(1..=5))
+                    .on_hover_text("Expansion depth");
+            }
+
+            if !subtree_data.visible_keys.is_empty() {
+                if controls_ui
+                    .button(egui_phosphor::regular::ARROWS_IN_SIMPLE)
+                    .on_hover_text("Collapse all child subtrees")
+                    .clicked()
+                {
+                    bus.user_action(UserAction::CollapseSubtrees(self.path));
+                }
+            }
+
+            if self.show_sum_chart || !sum_chart::sum_values(&subtree_data.elements).is_empty() {
+                controls_ui
+                    .toggle_value(&mut self.show_sum_chart, egui_phosphor::regular::CHART_BAR)
+                    .on_hover_text("Distribution of fetched sum item values");
+            }
+
+            if !subtree_data.elements.is_empty() {
+                controls_ui
+                    .toggle_value(&mut self.show_prefix_breakdown, egui_phosphor::regular::FUNNEL)
+                    .on_hover_text("Counts of fetched keys by prefix");
+            }
+
             if root_key.is_some() {
                 if controls_ui
                     .button(egui_phosphor::regular::TREE_STRUCTURE)
@@ -159,7 +894,65 @@ impl<'pa> SubtreeView<'pa> {
                     bus.user_action(UserAction::SelectMerkView(self.path));
                 }
             }
+
+            self.draw_export(controls_ui, bus, &mut subtree_data, decoders);
+        });
+    }
+
+    /// Draw a summary of changes found by the last refetch, hidden after a
+    /// while unless the detailed list is open
+    fn draw_refetch_diff(&self, ui: &mut egui::Ui, tree_data: &TreeData<'pa>) {
+        let Some(mut subtree_data) = tree_data.get_mut(&self.path) else {
+            return;
+        };
+        let Some(diff) = subtree_data.last_diff.as_mut() else {
+            return;
+        };
+
+        let time = ui.input(|i| i.time);
+        let shown_since = *diff.shown_since.get_or_insert(time);
+        if !diff.expanded && time - shown_since > REFETCH_DIFF_SECONDS {
+            subtree_data.last_diff = None;
+            return;
+        }
+
+        let mut dismiss = false;
+        ui.horizontal(|line| {
+            line.label(diff.summary())
+                .on_hover_text("Added / removed / changed keys since the previous fetch");
+            line.toggle_value(&mut diff.expanded, egui_phosphor::regular::LIST)
+                .on_hover_text("Show changed keys");
+            dismiss = line.button(egui_phosphor::regular::X).clicked();
         });
+
+        if diff.expanded {
+            let key_label = |key: &Key| {
+                let display = self
+                    .path
+                    .child(key.clone())
+                    .get_display_variant()
+                    .unwrap_or_else(|| BytesDisplayVariant::guess(key));
+                bytes_by_display_variant(key, &display)
+            };
+            ScrollArea::vertical()
+                .id_salt("refetch_diff")
+                .max_height(REFETCH_DIFF_LIST_HEIGHT)
+                .show(ui, |scroll| {
+                    for key in diff.added.iter() {
+                        scroll.label(format!("+ {}", key_label(key)));
+                    }
+                    for key in diff.removed.iter() {
+                        scroll.label(format!("- {}", key_label(key)));
+                    }
+                    for key in diff.changed.iter() {
+                        scroll.label(format!("~ {}", key_label(key)));
+                    }
+                });
+        }
+
+        if dismiss {
+            subtree_data.last_diff = None;
+        }
     }
 
     /// Draw elements of the subtree as a list
@@ -168,21 +961,60 @@ impl<'pa> SubtreeView<'pa> {
         ui: &mut egui::Ui,
         subtree_view_ctx: &mut SubtreeViewContext<'pf, 'pa, 'cs>,
         subtrees_map: &SubtreeDataMap<'pa>,
+        proof_data: Option<&SubtreeProofData>,
     ) {
+        let bus = subtree_view_ctx.bus;
         let mut element_view_ctx = subtree_view_ctx.element_view_context(self.path);
+        element_view_ctx.keys_only = self.keys_only;
 
         if let Some(mut subtree_data) = subtrees_map.get(&self.path).map(RefCell::borrow_mut) {
             let data: &mut SubtreeData = &mut subtree_data;
 
+            match data.state() {
+                SubtreeState::Loaded => {}
+                SubtreeState::Empty => {
+                    ui.label(egui::RichText::new("Empty subtree").weak())
+                        .on_hover_text(if data.root_key.is_none() && data.is_root_key_known() {
+                            "The subtree element in the parent subtree has no root key"
+                        } else {
+                            "Fetching the whole subtree returned no elements"
+                        });
+                }
+                SubtreeState::NotFetched => {
+                    if ui
+                        .button("Not fetched yet, click to fetch")
+                        .on_hover_text(if data.is_root_key_known() {
+                            "The subtree has elements"
+                        } else {
+                            "The parent subtree element is not fetched, the subtree may be empty"
+                        })
+                        .clicked()
+                    {
+                        self.fetch_n(bus, data, kv_per_page() as u16);
+                    }
+                }
+            }
+
+            let page_keys = self.page_keys(&data.elements);
+
             let elements = &mut data.elements;
             let visibility = &mut data.visible_keys;
+            let selection = &mut data.selected_keys;
+            let back_references = &data.back_references;
 
-            for (_, element) in elements
-                .iter_mut()
-                .skip(self.page_index * KV_PER_PAGE)
-                .take(KV_PER_PAGE)
-            {
-                element.draw(ui, &mut element_view_ctx, visibility, subtrees_map);
+            for key in page_keys {
+                let Some(element) = elements.get_mut(&key) else {
+                    continue;
+                };
+                element.draw(
+                    ui,
+                    &mut element_view_ctx,
+                    visibility,
+                    selection,
+                    back_references.get(&key),
+                    proof_data.and_then(|proof_data| proof_data.get(&key)),
+                    subtrees_map,
+                );
 
                 ui.separator();
             }
@@ -199,7 +1031,11 @@ impl<'pa> SubtreeView<'pa> {
         let Some(subtree_data) = subtrees_map.get(&self.path).map(RefCell::borrow) else {
             return;
         };
-        if subtree_data.elements.len() > KV_PER_PAGE {
+        let listed = match (&self.key_range, &self.prefix_filter) {
+            (None, None) => subtree_data.elements.len(),
+            _ => subtree_data.elements.keys().filter(self.listing_filter()).count(),
+        };
+        if listed > kv_per_page() {
             ui.horizontal(|pagination| {
                 if pagination
                     .add_enabled(self.page_index > 0, egui::Button::new("⬅"))
@@ -209,7 +1045,7 @@ impl<'pa> SubtreeView<'pa> {
                 }
                 if pagination
                     .add_enabled(
-                        (self.page_index + 1) * KV_PER_PAGE < subtree_data.elements.len(),
+                        (self.page_index + 1) * kv_per_page() < listed,
                         egui::Button::new("➡"),
                     )
                     .clicked()
@@ -221,21 +1057,36 @@ impl<'pa> SubtreeView<'pa> {
     }
 
     /// Draw a line to the parent if any
-    fn draw_parent_connection(&self, ui: &mut egui::Ui, coords: Pos2) {
-        if let Some(parent_path) = self.path.parent() {
-            if let Some(parent_pos) =
-                ui.memory(|mem| mem.area_rect(parent_path.id()).map(|rect| rect.center_bottom()))
-            {
-                let painter = ui.painter();
-                painter.line_segment(
-                    [parent_pos, coords + (NODE_WIDTH / 2., 0.).into()],
-                    Stroke {
-                        width: 1.0,
-                        color: subtree_line_color(ui.ctx()),
-                    },
-                );
-            }
-        }
+    fn draw_parent_connection(&self, ui: &mut egui::Ui, placement: Placement) {
+        ui.painter().line_segment(
+            [
+                placement.parent_anchor,
+                placement.pos + (node_width() / 2., 0.).into(),
+            ],
+            Stroke {
+                width: 1.0,
+                color: subtree_line_color(ui.ctx()),
+            },
+        );
+    }
+
+    /// Tells if a placed subtree is outside of the viewport, using its size
+    /// from the previous frame
+    fn is_offscreen(
+        &self,
+        ui: &egui::Ui,
+        subtree_view_ctx: &SubtreeViewContext,
+        placement: Placement,
+    ) -> bool {
+        let size = ui
+            .memory(|mem| {
+                mem.area_rect(subtree_view_ctx.area_id(self.path))
+                    .map(|rect| rect.size())
+            })
+            .unwrap_or(Vec2::new(node_width(), margin_vertical()));
+        let rect = egui::Rect::from_min_size(placement.pos, size);
+
+        !(subtree_view_ctx.transform * rect).intersects(subtree_view_ctx.rect)
     }
 
     /// Draw a subtree list view
@@ -245,12 +1096,26 @@ impl<'pa> SubtreeView<'pa> {
         ui: &mut egui::Ui,
         tree_data: &mut TreeData<'pa>,
         subtrees: &mut BTreeMap<Path<'pa>, SubtreeView<'pa>>,
-        coords: Option<Pos2>,
+        layout: &SubtreesLayout<'pa>,
+        placement: Option<Placement>,
         merk_panel_width: f32,
     ) {
-        let mut area_builder = egui::Area::new(self.path.id());
-        area_builder = if let Some(coords) = coords {
-            area_builder.fixed_pos(coords)
+        if let Some(placement) = placement.filter(|p| self.is_offscreen(ui, &subtree_view_ctx, *p)) {
+            self.draw_offscreen(
+                subtree_view_ctx,
+                ui,
+                tree_data,
+                subtrees,
+                layout,
+                placement,
+                merk_panel_width,
+            );
+            return;
+        }
+
+        let mut area_builder = egui::Area::new(subtree_view_ctx.area_id(self.path));
+        area_builder = if let Some(placement) = placement {
+            area_builder.fixed_pos(placement.pos)
         } else {
             area_builder.anchor(Align2::CENTER_CENTER, (merk_panel_width, 0.))
         };
@@ -260,27 +1125,82 @@ impl<'pa> SubtreeView<'pa> {
             .show(ui.ctx(), |area| {
                 area.set_clip_rect(subtree_view_ctx.transform.inverse() * subtree_view_ctx.rect);
 
+                let accesses = subtree_view_ctx
+                    .heatmap
+                    .then(|| subtree_view_ctx.bus.access().count(&self.path.to_vec()));
+                let fill = match accesses {
+                    Some(_) => access_heatmap::tint(subtree_view_ctx.bus.access().heat(&self.path.to_vec())),
+                    None => Color32::TRANSPARENT,
+                };
+
                 egui::Frame::default()
                     .rounding(egui::Rounding::same(4.0))
                     .inner_margin(egui::Margin::same(8.0))
+                    .fill(fill)
                     .stroke(Stroke {
                         width: 1.0,
                         color: Color32::DARK_GRAY,
                     })
                     .show(area, |subtree_ui| {
-                        subtree_ui.set_max_width(NODE_WIDTH);
-                        self.draw_controls(subtree_ui, subtree_view_ctx.bus, tree_data);
+                        subtree_ui.set_max_width(node_width());
+                        if let Some(accesses) = accesses {
+                            subtree_ui.label(format!("Accessed {accesses} times in the session"));
+                        }
+                        self.draw_controls(
+                            subtree_ui,
+                            subtree_view_ctx.bus,
+                            tree_data,
+                            subtree_view_ctx.profile_ctx.flags_decoders(),
+                            subtree_view_ctx.profile_ctx.default_query(),
+                        );
                         subtree_ui.separator();
 
-                        path_label(subtree_ui, self.path, &subtree_view_ctx.profile_ctx);
+                        let header = path_label(subtree_ui, self.path, &subtree_view_ctx.profile_ctx);
+                        self.draw_header_menu(
+                            header,
+                            subtree_view_ctx.bus,
+                            tree_data,
+                            &subtree_view_ctx.profile_ctx,
+                        );
+                        self.draw_refetch_diff(subtree_ui, tree_data);
+                        self.draw_quick_find(subtree_ui, subtree_view_ctx.bus, tree_data);
+                        self.draw_key_range(subtree_ui, subtree_view_ctx.bus, tree_data);
+                        if self.show_sum_chart {
+                            let redacted = subtree_view_ctx.profile_ctx.values_redacted(subtree_ui.ctx());
+                            if let Some(subtree_data) = tree_data.get(&self.path) {
+                                sum_chart::draw(subtree_ui, self.path, &subtree_data.elements, redacted);
+                            }
+                        }
+                        if self.show_prefix_breakdown {
+                            if let Some(subtree_data) = tree_data.get(&self.path) {
+                                if prefix_breakdown::draw(
+                                    subtree_ui,
+                                    self.path,
+                                    &subtree_data.elements,
+                                    &mut self.prefix_len,
+                                    &mut self.prefix_filter,
+                                ) {
+                                    self.page_index = 0;
+                                }
+                            }
+                        }
+                        if self.show_hash_link {
+                            hash_link::draw(subtree_ui, self.path, tree_data);
+                        }
+                        self.draw_category_legend(subtree_ui, &subtree_view_ctx, tree_data);
                         subtree_ui.separator();
 
-                        self.draw_elements(subtree_ui, &mut subtree_view_ctx, &tree_data.data);
+                        self.draw_elements(
+                            subtree_ui,
+                            &mut subtree_view_ctx,
+                            &tree_data.data,
+                            tree_data.proof_data.get(&self.path),
+                        );
 
                         self.draw_pagination(subtree_ui, &mut subtree_view_ctx, &tree_data.data);
 
-                        if let Some(self_pos) = coords {
-                            self.draw_parent_connection(subtree_ui, self_pos);
+                        if let Some(placement) = placement {
+                            self.draw_parent_connection(subtree_ui, placement);
                         }
                     })
             })
@@ -289,58 +1209,534 @@ impl<'pa> SubtreeView<'pa> {
 
         ui.ctx().set_transform_layer(area_id, subtree_view_ctx.transform);
 
-        if let Some(bottom_pos) =
-            ui.memory(|mem| mem.area_rect(self.path.id()).map(|rect| rect.center_bottom()))
-        {
-            let subtree_data = tree_data.get_or_create(self.path);
-            let visible_subtrees_width = subtree_data
-                .visible_keys
-                .iter()
-                .map(|k| {
-                    subtrees
-                        .entry(self.path.child(k.clone()))
-                        .or_insert_with(|| SubtreeView::new(self.path.child(k.clone())))
-                        .width
-                })
-                .sum();
+        if let Some(bottom_pos) = ui.memory(|mem| {
+            mem.area_rect(subtree_view_ctx.area_id(self.path))
+                .map(|rect| rect.center_bottom())
+        }) {
+            self.draw_children(
+                subtree_view_ctx,
+                ui,
+                tree_data,
+                subtrees,
+                layout,
+                bottom_pos,
+                merk_panel_width,
+            );
+        }
+    }
+
+    /// Skips building the subtree UI, only the line to the parent is drawn and
+    /// child subtrees are placed as if the subtree was there
+    fn draw_offscreen<'pf, 'cs>(
+        &mut self,
+        subtree_view_ctx: SubtreeViewContext<'pf, 'pa, 'cs>,
+        ui: &mut egui::Ui,
+        tree_data: &mut TreeData<'pa>,
+        subtrees: &mut BTreeMap<Path<'pa>, SubtreeView<'pa>>,
+        layout: &SubtreesLayout<'pa>,
+        placement: Placement,
+        merk_panel_width: f32,
+    ) {
+        let size = ui
+            .memory(|mem| {
+                mem.area_rect(subtree_view_ctx.area_id(self.path))
+                    .map(|rect| rect.size())
+            })
+            .unwrap_or(Vec2::new(node_width(), margin_vertical()));
+
+        let area_id = egui::Area::new(subtree_view_ctx.area_id(self.path).with("offscreen"))
+            .fixed_pos(placement.pos)
+            .constrain(false)
+            .show(ui.ctx(), |area| {
+                area.set_clip_rect(subtree_view_ctx.transform.inverse() * subtree_view_ctx.rect);
+                self.draw_parent_connection(area, placement);
+            })
+            .response
+            .layer_id;
+        ui.ctx().set_transform_layer(area_id, subtree_view_ctx.transform);
+
+        let bottom_pos = egui::Rect::from_min_size(placement.pos, size).center_bottom();
+        self.draw_children(
+            subtree_view_ctx,
+            ui,
+            tree_data,
+            subtrees,
+            layout,
+            bottom_pos,
+            merk_panel_width,
+        );
+    }
 
-            let width: usize = std::cmp::max(visible_subtrees_width, 1);
-            self.width = width;
-            let width_f = width_to_egui(width);
+    fn draw_children<'pf, 'cs>(
+        &mut self,
+        mut subtree_view_ctx: SubtreeViewContext<'pf, 'pa, 'cs>,
+        ui: &mut egui::Ui,
+        tree_data: &mut TreeData<'pa>,
+        subtrees: &mut BTreeMap<Path<'pa>, SubtreeView<'pa>>,
+        layout: &SubtreesLayout<'pa>,
+        bottom_pos: Pos2,
+        merk_panel_width: f32,
+    ) {
+        let subtree_data = tree_data.get_or_create(self.path);
+        let groups = group_siblings(&subtree_data.visible_keys, &subtree_view_ctx);
+        drop(subtree_data);
 
-            let mut current_x = bottom_pos.x - width_f / 2. - NODE_WIDTH / 2.;
-            let y = bottom_pos.y + NODE_MARGIN_VERTICAL;
+        let ctx = ui.ctx().clone();
+        let self_node = LayoutNode::Subtree(self.path);
+        let row_padding = layout.row_padding(&ctx, self_node);
+        let child_placement = |node| Placement {
+            pos: Pos2::new(
+                bottom_pos.x - node_width() / 2. + layout.offset(&ctx, self_node, node),
+                bottom_pos.y + row_padding + margin_vertical(),
+            ),
+            parent_anchor: bottom_pos,
+        };
 
-            let visible_keys = subtree_data.visible_keys.clone();
-            drop(subtree_data);
+        for (idx, group) in groups.into_iter().enumerate() {
+            if self.is_collapsed(&group) {
+                self.draw_collapsed_group(
+                    ui,
+                    &subtree_view_ctx,
+                    &group,
+                    child_placement(LayoutNode::CollapsedGroup(self.path, idx)),
+                );
+                continue;
+            }
 
-            for subtree_key in visible_keys {
+            for subtree_key in group.keys.iter() {
                 let path = self.path.child(subtree_key.clone());
 
-                let Some(mut subtree) = subtrees.remove(&path) else {
+                if let Some(chain) =
+                    collapsed_chain(Some(&*self), self.path, subtree_key, &subtree_view_ctx, tree_data)
+                {
+                    self.draw_chain(
+                        subtree_view_ctx.descendant(&chain),
+                        ui,
+                        tree_data,
+                        subtrees,
+                        layout,
+                        &chain,
+                        child_placement(LayoutNode::Chain(path)),
+                        merk_panel_width,
+                    );
                     continue;
-                };
-                let subtree_width = width_to_egui(subtree.width);
-                current_x += subtree_width / 2.;
+                }
+
+                let mut subtree = subtrees.remove(&path).unwrap_or_else(|| SubtreeView::new(path));
                 subtree.draw(
                     subtree_view_ctx.child(subtree_key.clone()),
                     ui,
                     tree_data,
                     subtrees,
-                    Some((current_x, y).into()),
+                    layout,
+                    Some(child_placement(LayoutNode::Subtree(path))),
                     merk_panel_width,
                 );
                 subtrees.insert(path, subtree);
-                current_x += subtree_width / 2. + NODE_MARGIN_HORIZONTAL;
             }
+
+            self.draw_group_frame(ui, &subtree_view_ctx, &group);
+        }
+    }
+
+    /// Colors of child subtree groups by profile category with the number of
+    /// subtrees in each, clicking a category collapses or expands its group
+    fn draw_category_legend(
+        &mut self,
+        ui: &mut egui::Ui,
+        subtree_view_ctx: &SubtreeViewContext,
+        tree_data: &TreeData<'pa>,
+    ) {
+        let Some(subtree_data) = tree_data.get(&self.path) else {
+            return;
+        };
+        let groups: Vec<SiblingGroup> = group_siblings(&subtree_data.visible_keys, subtree_view_ctx)
+            .into_iter()
+            .filter(|g| g.category.is_some())
+            .collect();
+        drop(subtree_data);
+        if groups.is_empty() {
+            return;
+        }
+
+        ui.horizontal_wrapped(|legend| {
+            for group in groups.iter() {
+                let Some(category) = &group.category else {
+                    continue;
+                };
+                let color = group.color(legend.ctx());
+                let collapsed = self.is_collapsed(group);
+                let (swatch, _) = legend.allocate_exact_size(Vec2::splat(10.), Sense::hover());
+                legend
+                    .painter()
+                    .rect_filled(swatch, egui::Rounding::same(2.), color);
+                if legend
+                    .selectable_label(
+                        collapsed,
+                        egui::RichText::new(format!("{category} ({})", group.keys.len())).color(color),
+                    )
+                    .on_hover_text(if collapsed {
+                        "Expand the group"
+                    } else {
+                        "Collapse the group"
+                    })
+                    .clicked()
+                {
+                    if collapsed {
+                        self.collapsed_groups.remove(category);
+                    } else {
+                        self.collapsed_groups.insert(category.clone());
+                    }
+                }
+            }
+        });
+    }
+
+    /// Draw a frame with a header around child subtrees of the same category
+    fn draw_group_frame(
+        &mut self,
+        ui: &egui::Ui,
+        subtree_view_ctx: &SubtreeViewContext,
+        group: &SiblingGroup,
+    ) {
+        let Some(category) = &group.category else {
+            return;
+        };
+
+        let Some(group_rect) = ui.memory(|mem| {
+            group
+                .keys
+                .iter()
+                .filter_map(|k| mem.area_rect(subtree_view_ctx.area_id(self.path.child(k.clone()))))
+                .reduce(|a, b| a.union(b))
+        }) else {
+            return;
+        };
+        let group_rect = group_rect.expand(GROUP_MARGIN);
+        let color = group.color(ui.ctx());
+
+        let area_id = egui::Area::new(subtree_view_ctx.area_id(self.path).with(category))
+            .fixed_pos(group_rect.left_top() - Vec2::new(0., GROUP_HEADER_HEIGHT))
+            .constrain(false)
+            .show(ui.ctx(), |area| {
+                area.set_clip_rect(subtree_view_ctx.transform.inverse() * subtree_view_ctx.rect);
+
+                area.horizontal(|header| {
+                    if header
+                        .button(egui_phosphor::regular::CARET_UP)
+                        .on_hover_text("Collapse the group")
+                        .clicked()
+                    {
+                        self.collapsed_groups.insert(category.clone());
+                    }
+                    header.label(egui::RichText::new(category).color(color));
+                });
+
+                area.painter().rect_stroke(
+                    group_rect,
+                    egui::Rounding::same(8.0),
+                    Stroke { width: 1.5, color },
+                );
+            })
+            .response
+            .layer_id;
+
+        ui.ctx().set_transform_layer(area_id, subtree_view_ctx.transform);
+    }
+
+    /// Draw a collapsed group of child subtrees in place of them
+    fn draw_collapsed_group(
+        &mut self,
+        ui: &egui::Ui,
+        subtree_view_ctx: &SubtreeViewContext,
+        group: &SiblingGroup,
+        placement: Placement,
+    ) {
+        let Some(category) = &group.category else {
+            return;
+        };
+        let color = group.color(ui.ctx());
+
+        let area_id = egui::Area::new(subtree_view_ctx.area_id(self.path).with(category))
+            .fixed_pos(placement.pos)
+            .constrain(false)
+            .show(ui.ctx(), |area| {
+                area.set_clip_rect(subtree_view_ctx.transform.inverse() * subtree_view_ctx.rect);
+
+                self.draw_parent_connection(area, placement);
+
+                egui::Frame::default()
+                    .rounding(egui::Rounding::same(8.0))
+                    .inner_margin(egui::Margin::same(8.0))
+                    .stroke(Stroke { width: 1.5, color })
+                    .show(area, |frame| {
+                        frame.set_width(node_width());
+                        frame.horizontal(|line| {
+                            if line
+                                .button(egui_phosphor::regular::CARET_DOWN)
+                                .on_hover_text("Expand the group")
+                                .clicked()
+                            {
+                                self.collapsed_groups.remove(category);
+                            }
+                            line.label(egui::RichText::new(category).color(color));
+                        });
+                        frame.label(format!("{} subtrees", group.keys.len()));
+                    });
+            })
+            .response
+            .layer_id;
+
+        ui.ctx().set_transform_layer(area_id, subtree_view_ctx.transform);
+    }
+
+    /// Draw a chain of placeholder-only subtrees collapsed into one widget and
+    /// the subtree it leads to below it
+    fn draw_chain<'pf, 'cs>(
+        &mut self,
+        end_view_ctx: SubtreeViewContext<'pf, 'pa, 'cs>,
+        ui: &mut egui::Ui,
+        tree_data: &mut TreeData<'pa>,
+        subtrees: &mut BTreeMap<Path<'pa>, SubtreeView<'pa>>,
+        layout: &SubtreesLayout<'pa>,
+        chain: &[Key],
+        placement: Placement,
+        merk_panel_width: f32,
+    ) {
+        let start = self.path.child(chain[0].clone());
+        let end = chain[1..].iter().fold(start, |path, key| path.child(key.clone()));
+        let chain_area_id = end_view_ctx.area_id(start).with("chain");
+        let skipped = chain[..chain.len() - 1]
+            .iter()
+            .scan(self.path, |path, key| {
+                *path = path.child(key.clone());
+                path.for_last_segment(|segment| segment.view_by_display())
+            })
+            .collect::<Vec<_>>()
+            .join(" / ");
+
+        let area_id = egui::Area::new(chain_area_id)
+            .fixed_pos(placement.pos)
+            .constrain(false)
+            .show(ui.ctx(), |area| {
+                area.set_clip_rect(end_view_ctx.transform.inverse() * end_view_ctx.rect);
+
+                self.draw_parent_connection(area, placement);
+
+                egui::Frame::default()
+                    .rounding(egui::Rounding::same(8.0))
+                    .inner_margin(egui::Margin::same(8.0))
+                    .stroke(Stroke {
+                        width: 1.0,
+                        color: Color32::GRAY,
+                    })
+                    .show(area, |frame| {
+                        frame.set_width(node_width());
+                        frame
+                            .horizontal(|line| {
+                                if line
+                                    .button(egui_phosphor::regular::CARET_DOWN)
+                                    .on_hover_text("Show the intermediate subtrees")
+                                    .clicked()
+                                {
+                                    self.expanded_chains.insert(chain[0].clone());
+                                }
+                                line.label(format!("… {} intermediate levels …", chain.len() - 1));
+                            })
+                            .response
+                            .on_hover_text(skipped);
+                    });
+            })
+            .response
+            .layer_id;
+
+        ui.ctx().set_transform_layer(area_id, end_view_ctx.transform);
+
+        let Some(bottom_pos) = ui.memory(|mem| mem.area_rect(chain_area_id).map(|rect| rect.center_bottom()))
+        else {
+            return;
+        };
+        let end_placement = Placement {
+            pos: Pos2::new(
+                bottom_pos.x - node_width() / 2.
+                    + layout.offset(ui.ctx(), LayoutNode::Chain(start), LayoutNode::Subtree(end)),
+                bottom_pos.y + layout.row_padding(ui.ctx(), LayoutNode::Chain(start)) + margin_vertical(),
+            ),
+            parent_anchor: bottom_pos,
+        };
+
+        let mut subtree = subtrees.remove(&end).unwrap_or_else(|| SubtreeView::new(end));
+        subtree.draw(
+            end_view_ctx,
+            ui,
+            tree_data,
+            subtrees,
+            layout,
+            Some(end_placement),
+            merk_panel_width,
+        );
+        subtrees.insert(end, subtree);
+    }
+}
+
+/// The single visible child subtree of a subtree that has no elements
+/// fetched, so it only leads the way down
+fn chain_link_key(path: Path, tree_data: &TreeData) -> Option<Key> {
+    let subtree_data = tree_data.get(&path)?;
+    let only_placeholders = subtree_data
+        .elements
+        .values()
+        .all(|e| !matches!(e.value, ElementOrPlaceholder::Element(_)));
+    (only_placeholders && subtree_data.visible_keys.len() == 1)
+        .then(|| subtree_data.visible_keys.first().cloned())
+        .flatten()
+}
+
+/// Keys from the subtree down through the chain of placeholder-only subtrees
+/// starting at the child with the key, the last one leads to the first
+/// subtree below the chain. Chains start under subtrees that are shown
+/// anyway, so an expanded chain doesn't collapse again one level lower.
+fn placeholder_chain(path: Path, key: &Key, tree_data: &TreeData) -> Option<Vec<Key>> {
+    if path.parent().is_some() && chain_link_key(path, tree_data).is_some() {
+        return None;
+    }
+    let mut keys = vec![key.clone()];
+    let mut current = path.child(key.clone());
+    while let Some(next) = chain_link_key(current, tree_data) {
+        current = current.child(next.clone());
+        keys.push(next);
+    }
+    (keys.len() > MIN_CHAIN_LEVELS).then_some(keys)
+}
+
+/// Chain to draw collapsed in place of the child subtree with the key
+fn collapsed_chain(
+    view: Option<&SubtreeView>,
+    path: Path,
+    key: &Key,
+    subtree_view_ctx: &SubtreeViewContext,
+    tree_data: &TreeData,
+) -> Option<Vec<Key>> {
+    if !subtree_view_ctx.collapse_chains || view.is_some_and(|v| v.expanded_chains.contains(key)) {
+        return None;
+    }
+    placeholder_chain(path, key, tree_data)
+}
+
+/// The first key that starts with the prefix
+fn first_with_prefix<'e>(elements: &'e SubtreeElements, prefix: &[u8]) -> Option<&'e Key> {
+    elements
+        .range(prefix.to_vec()..)
+        .next()
+        .map(|(key, _)| key)
+        .filter(|key| key.starts_with(prefix))
+}
+
+fn sort_keys(path: Path, elements: &SubtreeElements, order: KeyOrder) -> Vec<Key> {
+    let mut keys: Vec<Key> = elements.keys().cloned().collect();
+    match order {
+        KeyOrder::Key => {}
+        KeyOrder::Fetch => keys.sort_by_cached_key(|key| {
+            let fetched_at = elements[key].fetched.map(|f| f.at);
+            (fetched_at.is_none(), fetched_at)
+        }),
+        KeyOrder::Numeric => keys.sort_by_cached_key(|key| {
+            let display = path
+                .child(key.clone())
+                .get_display_variant()
+                .unwrap_or_else(|| BytesDisplayVariant::guess(key));
+            let number = bytes_as_number(key, &display);
+            (number.is_none(), number)
+        }),
+    }
+    keys
+}
+
+/// Split visible child subtrees into groups by their profile categories,
+/// keeping groups in order of their first key
+fn group_siblings(visible_keys: &BTreeSet<Key>, subtree_view_ctx: &SubtreeViewContext) -> Vec<SiblingGroup> {
+    let mut groups: Vec<SiblingGroup> = Vec::new();
+
+    for key in visible_keys {
+        let category = subtree_view_ctx.profile_ctx.key_category(key);
+        if let Some(group) =
+            category.and_then(|c| groups.iter_mut().find(|g| g.category.as_deref() == Some(c)))
+        {
+            group.keys.push(key.clone());
+        } else {
+            groups.push(SiblingGroup {
+                category: category.map(ToOwned::to_owned),
+                custom_color: category.and(subtree_view_ctx.profile_ctx.key_category_color(key)),
+                keys: vec![key.clone()],
+            });
         }
     }
+
+    groups
 }
 
-fn width_to_egui(width: usize) -> f32 {
-    if width > 0 {
-        width as f32 * NODE_WIDTH + (width - 1) as f32 * NODE_MARGIN_HORIZONTAL
-    } else {
-        0.
+/// Adds visible child subtrees of the subtree, collapsed groups, collapsed
+/// chains and all their descendants to the layout
+pub(super) fn collect_layout<'pa>(
+    path: Path<'pa>,
+    subtree_view_ctx: &mut SubtreeViewContext<'_, 'pa, '_>,
+    tree_data: &TreeData<'pa>,
+    subtrees: &BTreeMap<Path<'pa>, SubtreeView<'pa>>,
+    layout: &mut SubtreesLayout<'pa>,
+) {
+    let Some(groups) = tree_data
+        .get(&path)
+        .map(|subtree_data| group_siblings(&subtree_data.visible_keys, subtree_view_ctx))
+    else {
+        return;
+    };
+
+    let mut children = Vec::new();
+    for (idx, group) in groups.into_iter().enumerate() {
+        if subtrees.get(&path).is_some_and(|view| view.is_collapsed(&group)) {
+            let area = subtree_view_ctx
+                .area_id(path)
+                .with(group.category.as_deref().unwrap_or_default());
+            children.push((LayoutNode::CollapsedGroup(path, idx), area));
+            continue;
+        }
+        for key in group.keys {
+            let child_path = path.child(key.clone());
+            if let Some(chain) = collapsed_chain(subtrees.get(&path), path, &key, subtree_view_ctx, tree_data)
+            {
+                let end = chain[1..]
+                    .iter()
+                    .fold(child_path, |path, key| path.child(key.clone()));
+                collect_layout(
+                    end,
+                    &mut subtree_view_ctx.descendant(&chain),
+                    tree_data,
+                    subtrees,
+                    layout,
+                );
+                layout.add_children(
+                    LayoutNode::Chain(child_path),
+                    vec![(LayoutNode::Subtree(end), subtree_view_ctx.area_id(end))],
+                );
+                children.push((
+                    LayoutNode::Chain(child_path),
+                    subtree_view_ctx.area_id(child_path).with("chain"),
+                ));
+                continue;
+            }
+            collect_layout(
+                child_path,
+                &mut subtree_view_ctx.child(key),
+                tree_data,
+                subtrees,
+                layout,
+            );
+            children.push((
+                LayoutNode::Subtree(child_path),
+                subtree_view_ctx.area_id(child_path),
+            ));
+        }
     }
+
+    layout.add_children(LayoutNode::Subtree(path), children);
 }
@@ -0,0 +1,116 @@
+//! Distribution of fetched sum item values of a subtree, to spot outliers
+//! such as a single key holding most of a pool's balance.
+
+use eframe::egui::{self, RichText};
+use egui_plot::{Bar, BarChart, Plot};
+use grovedbg_types::{Element, Key};
+
+use super::{ElementOrPlaceholder, SubtreeElements};
+use crate::{
+    bytes_utils::{bytes_by_display_variant, BytesDisplayVariant},
+    format,
+    path_ctx::Path,
+    redaction,
+};
+
+const CHART_HEIGHT: f32 = 150.;
+
+/// Fetched sum item values, largest first
+pub(super) fn sum_values(elements: &SubtreeElements) -> Vec<(&Key, i64)> {
+    let mut values: Vec<(&Key, i64)> = elements
+        .iter()
+        .filter_map(|(key, element)| match &element.value {
+            ElementOrPlaceholder::Element(Element::SumItem { value, .. }) => Some((key, *value)),
+            _ => None,
+        })
+        .collect();
+    values.sort_by(|(_, a), (_, b)| b.cmp(a));
+    values
+}
+
+/// Share of the largest value in the total of absolute values, in percents
+fn largest_share(values: &[(&Key, i64)]) -> Option<f64> {
+    let total: f64 = values.iter().map(|(_, v)| v.unsigned_abs() as f64).sum();
+    let largest = values.iter().map(|(_, v)| v.unsigned_abs()).max()?;
+    (total > 0.).then(|| largest as f64 / total * 100.)
+}
+
+fn key_label(key: &[u8]) -> String {
+    bytes_by_display_variant(key, &BytesDisplayVariant::guess(key))
+}
+
+pub(super) fn draw(ui: &mut egui::Ui, path: Path, elements: &SubtreeElements, values_redacted: bool) {
+    let values = sum_values(elements);
+    if values.is_empty() {
+        ui.label("No sum items are fetched");
+        return;
+    }
+    if values_redacted {
+        redaction::draw_redacted(ui, values.len() * std::mem::size_of::<i64>());
+        return;
+    }
+
+    let total: i128 = values.iter().map(|(_, v)| *v as i128).sum();
+    ui.label(format!(
+        "{} sum items, total {}",
+        values.len(),
+        format::number(total)
+    ));
+    if let (Some(share), Some((key, _))) = (largest_share(&values), values.first()) {
+        let text = format!("Largest: {} with {share:.1}% of the total", key_label(key));
+        if share >= 50. && values.len() > 1 {
+            ui.label(RichText::new(text).color(ui.visuals().warn_fg_color));
+        } else {
+            ui.label(text);
+        }
+    }
+
+    let bars = values
+        .iter()
+        .enumerate()
+        .map(|(i, (key, value))| Bar::new(i as f64, *value as f64).name(key_label(key)))
+        .collect();
+    let chart = BarChart::new(bars).element_formatter(Box::new(|bar, _| {
+        format!("{}\n{}", bar.name, format::number(bar.value as i64))
+    }));
+
+    Plot::new(path.id().with("sum_chart"))
+        .height(CHART_HEIGHT)
+        .allow_scroll(false)
+        .show_x(false)
+        .show(ui, |plot_ui| plot_ui.bar_chart(chart));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_view::ElementView;
+
+    fn sum_item(key: u8, value: i64) -> (Key, ElementView) {
+        let mut element = ElementView::new_placeholder(vec![key]);
+        element.value = ElementOrPlaceholder::Element(Element::SumItem {
+            value,
+            element_flags: None,
+        });
+        (vec![key], element)
+    }
+
+    #[test]
+    fn largest_values_come_first() {
+        let elements: SubtreeElements = [
+            sum_item(1, 5),
+            sum_item(2, 90),
+            sum_item(3, -5),
+            (vec![4], ElementView::new_placeholder(vec![4])),
+        ]
+        .into_iter()
+        .collect();
+
+        let values = sum_values(&elements);
+        assert_eq!(
+            values.iter().map(|(_, v)| *v).collect::<Vec<_>>(),
+            vec![90, 5, -5]
+        );
+        assert_eq!(largest_share(&values), Some(90.));
+    }
+}
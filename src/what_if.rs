@@ -0,0 +1,326 @@
+//! What-if hashes: an item's value edited locally with the hashes it would
+//! change recomputed up to the GroveDB root hash, to follow hash propagation
+//! without writing anything to the database.
+
+use eframe::egui::{self, Grid, Label, RichText};
+use grovedbg_types::{CryptoHash, Element, Key};
+use strum::AsRefStr;
+
+use crate::{
+    bytes_utils::BytesInput,
+    merk_hash,
+    path_ctx::{path_as_json_hex, Path},
+    theme::{changed_hash_color, input_error_color},
+    tree_data::{SubtreeData, TreeData},
+    tree_view::ElementOrPlaceholder,
+};
+
+#[derive(Clone, Copy, PartialEq, AsRefStr)]
+pub(crate) enum HashKind {
+    #[strum(serialize = "Value hash")]
+    Value,
+    #[strum(serialize = "KV digest hash")]
+    KvDigest,
+    #[strum(serialize = "Node hash")]
+    Node,
+    /// Root node hash of a subtree's Merk
+    #[strum(serialize = "Subtree root hash")]
+    SubtreeRoot,
+}
+
+/// A hash that would change, `old` is `None` if it's not loaded
+pub(crate) struct HashChange<'pa> {
+    pub(crate) path: Path<'pa>,
+    /// `None` for a subtree root hash
+    pub(crate) key: Option<Key>,
+    pub(crate) kind: HashKind,
+    pub(crate) old: Option<CryptoHash>,
+    pub(crate) new: CryptoHash,
+}
+
+/// Recomputes hashes from the element at `key` with its new value hash up to
+/// the root subtree, every node and subtree element on the way must be
+/// loaded along with hashes of their siblings
+pub(crate) fn propagate<'pa>(
+    tree_data: &TreeData<'pa>,
+    mut path: Path<'pa>,
+    mut key: Key,
+    mut value_hash: CryptoHash,
+) -> Result<Vec<HashChange<'pa>>, &'static str> {
+    let mut changes = Vec::new();
+    loop {
+        let root_hash = {
+            let subtree = tree_data.get(&path).ok_or("A subtree on the way is not loaded")?;
+            propagate_merk(&subtree, path, key, value_hash, &mut changes)?
+        };
+        let Some((parent_path, parent_key)) = path.parent_with_key() else {
+            return Ok(changes);
+        };
+
+        let parent = tree_data
+            .get(&parent_path)
+            .ok_or("A parent subtree on the way is not loaded")?;
+        let element = parent
+            .elements
+            .get(&parent_key)
+            .ok_or("A subtree element on the way is not loaded")?;
+        let element_bytes = match &element.value {
+            ElementOrPlaceholder::Element(Element::Subtree {
+                root_key,
+                element_flags,
+            }) => merk_hash::subtree_element_bytes(root_key.as_deref(), None, element_flags.as_deref()),
+            ElementOrPlaceholder::Element(Element::Sumtree {
+                root_key,
+                sum,
+                element_flags,
+            }) => merk_hash::subtree_element_bytes(root_key.as_deref(), Some(*sum), element_flags.as_deref()),
+            _ => return Err("A subtree element on the way is not fetched"),
+        };
+        value_hash = merk_hash::combine_hash(&merk_hash::value_hash(&element_bytes), &root_hash);
+        path = parent_path;
+        key = parent_key;
+    }
+}
+
+/// Recomputes hashes within a single Merk from the node up to its root,
+/// returns the new root hash
+fn propagate_merk<'pa>(
+    subtree: &SubtreeData,
+    path: Path<'pa>,
+    key: Key,
+    value_hash: CryptoHash,
+    changes: &mut Vec<HashChange<'pa>>,
+) -> Result<CryptoHash, &'static str> {
+    let child_hash = |child: &Option<Key>| match child {
+        Some(child_key) => subtree
+            .elements
+            .get(child_key)
+            .and_then(|c| c.node_hash)
+            .map(Some)
+            .ok_or("A sibling hash on the way is unknown, fetch the node's children"),
+        None => Ok(None),
+    };
+
+    let element = subtree
+        .elements
+        .get(&key)
+        .ok_or("A node on the way is not loaded")?;
+    let kv_digest_hash = merk_hash::kv_digest_hash(&key, &value_hash);
+    let mut node_hash = merk_hash::node_hash(
+        &kv_digest_hash,
+        child_hash(&element.left_child)?.as_ref(),
+        child_hash(&element.right_child)?.as_ref(),
+    );
+    for (kind, old, new) in [
+        (HashKind::Value, element.value_hash, value_hash),
+        (HashKind::KvDigest, element.kv_digest_hash, kv_digest_hash),
+        (HashKind::Node, element.node_hash, node_hash),
+    ] {
+        changes.push(HashChange {
+            path,
+            key: Some(key.clone()),
+            kind,
+            old,
+            new,
+        });
+    }
+
+    let mut current = key;
+    while subtree.root_key.as_ref() != Some(&current) {
+        let (parent_key, parent) = subtree
+            .elements
+            .iter()
+            .find(|(_, e)| {
+                e.left_child.as_ref() == Some(&current) || e.right_child.as_ref() == Some(&current)
+            })
+            .ok_or("A parent node in the Merk tree is not loaded")?;
+        let kv_digest_hash = parent
+            .kv_digest_hash
+            .ok_or("A parent node in the Merk tree is not fetched")?;
+        let (left, right) = if parent.left_child.as_ref() == Some(&current) {
+            (Some(node_hash), child_hash(&parent.right_child)?)
+        } else {
+            (child_hash(&parent.left_child)?, Some(node_hash))
+        };
+        node_hash = merk_hash::node_hash(&kv_digest_hash, left.as_ref(), right.as_ref());
+        changes.push(HashChange {
+            path,
+            key: Some(parent_key.clone()),
+            kind: HashKind::Node,
+            old: parent.node_hash,
+            new: node_hash,
+        });
+        current = parent_key.clone();
+    }
+
+    changes.push(HashChange {
+        path,
+        key: None,
+        kind: HashKind::SubtreeRoot,
+        old: subtree.root_hash(),
+        new: node_hash,
+    });
+    Ok(node_hash)
+}
+
+/// Sandbox editing a single item, nothing is sent to GroveDB
+pub(crate) struct WhatIf<'pa> {
+    path: Path<'pa>,
+    key: Key,
+    flags: Option<Vec<u8>>,
+    value_input: BytesInput,
+    /// Recomputed value hash of the original value differs from the reported
+    /// one, so the item is serialized differently than assumed
+    serialization_mismatch: bool,
+}
+
+impl<'pa> WhatIf<'pa> {
+    /// `None` unless the element is a fetched item
+    pub(crate) fn new(path: Path<'pa>, key: Key, tree_data: &TreeData<'pa>) -> Option<Self> {
+        let subtree = tree_data.get(&path)?;
+        let element = subtree.elements.get(&key)?;
+        let ElementOrPlaceholder::Element(Element::Item { value, element_flags }) = &element.value else {
+            return None;
+        };
+        let original_hash =
+            merk_hash::value_hash(&merk_hash::item_element_bytes(value, element_flags.as_deref()));
+        Some(WhatIf {
+            serialization_mismatch: element
+                .value_hash
+                .is_some_and(|reported| reported != original_hash),
+            value_input: BytesInput::new_from_bytes(value.clone()),
+            flags: element_flags.clone(),
+            path,
+            key,
+        })
+    }
+
+    pub(crate) fn draw(&mut self, ui: &mut egui::Ui, tree_data: &TreeData<'pa>) {
+        ui.label(format!(
+            "Item {} at {}",
+            hex::encode(&self.key),
+            path_as_json_hex(&self.path.to_vec())
+        ));
+        ui.label(
+            RichText::new("Changes are computed locally and never written to GroveDB")
+                .weak()
+                .italics(),
+        );
+        ui.horizontal(|line| {
+            line.label("Value:");
+            self.value_input.draw(line);
+        });
+        if self.serialization_mismatch {
+            ui.label(
+                RichText::new(format!(
+                    "{} The original value doesn't reproduce the reported value hash, results may be off",
+                    egui_phosphor::regular::WARNING
+                ))
+                .color(input_error_color(ui.ctx())),
+            );
+        }
+        ui.separator();
+
+        let value = self.value_input.get_bytes();
+        let value_hash = merk_hash::value_hash(&merk_hash::item_element_bytes(&value, self.flags.as_deref()));
+        let changes = match propagate(tree_data, self.path, self.key.clone(), value_hash) {
+            Ok(changes) => changes,
+            Err(reason) => {
+                ui.label(reason);
+                return;
+            }
+        };
+
+        let changed = changes.iter().filter(|c| c.old != Some(c.new)).count();
+        ui.label(format!(
+            "{changed} of {} hashes on the way would change",
+            changes.len()
+        ));
+        egui::ScrollArea::vertical().show(ui, |scroll| {
+            Grid::new("what_if_changes")
+                .num_columns(4)
+                .striped(true)
+                .show(scroll, |grid| {
+                    for change in changes.iter() {
+                        let location = match &change.key {
+                            Some(key) => hex::encode(key),
+                            None if change.path.parent_with_key().is_none() => "GroveDB root".to_owned(),
+                            None => "subtree".to_owned(),
+                        };
+                        grid.add(Label::new(RichText::new(location).monospace()).truncate())
+                            .on_hover_text(path_as_json_hex(&change.path.to_vec()));
+                        grid.label(change.kind.as_ref());
+                        match change.old {
+                            Some(old) => {
+                                grid.add(Label::new(RichText::new(hex::encode(old)).monospace()).truncate())
+                            }
+                            None => grid.label(RichText::new("unknown").weak()),
+                        };
+                        let new = RichText::new(hex::encode(change.new)).monospace();
+                        let new = if change.old == Some(change.new) {
+                            new.weak()
+                        } else {
+                            new.color(changed_hash_color(grid.ctx()))
+                        };
+                        grid.add(Label::new(new).truncate());
+                        grid.end_row();
+                    }
+                });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{display_defaults::DisplayDefaults, path_ctx::PathCtx, tree_view::ElementView};
+
+    #[test]
+    fn hashes_propagate_to_the_merk_root() {
+        let path_ctx = PathCtx::new();
+        let mut tree_data = TreeData::new(&path_ctx, DisplayDefaults::default());
+        let root = path_ctx.get_root();
+
+        let hash = |key: &[u8], value: &[u8]| {
+            let value_hash = merk_hash::value_hash(value);
+            (value_hash, merk_hash::kv_digest_hash(key, &value_hash))
+        };
+        let (a_value, a_kv) = hash(b"a", b"1");
+        let (b_value, b_kv) = hash(b"b", b"2");
+        let a_node = merk_hash::node_hash(&a_kv, None, None);
+        {
+            let mut subtree = tree_data.get_or_create_mut(root);
+            subtree.set_root_key(Some(b"b".to_vec()));
+            let mut a = ElementView::new_placeholder(b"a".to_vec());
+            (a.value_hash, a.kv_digest_hash, a.node_hash) = (Some(a_value), Some(a_kv), Some(a_node));
+            let mut b = ElementView::new_placeholder(b"b".to_vec());
+            (b.value_hash, b.kv_digest_hash, b.left_child) = (Some(b_value), Some(b_kv), Some(b"a".to_vec()));
+            subtree.elements.insert(b"a".to_vec(), a);
+            subtree.elements.insert(b"b".to_vec(), b);
+        }
+
+        let new_value_hash = merk_hash::value_hash(b"3");
+        let changes = propagate(&tree_data, root, b"a".to_vec(), new_value_hash).unwrap();
+        assert_eq!(
+            changes.iter().map(|c| c.kind).collect::<Vec<_>>(),
+            vec![
+                HashKind::Value,
+                HashKind::KvDigest,
+                HashKind::Node,
+                HashKind::Node,
+                HashKind::SubtreeRoot
+            ]
+        );
+
+        let new_a_node = merk_hash::node_hash(&merk_hash::kv_digest_hash(b"a", &new_value_hash), None, None);
+        let new_root = merk_hash::node_hash(&b_kv, Some(&new_a_node), None);
+        let root_change = changes.last().unwrap();
+        assert_eq!(root_change.new, new_root);
+        assert_eq!(
+            root_change.old,
+            Some(merk_hash::node_hash(&b_kv, Some(&a_node), None))
+        );
+
+        assert!(propagate(&tree_data, root, b"c".to_vec(), new_value_hash).is_err());
+    }
+}